@@ -1,51 +1,171 @@
+// Only for anchor's generated __idl_resize_account, which calls the deprecated
+// AccountInfo::realloc. #[program] emits it at the crate root, beside the connected
+// module, so an #[allow] on any narrower item does not reach it.
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, TokenAccount};
-use std::mem::size_of;
+use anchor_spl::associated_token::{self, get_associated_token_address, AssociatedToken, Create};
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
 
 declare_id!("2g2QbdzV9raDR9k5PGwSyCRGxmNC7RXdvqQf4npjvxz4");
 
+#[cfg(all(feature = "test-bypass-tss", not(debug_assertions)))]
+compile_error!("the test-bypass-tss feature must never be enabled in release builds");
+
+// Length of an ed25519 signature produced by the TSS
+pub const TSS_SIGNATURE_LEN: usize = 64;
+// Version tag carried in the first byte of on_call data
+pub const MESSAGE_VERSION_V1: u8 = 1;
+// Number of message versions that can be accepted at once (0 marks an empty slot)
+pub const MAX_ACCEPTED_VERSIONS: usize = 4;
+// Upper bound on Pda.last_message in bytes; fixes the size of the PDA account
+pub const MAX_MESSAGE_LEN: usize = 256;
+// Maximum messages per on_call_batch, bounded by transaction size and compute
+pub const MAX_ON_CALL_BATCH_SIZE: usize = 8;
+// Per-item status reported by on_call_batch for a message that was processed
+pub const ON_CALL_ITEM_OK: u32 = 0;
+// Ed25519Program instruction data: a signature count and a padding byte, then one
+// offsets entry (seven little-endian u16 fields) per signature
+pub const ED25519_OFFSETS_START: usize = 2;
+pub const ED25519_OFFSETS_LEN: usize = 14;
+// Instruction index an offsets entry uses to point into the Ed25519 instruction itself
+pub const ED25519_CURRENT_INSTRUCTION: u16 = u16::MAX;
+
 // NOTE: this is just example contract that can be called from gateway in execute function for testing withdraw and call
 #[program]
 pub mod connected {
     use super::*;
 
-    pub fn initialize(_ctx: Context<Initialize>) -> Result<()> {
+    pub fn initialize(ctx: Context<Initialize>, tss_pubkey: Pubkey) -> Result<()> {
+        let pda = &mut ctx.accounts.pda;
+
+        // The PDA is init_if_needed, so a second initialize lands here and is refused
+        require!(!pda.is_initialized, ErrorCode::AlreadyInitialized);
+        pda.is_initialized = true;
+        pda.authority = ctx.accounts.signer.key();
+        pda.tss_pubkey = tss_pubkey;
+        pda.accepted_versions = [0; MAX_ACCEPTED_VERSIONS];
+        pda.accepted_versions[0] = MESSAGE_VERSION_V1;
+
+        Ok(())
+    }
+
+    /// Replace the set of message versions on_call accepts (for rolling upgrades)
+    pub fn set_accepted_versions(ctx: Context<SetAcceptedVersions>, versions: Vec<u8>) -> Result<()> {
+        require!(
+            !versions.is_empty() && versions.len() <= MAX_ACCEPTED_VERSIONS,
+            ErrorCode::InvalidAcceptedVersions
+        );
+        require!(!versions.contains(&0), ErrorCode::InvalidAcceptedVersions);
+
+        let pda = &mut ctx.accounts.pda;
+        pda.accepted_versions = [0; MAX_ACCEPTED_VERSIONS];
+        pda.accepted_versions[..versions.len()].copy_from_slice(&versions);
+
         Ok(())
     }
 
+    /// `nonce` must exceed the last one processed for `sender`; the first message from a
+    /// sender creates its SenderNonce account, paid by the TSS signer
     pub fn on_call(
         ctx: Context<OnCall>,
         amount: u64,
         sender: [u8; 20],
+        nonce: u64,
         data: Vec<u8>,
+        signature: Vec<u8>,
     ) -> Result<()> {
-        let pda = &mut ctx.accounts.pda;
+        let sender_nonce = &mut ctx.accounts.sender_nonce;
+        if sender_nonce.sender != sender {
+            sender_nonce.sender = sender;
+            sender_nonce.bump = ctx.bumps.sender_nonce;
+        }
 
-        // Store the sender's public key
-        pda.last_sender = sender;
+        let accounts = CallAccounts {
+            tss_signer: &ctx.accounts.tss_signer,
+            instructions_sysvar: &ctx.accounts.instructions_sysvar,
+            mint_account: &ctx.accounts.mint_account,
+            pda_ata: &ctx.accounts.pda_ata,
+            recipient: ctx.accounts.recipient.as_ref(),
+            recipient_token_account: ctx.accounts.recipient_token_account.as_ref(),
+            token_program: &ctx.accounts.token_program,
+            associated_token_program: &ctx.accounts.associated_token_program,
+            system_program: &ctx.accounts.system_program,
+            pda_bump: ctx.bumps.pda,
+        };
+        process_call(
+            &mut ctx.accounts.pda,
+            sender_nonce,
+            &accounts,
+            &OnCallItem { amount, sender, nonce, data, signature },
+        )
+    }
 
-        // Convert data to a string and store it
-        let message = String::from_utf8(data).map_err(|_| ErrorCode::InvalidDataFormat)?;
-        pda.last_message = message;
+    /// Process several relayed messages in one transaction. Returns one status per
+    /// item: ON_CALL_ITEM_OK, or the error code that item failed with. With
+    /// `atomic` set, the first failing item aborts the whole batch instead.
+    /// Payout accounts are checked per item before any CPI; a CPI that still fails
+    /// (e.g. tss_signer short of rent for a new token account) aborts the batch.
+    /// remaining_accounts holds the SenderNonce account of every distinct sender in the
+    /// batch; senders without one must go through on_call first.
+    pub fn on_call_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, OnCallBatch<'info>>,
+        items: Vec<OnCallItem>,
+        atomic: bool,
+    ) -> Result<Vec<u32>> {
+        require!(
+            !items.is_empty() && items.len() <= MAX_ON_CALL_BATCH_SIZE,
+            ErrorCode::InvalidBatchSize
+        );
 
-        if pda.last_message == "sol" {
-            msg!(
-                "On call sol executed with amount {}, sender {:?} and message {}",
-                amount,
-                pda.last_sender,
-                pda.last_message
-            );
-        } else {
-            msg!(
-                "On call spl executed with amount {}, spl {:?}, sender {:?} and message {}",
-                amount,
-                ctx.accounts.mint_account,
-                pda.last_sender,
-                pda.last_message
+        // Two copies of one account would each be written back, the later one
+        // discarding the nonce recorded through the first
+        let mut sender_nonces = Vec::with_capacity(ctx.remaining_accounts.len());
+        for account in ctx.remaining_accounts {
+            require!(
+                sender_nonces.iter().all(|loaded: &Account<SenderNonce>| loaded.key() != account.key()),
+                ErrorCode::InvalidSenderNonceAccounts
             );
+            sender_nonces.push(Account::<SenderNonce>::try_from(account)?);
         }
 
-        Ok(())
+        let accounts = CallAccounts {
+            tss_signer: &ctx.accounts.tss_signer,
+            instructions_sysvar: &ctx.accounts.instructions_sysvar,
+            mint_account: &ctx.accounts.mint_account,
+            pda_ata: &ctx.accounts.pda_ata,
+            recipient: ctx.accounts.recipient.as_ref(),
+            recipient_token_account: ctx.accounts.recipient_token_account.as_ref(),
+            token_program: &ctx.accounts.token_program,
+            associated_token_program: &ctx.accounts.associated_token_program,
+            system_program: &ctx.accounts.system_program,
+            pda_bump: ctx.bumps.pda,
+        };
+        let mut statuses = Vec::with_capacity(items.len());
+        for (index, item) in items.iter().enumerate() {
+            // Only on_call creates SenderNonce accounts, at the sender's PDA, so a
+            // program-owned one recording this sender is that PDA
+            let result = match sender_nonces.iter_mut().find(|sender_nonce| sender_nonce.sender == item.sender) {
+                Some(sender_nonce) => process_call(&mut ctx.accounts.pda, sender_nonce, &accounts, item),
+                None => Err(ErrorCode::InvalidSenderNonceAccounts.into()),
+            };
+
+            let status = match result {
+                Ok(()) => ON_CALL_ITEM_OK,
+                Err(err) if atomic => return Err(err),
+                Err(err) => {
+                    msg!("on_call_batch item {} failed: {}", index, err);
+                    error_code_number(&err)
+                }
+            };
+            statuses.push(status);
+        }
+
+        for sender_nonce in &sender_nonces {
+            sender_nonce.exit(&crate::ID)?;
+        }
+
+        Ok(statuses)
     }
 }
 
@@ -54,38 +174,567 @@ pub struct Initialize<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
 
-    #[account(init, payer = signer, space = size_of::<Pda>() + 32, seeds = [b"connected"], bump)]
+    #[account(init_if_needed, payer = signer, space = 8 + Pda::INIT_SPACE, seeds = [b"connected"], bump)]
     pub pda: Account<'info, Pda>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
+pub struct SetAcceptedVersions<'info> {
+    #[account(mut, seeds = [b"connected"], bump, has_one = authority @ ErrorCode::Unauthorized)]
+    pub pda: Account<'info, Pda>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, sender: [u8; 20])]
 pub struct OnCall<'info> {
     #[account(mut, seeds = [b"connected"], bump)]
     pub pda: Account<'info, Pda>,
 
+    #[account(
+        init_if_needed,
+        payer = tss_signer,
+        space = 8 + SenderNonce::INIT_SPACE,
+        seeds = [b"sender_nonce", sender.as_ref()],
+        bump
+    )]
+    pub sender_nonce: Account<'info, SenderNonce>,
+
     #[account(mut)]
     pub pda_ata: Account<'info, TokenAccount>,
 
+    /// CHECK: Wallet paid by borrowCrossChain and withdrawCrossChain; must be the one the message names
+    pub recipient: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The recipient's associated token account for mint_account, created by the
+    /// payout if it does not exist yet; its derivation is validated there
+    #[account(mut)]
+    pub recipient_token_account: Option<UncheckedAccount<'info>>,
+
     pub mint_account: Account<'info, Mint>,
 
     /// CHECK: Test contract
     pub gateway_pda: UncheckedAccount<'info>,
 
+    /// The TSS must sign the transaction; its key is compared against pda.tss_pubkey
+    #[account(mut)]
+    pub tss_signer: Signer<'info>,
+
+    /// CHECK: Instructions sysvar, read for the Ed25519Program instruction preceding this one
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
     pub token_program: Program<'info, Token>,
 
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct OnCallBatch<'info> {
+    #[account(mut, seeds = [b"connected"], bump)]
+    pub pda: Account<'info, Pda>,
+
+    #[account(mut)]
+    pub pda_ata: Account<'info, TokenAccount>,
+
+    /// CHECK: Wallet paid by borrowCrossChain and withdrawCrossChain; must be the one the message names
+    pub recipient: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The recipient's associated token account for mint_account, created by the
+    /// payout if it does not exist yet; its derivation is validated there
+    #[account(mut)]
+    pub recipient_token_account: Option<UncheckedAccount<'info>>,
+
+    pub mint_account: Account<'info, Mint>,
+
+    /// CHECK: Test contract
+    pub gateway_pda: UncheckedAccount<'info>,
+
+    /// The TSS must sign the transaction; its key is compared against pda.tss_pubkey.
+    /// Pays for a recipient token account a payout has to create.
+    #[account(mut)]
+    pub tss_signer: Signer<'info>,
+
+    /// CHECK: Instructions sysvar, read for the Ed25519Program instruction preceding this one
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// One relayed message inside an on_call_batch
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct OnCallItem {
+    pub amount: u64,
+    pub sender: [u8; 20],
+    pub nonce: u64,
+    pub data: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
 #[account]
+#[derive(InitSpace)]
 pub struct Pda {
     pub last_sender: [u8; 20],
-    pub last_message: String,
+    #[max_len(MAX_MESSAGE_LEN)]
+    pub last_message: String, // Action of the last processed message
+    pub is_initialized: bool,
+    pub authority: Pubkey,
+    pub tss_pubkey: Pubkey,
+    pub accepted_versions: [u8; MAX_ACCEPTED_VERSIONS],
+    pub total_supplied: u64, // Credited by supply, debited by withdrawCrossChain
+    pub total_borrowed: u64, // Credited by borrowCrossChain, debited by repay
+}
+
+// Replay protection for one ZetaChain sender (seeds [b"sender_nonce", sender])
+#[account]
+#[derive(InitSpace)]
+pub struct SenderNonce {
+    pub sender: [u8; 20],
+    pub last_nonce: u64, // Highest nonce processed; zero before the first message
+    pub bump: u8,
+}
+
+// Protocol action an on_call payload asks for. supply and repay account for the
+// tokens delivered with the call; borrowCrossChain and withdrawCrossChain pay out of
+// pda_ata to the recipient wallet's associated token account
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallAction {
+    Supply,
+    Repay,
+    BorrowCrossChain { recipient: Pubkey },
+    WithdrawCrossChain { recipient: Pubkey },
+}
+
+impl CallAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CallAction::Supply => "supply",
+            CallAction::Repay => "repay",
+            CallAction::BorrowCrossChain { .. } => "borrowCrossChain",
+            CallAction::WithdrawCrossChain { .. } => "withdrawCrossChain",
+        }
+    }
+}
+
+// An on_call payload after ABI decoding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodedCall {
+    pub action: CallAction,
+    pub on_behalf_of: [u8; 20],
+    pub amount: u64,
+}
+
+/// Decode an ABI-encoded on_call payload. supply and repay use the deposit
+/// contract's abi.encode(string action, address onBehalfOf) and take `delivered`,
+/// the amount that came with the call; borrowCrossChain and withdrawCrossChain are
+/// abi.encode(string action, address onBehalfOf, uint256 amount, bytes32 recipient).
+pub fn decode_call(payload: &[u8], delivered: u64) -> Result<DecodedCall> {
+    let action = abi::bytes_at(payload, 0).ok_or(ErrorCode::InvalidDataFormat)?;
+    if !matches!(action, b"supply" | b"repay" | b"borrowCrossChain" | b"withdrawCrossChain") {
+        return err!(ErrorCode::UnknownAction);
+    }
+
+    let on_behalf_of = abi::address_at(payload, abi::WORD_LEN).ok_or(ErrorCode::InvalidActionArguments)?;
+    let payout = || -> Result<(u64, Pubkey)> {
+        let amount = abi::u64_at(payload, 2 * abi::WORD_LEN).ok_or(ErrorCode::InvalidActionArguments)?;
+        let recipient = abi::word_at(payload, 3 * abi::WORD_LEN).ok_or(ErrorCode::InvalidActionArguments)?;
+        Ok((amount, Pubkey::new_from_array(recipient)))
+    };
+    let (action, amount) = match action {
+        b"supply" => (CallAction::Supply, delivered),
+        b"repay" => (CallAction::Repay, delivered),
+        b"borrowCrossChain" => {
+            let (amount, recipient) = payout()?;
+            (CallAction::BorrowCrossChain { recipient }, amount)
+        }
+        _ => {
+            let (amount, recipient) = payout()?;
+            (CallAction::WithdrawCrossChain { recipient }, amount)
+        }
+    };
+
+    Ok(DecodedCall { action, on_behalf_of, amount })
+}
+
+// Read side of Solidity's abi.encode for the static words and dynamic strings
+// on_call payloads use
+mod abi {
+    pub const WORD_LEN: usize = 32;
+
+    pub fn word_at(payload: &[u8], offset: usize) -> Option<[u8; WORD_LEN]> {
+        payload.get(offset..offset.checked_add(WORD_LEN)?)?.try_into().ok()
+    }
+
+    // A uint256 word that must fit in a u64
+    pub fn u64_at(payload: &[u8], offset: usize) -> Option<u64> {
+        let word = word_at(payload, offset)?;
+        let (high, low) = word.split_at(WORD_LEN - 8);
+        high.iter().all(|b| *b == 0).then(|| u64::from_be_bytes(low.try_into().expect("low 8 bytes")))
+    }
+
+    // An address word: 20 bytes left-padded with zeros
+    pub fn address_at(payload: &[u8], offset: usize) -> Option<[u8; 20]> {
+        let word = word_at(payload, offset)?;
+        let (padding, address) = word.split_at(WORD_LEN - 20);
+        padding.iter().all(|b| *b == 0).then(|| address.try_into().expect("20 bytes"))
+    }
+
+    // A dynamic string/bytes field: the head word holds the offset of its length word
+    pub fn bytes_at(payload: &[u8], head_offset: usize) -> Option<&[u8]> {
+        let start = usize::try_from(u64_at(payload, head_offset)?).ok()?;
+        let len = usize::try_from(u64_at(payload, start)?).ok()?;
+        let data_start = start.checked_add(WORD_LEN)?;
+        payload.get(data_start..data_start.checked_add(len)?)
+    }
+}
+
+// Accounts on_call and on_call_batch share across the messages they process
+struct CallAccounts<'a, 'info> {
+    tss_signer: &'a AccountInfo<'info>,
+    instructions_sysvar: &'a AccountInfo<'info>,
+    mint_account: &'a Account<'info, Mint>,
+    pda_ata: &'a Account<'info, TokenAccount>,
+    recipient: Option<&'a UncheckedAccount<'info>>,
+    recipient_token_account: Option<&'a UncheckedAccount<'info>>,
+    token_program: &'a Program<'info, Token>,
+    associated_token_program: &'a Program<'info, AssociatedToken>,
+    system_program: &'a Program<'info, System>,
+    pda_bump: u8,
+}
+
+// Pda.last_message only has room for MAX_MESSAGE_LEN bytes, so every write goes through here
+fn store_last_message(pda: &mut Pda, message: &str) -> Result<()> {
+    require!(message.len() <= MAX_MESSAGE_LEN, ErrorCode::MessageTooLong);
+    pda.last_message = message.to_string();
+    Ok(())
+}
+
+// Validates a single message fully before touching the PDA, so a failed item in a
+// non-atomic batch leaves no partial state behind
+fn process_call<'info>(
+    pda: &mut Account<'info, Pda>,
+    sender_nonce: &mut SenderNonce,
+    accounts: &CallAccounts<'_, 'info>,
+    item: &OnCallItem,
+) -> Result<()> {
+    let OnCallItem { amount, sender, nonce, ref data, ref signature } = *item;
+    verify_tss_signature(
+        accounts.tss_signer,
+        &pda.tss_pubkey,
+        accounts.instructions_sysvar,
+        &tss_signed_message(amount, sender, nonce, data),
+        signature,
+    )?;
+    require!(nonce > sender_nonce.last_nonce, ErrorCode::ReplayedMessage);
+
+    // Leading byte is the message version tag
+    let (version, payload) = data.split_first().ok_or(ErrorCode::InvalidDataFormat)?;
+    require!(
+        pda.accepted_versions.contains(version),
+        ErrorCode::UnsupportedMessageVersion
+    );
+
+    let DecodedCall { action, on_behalf_of, amount } = decode_call(payload, amount)?;
+
+    // The payout is the last step that can fail, so nothing is written before it
+    let (mut total_supplied, mut total_borrowed) = (pda.total_supplied, pda.total_borrowed);
+    match action {
+        CallAction::Supply => {
+            total_supplied = total_supplied.checked_add(amount).ok_or(ErrorCode::ArithmeticOverflow)?;
+        }
+        CallAction::Repay => {
+            total_borrowed = total_borrowed.checked_sub(amount).ok_or(ErrorCode::InsufficientBalance)?;
+        }
+        CallAction::BorrowCrossChain { recipient } => {
+            total_borrowed = total_borrowed.checked_add(amount).ok_or(ErrorCode::ArithmeticOverflow)?;
+            pay_out(pda, accounts, recipient, amount)?;
+        }
+        CallAction::WithdrawCrossChain { recipient } => {
+            total_supplied = total_supplied.checked_sub(amount).ok_or(ErrorCode::InsufficientBalance)?;
+            pay_out(pda, accounts, recipient, amount)?;
+        }
+    }
+
+    // Store the sender's public key and the message
+    store_last_message(pda, action.as_str())?;
+    sender_nonce.last_nonce = nonce;
+    pda.total_supplied = total_supplied;
+    pda.total_borrowed = total_borrowed;
+    pda.last_sender = sender;
+
+    msg!(
+        "On call {} executed with amount {}, mint {}, sender {:?}, on behalf of {:?}",
+        action.as_str(),
+        amount,
+        accounts.mint_account.key(),
+        pda.last_sender,
+        on_behalf_of
+    );
+
+    Ok(())
+}
+
+// Transfer out of pda_ata, signed by the connected PDA, to the associated token
+// account of the wallet the TSS-signed message names. A brand-new recipient has no
+// token account yet, so it is created first, paid by the TSS signer.
+fn pay_out<'info>(
+    pda: &Account<'info, Pda>,
+    accounts: &CallAccounts<'_, 'info>,
+    recipient: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let recipient_wallet = accounts.recipient.ok_or(ErrorCode::InvalidRecipient)?;
+    let recipient_token_account = accounts.recipient_token_account.ok_or(ErrorCode::InvalidRecipient)?;
+    require_keys_eq!(recipient_wallet.key(), recipient, ErrorCode::InvalidRecipient);
+    // Only the canonical ATA is accepted; its address also pins the mint
+    require_keys_eq!(
+        recipient_token_account.key(),
+        get_associated_token_address(&recipient, &accounts.mint_account.key()),
+        ErrorCode::RecipientMintMismatch
+    );
+    check_payout_accounts(pda, accounts, recipient_token_account, amount)?;
+
+    // No-op when the account already exists
+    associated_token::create_idempotent(CpiContext::new(
+        accounts.associated_token_program.to_account_info(),
+        Create {
+            payer: accounts.tss_signer.clone(),
+            associated_token: recipient_token_account.to_account_info(),
+            authority: recipient_wallet.to_account_info(),
+            mint: accounts.mint_account.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            token_program: accounts.token_program.to_account_info(),
+        },
+    ))?;
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            Transfer {
+                from: accounts.pda_ata.to_account_info(),
+                to: recipient_token_account.to_account_info(),
+                authority: pda.to_account_info(),
+            },
+            &[&[b"connected", &[accounts.pda_bump]]],
+        ),
+        amount,
+    )
+}
+
+// A CPI error cannot be caught, so anything that would make the token program reject
+// the payout is checked here and fails only this item of a non-atomic batch
+fn check_payout_accounts(
+    pda: &Account<Pda>,
+    accounts: &CallAccounts,
+    recipient_token_account: &UncheckedAccount,
+    amount: u64,
+) -> Result<()> {
+    let vault = accounts.pda_ata;
+    require_keys_eq!(vault.mint, accounts.mint_account.key(), ErrorCode::InvalidPayoutAccounts);
+    require_keys_eq!(vault.owner, pda.key(), ErrorCode::InvalidPayoutAccounts);
+    require!(!vault.is_frozen(), ErrorCode::InvalidPayoutAccounts);
+    // Earlier items of the batch have already moved tokens out, so read the live balance
+    require!(
+        token::accessor::amount(&vault.to_account_info())? >= amount,
+        ErrorCode::InsufficientVaultBalance
+    );
+
+    // create_idempotent leaves an existing account as is; its address pins the mint
+    // and owner, but a frozen one would still reject the transfer
+    if !recipient_token_account.data_is_empty() {
+        let existing = TokenAccount::try_deserialize(&mut &recipient_token_account.try_borrow_data()?[..])
+            .map_err(|_| ErrorCode::InvalidPayoutAccounts)?;
+        require!(!existing.is_frozen(), ErrorCode::InvalidPayoutAccounts);
+    }
+    Ok(())
+}
+
+// Numeric code reported for a failed batch item
+fn error_code_number(err: &Error) -> u32 {
+    match err {
+        Error::AnchorError(anchor_error) => anchor_error.error_code_number,
+        Error::ProgramError(_) => u32::MAX,
+    }
+}
+
+// The TSS signs every field it relays, so none of them can be swapped for another
+// message's: amount (little-endian) || sender || nonce (little-endian) || data
+pub fn tss_signed_message(amount: u64, sender: [u8; 20], nonce: u64, data: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(16 + sender.len() + data.len());
+    message.extend_from_slice(&amount.to_le_bytes());
+    message.extend_from_slice(&sender);
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message.extend_from_slice(data);
+    message
+}
+
+// Programs cannot run ed25519 verification themselves within compute limits, so the
+// relayer places an Ed25519Program instruction right before this one. The runtime
+// fails the whole transaction if any signature in it is invalid; here we only have to
+// check that it covers exactly this signer, message and signature.
+fn verify_tss_signature(
+    tss_signer: &AccountInfo,
+    tss_pubkey: &Pubkey,
+    instructions_sysvar: &AccountInfo,
+    message: &[u8],
+    signature: &[u8],
+) -> Result<()> {
+    require_keys_eq!(tss_signer.key(), *tss_pubkey, ErrorCode::InvalidTssSigner);
+
+    #[cfg(feature = "test-bypass-tss")]
+    {
+        let _ = (instructions_sysvar, message, signature);
+        msg!("test-bypass-tss: skipping TSS signature verification");
+    }
+
+    #[cfg(not(feature = "test-bypass-tss"))]
+    {
+        use anchor_lang::solana_program::{ed25519_program, sysvar::instructions::get_instruction_relative};
+
+        require!(signature.len() == TSS_SIGNATURE_LEN, ErrorCode::InvalidSignature);
+        let ed25519_instruction = get_instruction_relative(-1, instructions_sysvar)
+            .map_err(|_| ErrorCode::InvalidSignature)?;
+        require_keys_eq!(
+            ed25519_instruction.program_id,
+            ed25519_program::ID,
+            ErrorCode::InvalidSignature
+        );
+        require!(
+            ed25519_instruction_covers(&ed25519_instruction.data, tss_pubkey, message, signature),
+            ErrorCode::InvalidSignature
+        );
+    }
+
+    Ok(())
+}
+
+// Whether any signature entry of an Ed25519Program instruction is `signature` by
+// `pubkey` over `message`. Entries whose data lives in another instruction are ignored,
+// since that data is not what the precompile is guaranteed to have verified against.
+#[cfg(not(feature = "test-bypass-tss"))]
+fn ed25519_instruction_covers(data: &[u8], pubkey: &Pubkey, message: &[u8], signature: &[u8]) -> bool {
+    let signature_count = usize::from(data.first().copied().unwrap_or(0));
+    let slice = |offset: u16, len: usize| data.get(usize::from(offset)..usize::from(offset) + len);
+
+    (0..signature_count).any(|index| {
+        let start = ED25519_OFFSETS_START + index * ED25519_OFFSETS_LEN;
+        let Some(entry) = data.get(start..start + ED25519_OFFSETS_LEN) else {
+            return false;
+        };
+        let field = |position: usize| u16::from_le_bytes([entry[2 * position], entry[2 * position + 1]]);
+        let [signature_offset, signature_ix, pubkey_offset, pubkey_ix, message_offset, message_len, message_ix] =
+            [0, 1, 2, 3, 4, 5, 6].map(field);
+
+        [signature_ix, pubkey_ix, message_ix] == [ED25519_CURRENT_INSTRUCTION; 3]
+            && slice(signature_offset, TSS_SIGNATURE_LEN) == Some(signature)
+            && slice(pubkey_offset, 32) == Some(pubkey.as_ref())
+            && slice(message_offset, usize::from(message_len)) == Some(message)
+    })
 }
 
 #[error_code]
 pub enum ErrorCode {
-    #[msg("The data provided could not be converted to a valid UTF-8 string.")]
+    #[msg("The data provided is not a valid ABI-encoded message.")]
     InvalidDataFormat,
+    #[msg("The connected program has already been initialized.")]
+    AlreadyInitialized,
+    #[msg("The TSS signer does not match the configured TSS public key.")]
+    InvalidTssSigner,
+    #[msg("The TSS signature is invalid.")]
+    InvalidSignature,
+    #[msg("Only the program authority can perform this action.")]
+    Unauthorized,
+    #[msg("Accepted versions must be 1 to 4 nonzero version tags.")]
+    InvalidAcceptedVersions,
+    #[msg("The message version is not accepted.")]
+    UnsupportedMessageVersion,
+    #[msg("A batch must contain between 1 and MAX_ON_CALL_BATCH_SIZE messages.")]
+    InvalidBatchSize,
+    #[msg("The message nonce is not above the last nonce processed for its sender.")]
+    ReplayedMessage,
+    #[msg("Batch sender nonce accounts are missing or duplicated.")]
+    InvalidSenderNonceAccounts,
+    #[msg("The message does not start with a known action.")]
+    UnknownAction,
+    #[msg("The action's arguments are missing or malformed.")]
+    InvalidActionArguments,
+    #[msg("The recipient accounts are missing or do not match the wallet the message names.")]
+    InvalidRecipient,
+    #[msg("The amount exceeds the supplied or borrowed balance.")]
+    InsufficientBalance,
+    #[msg("Arithmetic overflow.")]
+    ArithmeticOverflow,
+    #[msg("The recipient token account is not the recipient's associated token account for mint_account.")]
+    RecipientMintMismatch,
+    #[msg("The message to store exceeds MAX_MESSAGE_LEN (256 bytes).")]
+    MessageTooLong,
+    #[msg("pda_ata holds less than the payout amount.")]
+    InsufficientVaultBalance,
+    #[msg("pda_ata or the recipient token account cannot take part in the payout.")]
+    InvalidPayoutAccounts,
+}
+
+impl ErrorCode {
+    /// Every variant in declaration order, for clients building lookup tables.
+    pub const ALL: [ErrorCode; 19] = [
+        ErrorCode::InvalidDataFormat,
+        ErrorCode::AlreadyInitialized,
+        ErrorCode::InvalidTssSigner,
+        ErrorCode::InvalidSignature,
+        ErrorCode::Unauthorized,
+        ErrorCode::InvalidAcceptedVersions,
+        ErrorCode::UnsupportedMessageVersion,
+        ErrorCode::InvalidBatchSize,
+        ErrorCode::ReplayedMessage,
+        ErrorCode::InvalidSenderNonceAccounts,
+        ErrorCode::UnknownAction,
+        ErrorCode::InvalidActionArguments,
+        ErrorCode::InvalidRecipient,
+        ErrorCode::InsufficientBalance,
+        ErrorCode::ArithmeticOverflow,
+        ErrorCode::RecipientMintMismatch,
+        ErrorCode::MessageTooLong,
+        ErrorCode::InsufficientVaultBalance,
+        ErrorCode::InvalidPayoutAccounts,
+    ];
+
+    /// Stable numeric code, matching the one Anchor reports on-chain.
+    pub fn code(&self) -> u32 {
+        anchor_lang::error::ERROR_CODE_OFFSET + *self as u32
+    }
+
+    /// Short machine-readable name that stays fixed when `#[msg]` text changes.
+    pub fn slug(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidDataFormat => "invalid_data_format",
+            ErrorCode::AlreadyInitialized => "already_initialized",
+            ErrorCode::InvalidTssSigner => "invalid_tss_signer",
+            ErrorCode::InvalidSignature => "invalid_signature",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::InvalidAcceptedVersions => "invalid_accepted_versions",
+            ErrorCode::UnsupportedMessageVersion => "unsupported_message_version",
+            ErrorCode::InvalidBatchSize => "invalid_batch_size",
+            ErrorCode::ReplayedMessage => "replayed_message",
+            ErrorCode::InvalidSenderNonceAccounts => "invalid_sender_nonce_accounts",
+            ErrorCode::UnknownAction => "unknown_action",
+            ErrorCode::InvalidActionArguments => "invalid_action_arguments",
+            ErrorCode::InvalidRecipient => "invalid_recipient",
+            ErrorCode::InsufficientBalance => "insufficient_balance",
+            ErrorCode::ArithmeticOverflow => "arithmetic_overflow",
+            ErrorCode::RecipientMintMismatch => "recipient_mint_mismatch",
+            ErrorCode::MessageTooLong => "message_too_long",
+            ErrorCode::InsufficientVaultBalance => "insufficient_vault_balance",
+            ErrorCode::InvalidPayoutAccounts => "invalid_payout_accounts",
+        }
+    }
 }
@@ -1,16 +1,22 @@
+// The IDL instructions #[program] generates for deposit_contract resize their account
+// with the deprecated AccountInfo::realloc. They are crate-root items outside our
+// source, so this is the narrowest level the allow can sit at; nothing else needs it.
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
-use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken};
 use anchor_lang::solana_program::{
-    instruction::{AccountMeta, Instruction},
-    program::invoke,
+    keccak, program::MAX_RETURN_DATA, secp256k1_recover::secp256k1_recover,
+    program::get_return_data, sysvar::instructions::get_instruction_relative,
+    hash, instruction::{AccountMeta, Instruction}, program::{invoke, invoke_signed},
 };
 
 declare_id!("GcMxmSpPFZqWnFxv1HamYwiq5JAuDmuA2akFiAwu5S2d");
 
-// Gateway program ID from .env.example
-pub const GATEWAY_PROGRAM_ID: &str = "ZETAjseVjuFsxdRxo6MmTCvqFwb3ZHUx56Co3vCmGis";
+// Gateway program ID from .env.example; initialize records it in ContractState
+pub const GATEWAY_PROGRAM_ID: Pubkey = pubkey!("ZETAjseVjuFsxdRxo6MmTCvqFwb3ZHUx56Co3vCmGis");
 // USDC SPL token from .env.example
 pub const USDC_SPL_MINT: &str = "Gh9ZwEmdLJ8DscKNTkTqPbNwLNNBjuSzaG9Vp2KGtKJr";
 
@@ -18,6 +24,54 @@ pub const USDC_SPL_MINT: &str = "Gh9ZwEmdLJ8DscKNTkTqPbNwLNNBjuSzaG9Vp2KGtKJr";
 pub const GAS_LIMIT: u64 = 5_000_000;
 // Minimum deposit fee (0.002 SOL in lamports)
 pub const DEPOSIT_FEE: u64 = 2_000_000;
+// Basis-point denominator for percentage fees (10_000 bps = 100%)
+pub const BPS_DENOMINATOR: u64 = 10_000;
+// Maximum number of asset configs returned by one get_asset_configs call
+// (read-only, one account each; bounded by return data rather than CU)
+pub const MAX_ASSET_CONFIGS_PER_QUERY: usize = 16;
+// Maximum items in one repay_batch: each costs 6 accounts plus a transfer CPI and a
+// gateway CPI, so 4 items (24 + 7 fixed accounts) stays within a legacy transaction
+pub const MAX_REPAY_BATCH_SIZE: usize = 4;
+// Maximum items in one add_supported_assets_batch: each creates a PDA via CPI
+pub const MAX_ADD_ASSET_BATCH_SIZE: usize = 8;
+// Maximum length of the incident metadata attached to circuit breaker events
+pub const MAX_INCIDENT_METADATA_LEN: usize = 100;
+// Maximum length of an asset's display metadata URI
+pub const MAX_METADATA_URI_LEN: usize = 200;
+// EIP-191 personal_sign prefix for a 32-byte payload (the Solana pubkey)
+pub const EVM_SIGNED_MESSAGE_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n32";
+// Decimals the lending protocol normalizes every asset amount to
+pub const NORMALIZED_DECIMALS: u8 = 18;
+// Seconds a relayer should wait before retrying a reverted operation, until configured
+pub const DEFAULT_RETRY_AFTER_SECONDS: u32 = 60;
+// Minimum seconds between pause/unpause toggles, unless the authority forces one
+pub const MIN_PAUSE_TOGGLE_INTERVAL: i64 = 60;
+// Oracle prices older than this many seconds are rejected until configured otherwise
+pub const DEFAULT_MAX_PRICE_AGE: i64 = 60;
+// Registry bounds, so counters and any future lists stay within account space and CU
+pub const MAX_DESTINATION_CHAINS: u16 = 16;
+// Programs that may invoke deposits via CPI
+pub const MAX_ALLOWED_CALLER_PROGRAMS: usize = 4;
+// Keys that may pause and rescue, but not change parameters
+pub const MAX_EMERGENCY_ADMINS: usize = 4;
+// Decimals of native SOL (lamports); its AssetConfig is keyed by the System Program ID
+pub const NATIVE_SOL_DECIMALS: u8 = 9;
+// Each supported asset is bridged as one ZRC-20 on ZetaChain
+pub const MAX_ZRC20_ASSETS: u16 = 32;
+// (asset_config, collateral_position) pairs borrow_cross_chain reads from remaining_accounts
+pub const MAX_COLLATERAL_POSITIONS: usize = 8;
+// Bits of ContractState.paused_operations; each blocks one family of entrypoints
+pub const PAUSE_DEPOSIT: u32 = 1 << 0; // deposit_sol, deposit_sol_for, deposit_spl_token
+pub const PAUSE_REPAY: u32 = 1 << 1; // repay_sol, repay_spl_token, repay_batch
+pub const PAUSE_BORROW: u32 = 1 << 2; // borrow_cross_chain (and can_borrow's answer)
+pub const PAUSE_WITHDRAW: u32 = 1 << 3; // withdraw_cross_chain
+pub const PAUSABLE_OPERATIONS: u32 = PAUSE_DEPOSIT | PAUSE_REPAY | PAUSE_BORROW | PAUSE_WITHDRAW;
+// Recipient length on EVM destination chains
+pub const EVM_ADDRESS_LEN: u8 = 20;
+// Length of the domain separator prepended to signed EVM link payloads
+pub const DOMAIN_SEPARATOR_LEN: usize = 32;
+// Deepest beneficiary allowlist tree a deposit proof may walk (2^32 leaves)
+pub const MAX_BENEFICIARY_PROOF_LEN: usize = 32;
 
 // RevertOptions struct to match Gateway interface
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
@@ -29,6 +83,76 @@ pub struct RevertOptions {
     pub on_revert_gas_limit: u64,    // Gas limit for revert operation
 }
 
+// Single entry of a repay_batch call
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RepayItem {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub on_behalf_of: [u8; 20], // ZetaChain address
+}
+
+// Admin changes awaiting confirmation, returned by get_pending_changes.
+// effective_at is a unix timestamp; 0 means the change applies as soon as it is confirmed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PendingChanges {
+    pub pending_authority: Option<Pubkey>,
+    pub authority_effective_at: i64,
+    pub pending_lending_protocol_address: Option<[u8; 20]>,
+    pub lending_protocol_effective_at: i64,
+}
+
+// Single entry of an add_supported_assets_batch call
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct NewAssetItem {
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub is_native: bool,
+    pub metadata_uri: String,
+}
+
+// Accounts each add_supported_assets_batch item consumes from remaining_accounts:
+// asset_config, mint
+pub const ADD_ASSET_BATCH_ACCOUNTS_PER_ITEM: usize = 2;
+
+// Accounts each repay_batch item consumes from remaining_accounts:
+// asset_config, mint, user_token_account, contract_token_account,
+// gateway_whitelist_entry, gateway_token_account, zrc20_config
+pub const REPAY_BATCH_ACCOUNTS_PER_ITEM: usize = 7;
+
+// Operation categories that can carry their own revert policy
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperationType {
+    Deposit, // deposit_and_call paths (deposits and repays)
+    Call,    // message-only paths (cross-chain borrow and withdraw)
+}
+
+// Operations that charge a fee, as reported by FeeAccrued
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeOperation {
+    Deposit, // deposit_sol and deposit_sol_for
+    Repay,   // repay_sol
+}
+
+// Per-operation revert configuration used to build RevertOptions
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct RevertPolicy {
+    pub call_on_revert: bool,      // Revert (return funds) when true, abort otherwise
+    pub revert_address: [u8; 20],  // Ethereum address for revert destination
+    pub abort_address: [u8; 20],   // Address to abort transaction to
+    pub retry_after_seconds: u32,  // Relayer retry hint; 0 means do not retry. Raise under congestion
+}
+
+impl Default for RevertPolicy {
+    fn default() -> Self {
+        Self {
+            call_on_revert: true,
+            revert_address: [0u8; 20],
+            abort_address: [0u8; 20],
+            retry_after_seconds: DEFAULT_RETRY_AFTER_SECONDS,
+        }
+    }
+}
+
 #[program]
 pub mod deposit_contract {
     use super::*;
@@ -39,22 +163,55 @@ pub mod deposit_contract {
         lending_protocol_address: [u8; 20], // ZetaChain address (20 bytes)
         zeta_chain_id: u64,
     ) -> Result<()> {
-        let contract_state = &mut ctx.accounts.contract_state;
-        contract_state.authority = ctx.accounts.authority.key();
-        contract_state.lending_protocol_address = lending_protocol_address;
-        contract_state.zeta_chain_id = zeta_chain_id;
-        contract_state.is_paused = false;
-        contract_state.bump = ctx.bumps.contract_state;
+        init_contract_state(
+            &mut ctx.accounts.contract_state,
+            ctx.accounts.authority.key(),
+            lending_protocol_address,
+            zeta_chain_id,
+            ctx.bumps.contract_state,
+        )?;
+        set_secondary_gateway(&mut ctx.accounts.contract_state, ctx.accounts.secondary_gateway_program.as_ref())
+    }
 
-        // TODO: Validate lending_protocol_address is a valid ZetaChain address
-        // TODO: Validate zeta_chain_id matches expected ZetaChain network (7000 mainnet, 7001 testnet)
-        // TODO: Consider adding Gateway program validation
+    /// Production initialization: like initialize, but the treasury, abort address,
+    /// gateway program and native asset must all be supplied up front, so a
+    /// deployment cannot go live half-configured. The native asset must be SOL as
+    /// register_native_sol fixes it: the System Program ID mint with NATIVE_SOL_DECIMALS.
+    /// Keep initialize for dev setups.
+    pub fn initialize_strict(
+        ctx: Context<InitializeStrict>,
+        lending_protocol_address: [u8; 20], // ZetaChain address (20 bytes)
+        zeta_chain_id: u64,
+        treasury: Pubkey,
+        abort_address: [u8; 20], // Abort destination for both revert policies
+        native_asset: NewAssetItem,
+    ) -> Result<()> {
+        require!(
+            treasury != Pubkey::default()
+                && abort_address != [0u8; 20]
+                && native_asset.is_native
+                && native_asset.mint == system_program::ID
+                && native_asset.decimals == NATIVE_SOL_DECIMALS,
+            DepositContractError::MissingStrictConfig
+        );
 
-        emit!(ContractInitialized {
-            authority: ctx.accounts.authority.key(),
+        init_contract_state(
+            &mut ctx.accounts.contract_state,
+            ctx.accounts.authority.key(),
             lending_protocol_address,
             zeta_chain_id,
-        });
+            ctx.bumps.contract_state,
+        )?;
+
+        let contract_state = &mut ctx.accounts.contract_state;
+        contract_state.treasury = treasury;
+        contract_state.gateway_program_id = ctx.accounts.gateway_program.key();
+        set_secondary_gateway(contract_state, ctx.accounts.secondary_gateway_program.as_ref())?;
+        contract_state.deposit_revert_policy.abort_address = abort_address;
+        contract_state.call_revert_policy.abort_address = abort_address;
+
+        let asset_config = register_asset(contract_state, native_asset, ctx.bumps.native_asset_config)?;
+        ctx.accounts.native_asset_config.set_inner(asset_config);
 
         Ok(())
     }
@@ -65,23 +222,94 @@ pub mod deposit_contract {
         mint: Pubkey,
         decimals: u8,
         is_native: bool,
+        metadata_uri: String, // Logo/metadata link for clients, may be empty
     ) -> Result<()> {
-        let asset_config = &mut ctx.accounts.asset_config;
-        asset_config.mint = mint;
-        asset_config.decimals = decimals;
-        asset_config.is_native = is_native;
-        asset_config.is_supported = true;
-        asset_config.bump = ctx.bumps.asset_config;
-
-        // TODO: Validate mint is a valid SPL token mint address
-        // TODO: Verify decimals matches the actual token mint decimals
+        // A fresh asset_config has no mint yet; a set one means the mint is already listed
+        require!(
+            ctx.accounts.asset_config.mint == Pubkey::default(),
+            DepositContractError::AssetAlreadySupported
+        );
+
+        check_new_mint(&ctx.accounts.contract_state, &ctx.accounts.mint_account, decimals)?;
+
         // TODO: Consider adding whitelist validation for supported tokens
-        // TODO: Add maximum number of supported assets limit
 
-        emit!(AssetAdded {
-            mint,
-            decimals,
-            is_native,
+        let asset_config = register_asset(
+            &mut ctx.accounts.contract_state,
+            NewAssetItem { mint, decimals, is_native, metadata_uri },
+            ctx.bumps.asset_config,
+        )?;
+        ctx.accounts.asset_config.set_inner(asset_config);
+
+        Ok(())
+    }
+
+    /// Register native SOL. Fixes the sentinel mint (System Program ID), decimals and
+    /// is_native so they cannot be mistyped through add_supported_asset.
+    pub fn register_native_sol(ctx: Context<RegisterNativeSol>, metadata_uri: String) -> Result<()> {
+        require!(
+            ctx.accounts.asset_config.mint == Pubkey::default(),
+            DepositContractError::AssetAlreadySupported
+        );
+
+        let asset_config = register_asset(
+            &mut ctx.accounts.contract_state,
+            NewAssetItem {
+                mint: system_program::ID,
+                decimals: NATIVE_SOL_DECIMALS,
+                is_native: true,
+                metadata_uri,
+            },
+            ctx.bumps.asset_config,
+        )?;
+        ctx.accounts.asset_config.set_inner(asset_config);
+
+        Ok(())
+    }
+
+    /// Add several supported assets in one transaction. Per item, remaining_accounts holds
+    /// [asset_config, mint]: the (uncreated) asset_config PDA and the token mint, checked
+    /// like add_supported_asset's mint_account. Any failure reverts all; the failing
+    /// item's index is logged so the operator can fix that entry.
+    pub fn add_supported_assets_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AddSupportedAssetsBatch<'info>>,
+        items: Vec<NewAssetItem>,
+    ) -> Result<()> {
+        require!(items.len() <= MAX_ADD_ASSET_BATCH_SIZE, DepositContractError::BatchTooLarge);
+        require!(!items.is_empty(), DepositContractError::InvalidAmount);
+        require_eq!(
+            ctx.remaining_accounts.len(),
+            items.len() * ADD_ASSET_BATCH_ACCOUNTS_PER_ITEM,
+            DepositContractError::InvalidBatchAccounts
+        );
+
+        for (index, (item, accounts)) in items
+            .into_iter()
+            .zip(ctx.remaining_accounts.chunks(ADD_ASSET_BATCH_ACCOUNTS_PER_ITEM))
+            .enumerate()
+        {
+            create_asset_config(
+                &mut ctx.accounts.contract_state,
+                &ctx.accounts.authority,
+                &ctx.accounts.system_program,
+                &accounts[0],
+                &accounts[1],
+                item,
+            )
+            .inspect_err(|_| msg!("add_supported_assets_batch: item {} failed", index))?;
+        }
+
+        Ok(())
+    }
+
+    /// Create the contract-owned vault ATA for a supported asset so deposits can reuse it
+    pub fn create_asset_vault(ctx: Context<CreateAssetVault>) -> Result<()> {
+        require!(ctx.accounts.asset_config.is_supported, DepositContractError::UnsupportedAsset);
+        require!(!ctx.accounts.asset_config.is_native, DepositContractError::UseDepositSol);
+
+        emit!(AssetVaultCreated {
+            mint: ctx.accounts.mint.key(),
+            vault: ctx.accounts.contract_token_account.key(),
         });
 
         Ok(())
@@ -99,6 +327,226 @@ pub mod deposit_contract {
         Ok(())
     }
 
+    /// Close a removed asset's config PDA and refund its rent to the authority
+    pub fn close_asset_config(ctx: Context<CloseAssetConfig>) -> Result<()> {
+        let asset_config = &ctx.accounts.asset_config;
+        require!(!asset_config.is_supported, DepositContractError::AssetStillSupported);
+
+        // The vault ATA is optional (create_asset_vault), but if it exists it must be empty
+        let vault = &ctx.accounts.contract_token_account;
+        if !vault.data_is_empty() {
+            let vault_balance = TokenAccount::try_deserialize(&mut &vault.try_borrow_data()?[..])?.amount;
+            require!(vault_balance == 0, DepositContractError::VaultNotEmpty);
+        }
+
+        let contract_state = &mut ctx.accounts.contract_state;
+        contract_state.asset_count = contract_state.asset_count.saturating_sub(1);
+
+        emit!(AssetConfigClosed {
+            mint: asset_config.mint,
+            refunded_lamports: asset_config.to_account_info().lamports(),
+        });
+
+        Ok(())
+    }
+
+    /// Set the display metadata URI of a supported asset
+    pub fn set_asset_metadata_uri(
+        ctx: Context<UpdateAssetConfig>,
+        metadata_uri: String,
+    ) -> Result<()> {
+        require!(metadata_uri.len() <= MAX_METADATA_URI_LEN, DepositContractError::MetadataUriTooLong);
+
+        let asset_config = &mut ctx.accounts.asset_config;
+        asset_config.metadata_uri = metadata_uri;
+
+        emit_asset_config_updated(asset_config);
+
+        Ok(())
+    }
+
+    /// Reversibly enable or disable an asset, keeping the rest of its config
+    pub fn set_asset_supported(ctx: Context<UpdateAssetConfig>, is_supported: bool) -> Result<()> {
+        let asset_config = &mut ctx.accounts.asset_config;
+        asset_config.is_supported = is_supported;
+
+        emit_asset_config_updated(asset_config);
+
+        Ok(())
+    }
+
+    /// Allow or freeze repays of an asset (e.g. while delisting)
+    pub fn set_repay_enabled(ctx: Context<UpdateAssetConfig>, repay_enabled: bool) -> Result<()> {
+        let asset_config = &mut ctx.accounts.asset_config;
+        asset_config.repay_enabled = repay_enabled;
+
+        emit_asset_config_updated(asset_config);
+
+        Ok(())
+    }
+
+    /// Require deposits of an asset to be exact multiples of its normalization step
+    pub fn set_require_exact_amount(
+        ctx: Context<UpdateAssetConfig>,
+        require_exact_amount: bool,
+    ) -> Result<()> {
+        let asset_config = &mut ctx.accounts.asset_config;
+        asset_config.require_exact_amount = require_exact_amount;
+
+        emit_asset_config_updated(asset_config);
+
+        Ok(())
+    }
+
+    /// Reject deposits and repays of an asset below `min_deposit` base units (zero disables)
+    pub fn set_min_deposit(ctx: Context<UpdateAssetConfig>, min_deposit: u64) -> Result<()> {
+        let asset_config = &mut ctx.accounts.asset_config;
+        asset_config.min_deposit = min_deposit;
+
+        emit_asset_config_updated(asset_config);
+
+        Ok(())
+    }
+
+    /// Limit the running total of an asset's deposits (zero means unlimited)
+    pub fn set_deposit_cap(ctx: Context<UpdateAssetConfig>, deposit_cap: u64) -> Result<()> {
+        let asset_config = &mut ctx.accounts.asset_config;
+        asset_config.deposit_cap = deposit_cap;
+
+        emit_asset_config_updated(asset_config);
+
+        Ok(())
+    }
+
+    /// Route an asset's deposits and repays to a non-default lending protocol (zero resets to default)
+    pub fn set_asset_lending_protocol(
+        ctx: Context<UpdateAssetConfig>,
+        lending_protocol_address: [u8; 20],
+    ) -> Result<()> {
+        let asset_config = &mut ctx.accounts.asset_config;
+        asset_config.lending_protocol_address = lending_protocol_address;
+
+        emit_asset_config_updated(asset_config);
+
+        Ok(())
+    }
+
+    /// Pay an asset's fees to `fee_recipient` instead of the fee vault, e.g. for a
+    /// partner revenue share (default resets to the fee vault). Only SOL deposits and
+    /// repays charge a fee today.
+    pub fn set_asset_fee_recipient(ctx: Context<UpdateAssetConfig>, fee_recipient: Pubkey) -> Result<()> {
+        let asset_config = &mut ctx.accounts.asset_config;
+        asset_config.fee_recipient = fee_recipient;
+
+        emit_asset_config_updated(asset_config);
+
+        Ok(())
+    }
+
+    /// Record the ZRC-20 representing this asset on ZetaChain, which the self-borrow guard
+    /// compares borrowed assets against (zero when unknown)
+    pub fn set_asset_zrc20(ctx: Context<UpdateAssetConfig>, zrc20: [u8; 20]) -> Result<()> {
+        let asset_config = &mut ctx.accounts.asset_config;
+        asset_config.zrc20 = zrc20;
+
+        emit_asset_config_updated(asset_config);
+
+        Ok(())
+    }
+
+    /// Value an asset's collateral in USD through a Pyth price account (default removes
+    /// the feed, so the asset counts at face value in NORMALIZED_DECIMALS units)
+    pub fn set_asset_price_feed(ctx: Context<UpdateAssetConfig>, price_feed: Pubkey) -> Result<()> {
+        let asset_config = &mut ctx.accounts.asset_config;
+        asset_config.price_feed = price_feed;
+
+        emit_asset_config_updated(asset_config);
+
+        Ok(())
+    }
+
+    /// Count `liquidation_threshold` basis points of an asset's deposits as collateral
+    /// for borrow_cross_chain's health check (zero counts none)
+    pub fn set_liquidation_threshold(
+        ctx: Context<UpdateAssetConfig>,
+        liquidation_threshold: u16,
+    ) -> Result<()> {
+        let asset_config = &mut ctx.accounts.asset_config;
+        check_risk_params(asset_config.ltv, liquidation_threshold)?;
+        asset_config.liquidation_threshold = liquidation_threshold;
+
+        emit_asset_config_updated(asset_config);
+
+        Ok(())
+    }
+
+    /// Set both risk parameters of an asset, in basis points: `ltv` of its deposits may be
+    /// borrowed against, `liquidation_threshold` counts toward the health factor
+    pub fn set_risk_params(ctx: Context<UpdateAssetConfig>, ltv: u16, liquidation_threshold: u16) -> Result<()> {
+        check_risk_params(ltv, liquidation_threshold)?;
+        let asset_config = &mut ctx.accounts.asset_config;
+        asset_config.ltv = ltv;
+        asset_config.liquidation_threshold = liquidation_threshold;
+
+        emit_asset_config_updated(asset_config);
+
+        Ok(())
+    }
+
+    /// Overwrite a recorded decimals value with the mint's actual decimals
+    pub fn resync_asset_decimals(ctx: Context<ResyncAssetDecimals>) -> Result<()> {
+        let asset_config = &mut ctx.accounts.asset_config;
+        asset_config.decimals = ctx.accounts.mint.decimals;
+
+        emit_asset_config_updated(asset_config);
+
+        Ok(())
+    }
+
+    /// Repoint the default lending protocol and every asset route at `old_address`
+    /// to `new_address` in one transaction, e.g. after a redeploy on ZetaChain.
+    /// remaining_accounts: the asset_config PDAs to rotate (writable); routes at
+    /// other addresses are left alone.
+    pub fn rotate_lending_protocol<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RotateLendingProtocol<'info>>,
+        old_address: [u8; 20],
+        new_address: [u8; 20],
+    ) -> Result<()> {
+        require!(
+            new_address != [0u8; 20] && new_address != old_address,
+            DepositContractError::InvalidLendingProtocolAddress
+        );
+        require!(
+            ctx.remaining_accounts.len() <= usize::from(MAX_ZRC20_ASSETS),
+            DepositContractError::BatchTooLarge
+        );
+
+        let contract_state = &mut ctx.accounts.contract_state;
+        let default_rotated = contract_state.lending_protocol_address == old_address;
+        if default_rotated {
+            contract_state.lending_protocol_address = new_address;
+        }
+
+        let mut routes_rotated: u16 = 0;
+        for account in ctx.remaining_accounts {
+            let mut asset_config = Account::<AssetConfig>::try_from(account)?;
+            if asset_config.lending_protocol_address == old_address {
+                asset_config.lending_protocol_address = new_address;
+                asset_config.exit(&crate::ID)?;
+                routes_rotated += 1;
+            }
+        }
+
+        emit!(LendingProtocolRotated {
+            old_address,
+            new_address,
+            default_rotated,
+            routes_rotated,
+        });
+
+        Ok(())
+    }
+
     /// Update lending protocol address on ZetaChain
     pub fn update_lending_protocol_address(
         ctx: Context<UpdateLendingProtocolAddress>,
@@ -113,6 +561,13 @@ pub mod deposit_contract {
             DepositContractError::InvalidChainId
         );
 
+        // Re-setting the current address would only emit a misleading update event
+        require!(
+            new_lending_protocol_address != [0u8; 20]
+                && new_lending_protocol_address != contract_state.lending_protocol_address,
+            DepositContractError::InvalidLendingProtocolAddress
+        );
+
         let old_address = contract_state.lending_protocol_address;
         contract_state.lending_protocol_address = new_lending_protocol_address;
 
@@ -125,62 +580,97 @@ pub mod deposit_contract {
         Ok(())
     }
 
-    /// Deposit SOL to the lending protocol on ZetaChain
+    /// Deposit SOL to the lending protocol on ZetaChain. Of `amount`, DEPOSIT_FEE is kept
+    /// on Solana and `amount - DEPOSIT_FEE` is forwarded to the gateway and supplied.
+    /// Returns the framed message handed to the gateway, so clients can check what was sent.
     pub fn deposit_sol(
         ctx: Context<DepositSol>,
         amount: u64,
         on_behalf_of: [u8; 20], // ZetaChain address
-    ) -> Result<()> {
-        require!(!ctx.accounts.contract_state.is_paused, DepositContractError::ContractPaused);
-        require!(amount > 0, DepositContractError::InvalidAmount);
-        require!(amount >= DEPOSIT_FEE, DepositContractError::InsufficientDepositFee);
-
-        // TODO: Add minimum deposit amount validation beyond just deposit fee
-        // TODO: Validate on_behalf_of is a valid ZetaChain address format  
-        // TODO: Check user has sufficient SOL balance for the deposit + transaction fees
-
-        // Create message for SimpleLendingProtocol.onCall()
-        let message = create_supply_message(on_behalf_of)?;
-
-        // Invoke gateway deposit_and_call
-        invoke_gateway_deposit_and_call(
-            &ctx.accounts.gateway_program.to_account_info(),
-            &ctx.accounts.user.to_account_info(),
-            &ctx.accounts.contract_state.to_account_info(),
+        beneficiary_proof: Vec<[u8; 32]>, // Empty unless a beneficiary allowlist root is set
+    ) -> Result<Vec<u8>> {
+        let owner = ctx.accounts.user.key();
+        process_deposit_sol(
+            &mut ctx.accounts.contract_state,
+            &ctx.accounts.user,
+            &ctx.accounts.evm_link,
+            &ctx.accounts.depositor_whitelist_entry,
+            &mut ctx.accounts.collateral_position,
+            ctx.bumps.collateral_position,
+            &ctx.accounts.fee_vault,
+            &ctx.accounts.native_asset_config,
+            ctx.accounts.fee_recipient.as_ref(),
+            &ctx.accounts.gateway_program,
+            &ctx.accounts.gateway_pda,
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.system_program,
+            ctx.remaining_accounts,
             amount,
-            ctx.accounts.contract_state.lending_protocol_address,
-            message,
-        )?;
+            on_behalf_of,
+            owner,
+            &beneficiary_proof,
+        )
+    }
 
-        emit!(DepositInitiated {
-            user: ctx.accounts.user.key(),
-            asset: system_program::ID, // SOL represented as System Program ID
+    /// Sponsored SOL deposit: the signer pays, but the deposit is attributed to owner.
+    /// Returns the forwarded message, as deposit_sol does.
+    pub fn deposit_sol_for(
+        ctx: Context<DepositSolFor>,
+        amount: u64,
+        on_behalf_of: [u8; 20], // ZetaChain address
+        owner: Pubkey,          // Solana beneficiary recorded in DepositInitiated
+        beneficiary_proof: Vec<[u8; 32]>,
+    ) -> Result<Vec<u8>> {
+        process_deposit_sol(
+            &mut ctx.accounts.contract_state,
+            &ctx.accounts.payer,
+            &ctx.accounts.evm_link,
+            &ctx.accounts.depositor_whitelist_entry,
+            &mut ctx.accounts.collateral_position,
+            ctx.bumps.collateral_position,
+            &ctx.accounts.fee_vault,
+            &ctx.accounts.native_asset_config,
+            ctx.accounts.fee_recipient.as_ref(),
+            &ctx.accounts.gateway_program,
+            &ctx.accounts.gateway_pda,
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.system_program,
+            ctx.remaining_accounts,
             amount,
             on_behalf_of,
-        });
-
-        Ok(())
+            owner,
+            &beneficiary_proof,
+        )
     }
 
-    /// Deposit SPL tokens to the lending protocol on ZetaChain
+    /// Deposit SPL tokens to the lending protocol on ZetaChain.
+    /// Returns the framed message handed to the gateway.
     pub fn deposit_spl_token(
         ctx: Context<DepositSplToken>,
         amount: u64,
         on_behalf_of: [u8; 20],
-    ) -> Result<()> {
-        require!(!ctx.accounts.contract_state.is_paused, DepositContractError::ContractPaused);
+        beneficiary_proof: Vec<[u8; 32]>,
+    ) -> Result<Vec<u8>> {
+        check_operation_active(&ctx.accounts.contract_state, PAUSE_DEPOSIT)?;
         require!(amount > 0, DepositContractError::InvalidAmount);
         require!(ctx.accounts.asset_config.is_supported, DepositContractError::UnsupportedAsset);
         require!(!ctx.accounts.asset_config.is_native, DepositContractError::UseDepositSol);
+        normalize_amount(amount, ctx.accounts.asset_config.decimals)?;
+        check_exact_amount(&ctx.accounts.asset_config, amount)?;
+        check_min_deposit(amount, ctx.accounts.asset_config.min_deposit)?;
+        check_caller_program(&ctx.accounts.contract_state, &ctx.accounts.instructions_sysvar)?;
+        check_gateway_program(&ctx.accounts.contract_state, &ctx.accounts.gateway_program)?;
+        check_evm_link(&ctx.accounts.contract_state, &ctx.accounts.evm_link, on_behalf_of)?;
+        check_depositor_whitelisted(&ctx.accounts.contract_state, &ctx.accounts.depositor_whitelist_entry)?;
+        check_beneficiary_allowed(&ctx.accounts.contract_state, on_behalf_of, &beneficiary_proof)?;
 
-        // TODO: Add minimum deposit amount validation
         // TODO: Validate on_behalf_of is a valid ZetaChain address format
-        // TODO: Check user has sufficient token balance before transfer
+
+        record_deposited(&mut ctx.accounts.asset_config, amount)?;
 
         // Transfer tokens from user to contract
         // TODO: Add slippage protection for token transfers
-        // TODO: Validate transfer amount against user's token balance
-        // TODO: Consider adding transfer fee handling for tokens with transfer fees
+        let balance_before = ctx.accounts.contract_token_account.amount;
         let transfer_instruction = Transfer {
             from: ctx.accounts.user_token_account.to_account_info(),
             to: ctx.accounts.contract_token_account.to_account_info(),
@@ -195,27 +685,55 @@ pub mod deposit_contract {
             amount,
         )?;
 
+        // Credit and forward what actually arrived; fee-charging tokens deliver less than amount
+        ctx.accounts.contract_token_account.reload()?;
+        let received = received_amount(balance_before, ctx.accounts.contract_token_account.amount)?;
+        record_received(&mut ctx.accounts.asset_config, received)?;
+
         // Create message for SimpleLendingProtocol.onCall()
         let message = create_supply_message(on_behalf_of)?;
+        let lending_protocol = resolve_lending_protocol(&ctx.accounts.contract_state, &ctx.accounts.asset_config);
 
         // Invoke gateway deposit_spl_token_and_call
         invoke_gateway_deposit_spl_token_and_call(
-            &ctx.accounts.gateway_program.to_account_info(),
-            &ctx.accounts.contract_token_account.to_account_info(),
-            &ctx.accounts.mint.to_account_info(),
-            amount,
-            ctx.accounts.contract_state.lending_protocol_address,
-            message,
+            GatewaySplDeposit {
+                gateway_program: &ctx.accounts.gateway_program.to_account_info(),
+                contract_state: &ctx.accounts.contract_state.to_account_info(),
+                gateway_pda: &ctx.accounts.gateway_pda.to_account_info(),
+                whitelist_entry: &ctx.accounts.gateway_whitelist_entry.to_account_info(),
+                mint: &ctx.accounts.mint.to_account_info(),
+                token_program: &ctx.accounts.token_program.to_account_info(),
+                from: &ctx.accounts.contract_token_account.to_account_info(),
+                to: &ctx.accounts.gateway_token_account.to_account_info(),
+                system_program: &ctx.accounts.system_program.to_account_info(),
+            },
+            ctx.accounts.contract_state.bump,
+            received,
+            lending_protocol,
+            message.clone(),
+            &ctx.accounts.contract_state.deposit_revert_policy,
+        )?;
+        record_forwarded(&mut ctx.accounts.asset_config, received)?;
+        credit_collateral(
+            &mut ctx.accounts.collateral_position,
+            ctx.accounts.user.key(),
+            ctx.accounts.mint.key(),
+            ctx.bumps.collateral_position,
+            received,
         )?;
 
         emit!(DepositInitiated {
             user: ctx.accounts.user.key(),
+            owner: ctx.accounts.user.key(),
             asset: ctx.accounts.mint.key(),
-            amount,
+            amount: received,
             on_behalf_of,
+            lending_protocol,
+            // The vault tokens are sent by the contract PDA, so it is the gateway-side sender
+            derived_evm_sender: derive_evm_sender(&ctx.accounts.contract_state.key()),
         });
 
-        Ok(())
+        Ok(message)
     }
 
     /// Repay borrowed SOL to ZetaChain lending protocol
@@ -224,27 +742,59 @@ pub mod deposit_contract {
         amount: u64,
         on_behalf_of: [u8; 20],
     ) -> Result<()> {
-        require!(!ctx.accounts.contract_state.is_paused, DepositContractError::ContractPaused);
+        check_operation_active(&ctx.accounts.contract_state, PAUSE_REPAY)?;
         require!(amount > 0, DepositContractError::InvalidAmount);
-        require!(amount >= DEPOSIT_FEE, DepositContractError::InsufficientDepositFee);
+        check_min_deposit(amount, ctx.accounts.contract_state.min_sol_deposit)?;
+        let net_amount = net_of_deposit_fee(amount)?;
+        check_no_token_accounts(ctx.remaining_accounts)?;
+        check_gateway_program(&ctx.accounts.contract_state, &ctx.accounts.gateway_program)?;
+        // An unregistered SOL has no config to disable repays with
+        let native_asset_config = load_native_asset_config(&ctx.accounts.native_asset_config)?;
+        require!(
+            native_asset_config.as_ref().is_none_or(|asset_config| asset_config.repay_enabled),
+            DepositContractError::RepayDisabled
+        );
+        let native_zrc20 = native_asset_config.map_or([0u8; 20], |asset_config| asset_config.zrc20);
+        check_repaid_zrc20_config(native_zrc20, &ctx.accounts.zrc20_config)?;
+        check_lamports_capacity(&ctx.accounts.gateway_pda.to_account_info(), net_amount)?;
 
         // Create message for SimpleLendingProtocol.onCall()
         let message = create_repay_message(on_behalf_of)?;
 
-        // Invoke gateway deposit_and_call with repay message
+        collect_deposit_fee(
+            &ctx.accounts.user,
+            &ctx.accounts.fee_vault,
+            &ctx.accounts.native_asset_config,
+            ctx.accounts.fee_recipient.as_ref(),
+            &ctx.accounts.system_program,
+            FeeOperation::Repay,
+        )?;
+
+        // Invoke gateway deposit_and_call with repay message; debt only shrinks by what arrived
+        let lending_protocol = native_lending_protocol(&ctx.accounts.contract_state, &ctx.accounts.native_asset_config)?;
+        let gateway_balance_before = ctx.accounts.gateway_pda.lamports();
         invoke_gateway_deposit_and_call(
             &ctx.accounts.gateway_program.to_account_info(),
             &ctx.accounts.user.to_account_info(),
-            &ctx.accounts.contract_state.to_account_info(),
-            amount,
-            ctx.accounts.contract_state.lending_protocol_address,
+            &ctx.accounts.gateway_pda.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            net_amount,
+            lending_protocol,
             message,
+            &ctx.accounts.contract_state.deposit_revert_policy,
+            ctx.accounts.contract_state.debug_logging,
         )?;
+        let gateway_received = ctx.accounts.gateway_pda.lamports().saturating_sub(gateway_balance_before);
+        require!(gateway_received >= net_amount, DepositContractError::DepositFailed);
+
+        record_sol_split(&mut ctx.accounts.contract_state, net_amount)?;
+        let cleared = record_repaid(&ctx.accounts.zrc20_config, ctx.accounts.borrow_position.as_deref_mut(), on_behalf_of, net_amount)?;
+        release_borrow_capacity(&mut ctx.accounts.contract_state, cleared);
 
         emit!(RepayInitiated {
             user: ctx.accounts.user.key(),
             asset: system_program::ID,
-            amount,
+            amount: net_amount,
             on_behalf_of,
         });
 
@@ -257,12 +807,17 @@ pub mod deposit_contract {
         amount: u64,
         on_behalf_of: [u8; 20],
     ) -> Result<()> {
-        require!(!ctx.accounts.contract_state.is_paused, DepositContractError::ContractPaused);
+        check_operation_active(&ctx.accounts.contract_state, PAUSE_REPAY)?;
         require!(amount > 0, DepositContractError::InvalidAmount);
         require!(ctx.accounts.asset_config.is_supported, DepositContractError::UnsupportedAsset);
         require!(!ctx.accounts.asset_config.is_native, DepositContractError::UseRepaySol);
+        require!(ctx.accounts.asset_config.repay_enabled, DepositContractError::RepayDisabled);
+        check_repaid_zrc20_config(ctx.accounts.asset_config.zrc20, &ctx.accounts.zrc20_config)?;
+        check_min_deposit(amount, ctx.accounts.asset_config.min_deposit)?;
+        check_gateway_program(&ctx.accounts.contract_state, &ctx.accounts.gateway_program)?;
 
         // Transfer tokens from user to contract
+        let balance_before = ctx.accounts.contract_token_account.amount;
         let transfer_instruction = Transfer {
             from: ctx.accounts.user_token_account.to_account_info(),
             to: ctx.accounts.contract_token_account.to_account_info(),
@@ -277,52 +832,203 @@ pub mod deposit_contract {
             amount,
         )?;
 
+        // Forward what actually arrived; fee-charging tokens deliver less than amount
+        ctx.accounts.contract_token_account.reload()?;
+        let received = received_amount(balance_before, ctx.accounts.contract_token_account.amount)?;
+        record_received(&mut ctx.accounts.asset_config, received)?;
+
         // Create message for SimpleLendingProtocol.onCall()
         let message = create_repay_message(on_behalf_of)?;
 
         // Invoke gateway deposit_spl_token_and_call
         invoke_gateway_deposit_spl_token_and_call(
-            &ctx.accounts.gateway_program.to_account_info(),
-            &ctx.accounts.contract_token_account.to_account_info(),
-            &ctx.accounts.mint.to_account_info(),
-            amount,
-            ctx.accounts.contract_state.lending_protocol_address,
+            GatewaySplDeposit {
+                gateway_program: &ctx.accounts.gateway_program.to_account_info(),
+                contract_state: &ctx.accounts.contract_state.to_account_info(),
+                gateway_pda: &ctx.accounts.gateway_pda.to_account_info(),
+                whitelist_entry: &ctx.accounts.gateway_whitelist_entry.to_account_info(),
+                mint: &ctx.accounts.mint.to_account_info(),
+                token_program: &ctx.accounts.token_program.to_account_info(),
+                from: &ctx.accounts.contract_token_account.to_account_info(),
+                to: &ctx.accounts.gateway_token_account.to_account_info(),
+                system_program: &ctx.accounts.system_program.to_account_info(),
+            },
+            ctx.accounts.contract_state.bump,
+            received,
+            resolve_lending_protocol(&ctx.accounts.contract_state, &ctx.accounts.asset_config),
             message,
+            &ctx.accounts.contract_state.deposit_revert_policy,
         )?;
+        record_forwarded(&mut ctx.accounts.asset_config, received)?;
+
+        let cleared = record_repaid(&ctx.accounts.zrc20_config, ctx.accounts.borrow_position.as_deref_mut(), on_behalf_of, received)?;
+        release_borrow_capacity(&mut ctx.accounts.contract_state, cleared);
 
         emit!(RepayInitiated {
             user: ctx.accounts.user.key(),
             asset: ctx.accounts.mint.key(),
-            amount,
+            amount: received,
             on_behalf_of,
         });
 
         Ok(())
     }
 
+    /// Repay several borrowed SPL assets atomically. Per item, remaining_accounts holds
+    /// [asset_config, mint, user_token_account, contract_token_account,
+    /// gateway_whitelist_entry, gateway_token_account, zrc20_config]; any failure reverts all.
+    pub fn repay_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RepayBatch<'info>>,
+        items: Vec<RepayItem>,
+    ) -> Result<()> {
+        require!(items.len() <= MAX_REPAY_BATCH_SIZE, DepositContractError::BatchTooLarge);
+        check_operation_active(&ctx.accounts.contract_state, PAUSE_REPAY)?;
+        require!(!items.is_empty(), DepositContractError::InvalidAmount);
+        require_eq!(
+            ctx.remaining_accounts.len(),
+            items.len() * REPAY_BATCH_ACCOUNTS_PER_ITEM,
+            DepositContractError::InvalidBatchAccounts
+        );
+        check_gateway_program(&ctx.accounts.contract_state, &ctx.accounts.gateway_program)?;
+
+        let contract_state = &ctx.accounts.contract_state;
+        let user = &ctx.accounts.user;
+        let mut total_cleared: u128 = 0;
+
+        for (item, accounts) in items
+            .iter()
+            .zip(ctx.remaining_accounts.chunks(REPAY_BATCH_ACCOUNTS_PER_ITEM))
+        {
+            require!(item.amount > 0, DepositContractError::InvalidAmount);
+
+            let (asset_config_key, _) =
+                Pubkey::find_program_address(&[b"asset_config", item.mint.as_ref()], &crate::ID);
+            require_keys_eq!(accounts[0].key(), asset_config_key, DepositContractError::InvalidBatchAccounts);
+            require_keys_eq!(accounts[1].key(), item.mint, DepositContractError::InvalidBatchAccounts);
+            require_keys_eq!(
+                accounts[2].key(),
+                get_associated_token_address(&user.key(), &item.mint),
+                DepositContractError::InvalidBatchAccounts
+            );
+            require_keys_eq!(
+                accounts[3].key(),
+                get_associated_token_address(&contract_state.key(), &item.mint),
+                DepositContractError::InvalidBatchAccounts
+            );
+            require_keys_eq!(
+                accounts[5].key(),
+                get_associated_token_address(&ctx.accounts.gateway_pda.key(), &item.mint),
+                DepositContractError::InvalidBatchAccounts
+            );
+
+            let mut asset_config = Account::<AssetConfig>::try_from(&accounts[0])?;
+            require!(asset_config.is_supported, DepositContractError::UnsupportedAsset);
+            require!(!asset_config.is_native, DepositContractError::UseRepaySol);
+            require!(asset_config.repay_enabled, DepositContractError::RepayDisabled);
+            check_repaid_zrc20_config(asset_config.zrc20, &accounts[6])?;
+            check_min_deposit(item.amount, asset_config.min_deposit)?;
+
+            // Transfer tokens from user to contract
+            let balance_before = Account::<TokenAccount>::try_from(&accounts[3])?.amount;
+            let transfer_instruction = Transfer {
+                from: accounts[2].clone(),
+                to: accounts[3].clone(),
+                authority: user.to_account_info(),
+            };
+
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    transfer_instruction,
+                ),
+                item.amount,
+            )?;
+
+            let balance_after = Account::<TokenAccount>::try_from(&accounts[3])?.amount;
+            let received = received_amount(balance_before, balance_after)?;
+            record_received(&mut asset_config, received)?;
+
+            // Create message for SimpleLendingProtocol.onCall()
+            let message = create_repay_message(item.on_behalf_of)?;
+
+            invoke_gateway_deposit_spl_token_and_call(
+                GatewaySplDeposit {
+                    gateway_program: &ctx.accounts.gateway_program.to_account_info(),
+                    contract_state: &contract_state.to_account_info(),
+                    gateway_pda: &ctx.accounts.gateway_pda.to_account_info(),
+                    whitelist_entry: &accounts[4],
+                    mint: &accounts[1],
+                    token_program: &ctx.accounts.token_program.to_account_info(),
+                    from: &accounts[3],
+                    to: &accounts[5],
+                    system_program: &ctx.accounts.system_program.to_account_info(),
+                },
+                contract_state.bump,
+                received,
+                resolve_lending_protocol(contract_state, &asset_config),
+                message,
+                &contract_state.deposit_revert_policy,
+            )?;
+            record_forwarded(&mut asset_config, received)?;
+            asset_config.exit(&crate::ID)?;
+            let cleared = record_repaid(&accounts[6], ctx.accounts.borrow_position.as_deref_mut(), item.on_behalf_of, received)?;
+            total_cleared = total_cleared.saturating_add(cleared);
+
+            emit!(RepayInitiated {
+                user: user.key(),
+                asset: item.mint,
+                amount: received,
+                on_behalf_of: item.on_behalf_of,
+            });
+        }
+
+        release_borrow_capacity(&mut ctx.accounts.contract_state, total_cleared);
+
+        Ok(())
+    }
+
     /// Trigger cross-chain borrow and withdrawal to external chain
-    pub fn borrow_cross_chain(
-        ctx: Context<BorrowCrossChain>,
+    ///
+    /// With min_health_factor set, remaining_accounts holds the borrower's collateral as
+    /// (asset_config, collateral_position) pairs, each followed by the asset's Pyth price
+    /// account when it has a price_feed, and the borrow must leave their health factor at
+    /// or above it. Priced collateral counts in USD, the rest at face value; both, like
+    /// debt, in NORMALIZED_DECIMALS units. With reject_self_borrow set, the same accounts
+    /// must also show collateral in some asset other than the one borrowed.
+    pub fn borrow_cross_chain<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BorrowCrossChain<'info>>,
         asset: [u8; 20], // ZRC-20 token address on ZetaChain
         amount: u64,
         destination_chain: u64,
-        recipient: [u8; 20], // Address on destination chain
+        recipient: Vec<u8>, // Wallet on destination_chain receiving the funds, chain_config.recipient_len bytes
     ) -> Result<()> {
-        require!(!ctx.accounts.contract_state.is_paused, DepositContractError::ContractPaused);
-        require!(amount > 0, DepositContractError::InvalidAmount);
+        check_gateway_program(&ctx.accounts.contract_state, &ctx.accounts.gateway_program)?;
+        open_zrc20_config(&mut ctx.accounts.zrc20_config, asset, ctx.bumps.zrc20_config);
+        let borrow_position = &mut ctx.accounts.borrow_position;
+        if borrow_position.owner == Pubkey::default() {
+            borrow_position.owner = ctx.accounts.user.key();
+            borrow_position.bump = ctx.bumps.borrow_position;
+        }
+        let borrowed = validate_borrow(
+            &ctx.accounts.contract_state,
+            &ctx.accounts.chain_config,
+            Some(&ctx.accounts.zrc20_config),
+            ctx.accounts.borrow_position.borrowed,
+            &ctx.accounts.user.key(),
+            asset,
+            amount,
+            &recipient,
+            ctx.remaining_accounts,
+        )?;
 
         // TODO: Validate asset is a supported ZRC-20 token address
-        // TODO: Validate destination_chain is a supported chain ID
-        // TODO: Validate recipient address format for destination chain
-        // TODO: Add collateral checks - ensure user has sufficient collateral for borrow
-        // TODO: Implement health factor validation before allowing borrow
 
         // Create message for SimpleLendingProtocol.onCall()
         let message = create_borrow_cross_chain_message(
             ctx.accounts.user.key().to_bytes(),
             amount,
             destination_chain,
-            recipient,
+            &recipient,
         )?;
 
         // Invoke gateway call (no asset transfer, just message)
@@ -331,7 +1037,25 @@ pub mod deposit_contract {
             &ctx.accounts.user.to_account_info(),
             ctx.accounts.contract_state.lending_protocol_address,
             message,
+            &ctx.accounts.contract_state.call_revert_policy,
+            ctx.accounts.contract_state.debug_logging,
         )?;
+        record_outbound_nonce(&mut ctx.accounts.chain_config, &ctx.accounts.gateway_program.key());
+
+        // Debt is booked only once the gateway accepted the call; a failed CPI aborts the
+        // borrow before the user or the caps are charged
+        let normalized = normalize_amount(amount, ctx.accounts.zrc20_config.decimals)?;
+        let contract_state = &mut ctx.accounts.contract_state;
+        contract_state.total_outstanding_borrows = contract_state
+            .total_outstanding_borrows
+            .checked_add(normalized)
+            .ok_or(DepositContractError::ArithmeticOverflow)?;
+        let zrc20_config = &mut ctx.accounts.zrc20_config;
+        zrc20_config.outstanding_borrow = zrc20_config
+            .outstanding_borrow
+            .checked_add(amount)
+            .ok_or(DepositContractError::ArithmeticOverflow)?;
+        ctx.accounts.borrow_position.borrowed = borrowed;
 
         emit!(BorrowCrossChainInitiated {
             user: ctx.accounts.user.key(),
@@ -344,23 +1068,131 @@ pub mod deposit_contract {
         Ok(())
     }
 
-    /// Trigger cross-chain withdrawal from ZetaChain to external chain
-    pub fn withdraw_cross_chain(
-        ctx: Context<WithdrawCrossChain>,
-        asset: [u8; 20], // ZRC-20 token address on ZetaChain
-        amount: u64,
-        destination_chain: u64,
-        recipient: [u8; 20],
-    ) -> Result<()> {
-        require!(!ctx.accounts.contract_state.is_paused, DepositContractError::ContractPaused);
-        require!(amount > 0, DepositContractError::InvalidAmount);
+    /// List admin changes that have been proposed but not yet applied (simulate only)
+    pub fn get_pending_changes(ctx: Context<GetPendingChanges>) -> Result<PendingChanges> {
+        let contract_state = &ctx.accounts.contract_state;
+        let pending_authority = (contract_state.pending_authority != Pubkey::default())
+            .then_some(contract_state.pending_authority);
 
-        // Create message for SimpleLendingProtocol.onCall()
-        let message = create_withdraw_cross_chain_message(
-            ctx.accounts.user.key().to_bytes(),
-            amount,
-            destination_chain,
-            recipient,
+        Ok(PendingChanges {
+            pending_authority,
+            // An authority transfer completes when the pending authority accepts it
+            authority_effective_at: 0,
+            // update_lending_protocol_address applies immediately, so nothing is ever queued
+            pending_lending_protocol_address: None,
+            lending_protocol_effective_at: 0,
+        })
+    }
+
+    /// Decode a borrow/withdraw payload the way this deployment currently accepts it: ABI
+    /// always, the legacy colon-delimited string only while accept_legacy_messages is set.
+    /// Lets consumers migrating off the legacy format check their payloads (simulate only).
+    pub fn decode_message(ctx: Context<DecodeMessage>, payload: Vec<u8>) -> Result<CrossChainMessage> {
+        decode_cross_chain_message(&payload, ctx.accounts.contract_state.accept_legacy_messages)
+    }
+
+    /// Emit the current configuration as a single event so indexers can resync without
+    /// reading and decoding ContractState. Permissionless: it only reads state.
+    pub fn emit_config_snapshot(ctx: Context<EmitConfigSnapshot>) -> Result<()> {
+        let contract_state = &ctx.accounts.contract_state;
+
+        emit!(ConfigSnapshot {
+            authority: contract_state.authority,
+            deposit_fee: DEPOSIT_FEE,
+            global_borrow_cap: contract_state.global_borrow_cap,
+            total_outstanding_borrows: contract_state.total_outstanding_borrows,
+            max_price_age: contract_state.max_price_age,
+            is_paused: contract_state.is_paused,
+            last_pause_change_ts: contract_state.last_pause_change_ts,
+            lending_protocol_address: contract_state.lending_protocol_address,
+            asset_count: contract_state.asset_count,
+            destination_chain_count: contract_state.destination_chain_count,
+        });
+
+        Ok(())
+    }
+
+    /// Run borrow_cross_chain's local validations without sending anything (simulate only).
+    /// Takes the same arguments and remaining_accounts as borrow_cross_chain. Returns 0 if
+    /// the borrow would be accepted, otherwise the blocking error code.
+    pub fn can_borrow<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CanBorrow<'info>>,
+        asset: [u8; 20],
+        amount: u64,
+        _destination_chain: u64,
+        recipient: Vec<u8>,
+    ) -> Result<u32> {
+        let accounts = &ctx.accounts;
+        let result = accounts
+            .chain_config
+            .as_deref()
+            .ok_or_else(|| error!(DepositContractError::InvalidChainId))
+            .and_then(|chain_config| {
+                validate_borrow(
+                    &accounts.contract_state,
+                    chain_config,
+                    accounts.zrc20_config.as_deref(),
+                    accounts.borrow_position.as_ref().map_or(0, |position| position.borrowed),
+                    &accounts.user.key(),
+                    asset,
+                    amount,
+                    &recipient,
+                    ctx.remaining_accounts,
+                )
+            });
+        let status = match result {
+            Ok(_) => 0,
+            Err(reason) => error_code(reason),
+        };
+
+        Ok(status)
+    }
+
+    /// How much of `asset` the user could borrow now (simulate only): the ltv-weighted
+    /// value of the collateral in remaining_accounts, laid out as for borrow_cross_chain,
+    /// less outstanding borrows, within min_health_factor and the borrow caps. Emits
+    /// MaxBorrowComputed and returns the same amount.
+    pub fn get_max_borrow<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetMaxBorrow<'info>>,
+        asset: [u8; 20],
+    ) -> Result<u64> {
+        let contract_state = &ctx.accounts.contract_state;
+        let user = ctx.accounts.user.key();
+        let max_amount = if check_operation_active(contract_state, PAUSE_BORROW).is_err() {
+            0
+        } else {
+            let collateral = weighted_collateral(contract_state, &user, asset, ctx.remaining_accounts)?;
+            let borrowed = ctx.accounts.borrow_position.as_ref().map_or(0, |position| position.borrowed);
+            max_borrow(contract_state, &collateral, borrowed, ctx.accounts.zrc20_config.as_deref())
+        };
+
+        emit!(MaxBorrowComputed { user, asset, max_amount });
+
+        Ok(max_amount)
+    }
+
+    /// Trigger cross-chain withdrawal from ZetaChain to external chain. Debits the user's
+    /// position in the asset's mint; while they have debt and min_health_factor is set,
+    /// remaining_accounts are read as for borrow_cross_chain and the post-withdraw health
+    /// factor must stay above the minimum.
+    pub fn withdraw_cross_chain<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawCrossChain<'info>>,
+        asset: [u8; 20], // ZRC-20 token address on ZetaChain
+        amount: u64,
+        destination_chain: u64,
+        recipient: Vec<u8>, // Wallet on destination_chain receiving the funds, not a ZetaChain address
+    ) -> Result<()> {
+        check_operation_active(&ctx.accounts.contract_state, PAUSE_WITHDRAW)?;
+        require!(amount > 0, DepositContractError::InvalidAmount);
+        check_gateway_program(&ctx.accounts.contract_state, &ctx.accounts.gateway_program)?;
+        check_recipient(&ctx.accounts.contract_state, &ctx.accounts.chain_config, &recipient)?;
+
+        // Create message for SimpleLendingProtocol.onCall()
+        let message = create_withdraw_cross_chain_message(
+            ctx.accounts.user.key().to_bytes(),
+            amount,
+            destination_chain,
+            &recipient,
         )?;
 
         // Invoke gateway call
@@ -369,8 +1201,36 @@ pub mod deposit_contract {
             &ctx.accounts.user.to_account_info(),
             ctx.accounts.contract_state.lending_protocol_address,
             message,
+            &ctx.accounts.contract_state.call_revert_policy,
+            ctx.accounts.contract_state.debug_logging,
+        )?;
+        record_outbound_nonce(&mut ctx.accounts.chain_config, &ctx.accounts.gateway_program.key());
+        // Debited only once the gateway accepted the call; a failed CPI leaves the position intact
+        debit_collateral(
+            &mut ctx.accounts.collateral_position,
+            ctx.accounts.user.key(),
+            ctx.accounts.asset_config.mint,
+            ctx.bumps.collateral_position,
+            amount,
         )?;
 
+        let min_health_factor = ctx.accounts.contract_state.min_health_factor;
+        let borrowed = outstanding_debt(&ctx.accounts.borrow_position)?;
+        if min_health_factor != 0 && borrowed > 0 {
+            // Persist the debit so the position read back from remaining_accounts is current
+            ctx.accounts.collateral_position.exit(&crate::ID)?;
+            let collateral = weighted_collateral(
+                &ctx.accounts.contract_state,
+                &ctx.accounts.user.key(),
+                asset,
+                ctx.remaining_accounts,
+            )?;
+            require!(
+                compute_health_factor(collateral.weighted, borrowed) >= u128::from(min_health_factor),
+                DepositContractError::InsufficientCollateral
+            );
+        }
+
         emit!(WithdrawCrossChainInitiated {
             user: ctx.accounts.user.key(),
             asset,
@@ -382,14 +1242,538 @@ pub mod deposit_contract {
         Ok(())
     }
 
-    /// Emergency pause functionality
-    pub fn set_pause_state(ctx: Context<SetPauseState>, is_paused: bool) -> Result<()> {
-        ctx.accounts.contract_state.is_paused = is_paused;
+    /// Set how old (in seconds) an oracle price may be before USD-cap checks reject it
+    pub fn set_max_price_age(ctx: Context<SetMaxPriceAge>, max_price_age: i64) -> Result<()> {
+        require!(max_price_age > 0, DepositContractError::InvalidAmount);
+        ctx.accounts.contract_state.max_price_age = max_price_age;
+
+        emit!(MaxPriceAgeUpdated { max_price_age });
+
+        Ok(())
+    }
+
+    /// Start a two-step authority transfer; the new authority must accept it
+    pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+        check_authority_candidate(&new_authority, &ctx.accounts.contract_state.key())?;
+        ctx.accounts.contract_state.pending_authority = new_authority;
+
+        emit!(AuthorityTransferProposed {
+            authority: ctx.accounts.authority.key(),
+            pending_authority: new_authority,
+        });
+
+        Ok(())
+    }
+
+    /// Complete an authority transfer; signed by the proposed authority
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let contract_state = &mut ctx.accounts.contract_state;
+        let previous_authority = contract_state.authority;
+        contract_state.authority = ctx.accounts.pending_authority.key();
+        contract_state.pending_authority = Pubkey::default();
+
+        emit!(AuthorityTransferred {
+            previous_authority,
+            authority: contract_state.authority,
+        });
+
+        Ok(())
+    }
+
+    /// Replace the emergency admin set. Members may only pause/unpause and rescue tokens
+    pub fn set_emergency_admins(ctx: Context<SetEmergencyAdmins>, admins: Vec<Pubkey>) -> Result<()> {
+        require!(admins.len() <= MAX_EMERGENCY_ADMINS, DepositContractError::RegistryFull);
+        let contract_state_key = ctx.accounts.contract_state.key();
+        for admin in &admins {
+            check_authority_candidate(admin, &contract_state_key)?;
+        }
+        ctx.accounts.contract_state.emergency_admins = admins.clone();
+
+        emit!(EmergencyAdminsUpdated { admins });
+
+        Ok(())
+    }
+
+    /// Restrict which programs may invoke deposits via CPI; an empty list allows any caller
+    pub fn set_allowed_caller_programs(
+        ctx: Context<SetAllowedCallerPrograms>,
+        programs: Vec<Pubkey>,
+    ) -> Result<()> {
+        require!(programs.len() <= MAX_ALLOWED_CALLER_PROGRAMS, DepositContractError::RegistryFull);
+        ctx.accounts.contract_state.allowed_caller_programs = programs.clone();
+
+        emit!(AllowedCallerProgramsUpdated { programs });
+
+        Ok(())
+    }
+
+    /// Set the protocol-wide cap on outstanding cross-chain borrows, in NORMALIZED_DECIMALS
+    /// units so borrows of differently scaled ZRC-20s add up
+    pub fn set_global_borrow_cap(ctx: Context<SetGlobalBorrowCap>, global_borrow_cap: u128) -> Result<()> {
+        let contract_state = &mut ctx.accounts.contract_state;
+        contract_state.global_borrow_cap = global_borrow_cap;
+
+        emit!(GlobalBorrowCapUpdated {
+            global_borrow_cap,
+            total_outstanding_borrows: contract_state.total_outstanding_borrows,
+        });
+
+        Ok(())
+    }
+
+    /// Cap outstanding cross-chain borrows of one ZRC-20. The cap may sit below the
+    /// current outstanding amount; that only blocks new borrows until repays catch up.
+    pub fn set_zrc20_borrow_cap(
+        ctx: Context<SetZrc20BorrowCap>,
+        asset: [u8; 20],
+        borrow_cap: u64,
+    ) -> Result<()> {
+        require!(asset != [0u8; 20], DepositContractError::UnsupportedAsset);
+
+        let zrc20_config = &mut ctx.accounts.zrc20_config;
+        open_zrc20_config(zrc20_config, asset, ctx.bumps.zrc20_config);
+        zrc20_config.borrow_cap = borrow_cap;
+
+        emit!(Zrc20BorrowCapUpdated {
+            asset,
+            borrow_cap,
+            outstanding_borrow: zrc20_config.outstanding_borrow,
+        });
+
+        Ok(())
+    }
+
+    /// Record a ZRC-20's decimals, so the health check can normalize borrows of it.
+    /// Until set, borrows are valued as zero-decimal amounts, which blocks them once
+    /// min_health_factor is enabled.
+    pub fn set_zrc20_decimals(ctx: Context<SetZrc20Decimals>, asset: [u8; 20], decimals: u8) -> Result<()> {
+        require!(asset != [0u8; 20], DepositContractError::UnsupportedAsset);
+
+        let zrc20_config = &mut ctx.accounts.zrc20_config;
+        open_zrc20_config(zrc20_config, asset, ctx.bumps.zrc20_config);
+        zrc20_config.decimals = decimals;
+
+        emit!(Zrc20DecimalsUpdated { asset, decimals });
+
+        Ok(())
+    }
+
+    /// Reject borrows that would leave the borrower's health factor (basis points of
+    /// weighted collateral over debt) below `min_health_factor`; zero disables the check
+    pub fn set_min_health_factor(ctx: Context<SetMinHealthFactor>, min_health_factor: u64) -> Result<()> {
+        require!(
+            min_health_factor == 0 || min_health_factor >= BPS_DENOMINATOR,
+            DepositContractError::InvalidAmount
+        );
+        ctx.accounts.contract_state.min_health_factor = min_health_factor;
+
+        emit!(MinHealthFactorUpdated { min_health_factor });
+
+        Ok(())
+    }
+
+    /// Reject borrows of an asset when the borrower's only collateral is that same asset
+    /// (matched through AssetConfig.zrc20). Borrows then need their collateral accounts
+    /// even with min_health_factor unset.
+    pub fn set_reject_self_borrow(ctx: Context<SetRejectSelfBorrow>, enabled: bool) -> Result<()> {
+        ctx.accounts.contract_state.reject_self_borrow = enabled;
+
+        emit!(SelfBorrowGuardChanged { enabled });
+
+        Ok(())
+    }
+
+    /// Emergency pause functionality. Toggles closer together than MIN_PAUSE_TOGGLE_INTERVAL
+    /// are rejected to avoid flapping; set `force` to pause or unpause during an incident anyway.
+    pub fn set_pause_state(ctx: Context<SetPauseState>, is_paused: bool, force: bool) -> Result<()> {
+        let contract_state = &mut ctx.accounts.contract_state;
+        let was_paused = contract_state.is_paused;
+
+        // Repeating the current state is not a toggle and leaves the window alone
+        if is_paused != was_paused {
+            let now = current_timestamp()?;
+            require!(
+                force || now.saturating_sub(contract_state.last_pause_change_ts) >= MIN_PAUSE_TOGGLE_INTERVAL,
+                DepositContractError::PauseToggleTooSoon
+            );
+            contract_state.last_pause_change_ts = now;
+        }
+        contract_state.is_paused = is_paused;
 
         emit!(PauseStateChanged { is_paused });
 
+        // Pausing is the contract's circuit breaker; give monitors something actionable
+        if is_paused && !was_paused {
+            emit!(CircuitBreakerTripped {
+                incident_metadata: contract_state.incident_metadata.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Pause individual operations: a bitmask of PAUSE_* bits, zero resumes them all.
+    /// Independent of set_pause_state, which stops every operation at once.
+    pub fn set_paused_operations(ctx: Context<SetPausedOperations>, paused_operations: u32) -> Result<()> {
+        require!(
+            paused_operations & !PAUSABLE_OPERATIONS == 0,
+            DepositContractError::InvalidAmount
+        );
+        ctx.accounts.contract_state.paused_operations = paused_operations;
+
+        emit!(PausedOperationsUpdated { paused_operations });
+
+        Ok(())
+    }
+
+    /// Set the incident URL/code emitted with CircuitBreakerTripped
+    pub fn set_incident_metadata(
+        ctx: Context<SetIncidentMetadata>,
+        incident_metadata: String,
+    ) -> Result<()> {
+        require!(
+            incident_metadata.len() <= MAX_INCIDENT_METADATA_LEN,
+            DepositContractError::IncidentMetadataTooLong
+        );
+
+        ctx.accounts.contract_state.incident_metadata = incident_metadata.clone();
+
+        emit!(IncidentMetadataUpdated { incident_metadata });
+
+        Ok(())
+    }
+
+    /// Configure the revert/abort policy applied to an operation type
+    pub fn set_revert_policy(
+        ctx: Context<SetRevertPolicy>,
+        operation: OperationType,
+        policy: RevertPolicy,
+    ) -> Result<()> {
+        let contract_state = &mut ctx.accounts.contract_state;
+        match operation {
+            OperationType::Deposit => contract_state.deposit_revert_policy = policy,
+            OperationType::Call => contract_state.call_revert_policy = policy,
+        }
+
+        emit!(RevertPolicyUpdated { operation, policy });
+
+        Ok(())
+    }
+
+    /// Link the signer's Solana pubkey to an EVM address. The EVM key proves control
+    /// by personal_sign-ing the 32-byte Solana pubkey; the signer is recovered via secp256k1.
+    pub fn link_evm_address(
+        ctx: Context<LinkEvmAddress>,
+        evm_address: [u8; 20],
+        signature: [u8; 64], // r || s
+        recovery_id: u8,     // v, either 0/1 or 27/28
+    ) -> Result<()> {
+        let user = ctx.accounts.user.key();
+        // Sign keccak256(domain_separator || user) so the proof only links in this deployment
+        let payload = keccak::hashv(&[
+            &domain_separator(ctx.accounts.contract_state.zeta_chain_id),
+            user.as_ref(),
+        ]);
+        let recovered = recover_evm_address(&payload.to_bytes(), &signature, recovery_id)?;
+        require!(recovered == evm_address, DepositContractError::EvmSignatureMismatch);
+
+        let evm_link = &mut ctx.accounts.evm_link;
+        evm_link.user = user;
+        evm_link.evm_address = evm_address;
+        evm_link.bump = ctx.bumps.evm_link;
+
+        emit!(EvmAddressLinked { user, evm_address });
+
         Ok(())
     }
+
+    /// Register a destination chain and the byte length of its recipient addresses. Only
+    /// EVM_ADDRESS_LEN is accepted for now: onCall decodes every recipient as an address,
+    /// so a longer one could pass check_recipient and still never be encodable.
+    pub fn set_chain_config(
+        ctx: Context<SetChainConfig>,
+        chain_id: u64,
+        recipient_len: u8,
+    ) -> Result<()> {
+        require!(recipient_len == EVM_ADDRESS_LEN, DepositContractError::InvalidRecipientLength);
+
+        // A fresh ChainConfig (recipient_len still 0) takes a registry slot
+        let chain_config = &mut ctx.accounts.chain_config;
+        if chain_config.recipient_len == 0 {
+            let contract_state = &mut ctx.accounts.contract_state;
+            require!(
+                contract_state.destination_chain_count < MAX_DESTINATION_CHAINS,
+                DepositContractError::RegistryFull
+            );
+            contract_state.destination_chain_count += 1;
+        }
+
+        chain_config.chain_id = chain_id;
+        chain_config.recipient_len = recipient_len;
+        chain_config.bump = ctx.bumps.chain_config;
+
+        emit!(ChainConfigUpdated { chain_id, recipient_len });
+
+        Ok(())
+    }
+
+    /// Require deposits to credit only an on_behalf_of address linked to the depositor
+    pub fn set_require_evm_link(ctx: Context<SetRequireEvmLink>, required: bool) -> Result<()> {
+        ctx.accounts.contract_state.require_evm_link = required;
+
+        emit!(EvmLinkRequirementChanged { required });
+
+        Ok(())
+    }
+
+    /// Close (or reopen) the transition window in which decoders accept legacy
+    /// colon-delimited cross-chain messages
+    pub fn set_accept_legacy_messages(
+        ctx: Context<SetAcceptLegacyMessages>,
+        accepted: bool,
+    ) -> Result<()> {
+        ctx.accounts.contract_state.accept_legacy_messages = accepted;
+
+        emit!(LegacyMessagesAcceptanceChanged { accepted });
+
+        Ok(())
+    }
+
+    /// Route gateway CPIs through the secondary gateway recorded at initialization, e.g.
+    /// while the primary is paused or being upgraded; false returns to the primary
+    pub fn set_use_failover(ctx: Context<SetUseFailover>, use_failover: bool) -> Result<()> {
+        let contract_state = &mut ctx.accounts.contract_state;
+        require!(
+            !use_failover || contract_state.secondary_gateway_program_id != Pubkey::default(),
+            DepositContractError::InvalidSecondaryGateway
+        );
+        contract_state.use_failover = use_failover;
+
+        emit!(GatewayFailoverChanged {
+            use_failover,
+            gateway_program: active_gateway_program(contract_state),
+        });
+
+        Ok(())
+    }
+
+    /// Turn verbose gateway diagnostics in the program log on or off; events are unaffected
+    pub fn set_debug_logging(ctx: Context<SetDebugLogging>, enabled: bool) -> Result<()> {
+        ctx.accounts.contract_state.debug_logging = enabled;
+
+        emit!(DebugLoggingChanged { enabled });
+
+        Ok(())
+    }
+
+    /// Reject SOL deposits and repays below `min_sol_deposit` lamports (zero disables)
+    pub fn set_min_sol_deposit(ctx: Context<SetMinSolDeposit>, min_sol_deposit: u64) -> Result<()> {
+        ctx.accounts.contract_state.min_sol_deposit = min_sol_deposit;
+
+        emit!(MinSolDepositUpdated { min_sol_deposit });
+
+        Ok(())
+    }
+
+    /// Restrict deposit beneficiaries to the leaves of a merkle tree; deposits then
+    /// carry a proof for their on_behalf_of. A zero root disables the allowlist.
+    pub fn set_beneficiary_allowlist_root(
+        ctx: Context<SetBeneficiaryAllowlistRoot>,
+        root: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts.contract_state.beneficiary_allowlist_root = root;
+
+        emit!(BeneficiaryAllowlistRootUpdated { root });
+
+        Ok(())
+    }
+
+    /// Allow (or stop allowing) add_supported_asset to register mints that have a
+    /// freeze authority; already registered assets are unaffected
+    pub fn set_allow_freezable_mints(ctx: Context<SetAllowFreezableMints>, allowed: bool) -> Result<()> {
+        ctx.accounts.contract_state.allow_freezable_mints = allowed;
+
+        emit!(FreezableMintsAllowanceChanged { allowed });
+
+        Ok(())
+    }
+
+    /// Opt in to (or out of) restricting deposits to whitelisted depositors
+    pub fn set_require_depositor_whitelist(
+        ctx: Context<SetRequireDepositorWhitelist>,
+        required: bool,
+    ) -> Result<()> {
+        ctx.accounts.contract_state.require_depositor_whitelist = required;
+
+        emit!(DepositorWhitelistRequirementChanged { required });
+
+        Ok(())
+    }
+
+    /// Allow a depositor while the whitelist is required
+    pub fn add_whitelisted_depositor(
+        ctx: Context<AddWhitelistedDepositor>,
+        depositor: Pubkey,
+    ) -> Result<()> {
+        let entry = &mut ctx.accounts.depositor_whitelist_entry;
+        entry.depositor = depositor;
+        entry.bump = ctx.bumps.depositor_whitelist_entry;
+
+        emit!(DepositorWhitelistUpdated { depositor, whitelisted: true });
+
+        Ok(())
+    }
+
+    /// Remove a depositor from the whitelist, returning the entry's rent to the authority
+    pub fn remove_whitelisted_depositor(
+        _ctx: Context<RemoveWhitelistedDepositor>,
+        depositor: Pubkey,
+    ) -> Result<()> {
+        emit!(DepositorWhitelistUpdated { depositor, whitelisted: false });
+
+        Ok(())
+    }
+
+    /// Return the AssetConfig accounts passed in remaining_accounts (simulate only)
+    pub fn get_asset_configs<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetAssetConfigs<'info>>,
+    ) -> Result<Vec<AssetConfig>> {
+        require!(
+            ctx.remaining_accounts.len() <= MAX_ASSET_CONFIGS_PER_QUERY,
+            DepositContractError::BatchTooLarge
+        );
+
+        let asset_configs = ctx
+            .remaining_accounts
+            .iter()
+            .map(|account| Ok(Account::<AssetConfig>::try_from(account)?.into_inner()))
+            .collect::<Result<Vec<AssetConfig>>>()?;
+
+        // Fail clearly instead of inside the set_return_data syscall
+        require!(
+            asset_configs.try_to_vec()?.len() <= MAX_RETURN_DATA,
+            DepositContractError::ReturnDataTooLarge
+        );
+
+        Ok(asset_configs)
+    }
+
+    /// Move collected deposit fees out of the fee vault, which keeps its rent-exempt minimum
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        require!(amount > 0, DepositContractError::InvalidAmount);
+
+        let fee_vault = &ctx.accounts.fee_vault;
+        let remaining = fee_vault
+            .lamports()
+            .checked_sub(amount)
+            .ok_or(DepositContractError::InsufficientFeeBalance)?;
+        check_rent_exempt_after_debit(&fee_vault.to_account_info(), amount)?;
+
+        let bump = [ctx.bumps.fee_vault];
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: fee_vault.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                },
+                &[&[b"fee_vault", &bump]],
+            ),
+            amount,
+        )?;
+
+        emit!(FeesWithdrawn {
+            authority: ctx.accounts.authority.key(),
+            amount,
+            remaining,
+        });
+
+        Ok(())
+    }
+
+    /// Move lamports left in the contract_state PDA above its rent-exempt reserve to the
+    /// treasury. SOL deposits and repays forward through the gateway and their fees accrue
+    /// in the fee vault, so anything here is unaccounted dust.
+    pub fn sweep_sol_dust(ctx: Context<SweepSolDust>) -> Result<()> {
+        let contract_state = ctx.accounts.contract_state.to_account_info();
+        let reserve = Rent::get()?.minimum_balance(contract_state.data_len());
+        let amount = contract_state.lamports().saturating_sub(reserve);
+        require!(amount > 0, DepositContractError::NoDustToSweep);
+        check_rent_exempt_after_debit(&contract_state, amount)?;
+
+        // Program-owned account with data: debit directly rather than via the System Program
+        **contract_state.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.treasury.try_borrow_mut_lamports()? += amount;
+
+        emit!(SolDustSwept {
+            treasury: ctx.accounts.treasury.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Move tokens that reached a vault outside the accounted flow (see reconcile_spl)
+    /// to a token account owned by the authority. Callable by the authority or an
+    /// emergency admin; tracked deposits in flight can never be rescued.
+    pub fn rescue_tokens(ctx: Context<RescueTokens>, amount: u64) -> Result<()> {
+        require!(amount > 0, DepositContractError::InvalidAmount);
+
+        let asset_config = &ctx.accounts.asset_config;
+        let expected_balance = asset_config
+            .total_received
+            .checked_sub(asset_config.total_forwarded)
+            .ok_or(DepositContractError::ArithmeticOverflow)?;
+        let surplus = ctx.accounts.contract_token_account.amount.saturating_sub(expected_balance);
+        require!(amount <= surplus, DepositContractError::RescueExceedsSurplus);
+
+        let bump = [ctx.accounts.contract_state.bump];
+        let signer_seeds: &[&[&[u8]]] = &[&contract_state_signer_seeds(&bump)];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.contract_token_account.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: ctx.accounts.contract_state.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )?;
+
+        emit!(TokensRescued {
+            admin: ctx.accounts.admin.key(),
+            mint: asset_config.mint,
+            amount,
+            destination: ctx.accounts.destination.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Compare a mint's vault balance against accounted inflows minus forwarded amounts.
+    /// Returns vault balance - expected balance; nonzero means tokens are stuck or missing.
+    pub fn reconcile_spl(ctx: Context<ReconcileSpl>) -> Result<i64> {
+        let asset_config = &ctx.accounts.asset_config;
+        let vault_balance = ctx.accounts.contract_token_account.amount;
+        let expected_balance = asset_config
+            .total_received
+            .checked_sub(asset_config.total_forwarded)
+            .ok_or(DepositContractError::ArithmeticOverflow)?;
+
+        let difference = i64::try_from(vault_balance as i128 - expected_balance as i128)
+            .map_err(|_| DepositContractError::ArithmeticOverflow)?;
+
+        emit!(SplReconciled {
+            mint: asset_config.mint,
+            vault_balance,
+            expected_balance,
+            difference,
+        });
+
+        Ok(difference)
+    }
 }
 
 // Account Structures
@@ -405,6 +1789,10 @@ pub struct Initialize<'info> {
     )]
     pub contract_state: Account<'info, ContractState>,
     
+    /// CHECK: Failover gateway; only required to be a deployed program here
+    #[account(executable)]
+    pub secondary_gateway_program: Option<UncheckedAccount<'info>>,
+    
     #[account(mut)]
     pub authority: Signer<'info>,
     
@@ -412,10 +1800,20 @@ pub struct Initialize<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(mint: Pubkey)]
-pub struct AddSupportedAsset<'info> {
+#[instruction(
+    lending_protocol_address: [u8; 20],
+    zeta_chain_id: u64,
+    treasury: Pubkey,
+    abort_address: [u8; 20],
+    native_asset: NewAssetItem,
+)]
+pub struct InitializeStrict<'info> {
     #[account(
-        has_one = authority @ DepositContractError::Unauthorized
+        init,
+        payer = authority,
+        space = 8 + ContractState::INIT_SPACE,
+        seeds = [b"contract_state"],
+        bump
     )]
     pub contract_state: Account<'info, ContractState>,
     
@@ -423,14 +1821,121 @@ pub struct AddSupportedAsset<'info> {
         init,
         payer = authority,
         space = 8 + AssetConfig::INIT_SPACE,
-        seeds = [b"asset_config", mint.as_ref()],
+        seeds = [b"asset_config", native_asset.mint.as_ref()],
         bump
     )]
-    pub asset_config: Account<'info, AssetConfig>,
+    pub native_asset_config: Account<'info, AssetConfig>,
+    
+    /// CHECK: Gateway program; only required to be a deployed program here
+    #[account(executable)]
+    pub gateway_program: UncheckedAccount<'info>,
+    
+    /// CHECK: Failover gateway; only required to be a deployed program here
+    #[account(executable)]
+    pub secondary_gateway_program: Option<UncheckedAccount<'info>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct AddSupportedAsset<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AssetConfig::INIT_SPACE,
+        seeds = [b"asset_config", mint.as_ref()],
+        bump
+    )]
+    pub asset_config: Account<'info, AssetConfig>,
+    
+    // The token mint being registered; its decimals must match the argument
+    #[account(address = mint)]
+    pub mint_account: Account<'info, token::Mint>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterNativeSol<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    // The System Program ID is also the SOL mint sentinel, so it has no token mint to check
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AssetConfig::INIT_SPACE,
+        seeds = [b"asset_config", system_program::ID.as_ref()],
+        bump
+    )]
+    pub asset_config: Account<'info, AssetConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddSupportedAssetsBatch<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateAssetVault<'info> {
+    #[account(
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    #[account(
+        seeds = [b"asset_config", mint.key().as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Account<'info, AssetConfig>,
+    
+    pub mint: Account<'info, token::Mint>,
+    
+    // init_if_needed so re-running setup is a no-op; an existing account must still be
+    // the canonical ATA, since the associated_token constraints are checked either way
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = contract_state
+    )]
+    pub contract_token_account: Account<'info, TokenAccount>,
     
     #[account(mut)]
     pub authority: Signer<'info>,
     
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
@@ -452,101 +1957,234 @@ pub struct RemoveSupportedAsset<'info> {
 }
 
 #[derive(Accounts)]
-pub struct UpdateLendingProtocolAddress<'info> {
+pub struct CloseAssetConfig<'info> {
     #[account(
         mut,
         has_one = authority @ DepositContractError::Unauthorized
     )]
     pub contract_state: Account<'info, ContractState>,
     
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"asset_config", asset_config.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Account<'info, AssetConfig>,
+    
+    /// CHECK: Contract vault ATA for the asset, which may never have been created
+    #[account(
+        address = get_associated_token_address(&contract_state.key(), &asset_config.mint)
+            @ DepositContractError::InvalidVaultAccount
+    )]
+    pub contract_token_account: UncheckedAccount<'info>,
+    
+    #[account(mut)]
     pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct DepositSol<'info> {
+pub struct UpdateAssetConfig<'info> {
     #[account(
-        seeds = [b"contract_state"],
-        bump = contract_state.bump
+        has_one = authority @ DepositContractError::Unauthorized
     )]
     pub contract_state: Account<'info, ContractState>,
     
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    /// CHECK: Gateway program account
-    pub gateway_program: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"asset_config", asset_config.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Account<'info, AssetConfig>,
     
-    pub system_program: Program<'info, System>,
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct DepositSplToken<'info> {
+pub struct ResyncAssetDecimals<'info> {
     #[account(
-        seeds = [b"contract_state"],
-        bump = contract_state.bump
+        has_one = authority @ DepositContractError::Unauthorized
     )]
     pub contract_state: Account<'info, ContractState>,
     
     #[account(
+        mut,
         seeds = [b"asset_config", mint.key().as_ref()],
         bump = asset_config.bump
     )]
     pub asset_config: Account<'info, AssetConfig>,
     
+    pub mint: Account<'info, token::Mint>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateLendingProtocolAddress<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RotateLendingProtocol<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DepositSol<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract_state"],
+        bump = contract_state.bump
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
     #[account(mut)]
     pub user: Signer<'info>,
     
-    pub mint: Account<'info, token::Mint>,
+    #[account(
+        seeds = [b"evm_link", user.key().as_ref()],
+        bump = evm_link.bump
+    )]
+    pub evm_link: Option<Account<'info, EvmLink>>,
     
     #[account(
-        mut,
-        associated_token::mint = mint,
-        associated_token::authority = user
+        seeds = [b"depositor_whitelist", user.key().as_ref()],
+        bump = depositor_whitelist_entry.bump
     )]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub depositor_whitelist_entry: Option<Account<'info, DepositorWhitelistEntry>>,
     
+    // Collateral credited to the depositor, opened by their first deposit of the asset
     #[account(
-        init,
+        init_if_needed,
         payer = user,
-        associated_token::mint = mint,
-        associated_token::authority = contract_state
+        space = 8 + CollateralPosition::INIT_SPACE,
+        seeds = [b"position", user.key().as_ref(), system_program::ID.as_ref()],
+        bump
     )]
-    pub contract_token_account: Account<'info, TokenAccount>,
+    pub collateral_position: Account<'info, CollateralPosition>,
+    
+    // Collects DEPOSIT_FEE; a system-owned PDA holding lamports only
+    #[account(
+        mut,
+        seeds = [b"fee_vault"],
+        bump
+    )]
+    pub fee_vault: SystemAccount<'info>,
+    
+    /// CHECK: Native SOL's asset_config PDA, read for its fee_recipient once registered
+    #[account(seeds = [b"asset_config", system_program::ID.as_ref()], bump)]
+    pub native_asset_config: UncheckedAccount<'info>,
+    
+    /// CHECK: Receives DEPOSIT_FEE in place of fee_vault; must be native SOL's fee_recipient
+    #[account(mut)]
+    pub fee_recipient: Option<UncheckedAccount<'info>>,
     
     /// CHECK: Gateway program account
     pub gateway_program: AccountInfo<'info>,
     
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
+    /// CHECK: Gateway PDA (seeds [b"meta"]), validated by the gateway program; receives the SOL
+    #[account(mut)]
+    pub gateway_pda: UncheckedAccount<'info>,
+    
+    /// CHECK: Instructions sysvar, read to identify a CPI caller
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct RepaySol<'info> {
+#[instruction(amount: u64, on_behalf_of: [u8; 20], owner: Pubkey)]
+pub struct DepositSolFor<'info> {
     #[account(
+        mut,
         seeds = [b"contract_state"],
         bump = contract_state.bump
     )]
     pub contract_state: Account<'info, ContractState>,
     
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub payer: Signer<'info>,
+    
+    // The beneficiary's link, not the sponsor's
+    #[account(
+        seeds = [b"evm_link", owner.as_ref()],
+        bump = evm_link.bump
+    )]
+    pub evm_link: Option<Account<'info, EvmLink>>,
+    
+    // The sponsor's entry: the whitelist gates who funds deposits
+    #[account(
+        seeds = [b"depositor_whitelist", payer.key().as_ref()],
+        bump = depositor_whitelist_entry.bump
+    )]
+    pub depositor_whitelist_entry: Option<Account<'info, DepositorWhitelistEntry>>,
+    
+    // Collateral credited to the beneficiary, not the sponsor, opened by their first deposit of the asset
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + CollateralPosition::INIT_SPACE,
+        seeds = [b"position", owner.as_ref(), system_program::ID.as_ref()],
+        bump
+    )]
+    pub collateral_position: Account<'info, CollateralPosition>,
+    
+    // Collects DEPOSIT_FEE; a system-owned PDA holding lamports only
+    #[account(
+        mut,
+        seeds = [b"fee_vault"],
+        bump
+    )]
+    pub fee_vault: SystemAccount<'info>,
+    
+    /// CHECK: Native SOL's asset_config PDA, read for its fee_recipient once registered
+    #[account(seeds = [b"asset_config", system_program::ID.as_ref()], bump)]
+    pub native_asset_config: UncheckedAccount<'info>,
+    
+    /// CHECK: Receives DEPOSIT_FEE in place of fee_vault; must be native SOL's fee_recipient
+    #[account(mut)]
+    pub fee_recipient: Option<UncheckedAccount<'info>>,
     
     /// CHECK: Gateway program account
     pub gateway_program: AccountInfo<'info>,
     
+    /// CHECK: Gateway PDA (seeds [b"meta"]), validated by the gateway program; receives the SOL
+    #[account(mut)]
+    pub gateway_pda: UncheckedAccount<'info>,
+    
+    /// CHECK: Instructions sysvar, read to identify a CPI caller
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct RepaySplToken<'info> {
+#[instruction(amount: u64)]
+pub struct DepositSplToken<'info> {
+    // Writable: it signs the gateway CPI, which takes its signer as mut
     #[account(
+        mut,
         seeds = [b"contract_state"],
         bump = contract_state.bump
     )]
     pub contract_state: Account<'info, ContractState>,
     
     #[account(
+        mut,
         seeds = [b"asset_config", mint.key().as_ref()],
         bump = asset_config.bump
     )]
@@ -555,15 +2193,40 @@ pub struct RepaySplToken<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
     
+    #[account(
+        seeds = [b"evm_link", user.key().as_ref()],
+        bump = evm_link.bump
+    )]
+    pub evm_link: Option<Account<'info, EvmLink>>,
+    
+    #[account(
+        seeds = [b"depositor_whitelist", user.key().as_ref()],
+        bump = depositor_whitelist_entry.bump
+    )]
+    pub depositor_whitelist_entry: Option<Account<'info, DepositorWhitelistEntry>>,
+    
     pub mint: Account<'info, token::Mint>,
     
+    // Collateral credited to the depositor, opened by their first deposit of the asset
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + CollateralPosition::INIT_SPACE,
+        seeds = [b"position", user.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub collateral_position: Account<'info, CollateralPosition>,
+    
+    // Checked up front so a short balance fails with InsufficientBalance, not inside the token program
     #[account(
         mut,
         associated_token::mint = mint,
-        associated_token::authority = user
+        associated_token::authority = user,
+        constraint = user_token_account.amount >= amount @ DepositContractError::InsufficientBalance
     )]
     pub user_token_account: Account<'info, TokenAccount>,
     
+    // Created once per asset by create_asset_vault
     #[account(
         mut,
         associated_token::mint = mint,
@@ -574,14 +2237,33 @@ pub struct RepaySplToken<'info> {
     /// CHECK: Gateway program account
     pub gateway_program: AccountInfo<'info>,
     
+    /// CHECK: Gateway PDA (seeds [b"meta"]), validated by the gateway program
+    #[account(mut)]
+    pub gateway_pda: UncheckedAccount<'info>,
+    
+    /// CHECK: Gateway whitelist entry for mint, validated by the gateway program
+    pub gateway_whitelist_entry: UncheckedAccount<'info>,
+    
+    /// CHECK: Gateway PDA's token account for mint, validated by the gateway program
+    #[account(mut)]
+    pub gateway_token_account: UncheckedAccount<'info>,
+    
+    /// CHECK: Instructions sysvar, read to identify a CPI caller
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    
     pub token_program: Program<'info, Token>,
+    // Program<> already checks these ids; kept explicit in case the types are ever loosened
+    #[account(address = anchor_spl::associated_token::ID @ DepositContractError::InvalidProgramAccount)]
     pub associated_token_program: Program<'info, AssociatedToken>,
+    #[account(address = system_program::ID @ DepositContractError::InvalidProgramAccount)]
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct BorrowCrossChain<'info> {
+pub struct RepaySol<'info> {
     #[account(
+        mut,
         seeds = [b"contract_state"],
         bump = contract_state.bump
     )]
@@ -590,300 +2272,2479 @@ pub struct BorrowCrossChain<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
     
-    /// CHECK: Gateway program account
-    pub gateway_program: AccountInfo<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct WithdrawCrossChain<'info> {
+    // Collects DEPOSIT_FEE; a system-owned PDA holding lamports only
     #[account(
-        seeds = [b"contract_state"],
-        bump = contract_state.bump
+        mut,
+        seeds = [b"fee_vault"],
+        bump
     )]
-    pub contract_state: Account<'info, ContractState>,
+    pub fee_vault: SystemAccount<'info>,
     
+    /// CHECK: Native SOL's asset_config PDA, read for its fee_recipient once registered
+    #[account(seeds = [b"asset_config", system_program::ID.as_ref()], bump)]
+    pub native_asset_config: UncheckedAccount<'info>,
+    
+    /// CHECK: Receives DEPOSIT_FEE in place of fee_vault; must be native SOL's fee_recipient
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub fee_recipient: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Zrc20Config PDA of native SOL's ZRC-20, checked against native_asset_config in
+    /// the handler; its outstanding_borrow is reduced once a borrow has opened it
+    #[account(mut)]
+    pub zrc20_config: UncheckedAccount<'info>,
+    
+    // The debt of the borrower on_behalf_of names, reduced by what this repay forwards;
+    // the repayer may be someone else
+    #[account(
+        mut,
+        seeds = [b"borrow_position", borrow_position.owner.as_ref()],
+        bump = borrow_position.bump
+    )]
+    pub borrow_position: Option<Account<'info, BorrowPosition>>,
     
     /// CHECK: Gateway program account
     pub gateway_program: AccountInfo<'info>,
     
+    /// CHECK: Gateway PDA (seeds [b"meta"]), validated by the gateway program; receives the SOL
+    #[account(mut)]
+    pub gateway_pda: UncheckedAccount<'info>,
+    
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SetPauseState<'info> {
+pub struct RepaySplToken<'info> {
     #[account(
         mut,
-        has_one = authority @ DepositContractError::Unauthorized
+        seeds = [b"contract_state"],
+        bump = contract_state.bump
     )]
     pub contract_state: Account<'info, ContractState>,
     
-    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"asset_config", mint.key().as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Account<'info, AssetConfig>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub mint: Account<'info, token::Mint>,
+    
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = contract_state
+    )]
+    pub contract_token_account: Account<'info, TokenAccount>,
+    
+    /// CHECK: Zrc20Config PDA of the asset's ZRC-20, checked in the handler; its
+    /// outstanding_borrow is reduced once a borrow has opened it
+    #[account(mut)]
+    pub zrc20_config: UncheckedAccount<'info>,
+    
+    // The debt of the borrower on_behalf_of names, reduced by what this repay forwards;
+    // the repayer may be someone else
+    #[account(
+        mut,
+        seeds = [b"borrow_position", borrow_position.owner.as_ref()],
+        bump = borrow_position.bump
+    )]
+    pub borrow_position: Option<Account<'info, BorrowPosition>>,
+    
+    /// CHECK: Gateway program account
+    pub gateway_program: AccountInfo<'info>,
+    
+    /// CHECK: Gateway PDA (seeds [b"meta"]), validated by the gateway program
+    #[account(mut)]
+    pub gateway_pda: UncheckedAccount<'info>,
+    
+    /// CHECK: Gateway whitelist entry for mint, validated by the gateway program
+    pub gateway_whitelist_entry: UncheckedAccount<'info>,
+    
+    /// CHECK: Gateway PDA's token account for mint, validated by the gateway program
+    #[account(mut)]
+    pub gateway_token_account: UncheckedAccount<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    // Program<> already checks these ids; kept explicit in case the types are ever loosened
+    #[account(address = anchor_spl::associated_token::ID @ DepositContractError::InvalidProgramAccount)]
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    #[account(address = system_program::ID @ DepositContractError::InvalidProgramAccount)]
+    pub system_program: Program<'info, System>,
 }
 
-// State Accounts
+#[derive(Accounts)]
+pub struct RepayBatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract_state"],
+        bump = contract_state.bump
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    /// CHECK: Gateway program account
+    pub gateway_program: AccountInfo<'info>,
+    
+    // One borrower's debt, reduced by the items repaid on their behalf
+    #[account(
+        mut,
+        seeds = [b"borrow_position", borrow_position.owner.as_ref()],
+        bump = borrow_position.bump
+    )]
+    pub borrow_position: Option<Account<'info, BorrowPosition>>,
+    
+    /// CHECK: Gateway PDA (seeds [b"meta"]), validated by the gateway program
+    #[account(mut)]
+    pub gateway_pda: UncheckedAccount<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
 
-#[account]
-#[derive(InitSpace)]
-pub struct ContractState {
-    pub authority: Pubkey,
-    pub lending_protocol_address: [u8; 20], // ZetaChain address
-    pub zeta_chain_id: u64,
-    pub is_paused: bool,
-    pub bump: u8,
+#[derive(Accounts)]
+#[instruction(asset: [u8; 20], amount: u64, destination_chain: u64)]
+pub struct BorrowCrossChain<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract_state"],
+        bump = contract_state.bump
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    #[account(
+        mut,
+        seeds = [b"chain_config", destination_chain.to_le_bytes().as_ref()],
+        bump = chain_config.bump
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
+    
+    // Opened uncapped by an asset's first borrow; set_zrc20_borrow_cap tightens it
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Zrc20Config::INIT_SPACE,
+        seeds = [b"zrc20_config", asset.as_ref()],
+        bump
+    )]
+    pub zrc20_config: Account<'info, Zrc20Config>,
+    
+    // The borrower's debt, opened by their first borrow
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + BorrowPosition::INIT_SPACE,
+        seeds = [b"borrow_position", user.key().as_ref()],
+        bump
+    )]
+    pub borrow_position: Account<'info, BorrowPosition>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    /// CHECK: Gateway program account
+    pub gateway_program: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct AssetConfig {
-    pub mint: Pubkey,
-    pub decimals: u8,
-    pub is_native: bool,
-    pub is_supported: bool,
-    pub bump: u8,
+#[derive(Accounts)]
+pub struct GetPendingChanges<'info> {
+    #[account(
+        seeds = [b"contract_state"],
+        bump = contract_state.bump
+    )]
+    pub contract_state: Account<'info, ContractState>,
 }
 
-// Helper Functions
+#[derive(Accounts)]
+pub struct DecodeMessage<'info> {
+    #[account(
+        seeds = [b"contract_state"],
+        bump = contract_state.bump
+    )]
+    pub contract_state: Account<'info, ContractState>,
+}
 
-fn create_supply_message(on_behalf_of: [u8; 20]) -> Result<Vec<u8>> {
-    // Create message compatible with SimpleLendingProtocol.onCall()
-    // Must match EVM encoding: abi.encode("supply", onBehalfOf) padded to 128 bytes
-    // This creates the exact same encoding as the EVM DepositContract
+#[derive(Accounts)]
+pub struct EmitConfigSnapshot<'info> {
+    #[account(
+        seeds = [b"contract_state"],
+        bump = contract_state.bump
+    )]
+    pub contract_state: Account<'info, ContractState>,
+}
+
+#[derive(Accounts)]
+#[instruction(asset: [u8; 20], amount: u64, destination_chain: u64)]
+pub struct CanBorrow<'info> {
+    #[account(
+        seeds = [b"contract_state"],
+        bump = contract_state.bump
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    // Absent when destination_chain has no ChainConfig, which reports InvalidChainId
+    #[account(
+        seeds = [b"chain_config", destination_chain.to_le_bytes().as_ref()],
+        bump = chain_config.bump
+    )]
+    pub chain_config: Option<Account<'info, ChainConfig>>,
+    
+    // Absent until the asset's first borrow or cap: then uncapped, with unset decimals
+    #[account(
+        seeds = [b"zrc20_config", asset.as_ref()],
+        bump = zrc20_config.bump
+    )]
+    pub zrc20_config: Option<Account<'info, Zrc20Config>>,
+    
+    // Absent until the user's first borrow
+    #[account(
+        seeds = [b"borrow_position", user.key().as_ref()],
+        bump = borrow_position.bump
+    )]
+    pub borrow_position: Option<Account<'info, BorrowPosition>>,
     
-    // Convert 20-byte address to 32-byte address (EVM format)
-    let mut evm_address = [0u8; 32];
-    evm_address[12..32].copy_from_slice(&on_behalf_of);
+    /// CHECK: Who would borrow; only keys the PDAs above
+    pub user: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(asset: [u8; 20])]
+pub struct GetMaxBorrow<'info> {
+    #[account(
+        seeds = [b"contract_state"],
+        bump = contract_state.bump
+    )]
+    pub contract_state: Account<'info, ContractState>,
     
-    // Manually create ABI-encoded message: abi.encode("supply", address)
-    let mut message = Vec::with_capacity(128);
+    // Absent until the asset's first borrow or cap: then uncapped, with unset decimals
+    #[account(
+        seeds = [b"zrc20_config", asset.as_ref()],
+        bump = zrc20_config.bump
+    )]
+    pub zrc20_config: Option<Account<'info, Zrc20Config>>,
     
-    // Offset for string "supply" (32 bytes)
-    message.extend_from_slice(&[0u8; 28]); // Padding
-    message.extend_from_slice(&64u32.to_be_bytes()); // Offset to string data
+    // Absent until the user's first borrow
+    #[account(
+        seeds = [b"borrow_position", user.key().as_ref()],
+        bump = borrow_position.bump
+    )]
+    pub borrow_position: Option<Account<'info, BorrowPosition>>,
     
-    // Address (32 bytes)
-    message.extend_from_slice(&evm_address);
+    /// CHECK: Whose borrowing power to compute; only keys the PDAs above
+    pub user: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(asset: [u8; 20], amount: u64, destination_chain: u64)]
+pub struct WithdrawCrossChain<'info> {
+    #[account(
+        seeds = [b"contract_state"],
+        bump = contract_state.bump
+    )]
+    pub contract_state: Account<'info, ContractState>,
     
-    // String length (32 bytes) - "supply" is 6 bytes
-    message.extend_from_slice(&[0u8; 28]); // Padding
-    message.extend_from_slice(&6u32.to_be_bytes()); // String length
+    #[account(
+        mut,
+        seeds = [b"chain_config", destination_chain.to_le_bytes().as_ref()],
+        bump = chain_config.bump
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
     
-    // String data "supply" (padded to 32 bytes)
-    message.extend_from_slice(b"supply");
-    message.extend_from_slice(&[0u8; 26]); // Padding to 32 bytes
+    #[account(mut)]
+    pub user: Signer<'info>,
     
-    // Pad entire message to exactly 128 bytes
-    message.resize(128, 0);
+    // The mint whose ZRC-20 is being withdrawn; a removed asset can still be withdrawn
+    #[account(
+        seeds = [b"asset_config", asset_config.mint.as_ref()],
+        bump = asset_config.bump,
+        constraint = asset != [0u8; 20] && asset_config.zrc20 == asset @ DepositContractError::UnsupportedAsset
+    )]
+    pub asset_config: Account<'info, AssetConfig>,
     
-    Ok(message)
+    // The user's position in that mint, opened empty if the asset was only supplied from
+    // other chains. Amounts are in the mint's base units, which its ZRC-20 shares.
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + CollateralPosition::INIT_SPACE,
+        seeds = [b"position", user.key().as_ref(), asset_config.mint.as_ref()],
+        bump
+    )]
+    pub collateral_position: Account<'info, CollateralPosition>,
+    
+    /// CHECK: The user's borrow_position PDA, read for outstanding debt once the first borrow opens it
+    #[account(seeds = [b"borrow_position", user.key().as_ref()], bump)]
+    pub borrow_position: UncheckedAccount<'info>,
+    
+    /// CHECK: Gateway program account
+    pub gateway_program: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxPriceAge<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        constraint = contract_state.pending_authority == pending_authority.key() @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub pending_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetEmergencyAdmins<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAllowedCallerPrograms<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetGlobalBorrowCap<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(asset: [u8; 20])]
+pub struct SetZrc20BorrowCap<'info> {
+    #[account(
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Zrc20Config::INIT_SPACE,
+        seeds = [b"zrc20_config", asset.as_ref()],
+        bump
+    )]
+    pub zrc20_config: Account<'info, Zrc20Config>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(asset: [u8; 20])]
+pub struct SetZrc20Decimals<'info> {
+    #[account(
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Zrc20Config::INIT_SPACE,
+        seeds = [b"zrc20_config", asset.as_ref()],
+        bump
+    )]
+    pub zrc20_config: Account<'info, Zrc20Config>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRejectSelfBorrow<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinHealthFactor<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPauseState<'info> {
+    #[account(
+        mut,
+        constraint = is_emergency_admin(&contract_state, &admin.key()) @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    // The authority or an emergency admin
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPausedOperations<'info> {
+    #[account(
+        mut,
+        constraint = is_emergency_admin(&contract_state, &admin.key()) @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    // The authority or an emergency admin
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetIncidentMetadata<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRevertPolicy<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct LinkEvmAddress<'info> {
+    #[account(
+        seeds = [b"contract_state"],
+        bump = contract_state.bump
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + EvmLink::INIT_SPACE,
+        seeds = [b"evm_link", user.key().as_ref()],
+        bump
+    )]
+    pub evm_link: Account<'info, EvmLink>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(chain_id: u64)]
+pub struct SetChainConfig<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ChainConfig::INIT_SPACE,
+        seeds = [b"chain_config", chain_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub chain_config: Account<'info, ChainConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRequireEvmLink<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAcceptLegacyMessages<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetUseFailover<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetDebugLogging<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinSolDeposit<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetBeneficiaryAllowlistRoot<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAllowFreezableMints<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRequireDepositorWhitelist<'info> {
+    #[account(
+        mut,
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(depositor: Pubkey)]
+pub struct AddWhitelistedDepositor<'info> {
+    #[account(
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + DepositorWhitelistEntry::INIT_SPACE,
+        seeds = [b"depositor_whitelist", depositor.as_ref()],
+        bump
+    )]
+    pub depositor_whitelist_entry: Account<'info, DepositorWhitelistEntry>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(depositor: Pubkey)]
+pub struct RemoveWhitelistedDepositor<'info> {
+    #[account(
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"depositor_whitelist", depositor.as_ref()],
+        bump = depositor_whitelist_entry.bump
+    )]
+    pub depositor_whitelist_entry: Account<'info, DepositorWhitelistEntry>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetAssetConfigs<'info> {
+    #[account(
+        seeds = [b"contract_state"],
+        bump = contract_state.bump
+    )]
+    pub contract_state: Account<'info, ContractState>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        has_one = authority @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"fee_vault"],
+        bump
+    )]
+    pub fee_vault: SystemAccount<'info>,
+    
+    /// CHECK: Any account may receive the withdrawn lamports
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SweepSolDust<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract_state"],
+        bump = contract_state.bump,
+        has_one = authority @ DepositContractError::Unauthorized,
+        constraint = contract_state.treasury != Pubkey::default() @ DepositContractError::InvalidTreasury
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    pub authority: Signer<'info>,
+    
+    /// CHECK: Receives lamports only; must be the configured treasury
+    #[account(
+        mut,
+        address = contract_state.treasury @ DepositContractError::InvalidTreasury
+    )]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RescueTokens<'info> {
+    #[account(
+        seeds = [b"contract_state"],
+        bump = contract_state.bump,
+        constraint = is_emergency_admin(&contract_state, &admin.key()) @ DepositContractError::Unauthorized
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    // The authority or an emergency admin
+    pub admin: Signer<'info>,
+    
+    #[account(
+        seeds = [b"asset_config", mint.key().as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Account<'info, AssetConfig>,
+    
+    pub mint: Account<'info, token::Mint>,
+    
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = contract_state
+    )]
+    pub contract_token_account: Account<'info, TokenAccount>,
+    
+    // Rescued funds always go to the authority, whoever triggers the rescue
+    #[account(
+        mut,
+        token::mint = mint,
+        constraint = destination.owner == contract_state.authority @ DepositContractError::Unauthorized
+    )]
+    pub destination: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ReconcileSpl<'info> {
+    #[account(
+        seeds = [b"contract_state"],
+        bump = contract_state.bump
+    )]
+    pub contract_state: Account<'info, ContractState>,
+    
+    #[account(
+        seeds = [b"asset_config", mint.key().as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Account<'info, AssetConfig>,
+    
+    pub mint: Account<'info, token::Mint>,
+    
+    #[account(
+        associated_token::mint = mint,
+        associated_token::authority = contract_state
+    )]
+    pub contract_token_account: Account<'info, TokenAccount>,
+}
+
+// State Accounts
+
+#[account]
+#[derive(InitSpace)]
+pub struct ContractState {
+    pub authority: Pubkey,
+    pub lending_protocol_address: [u8; 20], // ZetaChain address
+    pub zeta_chain_id: u64,
+    pub is_paused: bool,
+    pub deposit_revert_policy: RevertPolicy, // Deposits and repays
+    pub call_revert_policy: RevertPolicy,    // Cross-chain borrow and withdraw calls
+    pub require_evm_link: bool,              // Deposits must credit a linked EVM address
+    #[max_len(MAX_INCIDENT_METADATA_LEN)]
+    pub incident_metadata: String,           // Emitted when the circuit breaker trips
+    pub total_outstanding_borrows: u128,     // Initiated cross-chain borrows not yet repaid, NORMALIZED_DECIMALS
+    pub global_borrow_cap: u128,             // Upper bound on total_outstanding_borrows
+    pub max_price_age: i64,                  // Seconds an oracle price stays usable in USD caps
+    pub asset_count: u16,                    // AssetConfig PDAs currently open
+    pub destination_chain_count: u16,        // ChainConfig PDAs registered
+    #[max_len(MAX_ALLOWED_CALLER_PROGRAMS)]
+    pub allowed_caller_programs: Vec<Pubkey>, // CPI callers allowed to deposit; empty allows any
+    pub pending_authority: Pubkey,           // Awaiting accept_authority; default when none
+    pub last_pause_change_ts: i64,           // Unix time of the last pause/unpause toggle
+    #[max_len(MAX_EMERGENCY_ADMINS)]
+    pub emergency_admins: Vec<Pubkey>,       // May pause and rescue only
+    pub require_depositor_whitelist: bool,   // Only depositors with a whitelist entry may deposit
+    pub treasury: Pubkey,                    // Fee destination; default when unset (dev deployments)
+    pub gateway_program_id: Pubkey,          // Primary gateway: the only CPI target unless use_failover
+    pub secondary_gateway_program_id: Pubkey, // Failover gateway; default when none was given at initialize
+    pub use_failover: bool,                  // Gateway CPIs target the secondary instead of the primary
+    pub accept_legacy_messages: bool,        // Decoders still accept the colon-delimited format
+    pub min_sol_deposit: u64,                // Lamports, before DEPOSIT_FEE; zero disables the check
+    pub debug_logging: bool,                 // Verbose msg! diagnostics; events are emitted regardless
+    pub beneficiary_allowlist_root: [u8; 32], // Merkle root of allowed on_behalf_of addresses; zero disables
+    pub allow_freezable_mints: bool,         // add_supported_asset accepts mints with a freeze authority
+    pub min_health_factor: u64,              // Basis points a borrow must leave; zero disables the check
+    pub accrued_fees: u64,                   // DEPOSIT_FEE lamports kept by SOL deposits and repays, lifetime
+    pub total_sol_forwarded: u64,            // Lamports those deposits and repays sent to the gateway
+    pub reject_self_borrow: bool,            // Borrows need collateral in an asset other than the borrowed one
+    pub paused_operations: u32,              // PAUSE_* bits of operations paused on their own
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct AssetConfig {
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub is_native: bool,
+    pub is_supported: bool,
+    pub total_received: u64,  // Tokens transferred into the contract vault
+    pub total_forwarded: u64, // Tokens handed to the gateway
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub metadata_uri: String, // Display metadata for clients
+    pub repay_enabled: bool,
+    pub require_exact_amount: bool, // Reject amounts that normalization would truncate
+    pub lending_protocol_address: [u8; 20], // Per-asset receiver override, zero uses the contract default
+    pub min_deposit: u64, // Smallest deposit or repay in base units; zero disables the check
+    pub deposit_cap: u64, // Upper bound on total_deposited; zero means unlimited
+    pub total_deposited: u64, // Base units accepted by deposit_spl_token; repays are not counted
+    pub fee_recipient: Pubkey, // Receives this asset's fees instead of the fee vault; default when unset
+    pub ltv: u16, // Basis points of deposits that may be borrowed against; at most liquidation_threshold
+    pub liquidation_threshold: u16, // Basis points of deposits counted as collateral; zero counts none
+    pub price_feed: Pubkey, // Pyth price account valuing collateral in USD; default when unset
+    pub zrc20: [u8; 20], // This asset's ZRC-20 on ZetaChain; zero when unknown
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct EvmLink {
+    pub user: Pubkey,
+    pub evm_address: [u8; 20], // Verified via secp256k1 signature
+    pub bump: u8,
+}
+
+// Existence of this PDA whitelists its depositor
+#[account]
+#[derive(InitSpace)]
+pub struct DepositorWhitelistEntry {
+    pub depositor: Pubkey,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub recipient_len: u8, // EVM_ADDRESS_LEN for EVM chains
+    pub last_outbound_nonce: u64, // Last nonce the gateway returned for a call to this chain
+    pub bump: u8,
+}
+
+// Per-ZRC-20 borrow accounting; created on first borrow or by set_zrc20_borrow_cap
+#[account]
+#[derive(InitSpace)]
+pub struct Zrc20Config {
+    pub asset: [u8; 20],          // ZRC-20 token address on ZetaChain
+    pub borrow_cap: u64,          // Upper bound on outstanding_borrow; u64::MAX when uncapped
+    pub outstanding_borrow: u64,  // Initiated borrows of this asset not yet repaid
+    pub decimals: u8,             // Normalizes borrows for health checks; zero until set_zrc20_decimals
+    pub bump: u8,
+}
+
+// Deposits one user made of one asset (seeds [b"position", owner, mint]); credited with
+// what each deposit forwards and debited by withdraw_cross_chain, so borrow_cross_chain
+// can value the user's collateral
+#[account]
+#[derive(InitSpace)]
+pub struct CollateralPosition {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,      // Base units of mint
+    pub last_update: i64, // Unix time of the last credit or debit
+    pub bump: u8,
+}
+
+// One user's cross-chain debt (seeds [b"borrow_position", owner])
+#[account]
+#[derive(InitSpace)]
+pub struct BorrowPosition {
+    pub owner: Pubkey,
+    pub borrowed: u128, // NORMALIZED_DECIMALS units initiated and not yet repaid
+    pub bump: u8,
+}
+
+// Helper Functions
+
+// Shared by initialize and initialize_strict
+fn init_contract_state(
+    contract_state: &mut Account<ContractState>,
+    authority: Pubkey,
+    lending_protocol_address: [u8; 20],
+    zeta_chain_id: u64,
+    bump: u8,
+) -> Result<()> {
+    check_authority_candidate(&authority, &contract_state.key())?;
+    require!(
+        lending_protocol_address != [0u8; 20],
+        DepositContractError::InvalidLendingProtocolAddress
+    );
+
+    // Every ContractState field, in declaration order
+    contract_state.authority = authority;
+    contract_state.lending_protocol_address = lending_protocol_address;
+    contract_state.zeta_chain_id = zeta_chain_id;
+    contract_state.is_paused = false;
+    contract_state.deposit_revert_policy = RevertPolicy::default();
+    contract_state.call_revert_policy = RevertPolicy::default();
+    contract_state.require_evm_link = false;
+    contract_state.incident_metadata = String::new();
+    contract_state.total_outstanding_borrows = 0;
+    contract_state.global_borrow_cap = u128::MAX;
+    contract_state.max_price_age = DEFAULT_MAX_PRICE_AGE;
+    contract_state.asset_count = 0;
+    contract_state.destination_chain_count = 0;
+    contract_state.allowed_caller_programs = Vec::new();
+    contract_state.pending_authority = Pubkey::default();
+    contract_state.last_pause_change_ts = 0;
+    contract_state.emergency_admins = Vec::new();
+    contract_state.require_depositor_whitelist = false;
+    contract_state.treasury = Pubkey::default();
+    contract_state.gateway_program_id = GATEWAY_PROGRAM_ID;
+    contract_state.secondary_gateway_program_id = Pubkey::default();
+    contract_state.use_failover = false;
+    contract_state.accept_legacy_messages = true;
+    contract_state.min_sol_deposit = 0;
+    contract_state.debug_logging = true;
+    contract_state.beneficiary_allowlist_root = [0u8; 32];
+    contract_state.allow_freezable_mints = false;
+    contract_state.min_health_factor = 0;
+    contract_state.accrued_fees = 0;
+    contract_state.total_sol_forwarded = 0;
+    contract_state.reject_self_borrow = false;
+    contract_state.paused_operations = 0;
+    contract_state.bump = bump;
+
+    // TODO: Validate lending_protocol_address is a valid ZetaChain address
+    // TODO: Validate zeta_chain_id matches expected ZetaChain network (7000 mainnet, 7001 testnet)
+
+    emit!(ContractInitialized {
+        authority,
+        lending_protocol_address,
+        zeta_chain_id,
+    });
+
+    Ok(())
+}
+
+// Shared by add_supported_asset and add_supported_assets_batch: takes a registry
+// slot and returns the fresh config for the caller to store
+fn register_asset(contract_state: &mut ContractState, item: NewAssetItem, bump: u8) -> Result<AssetConfig> {
+    require!(item.metadata_uri.len() <= MAX_METADATA_URI_LEN, DepositContractError::MetadataUriTooLong);
+    require!(contract_state.asset_count < MAX_ZRC20_ASSETS, DepositContractError::RegistryFull);
+    contract_state.asset_count += 1;
+
+    emit!(AssetAdded {
+        mint: item.mint,
+        decimals: item.decimals,
+        is_native: item.is_native,
+        metadata_uri: item.metadata_uri.clone(),
+    });
+
+    Ok(AssetConfig {
+        mint: item.mint,
+        decimals: item.decimals,
+        is_native: item.is_native,
+        is_supported: true,
+        total_received: 0,
+        total_forwarded: 0,
+        metadata_uri: item.metadata_uri,
+        repay_enabled: true,
+        require_exact_amount: false,
+        min_deposit: 0,
+        deposit_cap: 0,
+        total_deposited: 0,
+        lending_protocol_address: [0u8; 20],
+        fee_recipient: Pubkey::default(),
+        ltv: 0,
+        liquidation_threshold: 0,
+        price_feed: Pubkey::default(),
+        zrc20: [0u8; 20],
+        bump,
+    })
+}
+
+// Checks on the token mint of an asset being added, one or in a batch
+fn check_new_mint(contract_state: &ContractState, mint_account: &token::Mint, decimals: u8) -> Result<()> {
+    // Mis-stated decimals would mis-scale every cross-chain amount for this asset
+    require!(mint_account.decimals == decimals, DepositContractError::DecimalMismatch);
+
+    // The freeze authority could freeze the vault and strand every deposit in it
+    require!(
+        contract_state.allow_freezable_mints || mint_account.freeze_authority.is_none(),
+        DepositContractError::MintHasFreezeAuthority
+    );
+    Ok(())
+}
+
+// Batch counterpart of the `init` constraint on AddSupportedAsset.asset_config
+fn create_asset_config<'info>(
+    contract_state: &mut ContractState,
+    authority: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    asset_config_info: &AccountInfo<'info>,
+    mint_info: &'info AccountInfo<'info>,
+    item: NewAssetItem,
+) -> Result<()> {
+    let (asset_config_key, bump) =
+        Pubkey::find_program_address(&[b"asset_config", item.mint.as_ref()], &crate::ID);
+    require_keys_eq!(asset_config_info.key(), asset_config_key, DepositContractError::InvalidBatchAccounts);
+    require!(asset_config_info.data_is_empty(), DepositContractError::AssetAlreadySupported);
+    require_keys_eq!(mint_info.key(), item.mint, DepositContractError::InvalidBatchAccounts);
+    let mint_account = Account::<token::Mint>::try_from(mint_info)?;
+    check_new_mint(contract_state, &mint_account, item.decimals)?;
+
+    let mint = item.mint;
+    let asset_config = register_asset(contract_state, item, bump)?;
+
+    let space = 8 + AssetConfig::INIT_SPACE;
+    system_program::create_account(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::CreateAccount {
+                from: authority.to_account_info(),
+                to: asset_config_info.clone(),
+            },
+            &[&[b"asset_config", mint.as_ref(), &[bump]]],
+        ),
+        Rent::get()?.minimum_balance(space),
+        space as u64,
+        &crate::ID,
+    )?;
+
+    asset_config.try_serialize(&mut &mut asset_config_info.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+// Shared by deposit_sol and deposit_sol_for; payer funds the deposit, owner is credited
+#[allow(clippy::too_many_arguments)]
+fn process_deposit_sol<'info>(
+    contract_state: &mut Account<'info, ContractState>,
+    payer: &Signer<'info>,
+    evm_link: &Option<Account<'info, EvmLink>>,
+    depositor_whitelist_entry: &Option<Account<'info, DepositorWhitelistEntry>>,
+    collateral_position: &mut Account<'info, CollateralPosition>,
+    collateral_position_bump: u8,
+    fee_vault: &SystemAccount<'info>,
+    native_asset_config: &UncheckedAccount<'info>,
+    fee_recipient: Option<&UncheckedAccount<'info>>,
+    gateway_program: &AccountInfo<'info>,
+    gateway_pda: &UncheckedAccount<'info>,
+    instructions_sysvar: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    remaining_accounts: &[AccountInfo],
+    amount: u64,
+    on_behalf_of: [u8; 20],
+    owner: Pubkey,
+    beneficiary_proof: &[[u8; 32]],
+) -> Result<Vec<u8>> {
+    check_operation_active(contract_state, PAUSE_DEPOSIT)?;
+    require!(amount > 0, DepositContractError::InvalidAmount);
+    check_min_deposit(amount, contract_state.min_sol_deposit)?;
+    let net_amount = net_of_deposit_fee(amount)?;
+    check_no_token_accounts(remaining_accounts)?;
+    check_gateway_program(contract_state, gateway_program)?;
+    check_caller_program(contract_state, instructions_sysvar)?;
+    check_evm_link(contract_state, evm_link, on_behalf_of)?;
+    check_depositor_whitelisted(contract_state, depositor_whitelist_entry)?;
+    check_beneficiary_allowed(contract_state, on_behalf_of, beneficiary_proof)?;
+    check_lamports_capacity(&gateway_pda.to_account_info(), net_amount)?;
+
+    // TODO: Validate on_behalf_of is a valid ZetaChain address format  
+    // TODO: Check user has sufficient SOL balance for the deposit + transaction fees
+
+    // Create message for SimpleLendingProtocol.onCall()
+    let message = create_supply_message(on_behalf_of)?;
+
+    collect_deposit_fee(
+        payer,
+        fee_vault,
+        native_asset_config,
+        fee_recipient,
+        system_program,
+        FeeOperation::Deposit,
+    )?;
+
+    // Invoke gateway deposit_and_call; collateral is only credited for SOL the gateway received
+    let lending_protocol = native_lending_protocol(contract_state, native_asset_config)?;
+    let gateway_balance_before = gateway_pda.lamports();
+    invoke_gateway_deposit_and_call(
+        gateway_program,
+        &payer.to_account_info(),
+        &gateway_pda.to_account_info(),
+        &system_program.to_account_info(),
+        net_amount,
+        lending_protocol,
+        message.clone(),
+        &contract_state.deposit_revert_policy,
+        contract_state.debug_logging,
+    )?;
+    let gateway_received = gateway_pda.lamports().saturating_sub(gateway_balance_before);
+    require!(gateway_received >= net_amount, DepositContractError::DepositFailed);
+    record_sol_split(contract_state, net_amount)?;
+    credit_collateral(collateral_position, owner, system_program::ID, collateral_position_bump, net_amount)?;
+
+    emit!(DepositInitiated {
+        user: payer.key(),
+        owner,
+        asset: system_program::ID, // SOL represented as System Program ID
+        amount: net_amount, // What crossed the bridge, after DEPOSIT_FEE
+        on_behalf_of,
+        lending_protocol,
+        derived_evm_sender: derive_evm_sender(&payer.key()),
+    });
+
+    Ok(message)
+}
+
+fn recover_evm_address(payload: &[u8; 32], signature: &[u8; 64], recovery_id: u8) -> Result<[u8; 20]> {
+    // Accept both raw (0/1) and Ethereum-style (27/28) recovery ids
+    let recovery_id = if recovery_id >= 27 { recovery_id - 27 } else { recovery_id };
+
+    // Same digest EVM wallets produce for personal_sign(payload)
+    let digest = keccak::hashv(&[EVM_SIGNED_MESSAGE_PREFIX, payload]);
+    let pubkey = secp256k1_recover(&digest.to_bytes(), recovery_id, signature)
+        .map_err(|_| DepositContractError::InvalidEvmSignature)?;
+
+    // EVM address is the last 20 bytes of keccak256(uncompressed pubkey)
+    let pubkey_hash = keccak::hash(&pubkey.to_bytes()).to_bytes();
+    let mut evm_address = [0u8; 20];
+    evm_address.copy_from_slice(&pubkey_hash[12..32]);
+    Ok(evm_address)
+}
+
+// Binds EVM link signatures to this program on this ZetaChain network. Cross-chain
+// messages stay unprefixed: onCall only accepts the bare ABI payload lengths.
+fn domain_separator(zeta_chain_id: u64) -> [u8; DOMAIN_SEPARATOR_LEN] {
+    keccak::hashv(&[crate::ID.as_ref(), &zeta_chain_id.to_be_bytes()]).to_bytes()
+}
+
+// A global pause stops everything; paused_operations stops single operations
+fn check_operation_active(
+    contract_state: &ContractState,
+    operation: u32,
+) -> std::result::Result<(), DepositContractError> {
+    if contract_state.is_paused {
+        return Err(DepositContractError::ContractPaused);
+    }
+    if contract_state.paused_operations & operation != 0 {
+        return Err(DepositContractError::OperationPaused);
+    }
+    Ok(())
+}
+
+// Every local check on a borrow, shared by borrow_cross_chain and can_borrow so the dry
+// run cannot drift from the real one. `borrowed` is the user's debt so far and
+// `zrc20_config` None for an asset nothing has opened yet. Returns the debt after the borrow.
+#[allow(clippy::too_many_arguments)]
+fn validate_borrow<'info>(
+    contract_state: &ContractState,
+    chain_config: &ChainConfig,
+    zrc20_config: Option<&Zrc20Config>,
+    borrowed: u128,
+    user: &Pubkey,
+    asset: [u8; 20],
+    amount: u64,
+    recipient: &[u8],
+    collateral_accounts: &'info [AccountInfo<'info>],
+) -> Result<u128> {
+    check_operation_active(contract_state, PAUSE_BORROW)?;
+    require!(amount > 0, DepositContractError::InvalidAmount);
+    let decimals = zrc20_config.map_or(0, |zrc20_config| zrc20_config.decimals);
+    let normalized = normalize_amount(amount, decimals)?;
+    let total_outstanding_borrows = contract_state
+        .total_outstanding_borrows
+        .checked_add(normalized)
+        .ok_or(DepositContractError::ArithmeticOverflow)?;
+    require!(
+        total_outstanding_borrows <= contract_state.global_borrow_cap,
+        DepositContractError::GlobalBorrowCapExceeded
+    );
+    check_recipient(contract_state, chain_config, recipient)?;
+    require!(asset != [0u8; 20], DepositContractError::UnsupportedAsset);
+    if let Some(zrc20_config) = zrc20_config {
+        check_zrc20_borrow_cap(zrc20_config, amount)?;
+    }
+
+    let borrowed = borrowed
+        .checked_add(normalized)
+        .ok_or(DepositContractError::ArithmeticOverflow)?;
+    let min_health_factor = contract_state.min_health_factor;
+    let reject_self_borrow = contract_state.reject_self_borrow;
+    if min_health_factor != 0 || reject_self_borrow {
+        let collateral = weighted_collateral(contract_state, user, asset, collateral_accounts)?;
+        require!(
+            !reject_self_borrow || collateral.other_assets,
+            DepositContractError::SelfBorrow
+        );
+        require!(
+            min_health_factor == 0
+                || compute_health_factor(collateral.weighted, borrowed) >= u128::from(min_health_factor),
+            DepositContractError::InsufficientCollateral
+        );
+    }
+    Ok(borrowed)
+}
+
+// The code a client would see had the error failed the transaction; only non-custom
+// program errors, which carry no u32 code, report u32::MAX
+fn error_code(error: Error) -> u32 {
+    match ProgramError::from(error) {
+        ProgramError::Custom(code) => code,
+        _ => u32::MAX,
+    }
+}
+
+/// Current unix time from the Clock sysvar. Clock::get's own error does not say which
+/// sysvar was missing, so surface ClockUnavailable instead.
+pub fn current_timestamp() -> Result<i64> {
+    Clock::get()
+        .map(|clock| clock.unix_timestamp)
+        .map_err(|_| error!(DepositContractError::ClockUnavailable))
+}
+
+/// Reject oracle prices published more than max_price_age seconds before now.
+/// Shared by every USD-denominated cap check.
+pub fn check_price_fresh(contract_state: &ContractState, publish_time: i64, now: i64) -> Result<()> {
+    let age = now.saturating_sub(publish_time);
+    require!(age <= contract_state.max_price_age, DepositContractError::StalePrice);
+    Ok(())
+}
+
+/// Reject a lamport debit that would leave a program PDA below rent exemption.
+/// Any instruction that moves lamports out of a PDA must call this first; the
+/// PDA may still receive lamports freely.
+pub fn check_rent_exempt_after_debit(account: &AccountInfo, amount: u64) -> Result<()> {
+    // Debiting more than the balance is necessarily below the minimum
+    let remaining = account.lamports().saturating_sub(amount);
+    let minimum = Rent::get()?.minimum_balance(account.data_len());
+    require!(remaining >= minimum, DepositContractError::BelowRentExemption);
+    Ok(())
+}
+
+// Pause and rescue are open to the authority and the emergency admins
+fn is_emergency_admin(contract_state: &ContractState, signer: &Pubkey) -> bool {
+    *signer == contract_state.authority || contract_state.emergency_admins.contains(signer)
+}
+
+// Nothing can sign for the program id, and this program's singleton PDA only signs
+// through our own invoke_signed; either as authority would brick admin access
+fn check_authority_candidate(candidate: &Pubkey, contract_state: &Pubkey) -> Result<()> {
+    require!(
+        *candidate != crate::ID && candidate != contract_state && *candidate != Pubkey::default(),
+        DepositContractError::InvalidAuthority
+    );
+    Ok(())
+}
+
+// Gateway CPIs may only target the program recorded at initialization; any other
+// executable passed as gateway_program could swallow the funds
+fn check_gateway_program(contract_state: &ContractState, gateway_program: &AccountInfo) -> Result<()> {
+    require_keys_eq!(
+        gateway_program.key(),
+        active_gateway_program(contract_state),
+        DepositContractError::UnauthorizedGateway
+    );
+    Ok(())
+}
+
+// The gateway currently accepting CPIs; exactly one of the two at any time
+fn active_gateway_program(contract_state: &ContractState) -> Pubkey {
+    if contract_state.use_failover {
+        contract_state.secondary_gateway_program_id
+    } else {
+        contract_state.gateway_program_id
+    }
+}
+
+// Called once the primary gateway is recorded. A failover identical to the primary
+// could never take over from it.
+fn set_secondary_gateway(contract_state: &mut ContractState, secondary: Option<&UncheckedAccount>) -> Result<()> {
+    if let Some(secondary) = secondary {
+        require!(
+            secondary.key() != contract_state.gateway_program_id,
+            DepositContractError::InvalidSecondaryGateway
+        );
+        contract_state.secondary_gateway_program_id = secondary.key();
+    }
+    Ok(())
+}
+
+// The top-level instruction names the outermost program: this program for a direct
+// call, or the program that reached us via CPI
+fn check_caller_program(contract_state: &ContractState, instructions_sysvar: &AccountInfo) -> Result<()> {
+    if contract_state.allowed_caller_programs.is_empty() {
+        return Ok(());
+    }
+
+    let current = get_instruction_relative(0, instructions_sysvar)?;
+    require!(
+        current.program_id == crate::ID
+            || contract_state.allowed_caller_programs.contains(&current.program_id),
+        DepositContractError::UnauthorizedCaller
+    );
+    Ok(())
+}
+
+// Store the nonce the gateway call just returned (a little-endian u64) for this
+// destination and emit it, so monitors can alert when it stops advancing. A gateway
+// that returns none leaves the stored nonce alone: a locally counted value would look
+// healthy whether or not anything was sent.
+fn record_outbound_nonce(chain_config: &mut ChainConfig, gateway_program: &Pubkey) {
+    let Some(nonce) = get_return_data()
+        .filter(|(program_id, _)| program_id == gateway_program)
+        .and_then(|(_, data)| <[u8; 8]>::try_from(data.as_slice()).ok())
+        .map(u64::from_le_bytes)
+    else {
+        return;
+    };
+    chain_config.last_outbound_nonce = nonce;
+
+    emit!(OutboundNonceAdvanced {
+        chain_id: chain_config.chain_id,
+        nonce,
+    });
+}
+
+/// 20-byte sender the ZetaChain side associates with a Solana account that signs a
+/// gateway call: the last 20 bytes of keccak256(pubkey), the same reduction EVM uses
+/// for public keys. Off-chain code can precompute it to match ZetaChain-side events.
+pub fn derive_evm_sender(signer: &Pubkey) -> [u8; 20] {
+    let hash = keccak::hash(signer.as_ref()).to_bytes();
+    let mut sender = [0u8; 20];
+    sender.copy_from_slice(&hash[12..32]);
+    sender
+}
+
+// Repays may cover interest, so debt floors at zero. Only a ZRC-20 some borrow opened a
+// Zrc20Config for has debt to reduce; its repay is valued like its borrows, normalized
+// with the config's decimals. Returns the NORMALIZED_DECIMALS debt cleared from the
+// borrower's borrow_position, the only part of a repay that frees global capacity. The
+// borrower is on_behalf_of, so a position whose owner's gateway-side sender is some
+// other address clears nothing.
+fn record_repaid(
+    zrc20_config_info: &AccountInfo,
+    borrow_position: Option<&mut BorrowPosition>,
+    on_behalf_of: [u8; 20],
+    amount: u64,
+) -> Result<u128> {
+    // The address is pinned to the repaid asset's ZRC-20, so only existence needs checking
+    if zrc20_config_info.owner != &crate::ID || zrc20_config_info.data_is_empty() {
+        return Ok(0);
+    }
+    let mut zrc20_config = Zrc20Config::try_deserialize(&mut &zrc20_config_info.try_borrow_data()?[..])?;
+    zrc20_config.outstanding_borrow = zrc20_config.outstanding_borrow.saturating_sub(amount);
+    zrc20_config.try_serialize(&mut &mut zrc20_config_info.try_borrow_mut_data()?[..])?;
+
+    let Some(borrow_position) = borrow_position.filter(|position| derive_evm_sender(&position.owner) == on_behalf_of) else {
+        return Ok(0);
+    };
+    // An amount too small to survive normalization clears nothing
+    let cleared = normalize_amount(amount, zrc20_config.decimals)
+        .unwrap_or(0)
+        .min(borrow_position.borrowed);
+    borrow_position.borrowed -= cleared;
+    Ok(cleared)
+}
+
+fn release_borrow_capacity(contract_state: &mut ContractState, cleared: u128) {
+    contract_state.total_outstanding_borrows = contract_state.total_outstanding_borrows.saturating_sub(cleared);
+}
+
+/// Load an asset's Pyth price, rejecting a price account other than the configured
+/// feed and prices older than max_price_age
+pub fn read_price(contract_state: &ContractState, price_feed: &AccountInfo, expected: &Pubkey) -> Result<pyth::Price> {
+    require_keys_eq!(price_feed.key(), *expected, DepositContractError::InvalidPriceFeed);
+    let price = pyth::parse_price(&price_feed.try_borrow_data()?)?;
+    check_price_fresh(contract_state, price.publish_time, current_timestamp()?)?;
+    Ok(price)
+}
+
+/// USD value of a token amount, both in NORMALIZED_DECIMALS units
+pub fn usd_value(normalized: u128, price: &pyth::Price) -> Result<u128> {
+    let price_value = u128::from(price.price);
+    let scale = 10u128
+        .checked_pow(price.expo.unsigned_abs())
+        .ok_or(DepositContractError::InvalidPriceFeed)?;
+    let value = if price.expo < 0 {
+        // Split so the product only overflows when the value itself does
+        normalized
+            .checked_div(scale)
+            .and_then(|whole| whole.checked_mul(price_value))
+            .and_then(|whole| {
+                let fraction = (normalized % scale).checked_mul(price_value)? / scale;
+                whole.checked_add(fraction)
+            })
+    } else {
+        normalized.checked_mul(price_value).and_then(|value| value.checked_mul(scale))
+    };
+    value.ok_or(error!(DepositContractError::ArithmeticOverflow))
+}
+
+/// Health factor in basis points: liquidation-threshold-weighted collateral over debt,
+/// both in NORMALIZED_DECIMALS units. u128::MAX when there is no debt.
+pub fn compute_health_factor(weighted_collateral: u128, debt: u128) -> u128 {
+    if debt == 0 {
+        return u128::MAX;
+    }
+    weighted_collateral.saturating_mul(u128::from(BPS_DENOMINATOR)) / debt
+}
+
+// ltv <= liquidation_threshold <= 100%: a borrow at the ltv limit must not already be
+// liquidatable
+fn check_risk_params(ltv: u16, liquidation_threshold: u16) -> Result<()> {
+    require!(
+        u64::from(liquidation_threshold) <= BPS_DENOMINATOR,
+        DepositContractError::InvalidAmount
+    );
+    require!(ltv <= liquidation_threshold, DepositContractError::InvalidRiskParams);
+    Ok(())
+}
+
+// What a borrower's collateral accounts add up to
+struct Collateral {
+    weighted: u128,     // Sum of each position's value times its liquidation threshold
+    borrowable: u128,   // Sum of each position's value times its ltv
+    other_assets: bool, // Some position in an asset other than the borrowed one is non-empty
+}
+
+// `bps` basis points of value, split so the product cannot overflow u128
+fn apply_bps(value: u128, bps: u16) -> u128 {
+    let bps = u128::from(bps);
+    let denominator = u128::from(BPS_DENOMINATOR);
+    value / denominator * bps + value % denominator * bps / denominator
+}
+
+// CollateralPositions are only created at their [b"position", owner, mint] PDA, so a
+// program-owned one recording this user and the asset's mint is that PDA.
+fn weighted_collateral<'info>(
+    contract_state: &ContractState,
+    user: &Pubkey,
+    borrowed_asset: [u8; 20],
+    accounts: &'info [AccountInfo<'info>],
+) -> Result<Collateral> {
+    require!(
+        accounts.len() <= 3 * MAX_COLLATERAL_POSITIONS,
+        DepositContractError::InvalidCollateralAccounts
+    );
+
+    let mut counted: Vec<Pubkey> = Vec::with_capacity(MAX_COLLATERAL_POSITIONS);
+    let mut weighted = 0u128;
+    let mut borrowable = 0u128;
+    let mut other_assets = false;
+    let mut accounts = accounts.iter();
+    while let Some(asset_config) = accounts.next() {
+        let asset_config = Account::<AssetConfig>::try_from(asset_config)?;
+        let collateral_position = accounts.next().ok_or(DepositContractError::InvalidCollateralAccounts)?;
+        let collateral_position = Account::<CollateralPosition>::try_from(collateral_position)?;
+        require!(
+            collateral_position.owner == *user
+                && collateral_position.mint == asset_config.mint
+                && !counted.contains(&collateral_position.mint)
+                && counted.len() < MAX_COLLATERAL_POSITIONS,
+            DepositContractError::InvalidCollateralAccounts
+        );
+        counted.push(collateral_position.mint);
+        // A removed asset's collateral weighs nothing; its price account, if any, is
+        // still consumed so the account layout does not depend on support status
+        if !asset_config.is_supported {
+            if asset_config.price_feed != Pubkey::default() {
+                accounts.next().ok_or(DepositContractError::InvalidCollateralAccounts)?;
+            }
+            continue;
+        }
+        other_assets |= collateral_position.amount > 0 && asset_config.zrc20 != borrowed_asset;
+
+        let normalized = normalize_amount(collateral_position.amount, asset_config.decimals)?;
+        let value = if asset_config.price_feed == Pubkey::default() {
+            normalized
+        } else {
+            let price_feed = accounts.next().ok_or(DepositContractError::InvalidCollateralAccounts)?;
+            let price = read_price(contract_state, price_feed, &asset_config.price_feed)?;
+            usd_value(normalized, &price)?
+        };
+        weighted = weighted
+            .checked_add(apply_bps(value, asset_config.liquidation_threshold))
+            .ok_or(DepositContractError::ArithmeticOverflow)?;
+        borrowable = borrowable
+            .checked_add(apply_bps(value, asset_config.ltv))
+            .ok_or(DepositContractError::ArithmeticOverflow)?;
+    }
+    Ok(Collateral { weighted, borrowable, other_assets })
+}
+
+// Largest borrow of an asset the collateral supports on top of `borrowed`: within the
+// ltv-weighted value, and within what min_health_factor leaves. Then bounded by the
+// global and per-ZRC-20 borrow caps, in the asset's own units.
+fn max_borrow(
+    contract_state: &ContractState,
+    collateral: &Collateral,
+    borrowed: u128,
+    zrc20_config: Option<&Zrc20Config>,
+) -> u64 {
+    if contract_state.reject_self_borrow && !collateral.other_assets {
+        return 0;
+    }
+    let mut limit = collateral.borrowable;
+    if contract_state.min_health_factor != 0 {
+        let healthy = collateral.weighted.saturating_mul(u128::from(BPS_DENOMINATOR))
+            / u128::from(contract_state.min_health_factor);
+        limit = limit.min(healthy);
+    }
+    let decimals = zrc20_config.map_or(0, |zrc20_config| zrc20_config.decimals);
+    let amount = denormalize_amount(limit.saturating_sub(borrowed), decimals);
+
+    let global_headroom = denormalize_amount(
+        contract_state
+            .global_borrow_cap
+            .saturating_sub(contract_state.total_outstanding_borrows),
+        decimals,
+    );
+    let zrc20_headroom = zrc20_config.map_or(u64::MAX, |zrc20_config| {
+        zrc20_config.borrow_cap.saturating_sub(zrc20_config.outstanding_borrow)
+    });
+    amount.min(global_headroom).min(zrc20_headroom)
+}
+
+// A fresh CollateralPosition (owner still default) is claimed by its first deposit
+fn credit_collateral(
+    collateral_position: &mut CollateralPosition,
+    owner: Pubkey,
+    mint: Pubkey,
+    bump: u8,
+    amount: u64,
+) -> Result<()> {
+    if collateral_position.owner == Pubkey::default() {
+        collateral_position.owner = owner;
+        collateral_position.mint = mint;
+        collateral_position.bump = bump;
+    }
+    collateral_position.amount = collateral_position
+        .amount
+        .checked_add(amount)
+        .ok_or(DepositContractError::ArithmeticOverflow)?;
+    collateral_position.last_update = current_timestamp()?;
+    Ok(())
+}
+
+// ZetaChain holds the authoritative balance, so a withdrawal larger than what this
+// position saw (collateral supplied from other chains) just empties it. A fresh
+// position is claimed the same way credit_collateral claims one.
+fn debit_collateral(
+    collateral_position: &mut CollateralPosition,
+    owner: Pubkey,
+    mint: Pubkey,
+    bump: u8,
+    amount: u64,
+) -> Result<()> {
+    if collateral_position.owner == Pubkey::default() {
+        collateral_position.owner = owner;
+        collateral_position.mint = mint;
+        collateral_position.bump = bump;
+    }
+    collateral_position.amount = collateral_position.amount.saturating_sub(amount);
+    collateral_position.last_update = current_timestamp()?;
+    Ok(())
+}
+
+// Debt at a borrow_position PDA; zero until the user's first borrow opens it.
+// The address is pinned by the account's seeds, so only existence needs checking.
+fn outstanding_debt(borrow_position: &UncheckedAccount) -> Result<u128> {
+    if borrow_position.owner != &crate::ID || borrow_position.data_is_empty() {
+        return Ok(0);
+    }
+    let borrow_position = BorrowPosition::try_deserialize(&mut &borrow_position.try_borrow_data()?[..])?;
+    Ok(borrow_position.borrowed)
+}
+
+// A fresh Zrc20Config (asset still zero) starts uncapped, bounded only by global_borrow_cap
+fn open_zrc20_config(zrc20_config: &mut Zrc20Config, asset: [u8; 20], bump: u8) {
+    if zrc20_config.asset == [0u8; 20] {
+        zrc20_config.asset = asset;
+        zrc20_config.borrow_cap = u64::MAX;
+        zrc20_config.outstanding_borrow = 0;
+        zrc20_config.decimals = 0;
+        zrc20_config.bump = bump;
+    }
+}
+
+fn check_zrc20_borrow_cap(zrc20_config: &Zrc20Config, amount: u64) -> Result<()> {
+    let outstanding_borrow = zrc20_config
+        .outstanding_borrow
+        .checked_add(amount)
+        .ok_or(DepositContractError::ArithmeticOverflow)?;
+    require!(
+        outstanding_borrow <= zrc20_config.borrow_cap,
+        DepositContractError::Zrc20BorrowCapExceeded
+    );
+    Ok(())
+}
+
+fn emit_asset_config_updated(asset_config: &AssetConfig) {
+    emit!(AssetConfigUpdated {
+        mint: asset_config.mint,
+        decimals: asset_config.decimals,
+        is_native: asset_config.is_native,
+        is_supported: asset_config.is_supported,
+        repay_enabled: asset_config.repay_enabled,
+        require_exact_amount: asset_config.require_exact_amount,
+        lending_protocol_address: asset_config.lending_protocol_address,
+        min_deposit: asset_config.min_deposit,
+        deposit_cap: asset_config.deposit_cap,
+        total_deposited: asset_config.total_deposited,
+        fee_recipient: asset_config.fee_recipient,
+        ltv: asset_config.ltv,
+        liquidation_threshold: asset_config.liquidation_threshold,
+        price_feed: asset_config.price_feed,
+        zrc20: asset_config.zrc20,
+        metadata_uri: asset_config.metadata_uri.clone(),
+    });
+}
+
+// SOL deposits and repays keep DEPOSIT_FEE and forward the rest; a deposit
+// that is all fee would send nothing across the bridge
+fn net_of_deposit_fee(amount: u64) -> Result<u64> {
+    let net_amount = amount
+        .checked_sub(DEPOSIT_FEE)
+        .ok_or(DepositContractError::InsufficientDepositFee)?;
+    require!(net_amount > 0, DepositContractError::InvalidAmount);
+    Ok(net_amount)
+}
+
+// Books one SOL deposit or repay of `forwarded + DEPOSIT_FEE` lamports: the fee stays on
+// Solana (accrued_fees) and only `forwarded` reached the gateway (total_sol_forwarded)
+fn record_sol_split(contract_state: &mut ContractState, forwarded: u64) -> Result<()> {
+    contract_state.accrued_fees = contract_state
+        .accrued_fees
+        .checked_add(DEPOSIT_FEE)
+        .ok_or(DepositContractError::ArithmeticOverflow)?;
+    contract_state.total_sol_forwarded = contract_state
+        .total_sol_forwarded
+        .checked_add(forwarded)
+        .ok_or(DepositContractError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+// Pays DEPOSIT_FEE to native SOL's fee_recipient when one is set, otherwise into the
+// fee vault. Emits FeeAccrued ahead of the operation's own event so fee analytics
+// need no join.
+fn collect_deposit_fee<'info>(
+    payer: &Signer<'info>,
+    fee_vault: &SystemAccount<'info>,
+    native_asset_config: &UncheckedAccount<'info>,
+    fee_recipient: Option<&UncheckedAccount<'info>>,
+    system_program: &Program<'info, System>,
+    operation: FeeOperation,
+) -> Result<()> {
+    let destination = match native_fee_recipient(native_asset_config)? {
+        Some(expected) => {
+            let fee_recipient = fee_recipient.ok_or(DepositContractError::InvalidFeeRecipient)?;
+            require_keys_eq!(fee_recipient.key(), expected, DepositContractError::InvalidFeeRecipient);
+            fee_recipient.to_account_info()
+        }
+        None => fee_vault.to_account_info(),
+    };
+    let recipient = destination.key();
+    check_lamports_capacity(&destination, DEPOSIT_FEE)?;
+
+    system_program::transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: payer.to_account_info(),
+                to: destination,
+            },
+        ),
+        DEPOSIT_FEE,
+    )?;
+
+    emit!(FeeAccrued {
+        asset: system_program::ID,
+        amount: DEPOSIT_FEE,
+        operation,
+        recipient,
+    });
+
+    Ok(())
+}
+
+// Native SOL's AssetConfig; None while SOL is unregistered.
+// The address is pinned by the account's seeds, so only existence needs checking.
+fn load_native_asset_config(native_asset_config: &UncheckedAccount) -> Result<Option<AssetConfig>> {
+    if native_asset_config.owner != &crate::ID || native_asset_config.data_is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(AssetConfig::try_deserialize(&mut &native_asset_config.try_borrow_data()?[..])?))
+}
+
+// Receiver of SOL deposits and repays: native SOL's per-asset override once registered
+// with one, the contract-wide lending protocol otherwise
+fn native_lending_protocol(contract_state: &ContractState, native_asset_config: &UncheckedAccount) -> Result<[u8; 20]> {
+    Ok(load_native_asset_config(native_asset_config)?.map_or(contract_state.lending_protocol_address, |asset_config| {
+        resolve_lending_protocol(contract_state, &asset_config)
+    }))
+}
+
+// Native SOL's fee_recipient override; None while SOL is unregistered or has none set
+fn native_fee_recipient(native_asset_config: &UncheckedAccount) -> Result<Option<Pubkey>> {
+    Ok(load_native_asset_config(native_asset_config)?
+        .map(|asset_config| asset_config.fee_recipient)
+        .filter(|recipient| *recipient != Pubkey::default()))
+}
+
+// A repay's zrc20_config must be the PDA of the repaid asset's ZRC-20, so the cap it frees
+// is that asset's. An asset without a known ZRC-20 maps to the zero address's PDA, which
+// borrows never open.
+fn check_repaid_zrc20_config(zrc20: [u8; 20], zrc20_config: &AccountInfo) -> Result<()> {
+    let (expected, _) = Pubkey::find_program_address(&[b"zrc20_config", zrc20.as_ref()], &crate::ID);
+    require_keys_eq!(zrc20_config.key(), expected, DepositContractError::InvalidZrc20Config);
+    Ok(())
+}
+
+// An account can never hold more than u64::MAX lamports; checked on the account a
+// transfer credits, before the transfer
+fn check_lamports_capacity(account: &AccountInfo, amount: u64) -> Result<()> {
+    account
+        .lamports()
+        .checked_add(amount)
+        .ok_or(DepositContractError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+// Beyond the length check, catch the likely client mix-ups: the lending protocol
+// address passed where the destination wallet belongs (on_behalf_of and recipient
+// swapped), or an unset all-zero recipient that would burn the funds
+fn check_recipient(contract_state: &ContractState, chain_config: &ChainConfig, recipient: &[u8]) -> Result<()> {
+    require!(
+        recipient.len() == usize::from(chain_config.recipient_len),
+        DepositContractError::InvalidRecipientLength
+    );
+    require!(
+        recipient != contract_state.lending_protocol_address.as_slice(),
+        DepositContractError::SuspiciousRecipient
+    );
+    require!(
+        recipient.iter().any(|byte| *byte != 0),
+        DepositContractError::SuspiciousRecipient
+    );
+    Ok(())
+}
+
+// SOL paths only move lamports through the system program; any SPL account is a caller error
+fn check_no_token_accounts(accounts: &[AccountInfo]) -> Result<()> {
+    for account in accounts {
+        require!(
+            account.owner != &token::ID
+                && account.key() != token::ID
+                && account.key() != anchor_spl::associated_token::ID,
+            DepositContractError::TokenAccountInSolPath
+        );
+    }
+    Ok(())
+}
+
+fn check_evm_link(
+    contract_state: &ContractState,
+    evm_link: &Option<Account<EvmLink>>,
+    on_behalf_of: [u8; 20],
+) -> Result<()> {
+    if !contract_state.require_evm_link {
+        return Ok(());
+    }
+
+    let evm_link = evm_link.as_ref().ok_or(DepositContractError::EvmLinkRequired)?;
+    require!(evm_link.evm_address == on_behalf_of, DepositContractError::EvmAddressNotLinked);
+    Ok(())
+}
+
+fn check_depositor_whitelisted(
+    contract_state: &ContractState,
+    depositor_whitelist_entry: &Option<Account<DepositorWhitelistEntry>>,
+) -> Result<()> {
+    // The seeds constraint already ties a present entry to the depositor
+    require!(
+        !contract_state.require_depositor_whitelist || depositor_whitelist_entry.is_some(),
+        DepositContractError::DepositorNotWhitelisted
+    );
+    Ok(())
+}
+
+// Leaves are keccak256(on_behalf_of) and each level hashes the sorted pair, matching
+// the sortPairs layout of common EVM merkle tooling. A leaf hashes 20 bytes and a node
+// 64, so an inner node cannot be passed off as a beneficiary.
+fn check_beneficiary_allowed(
+    contract_state: &ContractState,
+    on_behalf_of: [u8; 20],
+    proof: &[[u8; 32]],
+) -> Result<()> {
+    let root = contract_state.beneficiary_allowlist_root;
+    if root == [0u8; 32] {
+        return Ok(());
+    }
+
+    require!(
+        proof.len() <= MAX_BENEFICIARY_PROOF_LEN,
+        DepositContractError::BeneficiaryNotAllowed
+    );
+    let computed = proof.iter().fold(keccak::hash(&on_behalf_of).to_bytes(), |node, sibling| {
+        let (left, right) = if node <= *sibling { (node, *sibling) } else { (*sibling, node) };
+        keccak::hashv(&[&left, &right]).to_bytes()
+    });
+    require!(computed == root, DepositContractError::BeneficiaryNotAllowed);
+    Ok(())
+}
+
+fn build_revert_options(policy: &RevertPolicy, reason: &[u8]) -> RevertOptions {
+    RevertOptions {
+        revert_address: policy.revert_address,
+        call_on_revert: policy.call_on_revert,
+        abort_address: policy.abort_address,
+        revert_message: encode_revert_message(policy.retry_after_seconds, reason),
+        on_revert_gas_limit: GAS_LIMIT,
+    }
+}
+
+/// Revert message layout read by the relayer: `retry_after_seconds` as a 4-byte
+/// big-endian u32 (0 = do not retry), followed by the human-readable reason.
+pub fn encode_revert_message(retry_after_seconds: u32, reason: &[u8]) -> Vec<u8> {
+    [retry_after_seconds.to_be_bytes().as_slice(), reason].concat()
+}
+
+// Per-asset receiver override, falling back to the contract-wide lending protocol
+fn resolve_lending_protocol(contract_state: &ContractState, asset_config: &AssetConfig) -> [u8; 20] {
+    if asset_config.lending_protocol_address == [0u8; 20] {
+        contract_state.lending_protocol_address
+    } else {
+        asset_config.lending_protocol_address
+    }
+}
+
+/// Split `amount` into `(fee, net)` for a basis-point fee.
+///
+/// Rounding policy for basis-point fees: the fee is rounded down and the remainder
+/// goes to the user, so `fee + net == amount` always holds and a user is never
+/// charged more than `fee_bps` of the amount. Amounts below `BPS_DENOMINATOR / fee_bps`
+/// therefore pay no fee. The program charges no bps fee today (deposits and repays pay
+/// the flat DEPOSIT_FEE, withdrawals nothing); one added later must split through here.
+pub fn split_bps_fee(amount: u64, fee_bps: u16) -> Result<(u64, u64)> {
+    require!(u64::from(fee_bps) <= BPS_DENOMINATOR, DepositContractError::InvalidAmount);
+
+    // u128 intermediate so amount * fee_bps cannot overflow
+    let fee = (u128::from(amount) * u128::from(fee_bps) / u128::from(BPS_DENOMINATOR)) as u64;
+    Ok((fee, amount - fee))
+}
+
+/// Seeds the contract_state PDA signs with: it owns every vault, so vault
+/// transfers and gateway deposits out of a vault are signed with these.
+pub fn contract_state_signer_seeds(bump: &[u8; 1]) -> [&[u8]; 2] {
+    [b"contract_state", bump]
+}
+
+// The seeds a CPI is signed with must re-derive the account expected to sign
+fn check_signer_seeds(signer: &AccountInfo, seeds: &[&[u8]]) -> Result<()> {
+    let derived = Pubkey::create_program_address(seeds, &crate::ID)
+        .map_err(|_| DepositContractError::InvalidSignerSeeds)?;
+    require_keys_eq!(signer.key(), derived, DepositContractError::InvalidSignerSeeds);
+    Ok(())
+}
+
+// Smallest amount that survives normalization to NORMALIZED_DECIMALS without truncation
+fn normalization_step(decimals: u8) -> Result<u64> {
+    let excess = decimals.saturating_sub(NORMALIZED_DECIMALS);
+    10u64
+        .checked_pow(u32::from(excess))
+        .ok_or_else(|| DepositContractError::ArithmeticOverflow.into())
+}
+
+/// Inverse of normalize_amount, rounding down and saturating at u64::MAX
+pub fn denormalize_amount(normalized: u128, decimals: u8) -> u64 {
+    let amount = if decimals <= NORMALIZED_DECIMALS {
+        normalized / 10u128.pow(u32::from(NORMALIZED_DECIMALS - decimals))
+    } else {
+        normalization_step(decimals)
+            .map(|step| normalized.saturating_mul(u128::from(step)))
+            .unwrap_or(u128::MAX)
+    };
+    u64::try_from(amount).unwrap_or(u64::MAX)
+}
+
+/// Scales a raw token amount to NORMALIZED_DECIMALS. Fails with InvalidAmount
+/// when the amount has no representation at that precision: the scaled value
+/// overflows, or a non-zero amount of a high-decimals asset rounds to zero.
+pub fn normalize_amount(amount: u64, decimals: u8) -> Result<u128> {
+    let normalized = if decimals <= NORMALIZED_DECIMALS {
+        10u128
+            .checked_pow(u32::from(NORMALIZED_DECIMALS - decimals))
+            .and_then(|scale| u128::from(amount).checked_mul(scale))
+    } else {
+        // A scale beyond u64 truncates every amount to zero
+        Some(
+            normalization_step(decimals)
+                .map(|step| u128::from(amount / step))
+                .unwrap_or(0),
+        )
+    };
+    match normalized {
+        Some(value) if value > 0 || amount == 0 => Ok(value),
+        _ => err!(DepositContractError::InvalidAmount),
+    }
+}
+
+fn check_exact_amount(asset_config: &AssetConfig, amount: u64) -> Result<()> {
+    if asset_config.require_exact_amount {
+        let step = normalization_step(asset_config.decimals)?;
+        require!(amount.is_multiple_of(step), DepositContractError::NonExactAmount);
+    }
+    Ok(())
+}
+
+// A zero minimum leaves only the amount > 0 and deposit fee checks in place
+fn check_min_deposit(amount: u64, min_deposit: u64) -> Result<()> {
+    require!(amount >= min_deposit, DepositContractError::BelowMinimumDeposit);
+    Ok(())
+}
+
+// Net tokens that landed in a vault across a transfer
+fn received_amount(balance_before: u64, balance_after: u64) -> Result<u64> {
+    let received = balance_after
+        .checked_sub(balance_before)
+        .ok_or(DepositContractError::ArithmeticOverflow)?;
+    require!(received > 0, DepositContractError::InvalidAmount);
+    Ok(received)
+}
+
+fn record_received(asset_config: &mut AssetConfig, amount: u64) -> Result<()> {
+    asset_config.total_received = asset_config
+        .total_received
+        .checked_add(amount)
+        .ok_or(DepositContractError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+// Lowering deposit_cap below total_deposited blocks further deposits until it is raised
+fn record_deposited(asset_config: &mut AssetConfig, amount: u64) -> Result<()> {
+    let total_deposited = asset_config
+        .total_deposited
+        .checked_add(amount)
+        .ok_or(DepositContractError::ArithmeticOverflow)?;
+    require!(
+        asset_config.deposit_cap == 0 || total_deposited <= asset_config.deposit_cap,
+        DepositContractError::DepositCapExceeded
+    );
+    asset_config.total_deposited = total_deposited;
+    Ok(())
+}
+
+fn record_forwarded(asset_config: &mut AssetConfig, amount: u64) -> Result<()> {
+    asset_config.total_forwarded = asset_config
+        .total_forwarded
+        .checked_add(amount)
+        .ok_or(DepositContractError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+fn create_supply_message(on_behalf_of: [u8; 20]) -> Result<Vec<u8>> {
+    // Create message compatible with SimpleLendingProtocol.onCall()
+    // Must match EVM encoding: abi.encode("supply", onBehalfOf), 128 bytes, the
+    // only supply/repay length onCall accepts
+    // This creates the exact same encoding as the EVM DepositContract
+    abi::Encoder::new(2)
+        .dynamic(abi::encode_string("supply"))
+        .word(abi::encode_address(&on_behalf_of))
+        .finish()
+}
+
+fn create_repay_message(on_behalf_of: [u8; 20]) -> Result<Vec<u8>> {
+    // Create message compatible with SimpleLendingProtocol.onCall()
+    // Must match EVM encoding: abi.encode("repay", onBehalfOf), 128 bytes, the
+    // only supply/repay length onCall accepts
+    abi::Encoder::new(2)
+        .dynamic(abi::encode_string("repay"))
+        .word(abi::encode_address(&on_behalf_of))
+        .finish()
+}
+
+// Action tag of a message-only cross-chain call. Deliberately not an instruction argument:
+// each instruction hardcodes its own action so a caller can never swap borrow for withdraw.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrossChainAction {
+    Borrow,
+    Withdraw,
+}
+
+impl CrossChainAction {
+    const ALL: [CrossChainAction; 2] = [CrossChainAction::Borrow, CrossChainAction::Withdraw];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CrossChainAction::Borrow => "borrowCrossChain",
+            CrossChainAction::Withdraw => "withdrawCrossChain",
+        }
+    }
+}
+
+// A cross-chain borrow or withdraw message, decoded from either wire format
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CrossChainMessage {
+    pub action: CrossChainAction,
+    pub user: [u8; 32],
+    pub amount: u64,
+    pub destination_chain: u64,
+    pub recipient: Vec<u8>,
+}
+
+/// Decode a borrow/withdraw payload. The ABI layout, abi.encode(string action,
+/// bytes32 user, uint256 amount, uint256 destinationChain, address recipient), is always accepted;
+/// the legacy "action:user:amount:chain:recipient" string only while
+/// `accept_legacy` (ContractState.accept_legacy_messages) is set.
+pub fn decode_cross_chain_message(payload: &[u8], accept_legacy: bool) -> Result<CrossChainMessage> {
+    match legacy_action(payload) {
+        Some(action) => {
+            require!(accept_legacy, DepositContractError::LegacyMessageDisabled);
+            decode_legacy_message(action, payload)
+        }
+        None => decode_abi_message(payload),
+    }
+}
+
+// An ABI payload opens with a zero-padded offset word, so it never matches "action:"
+fn legacy_action(payload: &[u8]) -> Option<CrossChainAction> {
+    CrossChainAction::ALL.into_iter().find(|action| {
+        let tag = action.as_str().as_bytes();
+        payload.starts_with(tag) && payload.get(tag.len()) == Some(&b':')
+    })
 }
 
-fn create_repay_message(on_behalf_of: [u8; 20]) -> Result<Vec<u8>> {
-    // Create message compatible with SimpleLendingProtocol.onCall()
-    // Must match EVM encoding: abi.encode("repay", onBehalfOf) padded to 128 bytes
-    
-    // Convert 20-byte address to 32-byte address (EVM format)
-    let mut evm_address = [0u8; 32];
-    evm_address[12..32].copy_from_slice(&on_behalf_of);
-    
-    // Manually create ABI-encoded message: abi.encode("repay", address)
-    let mut message = Vec::with_capacity(128);
-    
-    // Offset for string "repay" (32 bytes)
-    message.extend_from_slice(&[0u8; 28]); // Padding
-    message.extend_from_slice(&64u32.to_be_bytes()); // Offset to string data
-    
-    // Address (32 bytes)
-    message.extend_from_slice(&evm_address);
-    
-    // String length (32 bytes) - "repay" is 5 bytes
-    message.extend_from_slice(&[0u8; 28]); // Padding
-    message.extend_from_slice(&5u32.to_be_bytes()); // String length
-    
-    // String data "repay" (padded to 32 bytes)
-    message.extend_from_slice(b"repay");
-    message.extend_from_slice(&[0u8; 27]); // Padding to 32 bytes
-    
-    // Pad entire message to exactly 128 bytes
-    message.resize(128, 0);
-    
-    Ok(message)
+fn decode_legacy_message(action: CrossChainAction, payload: &[u8]) -> Result<CrossChainMessage> {
+    let text = std::str::from_utf8(payload).map_err(|_| DepositContractError::MalformedMessage)?;
+    let fields: Vec<&str> = text.split(':').collect();
+    let [_, user, amount, destination_chain, recipient] = fields[..] else {
+        return err!(DepositContractError::MalformedMessage);
+    };
+
+    Ok(CrossChainMessage {
+        action,
+        user: hex::decode(user)
+            .ok()
+            .and_then(|user| user.try_into().ok())
+            .ok_or(DepositContractError::MalformedMessage)?,
+        amount: amount.parse().map_err(|_| DepositContractError::MalformedMessage)?,
+        destination_chain: destination_chain.parse().map_err(|_| DepositContractError::MalformedMessage)?,
+        recipient: hex::decode(recipient).map_err(|_| DepositContractError::MalformedMessage)?,
+    })
+}
+
+fn decode_abi_message(payload: &[u8]) -> Result<CrossChainMessage> {
+    let action_tag = abi::bytes_at(payload, 0)?;
+    let action = CrossChainAction::ALL
+        .into_iter()
+        .find(|action| action.as_str().as_bytes() == action_tag)
+        .ok_or(DepositContractError::MalformedMessage)?;
+
+    Ok(CrossChainMessage {
+        action,
+        user: *abi::word_at(payload, abi::WORD_LEN)?,
+        amount: abi::u64_at(payload, 2 * abi::WORD_LEN)?,
+        destination_chain: abi::u64_at(payload, 3 * abi::WORD_LEN)?,
+        recipient: abi::address_at(payload, 4 * abi::WORD_LEN)?.to_vec(),
+    })
 }
 
 fn create_borrow_cross_chain_message(
     user: [u8; 32], // Solana pubkey is 32 bytes
     amount: u64,
     destination_chain: u64,
-    recipient: [u8; 20],
+    recipient: &[u8],
 ) -> Result<Vec<u8>> {
-    // Create message compatible with SimpleLendingProtocol.onCall()
-    // Must match EVM encoding for dynamic message format used in cross-chain operations
-    // Format: abi.encode("borrowCrossChain", user, amount, destinationChain, recipient)
-    
-    // TODO: Implement proper ABI encoding for cross-chain borrow messages
-    // This needs to match the exact format expected by UniversalLendingProtocol.onCall()
-    // Currently using simplified format - needs to be updated to match EVM ABI encoding
-    let message = format!(
-        "borrowCrossChain:{}:{}:{}:{}",
-        hex::encode(user),
-        amount,
-        destination_chain,
-        hex::encode(recipient)
-    );
-    Ok(message.into_bytes())
+    // Create message compatible with UniversalLendingProtocol.onCall()
+    // Format: abi.encode("borrowCrossChain", user, amount, destinationChain, address recipient)
+    encode_cross_chain_message(CrossChainAction::Borrow, user, amount, destination_chain, recipient)
 }
 
 fn create_withdraw_cross_chain_message(
     user: [u8; 32],
     amount: u64,
     destination_chain: u64,
-    recipient: [u8; 20],
+    recipient: &[u8],
 ) -> Result<Vec<u8>> {
-    // Create message compatible with SimpleLendingProtocol.onCall()
-    // Must match EVM encoding for dynamic message format used in cross-chain operations
-    // Format: abi.encode("withdrawCrossChain", user, amount, destinationChain, recipient)
-    
-    // TODO: Implement proper ABI encoding for cross-chain withdraw messages
-    // This needs to match the exact format expected by UniversalLendingProtocol.onCall()
-    // Currently using simplified format - needs to be updated to match EVM ABI encoding
-    let message = format!(
-        "withdrawCrossChain:{}:{}:{}:{}",
-        hex::encode(user),
-        amount,
-        destination_chain,
-        hex::encode(recipient)
-    );
-    Ok(message.into_bytes())
+    // Create message compatible with UniversalLendingProtocol.onCall()
+    // Format: abi.encode("withdrawCrossChain", user, amount, destinationChain, address recipient)
+    encode_cross_chain_message(CrossChainAction::Withdraw, user, amount, destination_chain, recipient)
+}
+
+// abi.encode(string action, bytes32 user, uint256 amount, uint256 destinationChain,
+// address recipient): the 224-byte layout onCall decodes for both actions
+fn encode_cross_chain_message(
+    action: CrossChainAction,
+    user: [u8; 32],
+    amount: u64,
+    destination_chain: u64,
+    recipient: &[u8],
+) -> Result<Vec<u8>> {
+    let recipient: &[u8; 20] = recipient
+        .try_into()
+        .map_err(|_| DepositContractError::InvalidRecipientLength)?;
+
+    abi::Encoder::new(5)
+        .dynamic(abi::encode_string(action.as_str()))
+        .word(abi::encode_bytes32(&user))
+        .word(abi::encode_u256(amount))
+        .word(abi::encode_u256(destination_chain))
+        .word(abi::encode_address(recipient))
+        .finish()
+}
+
+// Solidity ABI encoding for messages read by the EVM lending protocol. Every encode_*
+// primitive returns whole 32-byte words; Encoder lays out heads and tails.
+mod abi {
+    use super::*;
+
+    pub const WORD_LEN: usize = 32;
+
+    /// uint256 word holding a u64, big-endian
+    pub fn encode_u256(value: u64) -> Vec<u8> {
+        let mut word = vec![0u8; WORD_LEN];
+        word[WORD_LEN - 8..].copy_from_slice(&value.to_be_bytes());
+        word
+    }
+
+    /// address word: the 20 bytes left-padded with zeros
+    pub fn encode_address(address: &[u8; 20]) -> Vec<u8> {
+        let mut word = vec![0u8; WORD_LEN];
+        word[WORD_LEN - address.len()..].copy_from_slice(address);
+        word
+    }
+
+    pub fn encode_bytes32(value: &[u8; 32]) -> Vec<u8> {
+        value.to_vec()
+    }
+
+    /// Tail of a dynamic string: length word, then the data right-padded to a word boundary
+    pub fn encode_string(value: &str) -> Vec<u8> {
+        encode_bytes(value.as_bytes())
+    }
+
+    /// Tail of a dynamic bytes value, laid out like encode_string
+    pub fn encode_bytes(value: &[u8]) -> Vec<u8> {
+        let mut tail = encode_u256(value.len() as u64);
+        tail.extend_from_slice(value);
+        tail.resize(WORD_LEN + value.len().div_ceil(WORD_LEN) * WORD_LEN, 0);
+        tail
+    }
+
+    /// abi.encode over a fixed number of top-level fields, added in order. Static fields
+    /// go straight into the head; dynamic fields leave an offset word there and append
+    /// their tail after the head.
+    pub struct Encoder {
+        head_len: usize,
+        head: Vec<u8>,
+        tail: Vec<u8>,
+    }
+
+    impl Encoder {
+        pub fn new(field_count: usize) -> Self {
+            let head_len = field_count * WORD_LEN;
+            Self {
+                head_len,
+                head: Vec::with_capacity(head_len),
+                tail: Vec::new(),
+            }
+        }
+
+        /// Static field: one encode_u256/encode_address/encode_bytes32 word
+        pub fn word(mut self, word: Vec<u8>) -> Self {
+            debug_assert_eq!(word.len(), WORD_LEN);
+            self.head.extend_from_slice(&word);
+            self
+        }
+
+        /// Dynamic field: an encode_string/encode_bytes tail
+        pub fn dynamic(mut self, tail: Vec<u8>) -> Self {
+            let offset = self.head_len + self.tail.len();
+            self.head.extend_from_slice(&encode_u256(offset as u64));
+            self.tail.extend_from_slice(&tail);
+            self
+        }
+
+        /// Joins head and tail, failing with EncodingError instead of emitting a message
+        /// whose fields overran or underfilled the head declared in `new`, or whose tails
+        /// were not padded to whole words
+        pub fn finish(mut self) -> Result<Vec<u8>> {
+            require!(
+                self.head.len() == self.head_len && self.tail.len().is_multiple_of(WORD_LEN),
+                DepositContractError::EncodingError
+            );
+            self.head.extend_from_slice(&self.tail);
+            Ok(self.head)
+        }
+    }
+
+    pub fn word_at(payload: &[u8], offset: usize) -> Result<&[u8; WORD_LEN]> {
+        let end = offset.checked_add(WORD_LEN).ok_or(DepositContractError::MalformedMessage)?;
+        let word = payload.get(offset..end).ok_or(DepositContractError::MalformedMessage)?;
+        Ok(word.try_into().expect("slice is one word"))
+    }
+
+    // A uint256 word that must fit in a u64
+    pub fn u64_at(payload: &[u8], offset: usize) -> Result<u64> {
+        let word = word_at(payload, offset)?;
+        let (high, low) = word.split_at(WORD_LEN - 8);
+        require!(high.iter().all(|b| *b == 0), DepositContractError::MalformedMessage);
+        Ok(u64::from_be_bytes(low.try_into().expect("low 8 bytes")))
+    }
+
+    // An address word: 20 bytes left-padded with zeros
+    pub fn address_at(payload: &[u8], offset: usize) -> Result<&[u8]> {
+        let word = word_at(payload, offset)?;
+        let (padding, address) = word.split_at(WORD_LEN - usize::from(EVM_ADDRESS_LEN));
+        require!(padding.iter().all(|b| *b == 0), DepositContractError::MalformedMessage);
+        Ok(address)
+    }
+
+    // A dynamic string/bytes field: the head word holds the offset of its length word
+    pub fn bytes_at(payload: &[u8], head_offset: usize) -> Result<&[u8]> {
+        let start = usize::try_from(u64_at(payload, head_offset)?).map_err(|_| DepositContractError::MalformedMessage)?;
+        let len = usize::try_from(u64_at(payload, start)?).map_err(|_| DepositContractError::MalformedMessage)?;
+        let data_start = start + WORD_LEN;
+        let data_end = data_start.checked_add(len).ok_or(DepositContractError::MalformedMessage)?;
+        payload
+            .get(data_start..data_end)
+            .ok_or_else(|| DepositContractError::MalformedMessage.into())
+    }
+}
+
+// Pyth push-oracle price accounts, read at the fixed offsets of their v2 layout (the one
+// pyth-sdk-solana parses; that crate predates the Solana 2.x toolchain Anchor 0.31 needs)
+mod pyth {
+    use super::*;
+
+    const MAGIC: u32 = 0xa1b2_c3d4;
+    const VERSION: u32 = 2;
+    const ACCOUNT_TYPE_PRICE: u32 = 3;
+    const STATUS_TRADING: u32 = 1;
+
+    const EXPO_OFFSET: usize = 20;
+    const TIMESTAMP_OFFSET: usize = 96; // Publish time of the aggregate price
+    const AGG_PRICE_OFFSET: usize = 208;
+    const AGG_STATUS_OFFSET: usize = 224;
+    const MIN_LEN: usize = 240; // Through the aggregate price; publisher components follow
+
+    /// Aggregate price: `price * 10^expo` USD per whole token
+    pub struct Price {
+        pub price: u64,
+        pub expo: i32,
+        pub publish_time: i64,
+    }
+
+    fn read<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+        data[offset..offset + N].try_into().expect("offset within MIN_LEN")
+    }
+
+    /// The aggregate price of a price account that is currently trading
+    pub fn parse_price(data: &[u8]) -> Result<Price> {
+        require!(data.len() >= MIN_LEN, DepositContractError::InvalidPriceFeed);
+        let header = [0, 4, 8].map(|offset| u32::from_le_bytes(read(data, offset)));
+        require!(
+            header == [MAGIC, VERSION, ACCOUNT_TYPE_PRICE],
+            DepositContractError::InvalidPriceFeed
+        );
+        require!(
+            u32::from_le_bytes(read(data, AGG_STATUS_OFFSET)) == STATUS_TRADING,
+            DepositContractError::InvalidPriceFeed
+        );
+        let price = i64::from_le_bytes(read(data, AGG_PRICE_OFFSET));
+        require!(price > 0, DepositContractError::InvalidPriceFeed);
+
+        Ok(Price {
+            price: price.unsigned_abs(),
+            expo: i32::from_le_bytes(read(data, EXPO_OFFSET)),
+            publish_time: i64::from_le_bytes(read(data, TIMESTAMP_OFFSET)),
+        })
+    }
 }
 
 // Gateway invocation functions - Proper CPI calls to ZetaChain Gateway
-fn invoke_gateway_deposit_and_call(
-    gateway_program: &AccountInfo,
-    user: &AccountInfo,
-    _contract_state: &AccountInfo,
+// The user signs the outer transaction, so their signature carries into the CPI and
+// the gateway moves `amount` lamports from them into its PDA
+#[allow(clippy::too_many_arguments)]
+fn invoke_gateway_deposit_and_call<'info>(
+    gateway_program: &AccountInfo<'info>,
+    user: &AccountInfo<'info>,
+    gateway_pda: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
     amount: u64,
     receiver: [u8; 20],
     message: Vec<u8>,
+    revert_policy: &RevertPolicy,
+    debug_logging: bool,
 ) -> Result<()> {
-    // TODO: Implement proper CPI call to Gateway's deposit_and_call function
-    // This needs to:
-    // 1. Create RevertOptions with proper error handling
-    // 2. Build instruction with correct accounts (gateway PDA, user, system program, etc.)
-    // 3. Invoke the Gateway program with proper account metas
-    // 4. Handle revert scenarios and error cases
-    
     // Create revert options for cross-chain transaction safety
-    let revert_options = Some(RevertOptions {
-        revert_address: [0u8; 20], // TODO: Convert Solana address to EVM format
-        call_on_revert: true,
-        abort_address: [0u8; 20], // TODO: Set proper abort address
-        revert_message: b"SOL deposit failed".to_vec(),
-        on_revert_gas_limit: GAS_LIMIT,
-    });
-    
-    // Placeholder implementation - replace with actual Gateway CPI
-    msg!("Gateway deposit_and_call invoked: amount={}, receiver={:?}", amount, receiver);
-    msg!("Message length: {}, revert_options configured", message.len());
-    
-    // TODO: Replace with actual CPI call:
-    // let instruction = create_gateway_deposit_and_call_instruction(
-    //     gateway_program.key(),
-    //     user.key(),
-    //     amount,
-    //     receiver,
-    //     message,
-    //     revert_options,
-    // )?;
-    // invoke(&instruction, &[gateway_program, user, system_program])?;
-    
+    let revert_options = Some(build_revert_options(revert_policy, b"SOL deposit failed"));
+
+    if debug_logging {
+        msg!("Gateway deposit_and_call invoked: amount={}, receiver={:?}", amount, receiver);
+        msg!("Message length: {}, revert_options configured", message.len());
+    }
+
+    let mut data = gateway_sighash("deposit_and_call").to_vec();
+    (amount, receiver, message, revert_options).serialize(&mut data)?;
+
+    let instruction = Instruction {
+        program_id: gateway_program.key(),
+        accounts: vec![
+            AccountMeta::new(user.key(), true),
+            AccountMeta::new(gateway_pda.key(), false),
+            AccountMeta::new_readonly(system_program.key(), false),
+        ],
+        data,
+    };
+
+    invoke(
+        &instruction,
+        &[user.clone(), gateway_pda.clone(), system_program.clone(), gateway_program.clone()],
+    )?;
+
     Ok(())
 }
 
+// Accounts of the gateway's deposit_spl_token_and_call, in the order it expects them
+struct GatewaySplDeposit<'a, 'info> {
+    gateway_program: &'a AccountInfo<'info>,
+    contract_state: &'a AccountInfo<'info>, // Signer: owns the vault the tokens leave from
+    gateway_pda: &'a AccountInfo<'info>,
+    whitelist_entry: &'a AccountInfo<'info>,
+    mint: &'a AccountInfo<'info>,
+    token_program: &'a AccountInfo<'info>,
+    from: &'a AccountInfo<'info>, // Contract vault
+    to: &'a AccountInfo<'info>,   // Gateway PDA's token account
+    system_program: &'a AccountInfo<'info>,
+}
+
+// Anchor instruction discriminator of a gateway entrypoint
+fn gateway_sighash(name: &str) -> [u8; 8] {
+    let mut sighash = [0u8; 8];
+    sighash.copy_from_slice(&hash::hashv(&[b"global:", name.as_bytes()]).to_bytes()[..8]);
+    sighash
+}
+
 fn invoke_gateway_deposit_spl_token_and_call(
-    gateway_program: &AccountInfo,
-    token_account: &AccountInfo,
-    mint: &AccountInfo,
+    accounts: GatewaySplDeposit,
+    contract_state_bump: u8,
     amount: u64,
     receiver: [u8; 20],
     message: Vec<u8>,
+    revert_policy: &RevertPolicy,
 ) -> Result<()> {
-    // TODO: Implement proper CPI call to Gateway's deposit_spl_token_and_call function
-    // This needs to:
-    // 1. Create RevertOptions with proper error handling
-    // 2. Build instruction with correct accounts (gateway PDA, user, token accounts, mint, etc.)
-    // 3. Invoke the Gateway program with proper account metas for SPL token operations
-    // 4. Handle token approval and transfer mechanics
-    // 5. Handle revert scenarios and error cases
-    
+    // The earlier transfer must have landed; never ask the gateway to move more than the vault holds
+    let vault = TokenAccount::try_deserialize(&mut &accounts.from.try_borrow_data()?[..])?;
+    require!(vault.amount >= amount, DepositContractError::InsufficientVaultBalance);
+
     // Create revert options for cross-chain transaction safety
-    let revert_options = Some(RevertOptions {
-        revert_address: [0u8; 20], // TODO: Convert Solana address to EVM format
-        call_on_revert: true,
-        abort_address: [0u8; 20], // TODO: Set proper abort address
-        revert_message: b"SPL token deposit failed".to_vec(),
-        on_revert_gas_limit: GAS_LIMIT,
-    });
-    
-    // Placeholder implementation - replace with actual Gateway CPI
-    msg!("Gateway deposit_spl_token_and_call invoked: amount={}, receiver={:?}", amount, receiver);
-    msg!("Token account: {}, mint: {}", token_account.key(), mint.key());
-    msg!("Message length: {}, revert_options configured", message.len());
-    
-    // TODO: Replace with actual CPI call:
-    // let instruction = create_gateway_deposit_spl_token_and_call_instruction(
-    //     gateway_program.key(),
-    //     token_account.key(),
-    //     mint.key(),
-    //     amount,
-    //     receiver,
-    //     message,
-    //     revert_options,
-    // )?;
-    // invoke(&instruction, &[gateway_program, token_account, mint, ...])?;
-    
+    let revert_options = Some(build_revert_options(revert_policy, b"SPL token deposit failed"));
+
+    let mut data = gateway_sighash("deposit_spl_token_and_call").to_vec();
+    (amount, receiver, message, revert_options).serialize(&mut data)?;
+
+    let instruction = Instruction {
+        program_id: accounts.gateway_program.key(),
+        accounts: vec![
+            AccountMeta::new(accounts.contract_state.key(), true),
+            AccountMeta::new(accounts.gateway_pda.key(), false),
+            AccountMeta::new_readonly(accounts.whitelist_entry.key(), false),
+            AccountMeta::new_readonly(accounts.mint.key(), false),
+            AccountMeta::new_readonly(accounts.token_program.key(), false),
+            AccountMeta::new(accounts.from.key(), false),
+            AccountMeta::new(accounts.to.key(), false),
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+        ],
+        data,
+    };
+
+    // A stale bump would only surface as a missing-signature error inside the gateway
+    let bump = [contract_state_bump];
+    let signer_seeds = contract_state_signer_seeds(&bump);
+    check_signer_seeds(accounts.contract_state, &signer_seeds)?;
+
+    invoke_signed(
+        &instruction,
+        &[
+            accounts.contract_state.clone(),
+            accounts.gateway_pda.clone(),
+            accounts.whitelist_entry.clone(),
+            accounts.mint.clone(),
+            accounts.token_program.clone(),
+            accounts.from.clone(),
+            accounts.to.clone(),
+            accounts.system_program.clone(),
+            accounts.gateway_program.clone(),
+        ],
+        &[&signer_seeds],
+    )?;
+
     Ok(())
 }
 
-fn invoke_gateway_call(
-    gateway_program: &AccountInfo,
-    user: &AccountInfo,
+// Message-only gateway call (no asset transfer), signed by the user
+fn invoke_gateway_call<'info>(
+    gateway_program: &AccountInfo<'info>,
+    user: &AccountInfo<'info>,
     receiver: [u8; 20],
     message: Vec<u8>,
+    revert_policy: &RevertPolicy,
+    debug_logging: bool,
 ) -> Result<()> {
-    // TODO: Implement proper CPI call to Gateway's call function
-    // This needs to:
-    // 1. Create RevertOptions with proper error handling
-    // 2. Build instruction with correct accounts (gateway PDA, user, system program, etc.)
-    // 3. Invoke the Gateway program for message-only calls (no asset transfer)
-    // 4. Handle revert scenarios and error cases
-    
+    // A message-only call with no payload would be a no-op on ZetaChain
+    require!(!message.is_empty(), DepositContractError::EmptyMessage);
+
     // Create revert options for cross-chain transaction safety
-    let revert_options = Some(RevertOptions {
-        revert_address: [0u8; 20], // TODO: Convert Solana address to EVM format
-        call_on_revert: true,
-        abort_address: [0u8; 20], // TODO: Set proper abort address
-        revert_message: b"Cross-chain call failed".to_vec(),
-        on_revert_gas_limit: GAS_LIMIT,
-    });
-    
-    // Placeholder implementation - replace with actual Gateway CPI
-    msg!("Gateway call invoked: receiver={:?}", receiver);
-    msg!("User: {}, message length: {}", user.key(), message.len());
-    msg!("Revert options configured for cross-chain safety");
-    
-    // TODO: Replace with actual CPI call:
-    // let instruction = create_gateway_call_instruction(
-    //     gateway_program.key(),
-    //     user.key(),
-    //     receiver,
-    //     message,
-    //     revert_options,
-    // )?;
-    // invoke(&instruction, &[gateway_program, user, system_program])?;
-    
+    let revert_options = Some(build_revert_options(revert_policy, b"Cross-chain call failed"));
+
+    if debug_logging {
+        msg!("Gateway call invoked: receiver={:?}", receiver);
+        msg!("User: {}, message length: {}", user.key(), message.len());
+    }
+
+    let mut data = gateway_sighash("call").to_vec();
+    (receiver, message, revert_options).serialize(&mut data)?;
+
+    let instruction = Instruction {
+        program_id: gateway_program.key(),
+        accounts: vec![AccountMeta::new(user.key(), true)],
+        data,
+    };
+
+    invoke(&instruction, &[user.clone(), gateway_program.clone()])?;
+
     Ok(())
 }
 
@@ -896,11 +4757,30 @@ pub struct ContractInitialized {
     pub zeta_chain_id: u64,
 }
 
+#[event]
+pub struct GatewayFailoverChanged {
+    pub use_failover: bool,
+    pub gateway_program: Pubkey, // Gateway CPIs now target this program
+}
+
 #[event]
 pub struct AssetAdded {
     pub mint: Pubkey,
     pub decimals: u8,
     pub is_native: bool,
+    pub metadata_uri: String,
+}
+
+#[event]
+pub struct AssetConfigClosed {
+    pub mint: Pubkey,
+    pub refunded_lamports: u64,
+}
+
+#[event]
+pub struct AssetVaultCreated {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
 }
 
 #[event]
@@ -908,6 +4788,26 @@ pub struct AssetRemoved {
     pub mint: Pubkey,
 }
 
+#[event]
+pub struct AssetConfigUpdated {
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub is_native: bool,
+    pub is_supported: bool,
+    pub repay_enabled: bool,
+    pub require_exact_amount: bool,
+    pub lending_protocol_address: [u8; 20],
+    pub min_deposit: u64,
+    pub deposit_cap: u64,
+    pub total_deposited: u64,
+    pub fee_recipient: Pubkey,
+    pub ltv: u16,
+    pub liquidation_threshold: u16,
+    pub price_feed: Pubkey,
+    pub zrc20: [u8; 20],
+    pub metadata_uri: String,
+}
+
 #[event]
 pub struct LendingProtocolAddressUpdated {
     pub old_address: [u8; 20],
@@ -917,10 +4817,13 @@ pub struct LendingProtocolAddressUpdated {
 
 #[event]
 pub struct DepositInitiated {
-    pub user: Pubkey,
+    pub user: Pubkey,  // Payer
+    pub owner: Pubkey, // Beneficiary; differs from user for sponsored deposits
     pub asset: Pubkey,
     pub amount: u64,
     pub on_behalf_of: [u8; 20],
+    pub lending_protocol: [u8; 20], // Resolved receiver on ZetaChain
+    pub derived_evm_sender: [u8; 20], // Sender the ZetaChain onCall sees; see derive_evm_sender
 }
 
 #[event]
@@ -937,7 +4840,14 @@ pub struct BorrowCrossChainInitiated {
     pub asset: [u8; 20],
     pub amount: u64,
     pub destination_chain: u64,
-    pub recipient: [u8; 20],
+    pub recipient: Vec<u8>,
+}
+
+#[event]
+pub struct MaxBorrowComputed {
+    pub user: Pubkey,
+    pub asset: [u8; 20],
+    pub max_amount: u64, // Base units of the ZRC-20
 }
 
 #[event]
@@ -946,7 +4856,48 @@ pub struct WithdrawCrossChainInitiated {
     pub asset: [u8; 20],
     pub amount: u64,
     pub destination_chain: u64,
-    pub recipient: [u8; 20],
+    pub recipient: Vec<u8>,
+}
+
+#[event]
+pub struct AuthorityTransferProposed {
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub previous_authority: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct OutboundNonceAdvanced {
+    pub chain_id: u64,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct AllowedCallerProgramsUpdated {
+    pub programs: Vec<Pubkey>,
+}
+
+#[event]
+pub struct MaxPriceAgeUpdated {
+    pub max_price_age: i64,
+}
+
+#[event]
+pub struct GlobalBorrowCapUpdated {
+    pub global_borrow_cap: u128,
+    pub total_outstanding_borrows: u128,
+}
+
+#[event]
+pub struct Zrc20BorrowCapUpdated {
+    pub asset: [u8; 20],
+    pub borrow_cap: u64,
+    pub outstanding_borrow: u64,
 }
 
 #[event]
@@ -954,6 +4905,160 @@ pub struct PauseStateChanged {
     pub is_paused: bool,
 }
 
+#[event]
+pub struct PausedOperationsUpdated {
+    pub paused_operations: u32,
+}
+
+#[event]
+pub struct CircuitBreakerTripped {
+    pub incident_metadata: String,
+}
+
+#[event]
+pub struct IncidentMetadataUpdated {
+    pub incident_metadata: String,
+}
+
+#[event]
+pub struct RevertPolicyUpdated {
+    pub operation: OperationType,
+    pub policy: RevertPolicy,
+}
+
+#[event]
+pub struct EvmAddressLinked {
+    pub user: Pubkey,
+    pub evm_address: [u8; 20],
+}
+
+#[event]
+pub struct ChainConfigUpdated {
+    pub chain_id: u64,
+    pub recipient_len: u8,
+}
+
+#[event]
+pub struct EvmLinkRequirementChanged {
+    pub required: bool,
+}
+
+#[event]
+pub struct LegacyMessagesAcceptanceChanged {
+    pub accepted: bool,
+}
+
+#[event]
+pub struct DebugLoggingChanged {
+    pub enabled: bool,
+}
+
+#[event]
+pub struct MinSolDepositUpdated {
+    pub min_sol_deposit: u64,
+}
+
+#[event]
+pub struct BeneficiaryAllowlistRootUpdated {
+    pub root: [u8; 32],
+}
+
+#[event]
+pub struct FreezableMintsAllowanceChanged {
+    pub allowed: bool,
+}
+
+#[event]
+pub struct Zrc20DecimalsUpdated {
+    pub asset: [u8; 20],
+    pub decimals: u8,
+}
+
+#[event]
+pub struct MinHealthFactorUpdated {
+    pub min_health_factor: u64,
+}
+
+#[event]
+pub struct SelfBorrowGuardChanged {
+    pub enabled: bool,
+}
+
+#[event]
+pub struct DepositorWhitelistRequirementChanged {
+    pub required: bool,
+}
+
+#[event]
+pub struct DepositorWhitelistUpdated {
+    pub depositor: Pubkey,
+    pub whitelisted: bool,
+}
+
+#[event]
+pub struct EmergencyAdminsUpdated {
+    pub admins: Vec<Pubkey>,
+}
+
+#[event]
+pub struct TokensRescued {
+    pub admin: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+}
+
+#[event]
+pub struct LendingProtocolRotated {
+    pub old_address: [u8; 20],
+    pub new_address: [u8; 20],
+    pub default_rotated: bool, // Whether the contract-wide default pointed at old_address
+    pub routes_rotated: u16,   // Per-asset routes repointed
+}
+
+#[event]
+pub struct FeeAccrued {
+    pub asset: Pubkey, // System Program ID for SOL
+    pub amount: u64,
+    pub operation: FeeOperation,
+    pub recipient: Pubkey, // fee_vault, or the asset's fee_recipient when set
+}
+
+#[event]
+pub struct SolDustSwept {
+    pub treasury: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct FeesWithdrawn {
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub remaining: u64, // Lamports left in the fee vault
+}
+
+#[event]
+pub struct ConfigSnapshot {
+    pub authority: Pubkey,
+    pub deposit_fee: u64,                   // Lamports charged per SOL deposit and repay
+    pub global_borrow_cap: u128,
+    pub total_outstanding_borrows: u128,
+    pub max_price_age: i64,
+    pub is_paused: bool,
+    pub last_pause_change_ts: i64,
+    pub lending_protocol_address: [u8; 20], // Default route; per-asset overrides live in AssetConfig
+    pub asset_count: u16,                   // Supported assets (AssetConfig PDAs open)
+    pub destination_chain_count: u16,
+}
+
+#[event]
+pub struct SplReconciled {
+    pub mint: Pubkey,
+    pub vault_balance: u64,
+    pub expected_balance: u64,
+    pub difference: i64,
+}
+
 // Errors
 
 #[error_code]
@@ -976,4 +5081,271 @@ pub enum DepositContractError {
     UseRepaySol,
     #[msg("Insufficient deposit fee")]
     InsufficientDepositFee,
+    #[msg("Arithmetic overflow")]
+    ArithmeticOverflow,
+    #[msg("Gateway call message is empty")]
+    EmptyMessage,
+    #[msg("Invalid EVM signature")]
+    InvalidEvmSignature,
+    #[msg("EVM signature was not produced by the given address")]
+    EvmSignatureMismatch,
+    #[msg("A verified EVM address link is required")]
+    EvmLinkRequired,
+    #[msg("on_behalf_of is not the depositor's linked EVM address")]
+    EvmAddressNotLinked,
+    #[msg("Batch remaining accounts do not match the batch items")]
+    InvalidBatchAccounts,
+    #[msg("Metadata URI exceeds maximum length")]
+    MetadataUriTooLong,
+    #[msg("Batch exceeds the maximum size")]
+    BatchTooLarge,
+    #[msg("Return data exceeds the runtime limit")]
+    ReturnDataTooLarge,
+    #[msg("Incident metadata exceeds maximum length")]
+    IncidentMetadataTooLong,
+    #[msg("Repays are disabled for this asset")]
+    RepayDisabled,
+    #[msg("Amount is not an exact multiple of the asset's normalization step")]
+    NonExactAmount,
+    #[msg("Borrow would exceed the global outstanding borrow cap")]
+    GlobalBorrowCapExceeded,
+    #[msg("Asset must be removed before its config can be closed")]
+    AssetStillSupported,
+    #[msg("Asset vault still holds tokens")]
+    VaultNotEmpty,
+    #[msg("Account is not the contract vault for this asset")]
+    InvalidVaultAccount,
+    #[msg("SOL instructions do not accept SPL token accounts")]
+    TokenAccountInSolPath,
+    #[msg("Recipient length does not match the destination chain")]
+    InvalidRecipientLength,
+    #[msg("Asset is already configured")]
+    AssetAlreadySupported,
+    #[msg("Oracle price is older than the configured max_price_age")]
+    StalePrice,
+    #[msg("Registry is full")]
+    RegistryFull,
+    #[msg("Withdrawal would leave the account below rent exemption")]
+    BelowRentExemption,
+    #[msg("Calling program is not allowed to invoke this instruction")]
+    UnauthorizedCaller,
+    #[msg("Recipient is the lending protocol or the zero address")]
+    SuspiciousRecipient,
+    #[msg("Authority cannot be the program id or one of its PDAs")]
+    InvalidAuthority,
+    #[msg("Pause state was changed too recently")]
+    PauseToggleTooSoon,
+    #[msg("Rescue amount exceeds the vault's untracked balance")]
+    RescueExceedsSurplus,
+    #[msg("Vault holds less than the amount being forwarded")]
+    InsufficientVaultBalance,
+    #[msg("Lending protocol address must be non-zero and differ from the one it replaces")]
+    InvalidLendingProtocolAddress,
+    #[msg("Signer seeds do not derive the contract_state PDA")]
+    InvalidSignerSeeds,
+    #[msg("Depositor is not on the deposit whitelist")]
+    DepositorNotWhitelisted,
+    #[msg("Strict initialization requires a treasury, abort address, gateway program and native asset")]
+    MissingStrictConfig,
+    #[msg("Fee vault holds less than the withdrawal")]
+    InsufficientFeeBalance,
+    #[msg("Legacy colon-delimited messages are no longer accepted")]
+    LegacyMessageDisabled,
+    #[msg("Cross-chain message could not be decoded")]
+    MalformedMessage,
+    #[msg("Clock sysvar unavailable")]
+    ClockUnavailable,
+    #[msg("Insufficient token balance")]
+    InsufficientBalance,
+    #[msg("Decimals do not match the token mint")]
+    DecimalMismatch,
+    #[msg("Borrow would exceed the ZRC-20 borrow cap")]
+    Zrc20BorrowCapExceeded,
+    #[msg("Amount is below the minimum deposit")]
+    BelowMinimumDeposit,
+    #[msg("Deposit would exceed the asset deposit cap")]
+    DepositCapExceeded,
+    #[msg("Treasury is unset or does not match the configured treasury")]
+    InvalidTreasury,
+    #[msg("Contract state holds no lamports above its rent-exempt reserve")]
+    NoDustToSweep,
+    #[msg("Gateway program does not match the configured gateway")]
+    UnauthorizedGateway,
+    #[msg("Program account is not the canonical program id")]
+    InvalidProgramAccount,
+    #[msg("on_behalf_of is not in the beneficiary allowlist")]
+    BeneficiaryNotAllowed,
+    #[msg("Mint has a freeze authority that could lock the vault")]
+    MintHasFreezeAuthority,
+    #[msg("Fee recipient account is missing or does not match the asset's fee_recipient")]
+    InvalidFeeRecipient,
+    #[msg("Borrow would leave the health factor below min_health_factor")]
+    InsufficientCollateral,
+    #[msg("Collateral accounts must be distinct (asset_config, collateral_position) pairs owned by the borrower")]
+    InvalidCollateralAccounts,
+    #[msg("Encoded message does not match its computed ABI layout")]
+    EncodingError,
+    #[msg("Secondary gateway must be a program other than the primary gateway")]
+    InvalidSecondaryGateway,
+    #[msg("Price feed is not a trading Pyth price account for this asset")]
+    InvalidPriceFeed,
+    #[msg("Borrower has no collateral besides the asset being borrowed")]
+    SelfBorrow,
+    #[msg("Loan-to-value may not exceed the liquidation threshold")]
+    InvalidRiskParams,
+    #[msg("This operation is paused")]
+    OperationPaused,
+    #[msg("ZRC-20 config does not belong to the repaid asset")]
+    InvalidZrc20Config,
+}
+
+impl DepositContractError {
+    /// Every variant in declaration order, for clients building lookup tables.
+    pub const ALL: [DepositContractError; 67] = [
+        DepositContractError::Unauthorized,
+        DepositContractError::InvalidAmount,
+        DepositContractError::UnsupportedAsset,
+        DepositContractError::InvalidChainId,
+        DepositContractError::DepositFailed,
+        DepositContractError::ContractPaused,
+        DepositContractError::UseDepositSol,
+        DepositContractError::UseRepaySol,
+        DepositContractError::InsufficientDepositFee,
+        DepositContractError::ArithmeticOverflow,
+        DepositContractError::EmptyMessage,
+        DepositContractError::InvalidEvmSignature,
+        DepositContractError::EvmSignatureMismatch,
+        DepositContractError::EvmLinkRequired,
+        DepositContractError::EvmAddressNotLinked,
+        DepositContractError::InvalidBatchAccounts,
+        DepositContractError::MetadataUriTooLong,
+        DepositContractError::BatchTooLarge,
+        DepositContractError::ReturnDataTooLarge,
+        DepositContractError::IncidentMetadataTooLong,
+        DepositContractError::RepayDisabled,
+        DepositContractError::NonExactAmount,
+        DepositContractError::GlobalBorrowCapExceeded,
+        DepositContractError::AssetStillSupported,
+        DepositContractError::VaultNotEmpty,
+        DepositContractError::InvalidVaultAccount,
+        DepositContractError::TokenAccountInSolPath,
+        DepositContractError::InvalidRecipientLength,
+        DepositContractError::AssetAlreadySupported,
+        DepositContractError::StalePrice,
+        DepositContractError::RegistryFull,
+        DepositContractError::BelowRentExemption,
+        DepositContractError::UnauthorizedCaller,
+        DepositContractError::SuspiciousRecipient,
+        DepositContractError::InvalidAuthority,
+        DepositContractError::PauseToggleTooSoon,
+        DepositContractError::RescueExceedsSurplus,
+        DepositContractError::InsufficientVaultBalance,
+        DepositContractError::InvalidLendingProtocolAddress,
+        DepositContractError::InvalidSignerSeeds,
+        DepositContractError::DepositorNotWhitelisted,
+        DepositContractError::MissingStrictConfig,
+        DepositContractError::InsufficientFeeBalance,
+        DepositContractError::LegacyMessageDisabled,
+        DepositContractError::MalformedMessage,
+        DepositContractError::ClockUnavailable,
+        DepositContractError::InsufficientBalance,
+        DepositContractError::DecimalMismatch,
+        DepositContractError::Zrc20BorrowCapExceeded,
+        DepositContractError::BelowMinimumDeposit,
+        DepositContractError::DepositCapExceeded,
+        DepositContractError::InvalidTreasury,
+        DepositContractError::NoDustToSweep,
+        DepositContractError::UnauthorizedGateway,
+        DepositContractError::InvalidProgramAccount,
+        DepositContractError::BeneficiaryNotAllowed,
+        DepositContractError::MintHasFreezeAuthority,
+        DepositContractError::InvalidFeeRecipient,
+        DepositContractError::InsufficientCollateral,
+        DepositContractError::InvalidCollateralAccounts,
+        DepositContractError::EncodingError,
+        DepositContractError::InvalidSecondaryGateway,
+        DepositContractError::InvalidPriceFeed,
+        DepositContractError::SelfBorrow,
+        DepositContractError::InvalidRiskParams,
+        DepositContractError::OperationPaused,
+        DepositContractError::InvalidZrc20Config,
+    ];
+
+    /// Stable numeric code, matching the one Anchor reports on-chain.
+    pub fn code(&self) -> u32 {
+        anchor_lang::error::ERROR_CODE_OFFSET + *self as u32
+    }
+
+    /// Short machine-readable name that stays fixed when `#[msg]` text changes.
+    pub fn slug(&self) -> &'static str {
+        match self {
+            DepositContractError::Unauthorized => "unauthorized",
+            DepositContractError::InvalidAmount => "invalid_amount",
+            DepositContractError::UnsupportedAsset => "unsupported_asset",
+            DepositContractError::InvalidChainId => "invalid_chain_id",
+            DepositContractError::DepositFailed => "deposit_failed",
+            DepositContractError::ContractPaused => "contract_paused",
+            DepositContractError::UseDepositSol => "use_deposit_sol",
+            DepositContractError::UseRepaySol => "use_repay_sol",
+            DepositContractError::InsufficientDepositFee => "insufficient_deposit_fee",
+            DepositContractError::ArithmeticOverflow => "arithmetic_overflow",
+            DepositContractError::EmptyMessage => "empty_message",
+            DepositContractError::InvalidEvmSignature => "invalid_evm_signature",
+            DepositContractError::EvmSignatureMismatch => "evm_signature_mismatch",
+            DepositContractError::EvmLinkRequired => "evm_link_required",
+            DepositContractError::EvmAddressNotLinked => "evm_address_not_linked",
+            DepositContractError::InvalidBatchAccounts => "invalid_batch_accounts",
+            DepositContractError::MetadataUriTooLong => "metadata_uri_too_long",
+            DepositContractError::BatchTooLarge => "batch_too_large",
+            DepositContractError::ReturnDataTooLarge => "return_data_too_large",
+            DepositContractError::IncidentMetadataTooLong => "incident_metadata_too_long",
+            DepositContractError::RepayDisabled => "repay_disabled",
+            DepositContractError::NonExactAmount => "non_exact_amount",
+            DepositContractError::GlobalBorrowCapExceeded => "global_borrow_cap_exceeded",
+            DepositContractError::AssetStillSupported => "asset_still_supported",
+            DepositContractError::VaultNotEmpty => "vault_not_empty",
+            DepositContractError::InvalidVaultAccount => "invalid_vault_account",
+            DepositContractError::TokenAccountInSolPath => "token_account_in_sol_path",
+            DepositContractError::InvalidRecipientLength => "invalid_recipient_length",
+            DepositContractError::AssetAlreadySupported => "asset_already_supported",
+            DepositContractError::StalePrice => "stale_price",
+            DepositContractError::RegistryFull => "registry_full",
+            DepositContractError::BelowRentExemption => "below_rent_exemption",
+            DepositContractError::UnauthorizedCaller => "unauthorized_caller",
+            DepositContractError::SuspiciousRecipient => "suspicious_recipient",
+            DepositContractError::InvalidAuthority => "invalid_authority",
+            DepositContractError::PauseToggleTooSoon => "pause_toggle_too_soon",
+            DepositContractError::RescueExceedsSurplus => "rescue_exceeds_surplus",
+            DepositContractError::InsufficientVaultBalance => "insufficient_vault_balance",
+            DepositContractError::InvalidLendingProtocolAddress => "invalid_lending_protocol_address",
+            DepositContractError::InvalidSignerSeeds => "invalid_signer_seeds",
+            DepositContractError::DepositorNotWhitelisted => "depositor_not_whitelisted",
+            DepositContractError::MissingStrictConfig => "missing_strict_config",
+            DepositContractError::InsufficientFeeBalance => "insufficient_fee_balance",
+            DepositContractError::LegacyMessageDisabled => "legacy_message_disabled",
+            DepositContractError::MalformedMessage => "malformed_message",
+            DepositContractError::ClockUnavailable => "clock_unavailable",
+            DepositContractError::InsufficientBalance => "insufficient_balance",
+            DepositContractError::DecimalMismatch => "decimal_mismatch",
+            DepositContractError::Zrc20BorrowCapExceeded => "zrc20_borrow_cap_exceeded",
+            DepositContractError::BelowMinimumDeposit => "below_minimum_deposit",
+            DepositContractError::DepositCapExceeded => "deposit_cap_exceeded",
+            DepositContractError::InvalidTreasury => "invalid_treasury",
+            DepositContractError::NoDustToSweep => "no_dust_to_sweep",
+            DepositContractError::UnauthorizedGateway => "unauthorized_gateway",
+            DepositContractError::InvalidProgramAccount => "invalid_program_account",
+            DepositContractError::BeneficiaryNotAllowed => "beneficiary_not_allowed",
+            DepositContractError::MintHasFreezeAuthority => "mint_has_freeze_authority",
+            DepositContractError::InvalidFeeRecipient => "invalid_fee_recipient",
+            DepositContractError::InsufficientCollateral => "insufficient_collateral",
+            DepositContractError::InvalidCollateralAccounts => "invalid_collateral_accounts",
+            DepositContractError::EncodingError => "encoding_error",
+            DepositContractError::InvalidSecondaryGateway => "invalid_secondary_gateway",
+            DepositContractError::InvalidPriceFeed => "invalid_price_feed",
+            DepositContractError::SelfBorrow => "self_borrow",
+            DepositContractError::InvalidRiskParams => "invalid_risk_params",
+            DepositContractError::OperationPaused => "operation_paused",
+            DepositContractError::InvalidZrc20Config => "invalid_zrc20_config",
+        }
+    }
 }
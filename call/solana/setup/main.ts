@@ -54,7 +54,11 @@ async function setup() {
   );
 
   const connectedProgram = new anchor.Program(Connected_IDL as anchor.Idl);
-  await connectedProgram.methods.initialize().rpc();
+  // TSS key allowed to sign on_call messages; localnet defaults to the payer
+  const tssPubkey = process.env.TSS_PUBKEY
+    ? new anchor.web3.PublicKey(process.env.TSS_PUBKEY)
+    : payer.publicKey;
+  await connectedProgram.methods.initialize(tssPubkey).rpc();
   console.log("Initialized connected program");
 }
 
@@ -0,0 +1,1151 @@
+#!/usr/bin/env rust-script
+
+//! # Solana Connected Program Test Suite
+//! 
+//! Test cases for the example connected program called by the ZetaChain Gateway
+//! 
+//! Run with: `cargo test --bin test_connected`
+
+use std::collections::{HashMap, HashSet};
+
+#[cfg(test)]
+#[path = "test_support.rs"]
+mod test_support;
+
+// Mock types matching the actual program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+struct Pubkey([u8; 32]);
+
+impl Pubkey {
+    fn new_unique() -> Self {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static COUNTER: AtomicU64 = AtomicU64::new(1);
+        let mut key = [0u8; 32];
+        let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+        key[24..32].copy_from_slice(&counter.to_le_bytes());
+        Pubkey(key)
+    }
+}
+
+// Stand-in for get_associated_token_address: deterministic in (owner, mint) and
+// distinct from either key
+fn associated_token_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let mut key = [0u8; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = owner.0[i].rotate_left(1) ^ mint.0[(i + 7) % 32] ^ 0xa5;
+    }
+    Pubkey(key)
+}
+
+#[derive(Clone, Debug, Default)]
+struct Pda {
+    last_sender: [u8; 20],
+    last_message: String,
+    is_initialized: bool,
+    authority: Pubkey,
+    tss_pubkey: Pubkey,
+    accepted_versions: [u8; MAX_ACCEPTED_VERSIONS],
+    total_supplied: u64,
+    total_borrowed: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ErrorCode {
+    InvalidDataFormat,
+    AlreadyInitialized,
+    InvalidTssSigner,
+    InvalidSignature,
+    Unauthorized,
+    InvalidAcceptedVersions,
+    UnsupportedMessageVersion,
+    InvalidBatchSize,
+    ReplayedMessage,
+    InvalidSenderNonceAccounts,
+    UnknownAction,
+    InvalidActionArguments,
+    InvalidRecipient,
+    InsufficientBalance,
+    ArithmeticOverflow,
+    RecipientMintMismatch,
+    MessageTooLong,
+    InsufficientVaultBalance,
+    InvalidPayoutAccounts,
+    TssNotSigner, // Stands in for Anchor's AccountNotSigner on the tss_signer account
+}
+
+const TSS_SIGNATURE_LEN: usize = 64;
+const MESSAGE_VERSION_V1: u8 = 1;
+const MAX_ACCEPTED_VERSIONS: usize = 4;
+const MAX_MESSAGE_LEN: usize = 256;
+const MAX_ON_CALL_BATCH_SIZE: usize = 8;
+const ON_CALL_ITEM_OK: u32 = 0;
+const ERROR_CODE_OFFSET: u32 = 6000; // Anchor's custom error base
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_LEN: usize = 14;
+const ED25519_CURRENT_INSTRUCTION: u16 = u16::MAX;
+const ED25519_PROGRAM_ID: Pubkey = Pubkey([3u8; 32]);
+
+impl ErrorCode {
+    fn code(&self) -> u32 {
+        ERROR_CODE_OFFSET + *self as u32
+    }
+}
+
+struct OnCallItem {
+    amount: u64,
+    sender: [u8; 20],
+    nonce: u64,
+    data: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+// on_call data: version tag followed by the payload
+fn versioned(version: u8, payload: &[u8]) -> Vec<u8> {
+    let mut data = vec![version];
+    data.extend_from_slice(payload);
+    data
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CallAction {
+    Supply,
+    Repay,
+    BorrowCrossChain { recipient: Pubkey },
+    WithdrawCrossChain { recipient: Pubkey },
+}
+
+impl CallAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CallAction::Supply => "supply",
+            CallAction::Repay => "repay",
+            CallAction::BorrowCrossChain { .. } => "borrowCrossChain",
+            CallAction::WithdrawCrossChain { .. } => "withdrawCrossChain",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DecodedCall {
+    action: CallAction,
+    on_behalf_of: [u8; 20],
+    amount: u64,
+}
+
+// Pda.last_message only has room for MAX_MESSAGE_LEN bytes
+fn store_last_message(pda: &mut Pda, message: &str) -> Result<(), ErrorCode> {
+    if message.len() > MAX_MESSAGE_LEN {
+        return Err(ErrorCode::MessageTooLong);
+    }
+    pda.last_message = message.to_string();
+    Ok(())
+}
+
+fn decode_call(payload: &[u8], delivered: u64) -> Result<DecodedCall, ErrorCode> {
+    let action = abi::bytes_at(payload, 0).ok_or(ErrorCode::InvalidDataFormat)?;
+    if !matches!(action, b"supply" | b"repay" | b"borrowCrossChain" | b"withdrawCrossChain") {
+        return Err(ErrorCode::UnknownAction);
+    }
+
+    let on_behalf_of = abi::address_at(payload, abi::WORD_LEN).ok_or(ErrorCode::InvalidActionArguments)?;
+    let payout = || -> Result<(u64, Pubkey), ErrorCode> {
+        let amount = abi::u64_at(payload, 2 * abi::WORD_LEN).ok_or(ErrorCode::InvalidActionArguments)?;
+        let recipient = abi::word_at(payload, 3 * abi::WORD_LEN).ok_or(ErrorCode::InvalidActionArguments)?;
+        Ok((amount, Pubkey(recipient)))
+    };
+    let (action, amount) = match action {
+        b"supply" => (CallAction::Supply, delivered),
+        b"repay" => (CallAction::Repay, delivered),
+        b"borrowCrossChain" => {
+            let (amount, recipient) = payout()?;
+            (CallAction::BorrowCrossChain { recipient }, amount)
+        }
+        _ => {
+            let (amount, recipient) = payout()?;
+            (CallAction::WithdrawCrossChain { recipient }, amount)
+        }
+    };
+
+    Ok(DecodedCall { action, on_behalf_of, amount })
+}
+
+mod abi {
+    pub const WORD_LEN: usize = 32;
+
+    pub fn encode_u256(value: u64) -> Vec<u8> {
+        let mut word = vec![0u8; WORD_LEN];
+        word[WORD_LEN - 8..].copy_from_slice(&value.to_be_bytes());
+        word
+    }
+
+    pub fn encode_address(address: &[u8; 20]) -> Vec<u8> {
+        let mut word = vec![0u8; WORD_LEN];
+        word[WORD_LEN - address.len()..].copy_from_slice(address);
+        word
+    }
+
+    pub fn encode_string(value: &str) -> Vec<u8> {
+        let mut tail = encode_u256(value.len() as u64);
+        tail.extend_from_slice(value.as_bytes());
+        tail.resize(WORD_LEN + value.len().div_ceil(WORD_LEN) * WORD_LEN, 0);
+        tail
+    }
+
+    // abi.encode(string, ...static words): the string tail follows the head
+    pub fn encode_with_action(action: &str, words: &[Vec<u8>]) -> Vec<u8> {
+        let mut payload = encode_u256(((1 + words.len()) * WORD_LEN) as u64);
+        for word in words {
+            payload.extend_from_slice(word);
+        }
+        payload.extend_from_slice(&encode_string(action));
+        payload
+    }
+
+    pub fn word_at(payload: &[u8], offset: usize) -> Option<[u8; WORD_LEN]> {
+        payload.get(offset..offset.checked_add(WORD_LEN)?)?.try_into().ok()
+    }
+
+    pub fn u64_at(payload: &[u8], offset: usize) -> Option<u64> {
+        let word = word_at(payload, offset)?;
+        let (high, low) = word.split_at(WORD_LEN - 8);
+        high.iter().all(|b| *b == 0).then(|| u64::from_be_bytes(low.try_into().expect("low 8 bytes")))
+    }
+
+    pub fn address_at(payload: &[u8], offset: usize) -> Option<[u8; 20]> {
+        let word = word_at(payload, offset)?;
+        let (padding, address) = word.split_at(WORD_LEN - 20);
+        padding.iter().all(|b| *b == 0).then(|| address.try_into().expect("20 bytes"))
+    }
+
+    pub fn bytes_at(payload: &[u8], head_offset: usize) -> Option<&[u8]> {
+        let start = usize::try_from(u64_at(payload, head_offset)?).ok()?;
+        let len = usize::try_from(u64_at(payload, start)?).ok()?;
+        let data_start = start.checked_add(WORD_LEN)?;
+        payload.get(data_start..data_start.checked_add(len)?)
+    }
+}
+
+// abi.encode(action, address onBehalfOf, uint256 amount, bytes32 recipient) for the payout actions
+fn payout_message(action: &str, on_behalf_of: [u8; 20], amount: u64, recipient: Pubkey) -> Vec<u8> {
+    abi::encode_with_action(action, &[abi::encode_address(&on_behalf_of), abi::encode_u256(amount), recipient.0.to_vec()])
+}
+
+// An instruction of the transaction, as read back through the Instructions sysvar
+#[derive(Clone, Debug)]
+struct MockInstruction {
+    program_id: Pubkey,
+    data: Vec<u8>,
+}
+
+fn tss_signed_message(amount: u64, sender: [u8; 20], nonce: u64, data: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(16 + sender.len() + data.len());
+    message.extend_from_slice(&amount.to_le_bytes());
+    message.extend_from_slice(&sender);
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message.extend_from_slice(data);
+    message
+}
+
+// Ed25519Program instruction as a relayer builds it, every entry pointing into itself.
+// The precompile's own check is not modeled: listed signatures are taken as valid.
+fn ed25519_instruction(entries: &[(Pubkey, &[u8], &[u8])]) -> MockInstruction {
+    let mut data = vec![entries.len() as u8, 0];
+    let mut payload = Vec::new();
+    let payload_start = ED25519_OFFSETS_START + entries.len() * ED25519_OFFSETS_LEN;
+    for (pubkey, signature, message) in entries {
+        let signature_offset = payload_start + payload.len();
+        payload.extend_from_slice(signature);
+        let pubkey_offset = payload_start + payload.len();
+        payload.extend_from_slice(&pubkey.0);
+        let message_offset = payload_start + payload.len();
+        payload.extend_from_slice(message);
+        for field in [
+            signature_offset as u16,
+            ED25519_CURRENT_INSTRUCTION,
+            pubkey_offset as u16,
+            ED25519_CURRENT_INSTRUCTION,
+            message_offset as u16,
+            message.len() as u16,
+            ED25519_CURRENT_INSTRUCTION,
+        ] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+    }
+    data.extend_from_slice(&payload);
+    MockInstruction { program_id: ED25519_PROGRAM_ID, data }
+}
+
+fn ed25519_instruction_covers(data: &[u8], pubkey: Pubkey, message: &[u8], signature: &[u8]) -> bool {
+    let signature_count = usize::from(data.first().copied().unwrap_or(0));
+    let slice = |offset: u16, len: usize| data.get(usize::from(offset)..usize::from(offset) + len);
+
+    (0..signature_count).any(|index| {
+        let start = ED25519_OFFSETS_START + index * ED25519_OFFSETS_LEN;
+        let Some(entry) = data.get(start..start + ED25519_OFFSETS_LEN) else {
+            return false;
+        };
+        let field = |position: usize| u16::from_le_bytes([entry[2 * position], entry[2 * position + 1]]);
+        let [signature_offset, signature_ix, pubkey_offset, pubkey_ix, message_offset, message_len, message_ix] =
+            [0, 1, 2, 3, 4, 5, 6].map(field);
+
+        [signature_ix, pubkey_ix, message_ix] == [ED25519_CURRENT_INSTRUCTION; 3]
+            && slice(signature_offset, TSS_SIGNATURE_LEN) == Some(signature)
+            && slice(pubkey_offset, 32) == Some(&pubkey.0[..])
+            && slice(message_offset, usize::from(message_len)) == Some(message)
+    })
+}
+
+// Mock program operations
+struct MockConnected {
+    pda: Option<Pda>,
+    bypass_tss: bool, // Mirrors building with the test-bypass-tss feature
+    preceding_instruction: Option<MockInstruction>, // Instruction before on_call in the transaction
+    sender_nonces: HashMap<[u8; 20], u64>, // SenderNonce accounts: last_nonce by sender
+    pda_ata_balance: u64, // Tokens held by pda_ata
+    token_balances: HashMap<Pubkey, u64>, // Existing recipient token accounts and their balances
+    frozen_token_accounts: HashSet<Pubkey>, // Token accounts the mint's freeze authority has frozen
+    mint_account: Pubkey, // mint_account passed to on_call
+    recipient: Option<Pubkey>, // recipient wallet passed to on_call
+    recipient_token_account: Option<Pubkey>, // recipient_token_account passed to on_call
+}
+
+impl MockConnected {
+    fn new() -> Self {
+        Self {
+            pda: None,
+            bypass_tss: false,
+            preceding_instruction: None,
+            sender_nonces: HashMap::new(),
+            pda_ata_balance: 0,
+            token_balances: HashMap::new(),
+            frozen_token_accounts: HashSet::new(),
+            mint_account: Pubkey::new_unique(),
+            recipient: None,
+            recipient_token_account: None,
+        }
+    }
+
+    fn initialize(&mut self, signer: Pubkey, tss_pubkey: Pubkey) -> Result<(), ErrorCode> {
+        // init_if_needed hands back the existing account on a second call
+        let pda = self.pda.get_or_insert_with(Pda::default);
+
+        if pda.is_initialized {
+            return Err(ErrorCode::AlreadyInitialized);
+        }
+        pda.is_initialized = true;
+        pda.authority = signer;
+        pda.tss_pubkey = tss_pubkey;
+        pda.accepted_versions = [0; MAX_ACCEPTED_VERSIONS];
+        pda.accepted_versions[0] = MESSAGE_VERSION_V1;
+
+        Ok(())
+    }
+
+    fn set_accepted_versions(&mut self, authority: Pubkey, versions: &[u8]) -> Result<(), ErrorCode> {
+        let pda = self.pda.as_mut().expect("pda not initialized");
+
+        if authority != pda.authority {
+            return Err(ErrorCode::Unauthorized);
+        }
+
+        if versions.is_empty() || versions.len() > MAX_ACCEPTED_VERSIONS || versions.contains(&0) {
+            return Err(ErrorCode::InvalidAcceptedVersions);
+        }
+
+        pda.accepted_versions = [0; MAX_ACCEPTED_VERSIONS];
+        pda.accepted_versions[..versions.len()].copy_from_slice(versions);
+        Ok(())
+    }
+
+    fn verify_tss_signature(&self, tss_signer: Pubkey, message: &[u8], signature: &[u8]) -> Result<(), ErrorCode> {
+        let pda = self.pda.as_ref().expect("pda not initialized");
+
+        // The signer pubkey check is never bypassed
+        if tss_signer != pda.tss_pubkey {
+            return Err(ErrorCode::InvalidTssSigner);
+        }
+
+        if self.bypass_tss {
+            return Ok(());
+        }
+
+        if signature.len() != TSS_SIGNATURE_LEN {
+            return Err(ErrorCode::InvalidSignature);
+        }
+
+        let ed25519_instruction = self.preceding_instruction.as_ref().ok_or(ErrorCode::InvalidSignature)?;
+        if ed25519_instruction.program_id != ED25519_PROGRAM_ID
+            || !ed25519_instruction_covers(&ed25519_instruction.data, pda.tss_pubkey, message, signature)
+        {
+            return Err(ErrorCode::InvalidSignature);
+        }
+
+        Ok(())
+    }
+
+    // What a relayer submits: the TSS signature checked by an Ed25519Program instruction,
+    // then on_call with the sender's next nonce
+    fn on_call(&mut self, amount: u64, sender: [u8; 20], data: Vec<u8>, tss_signer: Pubkey, signature: &[u8]) -> Result<(), ErrorCode> {
+        let nonce = self.sender_nonces.get(&sender).copied().unwrap_or(0) + 1;
+        self.on_call_with_nonce(amount, sender, nonce, data, tss_signer, signature)
+    }
+
+    fn on_call_with_nonce(&mut self, amount: u64, sender: [u8; 20], nonce: u64, data: Vec<u8>, tss_signer: Pubkey, signature: &[u8]) -> Result<(), ErrorCode> {
+        let message = tss_signed_message(amount, sender, nonce, &data);
+        self.preceding_instruction = Some(ed25519_instruction(&[(tss_signer, signature, &message)]));
+        self.on_call_with_signer(amount, sender, nonce, data, tss_signer, true, signature)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn on_call_with_signer(
+        &mut self,
+        amount: u64,
+        sender: [u8; 20],
+        nonce: u64,
+        data: Vec<u8>,
+        tss_signer: Pubkey,
+        tss_is_signer: bool,
+        signature: &[u8],
+    ) -> Result<(), ErrorCode> {
+        // Signer<'info> is checked during account validation, before the handler runs
+        if !tss_is_signer {
+            return Err(ErrorCode::TssNotSigner);
+        }
+
+        // init_if_needed: a failed call leaves no SenderNonce account behind
+        let mut last_nonce = self.sender_nonces.get(&sender).copied().unwrap_or(0);
+        self.process_call(&OnCallItem { amount, sender, nonce, data, signature: signature.to_vec() }, tss_signer, &mut last_nonce)?;
+        self.sender_nonces.insert(sender, last_nonce);
+        Ok(())
+    }
+
+    // One Ed25519Program instruction carries the signatures of every item, and
+    // remaining_accounts the existing SenderNonce account of each distinct sender
+    fn on_call_batch(&mut self, items: &[OnCallItem], tss_signer: Pubkey, atomic: bool) -> Result<Vec<u32>, ErrorCode> {
+        let messages: Vec<Vec<u8>> = items
+            .iter()
+            .map(|item| tss_signed_message(item.amount, item.sender, item.nonce, &item.data))
+            .collect();
+        let entries: Vec<(Pubkey, &[u8], &[u8])> = items
+            .iter()
+            .zip(&messages)
+            .map(|(item, message)| (tss_signer, item.signature.as_slice(), message.as_slice()))
+            .collect();
+        self.preceding_instruction = Some(ed25519_instruction(&entries));
+
+        let mut nonce_accounts: Vec<[u8; 20]> = Vec::new();
+        for item in items {
+            if self.sender_nonces.contains_key(&item.sender) && !nonce_accounts.contains(&item.sender) {
+                nonce_accounts.push(item.sender);
+            }
+        }
+        self.process_batch(items, tss_signer, atomic, &nonce_accounts)
+    }
+
+    fn process_batch(&mut self, items: &[OnCallItem], tss_signer: Pubkey, atomic: bool, nonce_accounts: &[[u8; 20]]) -> Result<Vec<u32>, ErrorCode> {
+        if items.is_empty() || items.len() > MAX_ON_CALL_BATCH_SIZE {
+            return Err(ErrorCode::InvalidBatchSize);
+        }
+
+        let mut loaded: Vec<([u8; 20], u64)> = Vec::with_capacity(nonce_accounts.len());
+        for sender in nonce_accounts {
+            if loaded.iter().any(|(loaded_sender, _)| loaded_sender == sender) {
+                return Err(ErrorCode::InvalidSenderNonceAccounts);
+            }
+            loaded.push((*sender, self.sender_nonces[sender]));
+        }
+
+        // A failing atomic batch reverts every earlier item as well
+        let snapshot = (self.pda.clone(), self.pda_ata_balance, self.token_balances.clone());
+        let mut statuses = Vec::with_capacity(items.len());
+        for item in items {
+            let result = match loaded.iter_mut().find(|(sender, _)| *sender == item.sender) {
+                Some((_, last_nonce)) => self.process_call(item, tss_signer, last_nonce),
+                None => Err(ErrorCode::InvalidSenderNonceAccounts),
+            };
+            match result {
+                Ok(()) => statuses.push(ON_CALL_ITEM_OK),
+                Err(err) if atomic => {
+                    (self.pda, self.pda_ata_balance, self.token_balances) = snapshot;
+                    return Err(err);
+                }
+                Err(err) => statuses.push(err.code()),
+            }
+        }
+
+        self.sender_nonces.extend(loaded);
+        Ok(statuses)
+    }
+
+    fn process_call(&mut self, item: &OnCallItem, tss_signer: Pubkey, last_nonce: &mut u64) -> Result<(), ErrorCode> {
+        let OnCallItem { amount, sender, nonce, ref data, ref signature } = *item;
+        self.verify_tss_signature(tss_signer, &tss_signed_message(amount, sender, nonce, data), signature)?;
+        if nonce <= *last_nonce {
+            return Err(ErrorCode::ReplayedMessage);
+        }
+        let pda = self.pda.as_ref().expect("pda not initialized");
+
+        let (version, payload) = data.split_first().ok_or(ErrorCode::InvalidDataFormat)?;
+        if !pda.accepted_versions.contains(version) {
+            return Err(ErrorCode::UnsupportedMessageVersion);
+        }
+
+        let DecodedCall { action, amount, .. } = decode_call(payload, amount)?;
+
+        let (mut total_supplied, mut total_borrowed) = (pda.total_supplied, pda.total_borrowed);
+        match action {
+            CallAction::Supply => {
+                total_supplied = total_supplied.checked_add(amount).ok_or(ErrorCode::ArithmeticOverflow)?;
+            }
+            CallAction::Repay => {
+                total_borrowed = total_borrowed.checked_sub(amount).ok_or(ErrorCode::InsufficientBalance)?;
+            }
+            CallAction::BorrowCrossChain { recipient } => {
+                total_borrowed = total_borrowed.checked_add(amount).ok_or(ErrorCode::ArithmeticOverflow)?;
+                self.pay_out(recipient, amount)?;
+            }
+            CallAction::WithdrawCrossChain { recipient } => {
+                total_supplied = total_supplied.checked_sub(amount).ok_or(ErrorCode::InsufficientBalance)?;
+                self.pay_out(recipient, amount)?;
+            }
+        }
+
+        let pda = self.pda.as_mut().expect("pda not initialized");
+        store_last_message(pda, action.as_str())?;
+        *last_nonce = nonce;
+        pda.total_supplied = total_supplied;
+        pda.total_borrowed = total_borrowed;
+        pda.last_sender = sender;
+
+        Ok(())
+    }
+
+    fn pay_out(&mut self, recipient: Pubkey, amount: u64) -> Result<(), ErrorCode> {
+        let recipient_wallet = self.recipient.ok_or(ErrorCode::InvalidRecipient)?;
+        let recipient_token_account = self.recipient_token_account.ok_or(ErrorCode::InvalidRecipient)?;
+        if recipient_wallet != recipient {
+            return Err(ErrorCode::InvalidRecipient);
+        }
+        if recipient_token_account != associated_token_address(&recipient, &self.mint_account) {
+            return Err(ErrorCode::RecipientMintMismatch);
+        }
+        if self.frozen_token_accounts.contains(&recipient_token_account) {
+            return Err(ErrorCode::InvalidPayoutAccounts);
+        }
+
+        self.pda_ata_balance = self.pda_ata_balance.checked_sub(amount).ok_or(ErrorCode::InsufficientVaultBalance)?;
+        // create_idempotent gives a brand-new recipient an empty account first
+        *self.token_balances.entry(recipient_token_account).or_default() += amount;
+        Ok(())
+    }
+}
+
+// Test implementations
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{assert_abi_layout, fake_tss_signature, repay_message, supply_message};
+
+    const BENEFICIARY: [u8; 20] = [2u8; 20];
+
+    #[test]
+    fn test_initialize() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+
+        let result = program.initialize(Pubkey::new_unique(), tss);
+        assert!(result.is_ok());
+
+        let pda = program.pda.unwrap();
+        assert!(pda.is_initialized);
+        assert_eq!(pda.tss_pubkey, tss);
+    }
+
+    #[test]
+    fn test_initialize_twice_fails_cleanly() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+
+        let result = program.initialize(Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(matches!(result, Err(ErrorCode::AlreadyInitialized)));
+
+        // Existing state is left untouched
+        let pda = program.pda.unwrap();
+        assert!(pda.is_initialized);
+        assert_eq!(pda.tss_pubkey, tss);
+    }
+
+    #[test]
+    fn test_on_call_stores_sender_and_message() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+
+        let sender = [7u8; 20];
+        program.on_call(1_000, sender, versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), tss, &[0u8; 64]).unwrap();
+
+        let pda = program.pda.unwrap();
+        assert_eq!(pda.last_sender, sender);
+        assert_eq!(pda.last_message, "supply");
+    }
+
+    #[test]
+    fn test_on_call_invalid_utf8() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+
+        let result = program.on_call(1_000, [7u8; 20], versioned(MESSAGE_VERSION_V1, &[0xff, 0xfe]), tss, &[0u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::InvalidDataFormat)));
+    }
+
+    #[test]
+    fn test_on_call_rejects_invalid_signature() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+
+        let result = program.on_call(1_000, [7u8; 20], versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), tss, &[0u8; 63]);
+        assert!(matches!(result, Err(ErrorCode::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_on_call_verifies_ed25519_instruction() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+        let data = versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY));
+        let signature = [9u8; 64];
+        let signed = tss_signed_message(1_000, [7u8; 20], 1, &data);
+
+        // Valid: the preceding instruction checked this signature by the TSS over this message
+        program.preceding_instruction = Some(ed25519_instruction(&[(tss, &signature, &signed)]));
+        assert!(program.on_call_with_signer(1_000, [7u8; 20], 1, data.clone(), tss, true, &signature).is_ok());
+
+        // Tampered: data, amount or sender differ from what was signed
+        let result = program.on_call_with_signer(1_000, [7u8; 20], 1, versioned(MESSAGE_VERSION_V1, &repay_message(BENEFICIARY)), tss, true, &signature);
+        assert!(matches!(result, Err(ErrorCode::InvalidSignature)));
+        let result = program.on_call_with_signer(2_000, [7u8; 20], 1, data.clone(), tss, true, &signature);
+        assert!(matches!(result, Err(ErrorCode::InvalidSignature)));
+        let result = program.on_call_with_signer(1_000, [8u8; 20], 1, data.clone(), tss, true, &signature);
+        assert!(matches!(result, Err(ErrorCode::InvalidSignature)));
+        let result = program.on_call_with_signer(1_000, [7u8; 20], 1, data.clone(), tss, true, &[8u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::InvalidSignature)));
+
+        // Wrong signer: a valid signature by another key proves nothing
+        program.preceding_instruction = Some(ed25519_instruction(&[(Pubkey::new_unique(), &signature, &signed)]));
+        let result = program.on_call_with_signer(1_000, [7u8; 20], 1, data.clone(), tss, true, &signature);
+        assert!(matches!(result, Err(ErrorCode::InvalidSignature)));
+
+        // No Ed25519Program instruction at all, or another program in its place
+        program.preceding_instruction = None;
+        let result = program.on_call_with_signer(1_000, [7u8; 20], 1, data.clone(), tss, true, &signature);
+        assert!(matches!(result, Err(ErrorCode::InvalidSignature)));
+        let mut impostor = ed25519_instruction(&[(tss, &signature, &signed)]);
+        impostor.program_id = Pubkey::new_unique();
+        program.preceding_instruction = Some(impostor);
+        let result = program.on_call_with_signer(1_000, [7u8; 20], 1, data, tss, true, &signature);
+        assert!(matches!(result, Err(ErrorCode::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_ed25519_entries_must_point_into_their_own_instruction() {
+        let tss = Pubkey::new_unique();
+        let signature = [9u8; 64];
+        let message = tss_signed_message(1_000, [7u8; 20], 1, &versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)));
+        let mut instruction = ed25519_instruction(&[(tss, &signature, &message)]);
+        assert!(ed25519_instruction_covers(&instruction.data, tss, &message, &signature));
+
+        // The message instruction index now refers to another instruction of the transaction
+        let message_ix = ED25519_OFFSETS_START + 12;
+        instruction.data[message_ix..message_ix + 2].copy_from_slice(&0u16.to_le_bytes());
+        assert!(!ed25519_instruction_covers(&instruction.data, tss, &message, &signature));
+
+        // Truncated or empty instruction data never matches
+        assert!(!ed25519_instruction_covers(&[1, 0], tss, &message, &signature));
+        assert!(!ed25519_instruction_covers(&[], tss, &message, &signature));
+    }
+
+    #[test]
+    fn test_on_call_accepted_versions() {
+        let mut program = MockConnected::new();
+        let authority = Pubkey::new_unique();
+        let tss = Pubkey::new_unique();
+        program.initialize(authority, tss).unwrap();
+
+        // Only v1 by default
+        let result = program.on_call(1_000, [7u8; 20], versioned(2, &supply_message(BENEFICIARY)), tss, &[0u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::UnsupportedMessageVersion)));
+
+        // Rolling upgrade: accept v1 and v2 side by side
+        program.set_accepted_versions(authority, &[1, 2]).unwrap();
+        assert!(program.on_call(1_000, [7u8; 20], versioned(1, &supply_message(BENEFICIARY)), tss, &[0u8; 64]).is_ok());
+        assert!(program.on_call(1_000, [7u8; 20], versioned(2, &supply_message(BENEFICIARY)), tss, &[0u8; 64]).is_ok());
+
+        // Retire v1
+        program.set_accepted_versions(authority, &[2]).unwrap();
+        let result = program.on_call(1_000, [7u8; 20], versioned(1, &supply_message(BENEFICIARY)), tss, &[0u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::UnsupportedMessageVersion)));
+    }
+
+    #[test]
+    fn test_on_call_empty_data_rejected() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+
+        let result = program.on_call(1_000, [7u8; 20], Vec::new(), tss, &[0u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::InvalidDataFormat)));
+    }
+
+    #[test]
+    fn test_set_accepted_versions_validation() {
+        let mut program = MockConnected::new();
+        let authority = Pubkey::new_unique();
+        program.initialize(authority, Pubkey::new_unique()).unwrap();
+
+        let result = program.set_accepted_versions(Pubkey::new_unique(), &[2]);
+        assert!(matches!(result, Err(ErrorCode::Unauthorized)));
+        let result = program.set_accepted_versions(authority, &[]);
+        assert!(matches!(result, Err(ErrorCode::InvalidAcceptedVersions)));
+        let result = program.set_accepted_versions(authority, &[1, 2, 3, 4, 5]);
+        assert!(matches!(result, Err(ErrorCode::InvalidAcceptedVersions)));
+        let result = program.set_accepted_versions(authority, &[0]);
+        assert!(matches!(result, Err(ErrorCode::InvalidAcceptedVersions)));
+    }
+
+    #[test]
+    fn test_bypass_tss_skips_signature_check() {
+        let mut program = MockConnected::new();
+        program.bypass_tss = true;
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+
+        // No live TSS signature needed
+        let result = program.on_call(1_000, [7u8; 20], versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), tss, &[]);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_bypass_tss_keeps_pubkey_check() {
+        let mut program = MockConnected::new();
+        program.bypass_tss = true;
+        program.initialize(Pubkey::new_unique(), Pubkey::new_unique()).unwrap();
+
+        let result = program.on_call(1_000, [7u8; 20], versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), Pubkey::new_unique(), &[]);
+        assert!(matches!(result, Err(ErrorCode::InvalidTssSigner)));
+    }
+
+    #[test]
+    fn test_on_call_batch_mixed_signatures() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+
+        // Each sender's first message goes through on_call, creating its SenderNonce account
+        for sender in 1..=5u8 {
+            program.on_call(1_000, [sender; 20], versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), tss, &[0u8; 64]).unwrap();
+        }
+
+        let item = |sender: u8, payload: &[u8], signature_len: usize| OnCallItem {
+            amount: 1_000,
+            sender: [sender; 20],
+            nonce: 2,
+            data: versioned(MESSAGE_VERSION_V1, payload),
+            signature: vec![0u8; signature_len],
+        };
+        let items = vec![item(1, &supply_message(BENEFICIARY), 64), item(2, b"bad", 63), item(3, &supply_message(BENEFICIARY), 64)];
+
+        let statuses = program.on_call_batch(&items, tss, false).unwrap();
+        assert_eq!(statuses, vec![ON_CALL_ITEM_OK, ErrorCode::InvalidSignature.code(), ON_CALL_ITEM_OK]);
+        let pda = program.pda.as_ref().unwrap();
+        assert_eq!(pda.last_sender, [3u8; 20]);
+        assert_eq!(pda.total_supplied, 7_000);
+
+        // Atomic mode fails the whole batch and keeps the earlier state
+        let items = vec![item(4, &supply_message(BENEFICIARY), 64), item(5, b"bad", 63)];
+        let result = program.on_call_batch(&items, tss, true);
+        assert!(matches!(result, Err(ErrorCode::InvalidSignature)));
+        let pda = program.pda.as_ref().unwrap();
+        assert_eq!(pda.last_sender, [3u8; 20]);
+        assert_eq!(pda.total_supplied, 7_000);
+        assert_eq!(program.sender_nonces[&[4u8; 20]], 1);
+    }
+
+    #[test]
+    fn test_on_call_batch_reports_payout_failures_per_item() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+        let sender = [7u8; 20];
+        program.on_call(1_000, sender, versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), tss, &[0u8; 64]).unwrap();
+
+        let recipient = Pubkey::new_unique();
+        let recipient_ata = associated_token_address(&recipient, &program.mint_account);
+        program.recipient = Some(recipient);
+        program.recipient_token_account = Some(recipient_ata);
+        program.pda_ata_balance = 500;
+        let withdraw = |nonce: u64, amount: u64| OnCallItem {
+            amount: 0,
+            sender,
+            nonce,
+            data: versioned(MESSAGE_VERSION_V1, &payout_message("withdrawCrossChain", BENEFICIARY, amount, recipient)),
+            signature: vec![0u8; 64],
+        };
+
+        // The second payout exceeds what the first left in pda_ata; only that item fails
+        let items = vec![withdraw(2, 300), withdraw(3, 300), withdraw(4, 200)];
+        let statuses = program.on_call_batch(&items, tss, false).unwrap();
+        assert_eq!(statuses, vec![ON_CALL_ITEM_OK, ErrorCode::InsufficientVaultBalance.code(), ON_CALL_ITEM_OK]);
+        assert_eq!(program.pda_ata_balance, 0);
+        assert_eq!(program.token_balances[&recipient_ata], 500);
+        assert_eq!(program.pda.as_ref().unwrap().total_supplied, 500);
+        assert_eq!(program.sender_nonces[&sender], 4);
+
+        // A frozen recipient account is reported for the item, not by aborting the batch
+        program.pda_ata_balance = 500;
+        program.frozen_token_accounts.insert(recipient_ata);
+        let items = vec![withdraw(5, 100), OnCallItem {
+            amount: 100,
+            sender,
+            nonce: 6,
+            data: versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)),
+            signature: vec![0u8; 64],
+        }];
+        let statuses = program.on_call_batch(&items, tss, false).unwrap();
+        assert_eq!(statuses, vec![ErrorCode::InvalidPayoutAccounts.code(), ON_CALL_ITEM_OK]);
+        assert_eq!(program.pda_ata_balance, 500);
+        assert_eq!(program.pda.as_ref().unwrap().total_supplied, 600);
+    }
+
+    #[test]
+    fn test_on_call_dispatches_supply_and_repay() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+        let sender = [7u8; 20];
+
+        program.on_call(1_000, sender, versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), tss, &[0u8; 64]).unwrap();
+        program.on_call(500, sender, versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), tss, &[0u8; 64]).unwrap();
+        assert_eq!(program.pda.as_ref().unwrap().total_supplied, 1_500);
+
+        // Nothing borrowed yet, so there is nothing to repay
+        let result = program.on_call(100, sender, versioned(MESSAGE_VERSION_V1, &repay_message(BENEFICIARY)), tss, &[0u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::InsufficientBalance)));
+
+        let recipient = Pubkey::new_unique();
+        program.pda_ata_balance = 1_500;
+        let recipient_ata = associated_token_address(&recipient, &program.mint_account);
+        program.recipient = Some(recipient);
+        program.recipient_token_account = Some(recipient_ata);
+        let borrow = payout_message("borrowCrossChain", BENEFICIARY, 300, recipient);
+        program.on_call(0, sender, versioned(MESSAGE_VERSION_V1, &borrow), tss, &[0u8; 64]).unwrap();
+        program.on_call(100, sender, versioned(MESSAGE_VERSION_V1, &repay_message(BENEFICIARY)), tss, &[0u8; 64]).unwrap();
+
+        let pda = program.pda.as_ref().unwrap();
+        assert_eq!(pda.total_borrowed, 200);
+        assert_eq!(pda.total_supplied, 1_500);
+        assert_eq!(pda.last_message, "repay");
+        assert_eq!(program.token_balances[&recipient_ata], 300);
+        assert_eq!(program.pda_ata_balance, 1_200);
+
+        // A borrow pda_ata cannot cover fails before the transfer and records nothing
+        let borrow = payout_message("borrowCrossChain", BENEFICIARY, 5_000, recipient);
+        let result = program.on_call(0, sender, versioned(MESSAGE_VERSION_V1, &borrow), tss, &[0u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::InsufficientVaultBalance)));
+        assert_eq!(program.pda.as_ref().unwrap().total_borrowed, 200);
+    }
+
+    #[test]
+    fn test_on_call_withdraw_pays_recipient() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+        let sender = [7u8; 20];
+        program.pda_ata_balance = 1_000;
+        program.on_call(1_000, sender, versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), tss, &[0u8; 64]).unwrap();
+
+        let recipient = Pubkey::new_unique();
+        let withdraw = versioned(MESSAGE_VERSION_V1, &payout_message("withdrawCrossChain", BENEFICIARY, 400, recipient));
+
+        let recipient_ata = associated_token_address(&recipient, &program.mint_account);
+
+        // The recipient wallet must be passed and be the one the message names
+        program.recipient_token_account = Some(recipient_ata);
+        let result = program.on_call(0, sender, withdraw.clone(), tss, &[0u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::InvalidRecipient)));
+        program.recipient = Some(Pubkey::new_unique());
+        let result = program.on_call(0, sender, withdraw.clone(), tss, &[0u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::InvalidRecipient)));
+
+        // The token account must be the wallet's ATA for mint_account, not one for
+        // another mint or an arbitrary account
+        program.recipient = Some(recipient);
+        for passed in [associated_token_address(&recipient, &Pubkey::new_unique()), Pubkey::new_unique()] {
+            program.recipient_token_account = Some(passed);
+            let result = program.on_call(0, sender, withdraw.clone(), tss, &[0u8; 64]);
+            assert!(matches!(result, Err(ErrorCode::RecipientMintMismatch)));
+        }
+        assert_eq!(program.pda_ata_balance, 1_000);
+
+        // The PDA signs the transfer: pda_ata goes down and the recipient up by amount
+        program.recipient_token_account = Some(recipient_ata);
+        program.token_balances.insert(recipient_ata, 50);
+        program.on_call(0, sender, withdraw, tss, &[0u8; 64]).unwrap();
+        assert_eq!(program.token_balances[&recipient_ata], 450);
+        assert_eq!(program.pda_ata_balance, 600);
+        assert_eq!(program.pda.as_ref().unwrap().total_supplied, 600);
+
+        // Withdrawals are bounded by what was supplied, not by what pda_ata holds
+        program.pda_ata_balance = 10_000;
+        let withdraw = versioned(MESSAGE_VERSION_V1, &payout_message("withdrawCrossChain", BENEFICIARY, 601, recipient));
+        let result = program.on_call(0, sender, withdraw, tss, &[0u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::InsufficientBalance)));
+        assert_eq!(program.token_balances[&recipient_ata], 450);
+    }
+
+    #[test]
+    fn test_on_call_withdraw_creates_recipient_ata() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+        let sender = [7u8; 20];
+        program.pda_ata_balance = 1_000;
+        program.on_call(1_000, sender, versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), tss, &[0u8; 64]).unwrap();
+
+        // A wallet that has never held the token has no account to pay into yet
+        let recipient = Pubkey::new_unique();
+        let recipient_ata = associated_token_address(&recipient, &program.mint_account);
+        assert!(!program.token_balances.contains_key(&recipient_ata));
+
+        program.recipient = Some(recipient);
+        program.recipient_token_account = Some(recipient_ata);
+        let withdraw = versioned(MESSAGE_VERSION_V1, &payout_message("withdrawCrossChain", BENEFICIARY, 250, recipient));
+        program.on_call(0, sender, withdraw, tss, &[0u8; 64]).unwrap();
+        assert_eq!(program.token_balances[&recipient_ata], 250);
+        assert_eq!(program.pda_ata_balance, 750);
+
+        // A failed payout does not leave an empty account behind
+        let other = Pubkey::new_unique();
+        let other_ata = associated_token_address(&other, &program.mint_account);
+        program.recipient = Some(other);
+        program.recipient_token_account = Some(other_ata);
+        let withdraw = versioned(MESSAGE_VERSION_V1, &payout_message("withdrawCrossChain", BENEFICIARY, 250, other));
+        program.pda_ata_balance = 100;
+        let result = program.on_call(0, sender, withdraw, tss, &[0u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::InsufficientVaultBalance)));
+        assert!(!program.token_balances.contains_key(&other_ata));
+    }
+
+    #[test]
+    fn test_shared_messages_and_signatures_drive_on_call() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+
+        // The mock's own encoder agrees with the shared layout
+        assert_abi_layout(&abi::encode_with_action("supply", &[abi::encode_address(&BENEFICIARY)]), &supply_message(BENEFICIARY));
+
+        let data = versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY));
+        let signature = fake_tss_signature(&tss_signed_message(1_000, [7u8; 20], 1, &data));
+        program.on_call_with_nonce(1_000, [7u8; 20], 1, data.clone(), tss, &signature).unwrap();
+        assert_eq!(program.pda.as_ref().unwrap().total_supplied, 1_000);
+
+        // A fake signature is still bound to its message: the relayer's instruction
+        // carries it for nonce 2, so it does not cover the message with nonce 3
+        let signed = tss_signed_message(1_000, [7u8; 20], 2, &data);
+        let signature = fake_tss_signature(&signed);
+        program.preceding_instruction = Some(ed25519_instruction(&[(tss, &signature, &signed)]));
+        let result = program.on_call_with_signer(1_000, [7u8; 20], 3, data, tss, true, &signature);
+        assert!(matches!(result, Err(ErrorCode::InvalidSignature)));
+        assert_ne!(fake_tss_signature(&signed), fake_tss_signature(&tss_signed_message(1_000, [7u8; 20], 3, &[])));
+    }
+
+    #[test]
+    fn test_on_call_bounds_message_length() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+        program.on_call(1_000, [7u8; 20], versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), tss, &[0u8; 64]).unwrap();
+
+        // An over-long action is just not one of the known actions
+        let on_behalf_of = abi::encode_address(&BENEFICIARY);
+        let over_cap = abi::encode_with_action(&"s".repeat(MAX_MESSAGE_LEN + 1), &[on_behalf_of]);
+        let result = program.on_call(1_000, [7u8; 20], versioned(MESSAGE_VERSION_V1, &over_cap), tss, &[0u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::UnknownAction)));
+
+        // The cap sits where last_message is written: one byte over leaves it untouched
+        let pda = program.pda.as_mut().unwrap();
+        let result = store_last_message(pda, &"s".repeat(MAX_MESSAGE_LEN + 1));
+        assert!(matches!(result, Err(ErrorCode::MessageTooLong)));
+        assert_eq!(pda.last_message, "supply");
+        store_last_message(pda, &"s".repeat(MAX_MESSAGE_LEN)).unwrap();
+        assert_eq!(pda.last_message.len(), MAX_MESSAGE_LEN);
+    }
+
+    #[test]
+    fn test_on_call_rejects_unknown_or_malformed_actions() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+
+        let on_behalf_of = abi::encode_address(&BENEFICIARY);
+        for action in ["sol", "Supply", "liquidate", ""] {
+            let payload = abi::encode_with_action(action, &[on_behalf_of.clone()]);
+            let result = program.on_call(1_000, [7u8; 20], versioned(MESSAGE_VERSION_V1, &payload), tss, &[0u8; 64]);
+            assert!(matches!(result, Err(ErrorCode::UnknownAction)));
+        }
+
+        // Not ABI at all: the old UTF-8 strings, empty or truncated payloads
+        let supply = supply_message(BENEFICIARY);
+        for payload in [&b"supply"[..], b"", &supply[..supply.len() - 32]] {
+            let result = program.on_call(1_000, [7u8; 20], versioned(MESSAGE_VERSION_V1, payload), tss, &[0u8; 64]);
+            assert!(matches!(result, Err(ErrorCode::InvalidDataFormat)));
+        }
+
+        // Known action, bad words: a dirty address padding or an amount beyond u64
+        let recipient = Pubkey::new_unique().0.to_vec();
+        let malformed = [
+            abi::encode_with_action("supply", &[vec![0xff; 32]]),
+            abi::encode_with_action("withdrawCrossChain", &[on_behalf_of.clone(), vec![0xff; 32], recipient]),
+        ];
+        for payload in malformed {
+            let result = program.on_call(1_000, [7u8; 20], versioned(MESSAGE_VERSION_V1, &payload), tss, &[0u8; 64]);
+            assert!(matches!(result, Err(ErrorCode::InvalidActionArguments)));
+        }
+        assert_eq!(program.pda.as_ref().unwrap().total_supplied, 0);
+    }
+
+    #[test]
+    fn test_on_call_decodes_deposit_contract_messages() {
+        // The deposit contract's create_supply_message layout decodes to the action,
+        // beneficiary and delivered amount
+        let supply = supply_message([9u8; 20]);
+        assert_eq!(supply.len(), 128);
+        let decoded = decode_call(&supply, 1_000).unwrap();
+        assert_eq!(decoded, DecodedCall { action: CallAction::Supply, on_behalf_of: [9u8; 20], amount: 1_000 });
+
+        let decoded = decode_call(&repay_message([9u8; 20]), 250).unwrap();
+        assert_eq!(decoded, DecodedCall { action: CallAction::Repay, on_behalf_of: [9u8; 20], amount: 250 });
+
+        // Payout actions carry their own amount and recipient
+        let recipient = Pubkey::new_unique();
+        let decoded = decode_call(&payout_message("withdrawCrossChain", [9u8; 20], 400, recipient), 0).unwrap();
+        assert_eq!(decoded, DecodedCall { action: CallAction::WithdrawCrossChain { recipient }, on_behalf_of: [9u8; 20], amount: 400 });
+
+        // And on_call acts on the decoded message
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+        program.on_call(1_000, [7u8; 20], versioned(MESSAGE_VERSION_V1, &supply), tss, &[0u8; 64]).unwrap();
+        let pda = program.pda.as_ref().unwrap();
+        assert_eq!(pda.last_message, "supply");
+        assert_eq!(pda.total_supplied, 1_000);
+    }
+
+    #[test]
+    fn test_on_call_rejects_replayed_nonce() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+        let data = versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY));
+
+        // Strictly increasing nonces succeed, gaps included
+        program.on_call_with_nonce(1_000, [7u8; 20], 1, data.clone(), tss, &[0u8; 64]).unwrap();
+        program.on_call_with_nonce(1_000, [7u8; 20], 2, data.clone(), tss, &[0u8; 64]).unwrap();
+        program.on_call_with_nonce(1_000, [7u8; 20], 5, data.clone(), tss, &[0u8; 64]).unwrap();
+        assert_eq!(program.sender_nonces[&[7u8; 20]], 5);
+
+        // Resubmitting a processed message, or any older nonce, is rejected
+        for nonce in [5, 3, 0] {
+            let result = program.on_call_with_nonce(1_000, [7u8; 20], nonce, data.clone(), tss, &[0u8; 64]);
+            assert!(matches!(result, Err(ErrorCode::ReplayedMessage)));
+        }
+        assert_eq!(program.sender_nonces[&[7u8; 20]], 5);
+
+        // Nonces are tracked per sender
+        program.on_call_with_nonce(1_000, [8u8; 20], 1, data.clone(), tss, &[0u8; 64]).unwrap();
+
+        // The nonce is signed: rewriting it in a captured message breaks the signature
+        let signed = tss_signed_message(1_000, [7u8; 20], 5, &data);
+        program.preceding_instruction = Some(ed25519_instruction(&[(tss, &[0u8; 64], &signed)]));
+        let result = program.on_call_with_signer(1_000, [7u8; 20], 6, data, tss, true, &[0u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_on_call_batch_sender_nonces() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+        program.on_call(1_000, [1u8; 20], versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), tss, &[0u8; 64]).unwrap();
+
+        let item = |sender: u8, nonce: u64| OnCallItem {
+            amount: 1_000,
+            sender: [sender; 20],
+            nonce,
+            data: versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)),
+            signature: vec![0u8; 64],
+        };
+
+        // Replays within the batch and senders without a SenderNonce account fail per item
+        let items = vec![item(1, 2), item(1, 2), item(1, 3), item(2, 1)];
+        let statuses = program.on_call_batch(&items, tss, false).unwrap();
+        assert_eq!(
+            statuses,
+            vec![
+                ON_CALL_ITEM_OK,
+                ErrorCode::ReplayedMessage.code(),
+                ON_CALL_ITEM_OK,
+                ErrorCode::InvalidSenderNonceAccounts.code(),
+            ]
+        );
+        assert_eq!(program.sender_nonces[&[1u8; 20]], 3);
+        assert!(!program.sender_nonces.contains_key(&[2u8; 20]));
+
+        // Passing one SenderNonce account twice is rejected outright
+        let result = program.process_batch(&[item(1, 4)], tss, false, &[[1u8; 20], [1u8; 20]]);
+        assert!(matches!(result, Err(ErrorCode::InvalidSenderNonceAccounts)));
+        assert_eq!(program.sender_nonces[&[1u8; 20]], 3);
+    }
+
+    #[test]
+    fn test_on_call_batch_size_bounds() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+
+        let result = program.on_call_batch(&[], tss, false);
+        assert!(matches!(result, Err(ErrorCode::InvalidBatchSize)));
+
+        let items: Vec<OnCallItem> = (0..=MAX_ON_CALL_BATCH_SIZE)
+            .map(|_| OnCallItem { amount: 0, sender: [1u8; 20], nonce: 1, data: versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), signature: vec![0u8; 64] })
+            .collect();
+        let result = program.on_call_batch(&items, tss, false);
+        assert!(matches!(result, Err(ErrorCode::InvalidBatchSize)));
+    }
+
+    #[test]
+    fn test_on_call_requires_tss_signer() {
+        let mut program = MockConnected::new();
+        let tss = Pubkey::new_unique();
+        program.initialize(Pubkey::new_unique(), tss).unwrap();
+
+        // Right key, but the account did not sign the transaction
+        let result = program.on_call_with_signer(1_000, [7u8; 20], 1, versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), tss, false, &[0u8; 64]);
+        assert!(matches!(result, Err(ErrorCode::TssNotSigner)));
+        assert!(program.pda.as_ref().unwrap().last_message.is_empty());
+
+        // The signer requirement holds even with test-bypass-tss
+        program.bypass_tss = true;
+        let result = program.on_call_with_signer(1_000, [7u8; 20], 1, versioned(MESSAGE_VERSION_V1, &supply_message(BENEFICIARY)), tss, false, &[]);
+        assert!(matches!(result, Err(ErrorCode::TssNotSigner)));
+    }
+}
+
+fn main() {
+    println!("🧪 Running Solana Connected Program Test Suite...");
+    println!("✅ All tests should pass!");
+    println!("Run with: cargo test --bin test_connected");
+}
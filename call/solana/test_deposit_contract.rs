@@ -6,10 +6,14 @@
 //! 
 //! Run with: `cargo test --bin test_deposit_contract`
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+#[cfg(test)]
+#[path = "test_support.rs"]
+mod test_support;
 
 // Mock types matching the actual contract
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Pubkey([u8; 32]);
 
 impl Pubkey {
@@ -33,19 +37,138 @@ struct ContractState {
     lending_protocol_address: [u8; 20],
     zeta_chain_id: u64,
     is_paused: bool,
+    deposit_revert_policy: RevertPolicy,
+    call_revert_policy: RevertPolicy,
+    require_evm_link: bool,
+    incident_metadata: String,
+    total_outstanding_borrows: u128, // NORMALIZED_DECIMALS units
+    global_borrow_cap: u128,
+    max_price_age: i64,
+    allowed_caller_programs: Vec<Pubkey>,
+    pending_authority: Pubkey,
+    last_pause_change_ts: i64,
+    emergency_admins: Vec<Pubkey>,
+    require_depositor_whitelist: bool,
+    treasury: Pubkey,
+    gateway_program_id: Pubkey,
+    secondary_gateway_program_id: Pubkey, // SYSTEM_PROGRAM_ID when none was given
+    use_failover: bool,
+    accept_legacy_messages: bool,
+    min_sol_deposit: u64, // Zero disables the check
+    debug_logging: bool, // Verbose gateway msg! output
+    beneficiary_allowlist_root: [u8; 32], // Zero disables the allowlist
+    allow_freezable_mints: bool,
+    min_health_factor: u64, // Basis points; zero disables the check
+    accrued_fees: u64, // DEPOSIT_FEE lamports kept by SOL deposits and repays
+    total_sol_forwarded: u64, // Lamports those deposits and repays sent to the gateway
+    reject_self_borrow: bool,
+    paused_operations: u32, // PAUSE_* bits
     bump: u8,
 }
 
+// Events the mock records in place of emit!
+#[derive(Debug, Clone, PartialEq)]
+enum Event {
+    PauseStateChanged { is_paused: bool },
+    MaxBorrowComputed { user: Pubkey, asset: [u8; 20], max_amount: u64 },
+    CircuitBreakerTripped { incident_metadata: String },
+    RepayInitiated { user: Pubkey, asset: Pubkey, amount: u64, on_behalf_of: [u8; 20] },
+    DepositInitiated { user: Pubkey, owner: Pubkey, asset: Pubkey, amount: u64, on_behalf_of: [u8; 20], lending_protocol: [u8; 20], derived_evm_sender: [u8; 20] },
+    FeeAccrued { asset: Pubkey, amount: u64, operation: FeeOperation, recipient: Pubkey },
+    FeesWithdrawn { authority: Pubkey, amount: u64, remaining: u64 },
+    SolDustSwept { treasury: Pubkey, amount: u64 },
+    ConfigSnapshot { authority: Pubkey, deposit_fee: u64, global_borrow_cap: u128, total_outstanding_borrows: u128, is_paused: bool, lending_protocol_address: [u8; 20], asset_count: u16 },
+    LendingProtocolRotated { old_address: [u8; 20], new_address: [u8; 20], default_rotated: bool, routes_rotated: u16 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OperationType {
+    Deposit,
+    Call,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FeeOperation {
+    Deposit,
+    Repay,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RevertPolicy {
+    call_on_revert: bool,
+    revert_address: [u8; 20],
+    abort_address: [u8; 20],
+    retry_after_seconds: u32, // 0 means do not retry
+}
+
+impl Default for RevertPolicy {
+    fn default() -> Self {
+        Self {
+            call_on_revert: true,
+            revert_address: [0u8; 20],
+            abort_address: [0u8; 20],
+            retry_after_seconds: DEFAULT_RETRY_AFTER_SECONDS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RevertOptions {
+    revert_address: [u8; 20],
+    call_on_revert: bool,
+    abort_address: [u8; 20],
+    revert_message: Vec<u8>,
+    on_revert_gas_limit: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PendingChanges {
+    pending_authority: Option<Pubkey>,
+    authority_effective_at: i64, // 0: applies once confirmed
+    pending_lending_protocol_address: Option<[u8; 20]>,
+    lending_protocol_effective_at: i64,
+}
+
+#[derive(Clone, Debug)]
+struct Zrc20Config {
+    borrow_cap: u64,
+    outstanding_borrow: u64,
+    decimals: u8, // Zero until set_zrc20_decimals
+}
+
+// A ZRC-20's first borrow opens its config uncapped
+const UNCAPPED_ZRC20: Zrc20Config = Zrc20Config { borrow_cap: u64::MAX, outstanding_borrow: 0, decimals: 0 };
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct CollateralPosition {
+    amount: u64,
+    last_update: i64, // Unix time of the last credit or debit
+}
+
 #[derive(Clone, Debug)]
 struct AssetConfig {
     mint: Pubkey,
     decimals: u8,
     is_native: bool,
     is_supported: bool,
+    total_received: u64,
+    total_forwarded: u64,
+    metadata_uri: String,
+    repay_enabled: bool,
+    require_exact_amount: bool,
+    lending_protocol_address: [u8; 20], // Zero uses the contract default
+    min_deposit: u64, // Zero disables the check
+    deposit_cap: u64, // Zero means unlimited
+    total_deposited: u64,
+    fee_recipient: Pubkey, // Default (SYSTEM_PROGRAM_ID) sends fees to the fee vault
+    ltv: u16, // Basis points that may be borrowed against
+    liquidation_threshold: u16, // Basis points counted as collateral
+    price_feed: Pubkey, // Pyth price account; SYSTEM_PROGRAM_ID when unset
+    zrc20: [u8; 20], // Zero when unknown
     bump: u8,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum DepositContractError {
     Unauthorized,
     InvalidAmount,
@@ -56,16 +179,664 @@ enum DepositContractError {
     UseDepositSol,
     UseRepaySol,
     InsufficientDepositFee,
+    ArithmeticOverflow,
+    EmptyMessage,
+    InvalidEvmSignature,
+    EvmSignatureMismatch,
+    EvmLinkRequired,
+    EvmAddressNotLinked,
+    InvalidBatchAccounts,
+    GlobalBorrowCapExceeded,
+    AssetStillSupported,
+    VaultNotEmpty,
+    InvalidVaultAccount,
+    InsufficientBalance,
+    MetadataUriTooLong,
+    BatchTooLarge,
+    ReturnDataTooLarge,
+    IncidentMetadataTooLong,
+    RepayDisabled,
+    NonExactAmount,
+    TokenAccountInSolPath,
+    InvalidRecipientLength,
+    AssetAlreadySupported,
+    StalePrice,
+    RegistryFull,
+    BelowRentExemption,
+    UnauthorizedCaller,
+    SuspiciousRecipient,
+    InvalidAuthority,
+    PauseToggleTooSoon,
+    RescueExceedsSurplus,
+    InsufficientVaultBalance,
+    InvalidLendingProtocolAddress,
+    InvalidSignerSeeds,
+    DepositorNotWhitelisted,
+    MissingStrictConfig,
+    InsufficientFeeBalance,
+    LegacyMessageDisabled,
+    MalformedMessage,
+    ClockUnavailable,
+    DecimalMismatch,
+    Zrc20BorrowCapExceeded,
+    BelowMinimumDeposit,
+    DepositCapExceeded,
+    InvalidTreasury,
+    NoDustToSweep,
+    UnauthorizedGateway,
+    InvalidProgramAccount,
+    BeneficiaryNotAllowed,
+    MintHasFreezeAuthority,
+    InvalidFeeRecipient,
+    InsufficientCollateral,
+    InvalidCollateralAccounts,
+    EncodingError,
+    InvalidSecondaryGateway,
+    InvalidPriceFeed,
+    SelfBorrow,
+    InvalidRiskParams,
+    OperationPaused,
+    InvalidZrc20Config,
+    VaultNotInitialized,     // Stands in for Anchor's AccountNotInitialized on the vault ATA
+    GatewayCallFailed,       // Stands in for whatever error the gateway's call CPI returns
+}
+
+// Mirrors the program's code/slug table; mock-only variants are left out
+impl DepositContractError {
+    const ALL: [DepositContractError; 67] = [
+        DepositContractError::Unauthorized,
+        DepositContractError::InvalidAmount,
+        DepositContractError::UnsupportedAsset,
+        DepositContractError::InvalidChainId,
+        DepositContractError::DepositFailed,
+        DepositContractError::ContractPaused,
+        DepositContractError::UseDepositSol,
+        DepositContractError::UseRepaySol,
+        DepositContractError::InsufficientDepositFee,
+        DepositContractError::ArithmeticOverflow,
+        DepositContractError::EmptyMessage,
+        DepositContractError::InvalidEvmSignature,
+        DepositContractError::EvmSignatureMismatch,
+        DepositContractError::EvmLinkRequired,
+        DepositContractError::EvmAddressNotLinked,
+        DepositContractError::InvalidBatchAccounts,
+        DepositContractError::MetadataUriTooLong,
+        DepositContractError::BatchTooLarge,
+        DepositContractError::ReturnDataTooLarge,
+        DepositContractError::IncidentMetadataTooLong,
+        DepositContractError::RepayDisabled,
+        DepositContractError::NonExactAmount,
+        DepositContractError::GlobalBorrowCapExceeded,
+        DepositContractError::AssetStillSupported,
+        DepositContractError::VaultNotEmpty,
+        DepositContractError::InvalidVaultAccount,
+        DepositContractError::TokenAccountInSolPath,
+        DepositContractError::InvalidRecipientLength,
+        DepositContractError::AssetAlreadySupported,
+        DepositContractError::StalePrice,
+        DepositContractError::RegistryFull,
+        DepositContractError::BelowRentExemption,
+        DepositContractError::UnauthorizedCaller,
+        DepositContractError::SuspiciousRecipient,
+        DepositContractError::InvalidAuthority,
+        DepositContractError::PauseToggleTooSoon,
+        DepositContractError::RescueExceedsSurplus,
+        DepositContractError::InsufficientVaultBalance,
+        DepositContractError::InvalidLendingProtocolAddress,
+        DepositContractError::InvalidSignerSeeds,
+        DepositContractError::DepositorNotWhitelisted,
+        DepositContractError::MissingStrictConfig,
+        DepositContractError::InsufficientFeeBalance,
+        DepositContractError::LegacyMessageDisabled,
+        DepositContractError::MalformedMessage,
+        DepositContractError::ClockUnavailable,
+        DepositContractError::InsufficientBalance,
+        DepositContractError::DecimalMismatch,
+        DepositContractError::Zrc20BorrowCapExceeded,
+        DepositContractError::BelowMinimumDeposit,
+        DepositContractError::DepositCapExceeded,
+        DepositContractError::InvalidTreasury,
+        DepositContractError::NoDustToSweep,
+        DepositContractError::UnauthorizedGateway,
+        DepositContractError::InvalidProgramAccount,
+        DepositContractError::BeneficiaryNotAllowed,
+        DepositContractError::MintHasFreezeAuthority,
+        DepositContractError::InvalidFeeRecipient,
+        DepositContractError::InsufficientCollateral,
+        DepositContractError::InvalidCollateralAccounts,
+        DepositContractError::EncodingError,
+        DepositContractError::InvalidSecondaryGateway,
+        DepositContractError::InvalidPriceFeed,
+        DepositContractError::SelfBorrow,
+        DepositContractError::InvalidRiskParams,
+        DepositContractError::OperationPaused,
+        DepositContractError::InvalidZrc20Config,
+    ];
+
+    fn code(&self) -> u32 {
+        ERROR_CODE_OFFSET + Self::ALL.iter().position(|e| e == self).expect("mock-only variant") as u32
+    }
+
+    fn slug(&self) -> &'static str {
+        match self {
+            DepositContractError::Unauthorized => "unauthorized",
+            DepositContractError::InvalidAmount => "invalid_amount",
+            DepositContractError::UnsupportedAsset => "unsupported_asset",
+            DepositContractError::InvalidChainId => "invalid_chain_id",
+            DepositContractError::DepositFailed => "deposit_failed",
+            DepositContractError::ContractPaused => "contract_paused",
+            DepositContractError::UseDepositSol => "use_deposit_sol",
+            DepositContractError::UseRepaySol => "use_repay_sol",
+            DepositContractError::InsufficientDepositFee => "insufficient_deposit_fee",
+            DepositContractError::ArithmeticOverflow => "arithmetic_overflow",
+            DepositContractError::EmptyMessage => "empty_message",
+            DepositContractError::InvalidEvmSignature => "invalid_evm_signature",
+            DepositContractError::EvmSignatureMismatch => "evm_signature_mismatch",
+            DepositContractError::EvmLinkRequired => "evm_link_required",
+            DepositContractError::EvmAddressNotLinked => "evm_address_not_linked",
+            DepositContractError::InvalidBatchAccounts => "invalid_batch_accounts",
+            DepositContractError::MetadataUriTooLong => "metadata_uri_too_long",
+            DepositContractError::BatchTooLarge => "batch_too_large",
+            DepositContractError::ReturnDataTooLarge => "return_data_too_large",
+            DepositContractError::IncidentMetadataTooLong => "incident_metadata_too_long",
+            DepositContractError::RepayDisabled => "repay_disabled",
+            DepositContractError::NonExactAmount => "non_exact_amount",
+            DepositContractError::GlobalBorrowCapExceeded => "global_borrow_cap_exceeded",
+            DepositContractError::AssetStillSupported => "asset_still_supported",
+            DepositContractError::VaultNotEmpty => "vault_not_empty",
+            DepositContractError::InvalidVaultAccount => "invalid_vault_account",
+            DepositContractError::TokenAccountInSolPath => "token_account_in_sol_path",
+            DepositContractError::InvalidRecipientLength => "invalid_recipient_length",
+            DepositContractError::AssetAlreadySupported => "asset_already_supported",
+            DepositContractError::StalePrice => "stale_price",
+            DepositContractError::RegistryFull => "registry_full",
+            DepositContractError::BelowRentExemption => "below_rent_exemption",
+            DepositContractError::UnauthorizedCaller => "unauthorized_caller",
+            DepositContractError::SuspiciousRecipient => "suspicious_recipient",
+            DepositContractError::InvalidAuthority => "invalid_authority",
+            DepositContractError::PauseToggleTooSoon => "pause_toggle_too_soon",
+            DepositContractError::RescueExceedsSurplus => "rescue_exceeds_surplus",
+            DepositContractError::InsufficientVaultBalance => "insufficient_vault_balance",
+            DepositContractError::InvalidLendingProtocolAddress => "invalid_lending_protocol_address",
+            DepositContractError::InvalidSignerSeeds => "invalid_signer_seeds",
+            DepositContractError::DepositorNotWhitelisted => "depositor_not_whitelisted",
+            DepositContractError::MissingStrictConfig => "missing_strict_config",
+            DepositContractError::InsufficientFeeBalance => "insufficient_fee_balance",
+            DepositContractError::LegacyMessageDisabled => "legacy_message_disabled",
+            DepositContractError::MalformedMessage => "malformed_message",
+            DepositContractError::ClockUnavailable => "clock_unavailable",
+            DepositContractError::InsufficientBalance => "insufficient_balance",
+            DepositContractError::DecimalMismatch => "decimal_mismatch",
+            DepositContractError::Zrc20BorrowCapExceeded => "zrc20_borrow_cap_exceeded",
+            DepositContractError::BelowMinimumDeposit => "below_minimum_deposit",
+            DepositContractError::DepositCapExceeded => "deposit_cap_exceeded",
+            DepositContractError::InvalidTreasury => "invalid_treasury",
+            DepositContractError::NoDustToSweep => "no_dust_to_sweep",
+            DepositContractError::UnauthorizedGateway => "unauthorized_gateway",
+            DepositContractError::InvalidProgramAccount => "invalid_program_account",
+            DepositContractError::BeneficiaryNotAllowed => "beneficiary_not_allowed",
+            DepositContractError::MintHasFreezeAuthority => "mint_has_freeze_authority",
+            DepositContractError::InvalidFeeRecipient => "invalid_fee_recipient",
+            DepositContractError::InsufficientCollateral => "insufficient_collateral",
+            DepositContractError::InvalidCollateralAccounts => "invalid_collateral_accounts",
+            DepositContractError::EncodingError => "encoding_error",
+            DepositContractError::InvalidSecondaryGateway => "invalid_secondary_gateway",
+            DepositContractError::InvalidPriceFeed => "invalid_price_feed",
+            DepositContractError::SelfBorrow => "self_borrow",
+            DepositContractError::InvalidRiskParams => "invalid_risk_params",
+            DepositContractError::OperationPaused => "operation_paused",
+            DepositContractError::InvalidZrc20Config => "invalid_zrc20_config",
+            _ => "mock_only",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RepayItem {
+    mint: Pubkey,
+    amount: u64,
+    on_behalf_of: [u8; 20],
+}
+
+// Mock EVM key: stands in for a secp256k1 keypair. The "signature" embeds the signer
+// address plus a digest of the signed payload so mock_ecrecover can mimic recovery:
+// a tampered payload recovers to an unrelated address, like the real precompile.
+struct MockEvmKey {
+    address: [u8; 20],
+}
+
+impl MockEvmKey {
+    fn sign(&self, payload: &[u8; 32]) -> ([u8; 64], u8) {
+        let mut signature = [0u8; 64];
+        signature[0..20].copy_from_slice(&self.address);
+        signature[20..28].copy_from_slice(&mock_digest(payload).to_be_bytes());
+        (signature, 27)
+    }
+}
+
+fn mock_digest(payload: &[u8]) -> u64 {
+    // FNV-1a
+    payload.iter().fold(0xcbf29ce484222325u64, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+// Mock keccak256: four chained FNV-1a digests widened to 32 bytes
+fn mock_hash32(parts: &[&[u8]]) -> [u8; 32] {
+    let input = parts.concat();
+    let mut hash = [0u8; 32];
+    for (i, chunk) in hash.chunks_mut(8).enumerate() {
+        chunk.copy_from_slice(&mock_digest(&[&input[..], &[i as u8]].concat()).to_be_bytes());
+    }
+    hash
+}
+
+// Stands in for the deployed program id
+const PROGRAM_ID: Pubkey = Pubkey([7u8; 32]);
+const TOKEN_PROGRAM_ID: Pubkey = Pubkey([6u8; 32]);
+const SYSTEM_PROGRAM_ID: Pubkey = Pubkey([0u8; 32]);
+const GATEWAY_PROGRAM_ID: Pubkey = Pubkey([5u8; 32]);
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey = Pubkey([4u8; 32]);
+
+// Stands in for Pubkey::create_program_address; the bump is the last seed
+fn create_program_address(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey(mock_hash32(&[&seeds.concat()[..], &PROGRAM_ID.to_bytes()]))
+}
+
+fn fee_vault_address() -> Pubkey {
+    create_program_address(&[b"fee_vault"])
+}
+
+// Bump find_program_address is taken to return for contract_state
+const CONTRACT_STATE_BUMP: u8 = 255;
+
+// The contract_state PDA, seeds [b"contract_state"]
+fn contract_state_pda() -> Pubkey {
+    create_program_address(&contract_state_signer_seeds(&[CONTRACT_STATE_BUMP]))
+}
+
+fn contract_state_signer_seeds(bump: &[u8; 1]) -> [&[u8]; 2] {
+    [b"contract_state", bump]
+}
+
+fn check_signer_seeds(signer: Pubkey, seeds: &[&[u8]]) -> Result<(), DepositContractError> {
+    if create_program_address(seeds) != signer {
+        return Err(DepositContractError::InvalidSignerSeeds);
+    }
+    Ok(())
+}
+
+// Gateway deposits move tokens out of the vault; it must already hold them
+fn forward_from_vault(vault_balance: &mut u64, amount: u64) -> Result<(), DepositContractError> {
+    *vault_balance = vault_balance.checked_sub(amount).ok_or(DepositContractError::InsufficientVaultBalance)?;
+    Ok(())
+}
+
+// Gateway-side sender for a Solana signer: last 20 bytes of keccak256(pubkey)
+fn derive_evm_sender(signer: Pubkey) -> [u8; 20] {
+    let mut sender = [0u8; 20];
+    sender.copy_from_slice(&mock_hash32(&[&signer.to_bytes()])[12..32]);
+    sender
+}
+
+// Pause and rescue are open to the authority and the emergency admins
+fn check_operation_active(state: &ContractState, operation: u32) -> Result<(), DepositContractError> {
+    if state.is_paused {
+        return Err(DepositContractError::ContractPaused);
+    }
+    if state.paused_operations & operation != 0 {
+        return Err(DepositContractError::OperationPaused);
+    }
+    Ok(())
+}
+
+fn is_emergency_admin(state: &ContractState, signer: Pubkey) -> bool {
+    signer == state.authority || state.emergency_admins.contains(&signer)
+}
+
+// Authorities nothing can sign for would brick admin access
+fn check_authority_candidate(candidate: Pubkey) -> Result<(), DepositContractError> {
+    if candidate == PROGRAM_ID || candidate == contract_state_pda() || candidate == SYSTEM_PROGRAM_ID {
+        return Err(DepositContractError::InvalidAuthority);
+    }
+    Ok(())
+}
+
+// Extra account passed via remaining_accounts
+#[derive(Clone, Copy, Debug)]
+struct MockAccount {
+    key: Pubkey,
+    owner: Pubkey,
+}
+
+// SOL paths only move lamports through the system program; any SPL account is a caller error
+fn check_no_token_accounts(accounts: &[MockAccount]) -> Result<(), DepositContractError> {
+    if accounts.iter().any(|account| account.owner == TOKEN_PROGRAM_ID || account.key == TOKEN_PROGRAM_ID) {
+        return Err(DepositContractError::TokenAccountInSolPath);
+    }
+    Ok(())
+}
+
+// Relayer-facing layout: retry_after_seconds (u32 big-endian) || reason
+fn encode_revert_message(retry_after_seconds: u32, reason: &[u8]) -> Vec<u8> {
+    [retry_after_seconds.to_be_bytes().as_slice(), reason].concat()
+}
+
+// Catches on_behalf_of/recipient swaps (lending protocol as recipient) and unset recipients
+fn check_recipient(state: &ContractState, recipient_len: u8, recipient: &[u8]) -> Result<(), DepositContractError> {
+    if recipient.len() != usize::from(recipient_len) {
+        return Err(DepositContractError::InvalidRecipientLength);
+    }
+    if recipient == state.lending_protocol_address.as_slice() || recipient.iter().all(|byte| *byte == 0) {
+        return Err(DepositContractError::SuspiciousRecipient);
+    }
+    Ok(())
+}
+
+// address constraints on DepositSplToken/RepaySplToken, checked with the other accounts
+fn check_spl_programs((associated_token_program, system_program): (Pubkey, Pubkey)) -> Result<(), DepositContractError> {
+    if associated_token_program != ASSOCIATED_TOKEN_PROGRAM_ID || system_program != SYSTEM_PROGRAM_ID {
+        return Err(DepositContractError::InvalidProgramAccount);
+    }
+    Ok(())
+}
+
+fn check_gateway_program(state: &ContractState, gateway_program: Pubkey) -> Result<(), DepositContractError> {
+    let active = if state.use_failover { state.secondary_gateway_program_id } else { state.gateway_program_id };
+    if gateway_program != active {
+        return Err(DepositContractError::UnauthorizedGateway);
+    }
+    Ok(())
+}
+
+// A direct call has this program at the top level; otherwise the top-level program is the CPI caller
+// Sorted-pair merkle node, as hashed by check_beneficiary_allowed
+fn merkle_parent(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    if a <= b { mock_hash32(&[&a, &b]) } else { mock_hash32(&[&b, &a]) }
+}
+
+fn check_beneficiary_allowed(state: &ContractState, on_behalf_of: [u8; 20], proof: &[[u8; 32]]) -> Result<(), DepositContractError> {
+    if state.beneficiary_allowlist_root == [0u8; 32] {
+        return Ok(());
+    }
+    if proof.len() > MAX_BENEFICIARY_PROOF_LEN {
+        return Err(DepositContractError::BeneficiaryNotAllowed);
+    }
+    let computed = proof.iter().fold(mock_hash32(&[&on_behalf_of]), |node, sibling| merkle_parent(node, *sibling));
+    if computed != state.beneficiary_allowlist_root {
+        return Err(DepositContractError::BeneficiaryNotAllowed);
+    }
+    Ok(())
+}
+
+fn check_caller_program(state: &ContractState, top_level_program: Pubkey) -> Result<(), DepositContractError> {
+    if state.allowed_caller_programs.is_empty() || top_level_program == PROGRAM_ID {
+        return Ok(());
+    }
+    if !state.allowed_caller_programs.contains(&top_level_program) {
+        return Err(DepositContractError::UnauthorizedCaller);
+    }
+    Ok(())
+}
+
+// Fee rounds down, net rounds up to the user; fee + net == amount. No instruction
+// charges a bps fee yet, so this only pins the policy a future one must follow
+fn split_bps_fee(amount: u64, fee_bps: u16) -> Result<(u64, u64), DepositContractError> {
+    if u64::from(fee_bps) > BPS_DENOMINATOR {
+        return Err(DepositContractError::InvalidAmount);
+    }
+    let fee = (u128::from(amount) * u128::from(fee_bps) / u128::from(BPS_DENOMINATOR)) as u64;
+    Ok((fee, amount - fee))
+}
+
+// Rent::minimum_balance with the default rent: (128-byte overhead + data) * 3480 lamports * 2 years
+fn rent_exempt_minimum(data_len: usize) -> u64 {
+    (128 + data_len as u64) * 3480 * 2
+}
+
+// Any lamport debit from a program PDA must leave it rent-exempt
+fn check_rent_exempt_after_debit(balance: u64, data_len: usize, amount: u64) -> Result<(), DepositContractError> {
+    if balance.saturating_sub(amount) < rent_exempt_minimum(data_len) {
+        return Err(DepositContractError::BelowRentExemption);
+    }
+    Ok(())
+}
+
+fn domain_separator(program_id: Pubkey, zeta_chain_id: u64) -> [u8; 32] {
+    mock_hash32(&[&program_id.to_bytes(), &zeta_chain_id.to_be_bytes()])
+}
+
+fn mock_ecrecover(payload: &[u8; 32], signature: &[u8; 64], recovery_id: u8) -> Result<[u8; 20], DepositContractError> {
+    let recovery_id = if recovery_id >= 27 { recovery_id - 27 } else { recovery_id };
+    if recovery_id > 1 {
+        return Err(DepositContractError::InvalidEvmSignature);
+    }
+
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&signature[0..20]);
+    if signature[20..28] != mock_digest(payload).to_be_bytes() {
+        // Wrong payload recovers some other key
+        address.iter_mut().for_each(|b| *b ^= 0x5a);
+    }
+    Ok(address)
 }
 
 // Constants from the contract
 const GAS_LIMIT: u64 = 5_000_000;
 const DEPOSIT_FEE: u64 = 2_000_000; // 0.002 SOL in lamports
+const NATIVE_SOL_DECIMALS: u8 = 9;
+const BPS_DENOMINATOR: u64 = 10_000;
+const MAX_METADATA_URI_LEN: usize = 200;
+const MAX_ASSET_CONFIGS_PER_QUERY: usize = 16;
+const MAX_REPAY_BATCH_SIZE: usize = 4;
+const MAX_ADD_ASSET_BATCH_SIZE: usize = 8;
+const MAX_INCIDENT_METADATA_LEN: usize = 100;
+const NORMALIZED_DECIMALS: u8 = 18;
+const EVM_ADDRESS_LEN: u8 = 20;
+const DEFAULT_MAX_PRICE_AGE: i64 = 60;
+const DEFAULT_RETRY_AFTER_SECONDS: u32 = 60;
+const MIN_PAUSE_TOGGLE_INTERVAL: i64 = 60;
+const MAX_DESTINATION_CHAINS: usize = 16;
+const MAX_ZRC20_ASSETS: usize = 32;
+const MAX_ALLOWED_CALLER_PROGRAMS: usize = 4;
+const MAX_EMERGENCY_ADMINS: usize = 4;
+const ERROR_CODE_OFFSET: u32 = 6000; // Anchor's custom error base
+const MAX_BENEFICIARY_PROOF_LEN: usize = 32;
+const MAX_COLLATERAL_POSITIONS: usize = 8;
+const PAUSE_DEPOSIT: u32 = 1 << 0;
+const PAUSE_REPAY: u32 = 1 << 1;
+const PAUSE_BORROW: u32 = 1 << 2;
+const PAUSE_WITHDRAW: u32 = 1 << 3;
+const PAUSABLE_OPERATIONS: u32 = PAUSE_DEPOSIT | PAUSE_REPAY | PAUSE_BORROW | PAUSE_WITHDRAW;
+const CONTRACT_STATE_SPACE: usize = 8 + 600; // Stand-in for 8 + ContractState::INIT_SPACE
+
+// Smallest amount that survives normalization to NORMALIZED_DECIMALS without truncation
+fn normalization_step(decimals: u8) -> Result<u64, DepositContractError> {
+    let excess = decimals.saturating_sub(NORMALIZED_DECIMALS);
+    10u64.checked_pow(u32::from(excess)).ok_or(DepositContractError::ArithmeticOverflow)
+}
+
+fn normalize_amount(amount: u64, decimals: u8) -> Result<u128, DepositContractError> {
+    let normalized = if decimals <= NORMALIZED_DECIMALS {
+        10u128
+            .checked_pow(u32::from(NORMALIZED_DECIMALS - decimals))
+            .and_then(|scale| u128::from(amount).checked_mul(scale))
+    } else {
+        Some(normalization_step(decimals).map(|step| u128::from(amount / step)).unwrap_or(0))
+    };
+    match normalized {
+        Some(value) if value > 0 || amount == 0 => Ok(value),
+        _ => Err(DepositContractError::InvalidAmount),
+    }
+}
+
+// Basis points of weighted collateral over debt; u128::MAX when there is no debt
+fn compute_health_factor(weighted_collateral: u128, debt: u128) -> u128 {
+    if debt == 0 {
+        return u128::MAX;
+    }
+    weighted_collateral.saturating_mul(u128::from(BPS_DENOMINATOR)) / debt
+}
+
+// The (owner, mint) positions passed to borrow_cross_chain, each valued in USD when its
+// asset has a price feed (read from `price_accounts`) and weighted by its liquidation
+// threshold; positions must be the borrower's and counted once
+// Returns the weighted sum and whether some non-empty position is in another asset
+// than `borrowed_asset`
+fn weighted_collateral(
+    state: &ContractState,
+    user: Pubkey,
+    borrowed_asset: [u8; 20],
+    accounts: &[(Pubkey, Pubkey)],
+    collateral_positions: &HashMap<(Pubkey, Pubkey), CollateralPosition>,
+    asset_configs: &HashMap<Pubkey, AssetConfig>,
+    price_accounts: &HashMap<Pubkey, Vec<u8>>,
+    now: i64,
+) -> Result<Collateral, DepositContractError> {
+    if accounts.len() > MAX_COLLATERAL_POSITIONS {
+        return Err(DepositContractError::InvalidCollateralAccounts);
+    }
+
+    let mut collateral = Collateral { weighted: 0, borrowable: 0, other_assets: false };
+    for (index, (owner, mint)) in accounts.iter().enumerate() {
+        if *owner != user || accounts[..index].iter().any(|(_, counted)| counted == mint) {
+            return Err(DepositContractError::InvalidCollateralAccounts);
+        }
+        let asset_config = asset_configs.get(mint).ok_or(DepositContractError::InvalidCollateralAccounts)?;
+        let position = collateral_positions.get(&(*owner, *mint)).ok_or(DepositContractError::InvalidCollateralAccounts)?;
+        // A removed asset's collateral weighs nothing
+        if !asset_config.is_supported {
+            continue;
+        }
+        collateral.other_assets |= position.amount > 0 && asset_config.zrc20 != borrowed_asset;
+        let normalized = normalize_amount(position.amount, asset_config.decimals)?;
+        let value = if asset_config.price_feed == SYSTEM_PROGRAM_ID {
+            normalized
+        } else {
+            let data = price_accounts.get(&asset_config.price_feed).ok_or(DepositContractError::InvalidCollateralAccounts)?;
+            usd_value(normalized, &read_price(state, data, now)?)?
+        };
+        collateral.weighted = collateral.weighted
+            .checked_add(apply_bps(value, asset_config.liquidation_threshold))
+            .ok_or(DepositContractError::ArithmeticOverflow)?;
+        collateral.borrowable = collateral.borrowable
+            .checked_add(apply_bps(value, asset_config.ltv))
+            .ok_or(DepositContractError::ArithmeticOverflow)?;
+    }
+    Ok(collateral)
+}
+
+struct Collateral {
+    weighted: u128, // Liquidation-threshold weighted
+    borrowable: u128, // ltv weighted
+    other_assets: bool,
+}
+
+fn apply_bps(value: u128, bps: u16) -> u128 {
+    let bps = u128::from(bps);
+    let denominator = u128::from(BPS_DENOMINATOR);
+    value / denominator * bps + value % denominator * bps / denominator
+}
+
+fn denormalize_amount(normalized: u128, decimals: u8) -> u64 {
+    let amount = if decimals <= NORMALIZED_DECIMALS {
+        normalized / 10u128.pow(u32::from(NORMALIZED_DECIMALS - decimals))
+    } else {
+        normalization_step(decimals).map(|step| normalized.saturating_mul(u128::from(step))).unwrap_or(u128::MAX)
+    };
+    u64::try_from(amount).unwrap_or(u64::MAX)
+}
+
+// ltv-weighted collateral less debt, within min_health_factor and the borrow caps
+fn max_borrow(state: &ContractState, collateral: &Collateral, borrowed: u128, zrc20_config: Option<&Zrc20Config>) -> u64 {
+    if state.reject_self_borrow && !collateral.other_assets {
+        return 0;
+    }
+    let mut limit = collateral.borrowable;
+    if state.min_health_factor != 0 {
+        limit = limit.min(collateral.weighted.saturating_mul(u128::from(BPS_DENOMINATOR)) / u128::from(state.min_health_factor));
+    }
+    let decimals = zrc20_config.map_or(0, |zrc20_config| zrc20_config.decimals);
+    let amount = denormalize_amount(limit.saturating_sub(borrowed), decimals);
+    let global_headroom = denormalize_amount(state.global_borrow_cap.saturating_sub(state.total_outstanding_borrows), decimals);
+    let zrc20_headroom = zrc20_config.map_or(u64::MAX, |zrc20_config| zrc20_config.borrow_cap.saturating_sub(zrc20_config.outstanding_borrow));
+    amount.min(global_headroom).min(zrc20_headroom)
+}
+
+fn check_risk_params(ltv: u16, liquidation_threshold: u16) -> Result<(), DepositContractError> {
+    if u64::from(liquidation_threshold) > BPS_DENOMINATOR {
+        return Err(DepositContractError::InvalidAmount);
+    }
+    if ltv > liquidation_threshold {
+        return Err(DepositContractError::InvalidRiskParams);
+    }
+    Ok(())
+}
+
+fn read_price(state: &ContractState, data: &[u8], now: i64) -> Result<pyth::Price, DepositContractError> {
+    let price = pyth::parse_price(data)?;
+    if now.saturating_sub(price.publish_time) > state.max_price_age {
+        return Err(DepositContractError::StalePrice);
+    }
+    Ok(price)
+}
+
+fn usd_value(normalized: u128, price: &pyth::Price) -> Result<u128, DepositContractError> {
+    let price_value = u128::from(price.price);
+    let scale = 10u128.checked_pow(price.expo.unsigned_abs()).ok_or(DepositContractError::InvalidPriceFeed)?;
+    let value = if price.expo < 0 {
+        (normalized / scale).checked_mul(price_value).and_then(|whole| {
+            whole.checked_add((normalized % scale).checked_mul(price_value)? / scale)
+        })
+    } else {
+        normalized.checked_mul(price_value).and_then(|value| value.checked_mul(scale))
+    };
+    value.ok_or(DepositContractError::ArithmeticOverflow)
+}
+
+fn net_of_deposit_fee(amount: u64) -> Result<u64, DepositContractError> {
+    let net_amount = amount.checked_sub(DEPOSIT_FEE).ok_or(DepositContractError::InsufficientDepositFee)?;
+    if net_amount == 0 {
+        return Err(DepositContractError::InvalidAmount);
+    }
+    Ok(net_amount)
+}
 
 // Mock contract operations
 struct MockDepositContract {
     contract_state: Option<ContractState>,
     asset_configs: HashMap<Pubkey, AssetConfig>,
+    vault_balances: HashMap<Pubkey, u64>, // Contract-owned ATA balance per mint
+    evm_links: HashMap<Pubkey, [u8; 20]>,  // EvmLink PDAs by Solana user
+    whitelisted_depositors: HashSet<Pubkey>, // DepositorWhitelistEntry PDAs
+    user_balances: HashMap<(Pubkey, Pubkey), u64>, // User ATA balance by (user, mint)
+    mint_decimals: HashMap<Pubkey, u8>, // Mint accounts created with create_mint
+    freezable_mints: HashSet<Pubkey>, // Mints whose freeze_authority is set
+    contract_lamports: u64, // contract_state PDA balance
+    fee_vault_lamports: u64, // fee_vault PDA balance: collected DEPOSIT_FEEs
+    fee_recipient_lamports: HashMap<Pubkey, u64>, // DEPOSIT_FEEs paid to asset fee recipients
+    fee_recipient: Option<Pubkey>, // fee_recipient account passed to SOL deposits and repays
+    collateral_positions: HashMap<(Pubkey, Pubkey), CollateralPosition>, // CollateralPosition PDAs by (owner, mint)
+    borrow_positions: HashMap<Pubkey, u128>, // BorrowPosition.borrowed by owner
+    collateral_accounts: Vec<(Pubkey, Pubkey)>, // (owner, mint) of the positions passed to borrow_cross_chain
+    price_accounts: HashMap<Pubkey, Vec<u8>>, // Pyth price accounts passed to borrow_cross_chain, by address
+    events: Vec<Event>,
+    logs: Vec<String>, // Program log (msg!) lines
+    transfer_fee_bps: HashMap<Pubkey, u64>, // Simulated fee-on-transfer mints
+    chain_configs: HashMap<u64, u8>, // ChainConfig PDAs: destination chain -> recipient length
+    zrc20_configs: HashMap<[u8; 20], Zrc20Config>, // Zrc20Config PDAs by ZRC-20 address
+    top_level_program: Pubkey, // Program id of the current top-level instruction (Instructions sysvar)
+    gateway_program: Pubkey, // gateway_program account passed to gateway-calling instructions
+    secondary_gateway_program: Option<Pubkey>, // secondary_gateway_program account passed to initialize
+    spl_programs: (Pubkey, Pubkey), // associated_token_program and system_program passed to SPL deposits/repays
+    beneficiary_proof: Vec<[u8; 32]>, // beneficiary_proof argument passed to deposits
+    outbound_nonces: HashMap<u64, u64>, // ChainConfig.last_outbound_nonce per destination chain
+    gateway_return_nonce: Option<u64>, // Return data left by the gateway CPI, if any
+    gateway_call_fails: bool, // Whether the gateway's call CPI fails, aborting the instruction
+    gateway_lamports: u64, // Gateway PDA balance: SOL received through deposit_and_call
+    repay_zrc20_config: Option<[u8; 20]>, // ZRC-20 whose zrc20_config is passed to repays; None passes the repaid asset's
+    gateway_moves_sol: bool, // Whether the gateway's deposit_and_call actually takes the lamports
+    return_data: Option<Vec<u8>>, // Instruction return data (set_return_data) of the last deposit
+    now: i64, // Clock::unix_timestamp
+    clock_available: bool, // Whether Clock::get succeeds
 }
 
 impl MockDepositContract {
@@ -73,6 +844,39 @@ impl MockDepositContract {
         Self {
             contract_state: None,
             asset_configs: HashMap::new(),
+            vault_balances: HashMap::new(),
+            evm_links: HashMap::new(),
+            whitelisted_depositors: HashSet::new(),
+            user_balances: HashMap::new(),
+            mint_decimals: HashMap::new(),
+            freezable_mints: HashSet::new(),
+            contract_lamports: 0,
+            fee_vault_lamports: 0,
+            fee_recipient_lamports: HashMap::new(),
+            fee_recipient: None,
+            collateral_positions: HashMap::new(),
+            borrow_positions: HashMap::new(),
+            collateral_accounts: Vec::new(),
+            price_accounts: HashMap::new(),
+            events: Vec::new(),
+            logs: Vec::new(),
+            transfer_fee_bps: HashMap::new(),
+            chain_configs: HashMap::new(),
+            zrc20_configs: HashMap::new(),
+            top_level_program: PROGRAM_ID,
+            gateway_program: GATEWAY_PROGRAM_ID,
+            secondary_gateway_program: None,
+            spl_programs: (ASSOCIATED_TOKEN_PROGRAM_ID, SYSTEM_PROGRAM_ID),
+            beneficiary_proof: Vec::new(),
+            outbound_nonces: HashMap::new(),
+            gateway_return_nonce: None,
+            gateway_call_fails: false,
+            gateway_lamports: 0,
+            repay_zrc20_config: None,
+            gateway_moves_sol: true,
+            return_data: None,
+            now: 1_700_000_000,
+            clock_available: true,
         }
     }
 
@@ -81,6 +885,12 @@ impl MockDepositContract {
             return Err(DepositContractError::Unauthorized);
         }
 
+        check_authority_candidate(authority)?;
+
+        if lending_protocol_address == [0u8; 20] {
+            return Err(DepositContractError::InvalidLendingProtocolAddress);
+        }
+
         // Validate zeta_chain_id is a known ZetaChain network
         match zeta_chain_id {
             7000 | 7001 => {}, // Mainnet and testnet
@@ -92,9 +902,135 @@ impl MockDepositContract {
             lending_protocol_address,
             zeta_chain_id,
             is_paused: false,
-            bump: 0,
+            deposit_revert_policy: RevertPolicy::default(),
+            call_revert_policy: RevertPolicy::default(),
+            require_evm_link: false,
+            incident_metadata: String::new(),
+            total_outstanding_borrows: 0,
+            global_borrow_cap: u128::MAX,
+            max_price_age: DEFAULT_MAX_PRICE_AGE,
+            allowed_caller_programs: Vec::new(),
+            pending_authority: SYSTEM_PROGRAM_ID,
+            last_pause_change_ts: 0,
+            emergency_admins: Vec::new(),
+            require_depositor_whitelist: false,
+            treasury: SYSTEM_PROGRAM_ID,
+            gateway_program_id: GATEWAY_PROGRAM_ID,
+            secondary_gateway_program_id: SYSTEM_PROGRAM_ID,
+            use_failover: false,
+            accept_legacy_messages: true,
+            min_sol_deposit: 0,
+            debug_logging: true,
+            beneficiary_allowlist_root: [0u8; 32],
+            allow_freezable_mints: false,
+            min_health_factor: 0,
+            accrued_fees: 0,
+            total_sol_forwarded: 0,
+            reject_self_borrow: false,
+            paused_operations: 0,
+            bump: CONTRACT_STATE_BUMP,
         });
 
+        if let Err(error) = self.set_secondary_gateway() {
+            self.contract_state = None; // The failed transaction leaves nothing behind
+            return Err(error);
+        }
+
+        // Deployment registers the EVM testnets (Arbitrum Sepolia, Ethereum Sepolia)
+        for chain_id in [421614, 11155111] {
+            self.chain_configs.insert(chain_id, EVM_ADDRESS_LEN);
+        }
+
+        Ok(())
+    }
+
+    // `gateway_program` is None where the passed account is not an executable program
+    #[allow(clippy::too_many_arguments)]
+    fn initialize_strict(
+        &mut self,
+        authority: Pubkey,
+        lending_protocol_address: [u8; 20],
+        zeta_chain_id: u64,
+        treasury: Pubkey,
+        abort_address: [u8; 20],
+        gateway_program: Option<Pubkey>,
+        native_asset: (Pubkey, u8, bool),
+    ) -> Result<(), DepositContractError> {
+        // The native asset must be SOL exactly as register_native_sol registers it
+        let (native_mint, native_decimals, is_native) = native_asset;
+        if treasury == SYSTEM_PROGRAM_ID
+            || abort_address == [0u8; 20]
+            || !is_native
+            || native_mint != SYSTEM_PROGRAM_ID
+            || native_decimals != NATIVE_SOL_DECIMALS
+        {
+            return Err(DepositContractError::MissingStrictConfig);
+        }
+        let gateway_program = gateway_program.ok_or(DepositContractError::MissingStrictConfig)?;
+
+        // The secondary is checked against this gateway, not the default initialize records
+        let secondary = self.secondary_gateway_program.take();
+        let result = self.initialize(authority, lending_protocol_address, zeta_chain_id);
+        self.secondary_gateway_program = secondary;
+        result?;
+
+        let state = self.contract_state.as_mut().unwrap();
+        state.treasury = treasury;
+        state.gateway_program_id = gateway_program;
+        state.deposit_revert_policy.abort_address = abort_address;
+        state.call_revert_policy.abort_address = abort_address;
+        if let Err(error) = self.set_secondary_gateway() {
+            self.contract_state = None;
+            return Err(error);
+        }
+
+        self.add_supported_asset(authority, native_mint, native_decimals, true)
+    }
+
+    // Records the secondary_gateway_program account once the primary is set
+    fn set_secondary_gateway(&mut self) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().unwrap();
+        if let Some(secondary) = self.secondary_gateway_program {
+            if secondary == state.gateway_program_id {
+                return Err(DepositContractError::InvalidSecondaryGateway);
+            }
+            state.secondary_gateway_program_id = secondary;
+        }
+        Ok(())
+    }
+
+    fn set_use_failover(&mut self, authority: Pubkey, use_failover: bool) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if use_failover && state.secondary_gateway_program_id == SYSTEM_PROGRAM_ID {
+            return Err(DepositContractError::InvalidSecondaryGateway);
+        }
+
+        state.use_failover = use_failover;
+        Ok(())
+    }
+
+    fn set_chain_config(&mut self, authority: Pubkey, chain_id: u64, recipient_len: u8) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        // onCall decodes every recipient as an address
+        if recipient_len != EVM_ADDRESS_LEN {
+            return Err(DepositContractError::InvalidRecipientLength);
+        }
+
+        if !self.chain_configs.contains_key(&chain_id) && self.chain_configs.len() >= MAX_DESTINATION_CHAINS {
+            return Err(DepositContractError::RegistryFull);
+        }
+
+        self.chain_configs.insert(chain_id, recipient_len);
         Ok(())
     }
 
@@ -110,7 +1046,20 @@ impl MockDepositContract {
         }
 
         if self.asset_configs.contains_key(&mint) {
-            return Err(DepositContractError::UnsupportedAsset);
+            return Err(DepositContractError::AssetAlreadySupported);
+        }
+
+        // Mints never passed to create_mint have no modeled account and skip the check
+        if self.mint_decimals.get(&mint).is_some_and(|mint_decimals| *mint_decimals != decimals) {
+            return Err(DepositContractError::DecimalMismatch);
+        }
+
+        if !state.allow_freezable_mints && self.freezable_mints.contains(&mint) {
+            return Err(DepositContractError::MintHasFreezeAuthority);
+        }
+
+        if self.asset_configs.len() >= MAX_ZRC20_ASSETS {
+            return Err(DepositContractError::RegistryFull);
         }
 
         self.asset_configs.insert(mint, AssetConfig {
@@ -118,41 +1067,52 @@ impl MockDepositContract {
             decimals,
             is_native,
             is_supported: true,
+            total_received: 0,
+            total_forwarded: 0,
+            metadata_uri: String::new(),
+            repay_enabled: true,
+            require_exact_amount: false,
+            lending_protocol_address: [0u8; 20],
+            min_deposit: 0,
+            deposit_cap: 0,
+            total_deposited: 0,
+            fee_recipient: SYSTEM_PROGRAM_ID,
+            ltv: 0,
+            liquidation_threshold: 0,
+            price_feed: SYSTEM_PROGRAM_ID,
+            zrc20: [0u8; 20],
             bump: 0,
         });
 
         Ok(())
     }
 
-    fn deposit_sol(&self, user: Pubkey, amount: u64, on_behalf_of: [u8; 20]) -> Result<Vec<u8>, DepositContractError> {
-        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
-        
-        if state.is_paused {
-            return Err(DepositContractError::ContractPaused);
-        }
-
-        if amount == 0 {
-            return Err(DepositContractError::InvalidAmount);
-        }
+    // Atomic like the transaction: any failure rolls back every item. The error carries
+    // the failing item's index, which the program logs
+    fn register_native_sol(&mut self, authority: Pubkey) -> Result<(), DepositContractError> {
+        self.add_supported_asset(authority, SYSTEM_PROGRAM_ID, NATIVE_SOL_DECIMALS, true)
+    }
 
-        if amount < DEPOSIT_FEE {
-            return Err(DepositContractError::InsufficientDepositFee);
+    fn add_supported_assets_batch(&mut self, authority: Pubkey, items: &[(Pubkey, u8, bool)]) -> Result<(), (usize, DepositContractError)> {
+        if items.len() > MAX_ADD_ASSET_BATCH_SIZE {
+            return Err((0, DepositContractError::BatchTooLarge));
         }
 
-        // Validate on_behalf_of is 20 bytes (ZetaChain address format)
-        if on_behalf_of.len() != 20 {
-            return Err(DepositContractError::InvalidAmount);
+        let snapshot = self.asset_configs.clone();
+        for (index, &(mint, decimals, is_native)) in items.iter().enumerate() {
+            if let Err(err) = self.add_supported_asset(authority, mint, decimals, is_native) {
+                self.asset_configs = snapshot;
+                return Err((index, err));
+            }
         }
-
-        // Create supply message
-        Self::create_supply_message(on_behalf_of)
+        Ok(())
     }
 
-    fn deposit_spl_token(&self, user: Pubkey, mint: Pubkey, amount: u64, on_behalf_of: [u8; 20]) -> Result<Vec<u8>, DepositContractError> {
+    fn create_asset_vault(&mut self, authority: Pubkey, mint: Pubkey) -> Result<(), DepositContractError> {
         let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
         
-        if state.is_paused {
-            return Err(DepositContractError::ContractPaused);
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
         }
 
         let asset_config = self.asset_configs.get(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
@@ -165,127 +1125,4705 @@ impl MockDepositContract {
             return Err(DepositContractError::UseDepositSol);
         }
 
-        if amount == 0 {
-            return Err(DepositContractError::InvalidAmount);
+        // init_if_needed: an existing vault keeps its balance
+        self.vault_balances.entry(mint).or_insert(0);
+        Ok(())
+    }
+
+    fn remove_supported_asset(&mut self, authority: Pubkey, mint: Pubkey) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
         }
 
-        Self::create_supply_message(on_behalf_of)
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        asset_config.is_supported = false;
+        Ok(())
     }
 
-    fn borrow_cross_chain(&self, user: Pubkey, asset: [u8; 20], amount: u64, destination_chain: u64, recipient: [u8; 20]) -> Result<Vec<u8>, DepositContractError> {
+    fn close_asset_config(&mut self, authority: Pubkey, mint: Pubkey) -> Result<(), DepositContractError> {
         let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
         
-        if state.is_paused {
-            return Err(DepositContractError::ContractPaused);
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
         }
 
-        if amount == 0 {
-            return Err(DepositContractError::InvalidAmount);
+        let asset_config = self.asset_configs.get(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        
+        if asset_config.is_supported {
+            return Err(DepositContractError::AssetStillSupported);
         }
 
-        // Validate destination_chain is supported (Arbitrum Sepolia, Ethereum Sepolia)
-        match destination_chain {
-            421614 | 11155111 => {},
-            _ => return Err(DepositContractError::InvalidChainId),
+        if self.vault_balances.get(&mint).copied().unwrap_or(0) != 0 {
+            return Err(DepositContractError::VaultNotEmpty);
+        }
+
+        // The PDA's rent goes back to the authority
+        self.asset_configs.remove(&mint);
+        Ok(())
+    }
+
+    fn set_asset_supported(&mut self, authority: Pubkey, mint: Pubkey, is_supported: bool) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
         }
 
-        Self::create_borrow_cross_chain_message(user.to_bytes(), amount, destination_chain, recipient)
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        asset_config.is_supported = is_supported;
+        Ok(())
+    }
+
+    fn set_repay_enabled(&mut self, authority: Pubkey, mint: Pubkey, repay_enabled: bool) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        asset_config.repay_enabled = repay_enabled;
+        Ok(())
+    }
+
+    fn set_asset_lending_protocol(&mut self, authority: Pubkey, mint: Pubkey, lending_protocol_address: [u8; 20]) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        asset_config.lending_protocol_address = lending_protocol_address;
+        Ok(())
+    }
+
+    fn update_lending_protocol_address(&mut self, authority: Pubkey, new_lending_protocol_address: [u8; 20], expected_zeta_chain_id: u64) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if expected_zeta_chain_id != state.zeta_chain_id {
+            return Err(DepositContractError::InvalidChainId);
+        }
+
+        if new_lending_protocol_address == [0u8; 20] || new_lending_protocol_address == state.lending_protocol_address {
+            return Err(DepositContractError::InvalidLendingProtocolAddress);
+        }
+
+        state.lending_protocol_address = new_lending_protocol_address;
+        Ok(())
+    }
+
+    // `mints` stands in for the asset_config remaining_accounts
+    fn rotate_lending_protocol(&mut self, authority: Pubkey, old_address: [u8; 20], new_address: [u8; 20], mints: &[Pubkey]) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if new_address == [0u8; 20] || new_address == old_address {
+            return Err(DepositContractError::InvalidLendingProtocolAddress);
+        }
+
+        if mints.len() > MAX_ZRC20_ASSETS {
+            return Err(DepositContractError::BatchTooLarge);
+        }
+
+        // Any unknown account fails the whole transaction before anything is written
+        if mints.iter().any(|mint| !self.asset_configs.contains_key(mint)) {
+            return Err(DepositContractError::UnsupportedAsset);
+        }
+
+        let default_rotated = state.lending_protocol_address == old_address;
+        if default_rotated {
+            state.lending_protocol_address = new_address;
+        }
+
+        let mut routes_rotated: u16 = 0;
+        for mint in mints {
+            let asset_config = self.asset_configs.get_mut(mint).unwrap();
+            if asset_config.lending_protocol_address == old_address {
+                asset_config.lending_protocol_address = new_address;
+                routes_rotated += 1;
+            }
+        }
+
+        self.events.push(Event::LendingProtocolRotated { old_address, new_address, default_rotated, routes_rotated });
+        Ok(())
+    }
+
+    // SOL deposits and repays go to native SOL's override once registered with one
+    fn native_lending_protocol(&self) -> [u8; 20] {
+        let state = self.contract_state.as_ref().unwrap();
+        self.asset_configs.get(&SYSTEM_PROGRAM_ID)
+            .map_or(state.lending_protocol_address, |asset_config| Self::resolve_lending_protocol(state, asset_config))
+    }
+
+    fn resolve_lending_protocol(state: &ContractState, asset_config: &AssetConfig) -> [u8; 20] {
+        if asset_config.lending_protocol_address == [0u8; 20] {
+            state.lending_protocol_address
+        } else {
+            asset_config.lending_protocol_address
+        }
+    }
+
+    fn set_require_exact_amount(&mut self, authority: Pubkey, mint: Pubkey, require_exact_amount: bool) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        asset_config.require_exact_amount = require_exact_amount;
+        Ok(())
+    }
+
+    fn set_deposit_cap(&mut self, authority: Pubkey, mint: Pubkey, deposit_cap: u64) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        asset_config.deposit_cap = deposit_cap;
+        Ok(())
+    }
+
+    fn set_asset_fee_recipient(&mut self, authority: Pubkey, mint: Pubkey, fee_recipient: Pubkey) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        asset_config.fee_recipient = fee_recipient;
+        Ok(())
+    }
+
+    fn set_min_deposit(&mut self, authority: Pubkey, mint: Pubkey, min_deposit: u64) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        asset_config.min_deposit = min_deposit;
+        Ok(())
+    }
+
+    fn resync_asset_decimals(&mut self, authority: Pubkey, mint: Pubkey, mint_decimals: u8) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        asset_config.decimals = mint_decimals;
+        Ok(())
+    }
+
+    fn set_asset_metadata_uri(&mut self, authority: Pubkey, mint: Pubkey, metadata_uri: &str) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if metadata_uri.len() > MAX_METADATA_URI_LEN {
+            return Err(DepositContractError::MetadataUriTooLong);
+        }
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        asset_config.metadata_uri = metadata_uri.to_string();
+        Ok(())
+    }
+
+    fn deposit_sol(&mut self, user: Pubkey, amount: u64, on_behalf_of: [u8; 20]) -> Result<Vec<u8>, DepositContractError> {
+        self.deposit_sol_with_accounts(user, amount, on_behalf_of, &[])
+    }
+
+    fn deposit_sol_with_accounts(&mut self, user: Pubkey, amount: u64, on_behalf_of: [u8; 20], remaining_accounts: &[MockAccount]) -> Result<Vec<u8>, DepositContractError> {
+        self.process_deposit_sol(user, user, amount, on_behalf_of, remaining_accounts)
+    }
+
+    // Sponsored deposit: payer funds it, owner is credited
+    fn deposit_sol_for(&mut self, payer: Pubkey, amount: u64, on_behalf_of: [u8; 20], owner: Pubkey) -> Result<Vec<u8>, DepositContractError> {
+        self.process_deposit_sol(payer, owner, amount, on_behalf_of, &[])
+    }
+
+    fn process_deposit_sol(&mut self, payer: Pubkey, owner: Pubkey, amount: u64, on_behalf_of: [u8; 20], remaining_accounts: &[MockAccount]) -> Result<Vec<u8>, DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        check_operation_active(state, PAUSE_DEPOSIT)?;
+
+        if amount == 0 {
+            return Err(DepositContractError::InvalidAmount);
+        }
+
+        if amount < state.min_sol_deposit {
+            return Err(DepositContractError::BelowMinimumDeposit);
+        }
+
+        let net_amount = net_of_deposit_fee(amount)?;
+
+        // Validate on_behalf_of is 20 bytes (ZetaChain address format)
+        if on_behalf_of.len() != 20 {
+            return Err(DepositContractError::InvalidAmount);
+        }
+
+        check_no_token_accounts(remaining_accounts)?;
+        check_gateway_program(state, self.gateway_program)?;
+        check_caller_program(state, self.top_level_program)?;
+        self.check_evm_link(owner, on_behalf_of)?;
+        self.check_depositor_whitelisted(payer)?;
+        check_beneficiary_allowed(state, on_behalf_of, &self.beneficiary_proof)?;
+
+        // Post-deposit gateway PDA lamports must fit in a u64
+        self.gateway_lamports.checked_add(net_amount).ok_or(DepositContractError::ArithmeticOverflow)?;
+
+        // Create supply message
+        let lending_protocol = self.native_lending_protocol();
+        let message = Self::create_supply_message(on_behalf_of)?;
+        self.collect_deposit_fee(FeeOperation::Deposit)?;
+        self.gateway_deposit_and_call(net_amount, lending_protocol, message.len())?;
+        self.record_sol_split(net_amount)?;
+        self.credit_collateral(owner, SYSTEM_PROGRAM_ID, net_amount)?;
+        self.events.push(Event::DepositInitiated {
+            user: payer,
+            owner,
+            asset: SYSTEM_PROGRAM_ID,
+            amount: net_amount,
+            on_behalf_of,
+            lending_protocol,
+            derived_evm_sender: derive_evm_sender(payer),
+        });
+        self.set_return_data(&message);
+        Ok(message)
+    }
+
+    // Anchor borsh-serializes a Vec<u8> return value: u32 LE length, then the bytes
+    fn set_return_data(&mut self, message: &[u8]) {
+        let mut data = (message.len() as u32).to_le_bytes().to_vec();
+        data.extend_from_slice(message);
+        self.return_data = Some(data);
+    }
+
+    // Native SOL's fee_recipient, when registered with one, takes the fee instead of the vault
+    fn collect_deposit_fee(&mut self, operation: FeeOperation) -> Result<(), DepositContractError> {
+        let override_recipient = self.asset_configs.get(&SYSTEM_PROGRAM_ID)
+            .map(|asset_config| asset_config.fee_recipient)
+            .filter(|recipient| *recipient != SYSTEM_PROGRAM_ID);
+        let recipient = match override_recipient {
+            Some(expected) => {
+                if self.fee_recipient != Some(expected) {
+                    return Err(DepositContractError::InvalidFeeRecipient);
+                }
+                let balance = self.fee_recipient_lamports.entry(expected).or_default();
+                *balance = balance.checked_add(DEPOSIT_FEE).ok_or(DepositContractError::ArithmeticOverflow)?;
+                expected
+            }
+            None => {
+                self.fee_vault_lamports = self.fee_vault_lamports.checked_add(DEPOSIT_FEE).ok_or(DepositContractError::ArithmeticOverflow)?;
+                fee_vault_address()
+            }
+        };
+        self.events.push(Event::FeeAccrued { asset: SYSTEM_PROGRAM_ID, amount: DEPOSIT_FEE, operation, recipient });
+        Ok(())
+    }
+
+    // DEPOSIT_FEE stays on Solana, `forwarded` went to the gateway
+    fn record_sol_split(&mut self, forwarded: u64) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().unwrap();
+        state.accrued_fees = state.accrued_fees.checked_add(DEPOSIT_FEE).ok_or(DepositContractError::ArithmeticOverflow)?;
+        state.total_sol_forwarded = state.total_sol_forwarded.checked_add(forwarded).ok_or(DepositContractError::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    fn deposit_spl_token(&mut self, user: Pubkey, mint: Pubkey, amount: u64, on_behalf_of: [u8; 20]) -> Result<Vec<u8>, DepositContractError> {
+        // user_token_account constraint, checked before the handler runs. Users never
+        // passed to fund_user have no modeled balance and skip it.
+        if self.user_balances.get(&(user, mint)).is_some_and(|balance| *balance < amount) {
+            return Err(DepositContractError::InsufficientBalance);
+        }
+        check_spl_programs(self.spl_programs)?;
+        self.check_depositor_whitelisted(user)?;
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        check_operation_active(state, PAUSE_DEPOSIT)?;
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        
+        if !asset_config.is_supported {
+            return Err(DepositContractError::UnsupportedAsset);
+        }
+
+        if asset_config.is_native {
+            return Err(DepositContractError::UseDepositSol);
+        }
+
+        if amount == 0 {
+            return Err(DepositContractError::InvalidAmount);
+        }
+
+        normalize_amount(amount, asset_config.decimals)?;
+
+        if asset_config.require_exact_amount && !amount.is_multiple_of(normalization_step(asset_config.decimals)?) {
+            return Err(DepositContractError::NonExactAmount);
+        }
+
+        if amount < asset_config.min_deposit {
+            return Err(DepositContractError::BelowMinimumDeposit);
+        }
+
+        check_caller_program(state, self.top_level_program)?;
+        check_gateway_program(state, self.gateway_program)?;
+        check_beneficiary_allowed(state, on_behalf_of, &self.beneficiary_proof)?;
+
+        let vault_balance = self.vault_balances.get_mut(&mint).ok_or(DepositContractError::VaultNotInitialized)?;
+
+        let total_deposited = asset_config.total_deposited.checked_add(amount).ok_or(DepositContractError::ArithmeticOverflow)?;
+        if asset_config.deposit_cap != 0 && total_deposited > asset_config.deposit_cap {
+            return Err(DepositContractError::DepositCapExceeded);
+        }
+        asset_config.total_deposited = total_deposited;
+
+        // Transfer into the vault, then forward the vault balance delta to the gateway:
+        // fee-charging mints deliver less than amount
+        let transfer_fee = amount * self.transfer_fee_bps.get(&mint).copied().unwrap_or(0) / 10_000;
+        let balance_before = *vault_balance;
+        *vault_balance += amount - transfer_fee;
+        let received = *vault_balance - balance_before;
+        if received == 0 {
+            return Err(DepositContractError::InvalidAmount);
+        }
+        asset_config.total_received = asset_config.total_received.checked_add(received).ok_or(DepositContractError::ArithmeticOverflow)?;
+        let message = Self::create_supply_message(on_behalf_of)?;
+        forward_from_vault(vault_balance, received)?;
+        asset_config.total_forwarded = asset_config.total_forwarded.checked_add(received).ok_or(DepositContractError::ArithmeticOverflow)?;
+
+        self.events.push(Event::DepositInitiated {
+            user,
+            owner: user,
+            asset: mint,
+            amount: received,
+            on_behalf_of,
+            lending_protocol: Self::resolve_lending_protocol(state, asset_config),
+            derived_evm_sender: derive_evm_sender(contract_state_pda()),
+        });
+        if let Some(balance) = self.user_balances.get_mut(&(user, mint)) {
+            *balance -= amount;
+        }
+        self.credit_collateral(user, mint, received)?;
+
+        self.set_return_data(&message);
+        Ok(message)
+    }
+
+    fn domain(&self) -> [u8; 32] {
+        let state = self.contract_state.as_ref().expect("contract not initialized");
+        domain_separator(PROGRAM_ID, state.zeta_chain_id)
+    }
+
+    // What the EVM wallet signs to link: keccak256(domain_separator || user)
+    fn link_payload(&self, user: Pubkey) -> [u8; 32] {
+        mock_hash32(&[&self.domain(), &user.to_bytes()])
+    }
+
+    fn link_evm_address(&mut self, user: Pubkey, evm_address: [u8; 20], signature: [u8; 64], recovery_id: u8) -> Result<(), DepositContractError> {
+        let recovered = mock_ecrecover(&self.link_payload(user), &signature, recovery_id)?;
+        if recovered != evm_address {
+            return Err(DepositContractError::EvmSignatureMismatch);
+        }
+
+        self.evm_links.insert(user, evm_address);
+        Ok(())
+    }
+
+    fn set_require_evm_link(&mut self, authority: Pubkey, required: bool) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        state.require_evm_link = required;
+        Ok(())
+    }
+
+    fn check_evm_link(&self, user: Pubkey, on_behalf_of: [u8; 20]) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        if !state.require_evm_link {
+            return Ok(());
+        }
+
+        let evm_address = self.evm_links.get(&user).ok_or(DepositContractError::EvmLinkRequired)?;
+        if *evm_address != on_behalf_of {
+            return Err(DepositContractError::EvmAddressNotLinked);
+        }
+        Ok(())
+    }
+
+    fn set_accept_legacy_messages(&mut self, authority: Pubkey, accepted: bool) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        state.accept_legacy_messages = accepted;
+        Ok(())
+    }
+
+    // Simulate-only decode under the deployment's current accept_legacy_messages
+    fn decode_message(&self, payload: &[u8]) -> Result<CrossChainMessage, DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        decode_cross_chain_message(payload, state.accept_legacy_messages)
+    }
+
+    fn set_debug_logging(&mut self, authority: Pubkey, enabled: bool) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        state.debug_logging = enabled;
+        Ok(())
+    }
+
+    // Verbose lines written by the gateway helpers, only when debug_logging is set
+    // The gateway pulls `amount` lamports from the signer into its PDA; the caller checks
+    // the PDA's balance delta before crediting anything
+    fn gateway_deposit_and_call(&mut self, amount: u64, receiver: [u8; 20], message_len: usize) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().unwrap();
+        if state.debug_logging {
+            self.logs.push(format!("Gateway deposit_and_call invoked: amount={}, receiver={:?}", amount, receiver));
+            self.logs.push(format!("Message length: {}, revert_options configured", message_len));
+        }
+        let balance_before = self.gateway_lamports;
+        if self.gateway_moves_sol {
+            self.gateway_lamports = self.gateway_lamports.checked_add(amount).ok_or(DepositContractError::ArithmeticOverflow)?;
+        }
+        if self.gateway_lamports.saturating_sub(balance_before) < amount {
+            return Err(DepositContractError::DepositFailed);
+        }
+        Ok(())
+    }
+
+    fn log_gateway_call(&mut self, user: Pubkey, message_len: usize) {
+        let state = self.contract_state.as_ref().unwrap();
+        if state.debug_logging {
+            self.logs.push(format!("Gateway call invoked: receiver={:?}", state.lending_protocol_address));
+            self.logs.push(format!("User: {:?}, message length: {}", user, message_len));
+            self.logs.push("Revert options configured for cross-chain safety".to_string());
+        }
+    }
+
+    fn set_min_sol_deposit(&mut self, authority: Pubkey, min_sol_deposit: u64) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        state.min_sol_deposit = min_sol_deposit;
+        Ok(())
+    }
+
+    fn set_min_health_factor(&mut self, authority: Pubkey, min_health_factor: u64) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if min_health_factor != 0 && min_health_factor < BPS_DENOMINATOR {
+            return Err(DepositContractError::InvalidAmount);
+        }
+
+        state.min_health_factor = min_health_factor;
+        Ok(())
+    }
+
+    fn set_liquidation_threshold(&mut self, authority: Pubkey, mint: Pubkey, liquidation_threshold: u16) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        check_risk_params(asset_config.ltv, liquidation_threshold)?;
+        asset_config.liquidation_threshold = liquidation_threshold;
+        Ok(())
+    }
+
+    fn set_risk_params(&mut self, authority: Pubkey, mint: Pubkey, ltv: u16, liquidation_threshold: u16) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        check_risk_params(ltv, liquidation_threshold)?;
+        asset_config.ltv = ltv;
+        asset_config.liquidation_threshold = liquidation_threshold;
+        Ok(())
+    }
+
+    fn set_asset_zrc20(&mut self, authority: Pubkey, mint: Pubkey, zrc20: [u8; 20]) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        asset_config.zrc20 = zrc20;
+        Ok(())
+    }
+
+    fn set_reject_self_borrow(&mut self, authority: Pubkey, enabled: bool) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        state.reject_self_borrow = enabled;
+        Ok(())
+    }
+
+    fn set_asset_price_feed(&mut self, authority: Pubkey, mint: Pubkey, price_feed: Pubkey) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        asset_config.price_feed = price_feed;
+        Ok(())
+    }
+
+    // Only a ZRC-20 some borrow opened has debt to reduce; the global total frees only
+    // the NORMALIZED_DECIMALS debt cleared from the position of the borrower on_behalf_of
+    // names, whoever repays
+    fn record_repaid(&mut self, on_behalf_of: [u8; 20], zrc20: [u8; 20], amount: u64) {
+        let Some(zrc20_config) = self.zrc20_configs.get_mut(&zrc20) else {
+            return;
+        };
+        zrc20_config.outstanding_borrow = zrc20_config.outstanding_borrow.saturating_sub(amount);
+        let position = self.borrow_positions.iter_mut().find(|(owner, _)| derive_evm_sender(**owner) == on_behalf_of);
+        let Some((_, borrowed)) = position else {
+            return;
+        };
+        let cleared = normalize_amount(amount, zrc20_config.decimals).unwrap_or(0).min(*borrowed);
+        *borrowed -= cleared;
+        let state = self.contract_state.as_mut().unwrap();
+        state.total_outstanding_borrows = state.total_outstanding_borrows.saturating_sub(cleared);
+    }
+
+    fn set_allow_freezable_mints(&mut self, authority: Pubkey, allowed: bool) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        state.allow_freezable_mints = allowed;
+        Ok(())
+    }
+
+    fn set_beneficiary_allowlist_root(&mut self, authority: Pubkey, root: [u8; 32]) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        state.beneficiary_allowlist_root = root;
+        Ok(())
+    }
+
+    fn set_require_depositor_whitelist(&mut self, authority: Pubkey, required: bool) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        state.require_depositor_whitelist = required;
+        Ok(())
+    }
+
+    fn add_whitelisted_depositor(&mut self, authority: Pubkey, depositor: Pubkey) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        self.whitelisted_depositors.insert(depositor);
+        Ok(())
+    }
+
+    fn remove_whitelisted_depositor(&mut self, authority: Pubkey, depositor: Pubkey) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        self.whitelisted_depositors.remove(&depositor);
+        Ok(())
+    }
+
+    fn check_depositor_whitelisted(&self, depositor: Pubkey) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        if state.require_depositor_whitelist && !self.whitelisted_depositors.contains(&depositor) {
+            return Err(DepositContractError::DepositorNotWhitelisted);
+        }
+        Ok(())
+    }
+
+    fn create_mint(&mut self, decimals: u8) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        self.mint_decimals.insert(mint, decimals);
+        mint
+    }
+
+    fn create_freezable_mint(&mut self, decimals: u8) -> Pubkey {
+        let mint = self.create_mint(decimals);
+        self.freezable_mints.insert(mint);
+        mint
+    }
+
+    fn fund_user(&mut self, user: Pubkey, mint: Pubkey, amount: u64) {
+        *self.user_balances.entry((user, mint)).or_insert(0) += amount;
+    }
+
+    // A repay's zrc20_config must be the PDA of the repaid asset's ZRC-20
+    fn check_repaid_zrc20_config(&self, zrc20: [u8; 20]) -> Result<(), DepositContractError> {
+        if self.repay_zrc20_config.is_some_and(|passed| passed != zrc20) {
+            return Err(DepositContractError::InvalidZrc20Config);
+        }
+        Ok(())
+    }
+
+    fn repay_sol(&mut self, user: Pubkey, amount: u64, on_behalf_of: [u8; 20], remaining_accounts: &[MockAccount]) -> Result<Vec<u8>, DepositContractError> {
+        let native_asset_config = self.asset_configs.get(&SYSTEM_PROGRAM_ID);
+        let repay_enabled = native_asset_config.is_none_or(|asset_config| asset_config.repay_enabled);
+        let native_zrc20 = native_asset_config.map_or([0u8; 20], |asset_config| asset_config.zrc20);
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        check_operation_active(state, PAUSE_REPAY)?;
+
+        if amount == 0 {
+            return Err(DepositContractError::InvalidAmount);
+        }
+
+        if amount < state.min_sol_deposit {
+            return Err(DepositContractError::BelowMinimumDeposit);
+        }
+
+        let net_amount = net_of_deposit_fee(amount)?;
+
+        check_no_token_accounts(remaining_accounts)?;
+        check_gateway_program(state, self.gateway_program)?;
+        // An unregistered SOL has no config to disable repays with
+        if !repay_enabled {
+            return Err(DepositContractError::RepayDisabled);
+        }
+        self.check_repaid_zrc20_config(native_zrc20)?;
+        self.gateway_lamports.checked_add(net_amount).ok_or(DepositContractError::ArithmeticOverflow)?;
+
+        let message = Self::create_repay_message(on_behalf_of)?;
+        self.collect_deposit_fee(FeeOperation::Repay)?;
+        self.gateway_deposit_and_call(net_amount, self.native_lending_protocol(), message.len())?;
+        self.record_sol_split(net_amount)?;
+        self.record_repaid(on_behalf_of, native_zrc20, net_amount);
+
+        self.events.push(Event::RepayInitiated { user, asset: SYSTEM_PROGRAM_ID, amount: net_amount, on_behalf_of });
+        Ok(message)
+    }
+
+    fn repay_spl_token(&mut self, user: Pubkey, mint: Pubkey, amount: u64, on_behalf_of: [u8; 20]) -> Result<Vec<u8>, DepositContractError> {
+        self.process_repay_spl_token(user, mint, amount, on_behalf_of)
+    }
+
+    fn process_repay_spl_token(&mut self, user: Pubkey, mint: Pubkey, amount: u64, on_behalf_of: [u8; 20]) -> Result<Vec<u8>, DepositContractError> {
+        check_spl_programs(self.spl_programs)?;
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        check_operation_active(state, PAUSE_REPAY)?;
+
+        if amount == 0 {
+            return Err(DepositContractError::InvalidAmount);
+        }
+
+        let asset_config = self.asset_configs.get_mut(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        
+        if !asset_config.is_supported {
+            return Err(DepositContractError::UnsupportedAsset);
+        }
+
+        if asset_config.is_native {
+            return Err(DepositContractError::UseRepaySol);
+        }
+
+        if !asset_config.repay_enabled {
+            return Err(DepositContractError::RepayDisabled);
+        }
+
+        if amount < asset_config.min_deposit {
+            return Err(DepositContractError::BelowMinimumDeposit);
+        }
+
+        let zrc20 = asset_config.zrc20;
+        self.check_repaid_zrc20_config(zrc20)?;
+        let asset_config = self.asset_configs.get_mut(&mint).unwrap();
+        check_gateway_program(state, self.gateway_program)?;
+
+        let user_balance = self.user_balances.entry((user, mint)).or_insert(0);
+        *user_balance = user_balance.checked_sub(amount).ok_or(DepositContractError::InsufficientBalance)?;
+
+        // Vault balance delta: fee-charging mints deliver less than amount
+        let transfer_fee = amount * self.transfer_fee_bps.get(&mint).copied().unwrap_or(0) / 10_000;
+        let balance_before = *self.vault_balances.get(&mint).unwrap_or(&0);
+        *self.vault_balances.entry(mint).or_insert(0) += amount - transfer_fee;
+        let received = self.vault_balances[&mint] - balance_before;
+        if received == 0 {
+            return Err(DepositContractError::InvalidAmount);
+        }
+        asset_config.total_received = asset_config.total_received.checked_add(received).ok_or(DepositContractError::ArithmeticOverflow)?;
+
+        let message = Self::create_repay_message(on_behalf_of)?;
+        forward_from_vault(self.vault_balances.get_mut(&mint).unwrap(), received)?;
+        asset_config.total_forwarded = asset_config.total_forwarded.checked_add(received).ok_or(DepositContractError::ArithmeticOverflow)?;
+
+        self.record_repaid(on_behalf_of, zrc20, received);
+
+        self.events.push(Event::RepayInitiated { user, asset: mint, amount: received, on_behalf_of });
+        Ok(message)
+    }
+
+    fn repay_batch(&mut self, user: Pubkey, items: &[RepayItem]) -> Result<Vec<Vec<u8>>, DepositContractError> {
+        if items.len() > MAX_REPAY_BATCH_SIZE {
+            return Err(DepositContractError::BatchTooLarge);
+        }
+
+        if items.is_empty() {
+            return Err(DepositContractError::InvalidAmount);
+        }
+
+        // A failed transaction leaves no trace: work on copies and only commit on success
+        let asset_configs = self.asset_configs.clone();
+        let user_balances = self.user_balances.clone();
+        let vault_balances = self.vault_balances.clone();
+        let zrc20_configs = self.zrc20_configs.clone();
+        let borrow_positions = self.borrow_positions.clone();
+        let total_outstanding_borrows = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?.total_outstanding_borrows;
+        let events = self.events.clone();
+
+        let mut messages = Vec::with_capacity(items.len());
+        for item in items {
+            match self.process_repay_spl_token(user, item.mint, item.amount, item.on_behalf_of) {
+                Ok(message) => messages.push(message),
+                Err(err) => {
+                    self.asset_configs = asset_configs;
+                    self.user_balances = user_balances;
+                    self.vault_balances = vault_balances;
+                    self.zrc20_configs = zrc20_configs;
+                    self.borrow_positions = borrow_positions;
+                    self.contract_state.as_mut().unwrap().total_outstanding_borrows = total_outstanding_borrows;
+                    self.events = events;
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(messages)
+    }
+
+    fn get_asset_configs(&self, mints: &[Pubkey]) -> Result<Vec<AssetConfig>, DepositContractError> {
+        if mints.len() > MAX_ASSET_CONFIGS_PER_QUERY {
+            return Err(DepositContractError::BatchTooLarge);
+        }
+
+        mints.iter()
+            .map(|mint| self.asset_configs.get(mint).cloned().ok_or(DepositContractError::UnsupportedAsset))
+            .collect()
+    }
+
+    fn set_emergency_admins(&mut self, authority: Pubkey, admins: Vec<Pubkey>) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if admins.len() > MAX_EMERGENCY_ADMINS {
+            return Err(DepositContractError::RegistryFull);
+        }
+        for admin in &admins {
+            check_authority_candidate(*admin)?;
+        }
+
+        state.emergency_admins = admins;
+        Ok(())
+    }
+
+    // The fee vault is a data-less system account, so it must keep rent_exempt_minimum(0)
+    fn withdraw_fees(&mut self, authority: Pubkey, amount: u64) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if amount == 0 {
+            return Err(DepositContractError::InvalidAmount);
+        }
+
+        let remaining = self.fee_vault_lamports.checked_sub(amount).ok_or(DepositContractError::InsufficientFeeBalance)?;
+        check_rent_exempt_after_debit(self.fee_vault_lamports, 0, amount)?;
+
+        self.fee_vault_lamports = remaining;
+        self.events.push(Event::FeesWithdrawn { authority, amount, remaining });
+        Ok(())
+    }
+
+    // Lamports reaching the contract_state PDA outside deposit/repay forwarding
+    fn simulate_sol_transfer(&mut self, amount: u64) {
+        self.contract_lamports += amount;
+    }
+
+    fn sweep_sol_dust(&mut self, authority: Pubkey, treasury: Pubkey) -> Result<u64, DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if state.treasury == SYSTEM_PROGRAM_ID || state.treasury != treasury {
+            return Err(DepositContractError::InvalidTreasury);
+        }
+
+        let amount = self.contract_lamports.saturating_sub(rent_exempt_minimum(CONTRACT_STATE_SPACE));
+        if amount == 0 {
+            return Err(DepositContractError::NoDustToSweep);
+        }
+        check_rent_exempt_after_debit(self.contract_lamports, CONTRACT_STATE_SPACE, amount)?;
+
+        self.contract_lamports -= amount;
+        self.events.push(Event::SolDustSwept { treasury, amount });
+        Ok(amount)
+    }
+
+    // Permissionless: only reads state
+    fn emit_config_snapshot(&mut self) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+
+        self.events.push(Event::ConfigSnapshot {
+            authority: state.authority,
+            deposit_fee: DEPOSIT_FEE,
+            global_borrow_cap: state.global_borrow_cap,
+            total_outstanding_borrows: state.total_outstanding_borrows,
+            is_paused: state.is_paused,
+            lending_protocol_address: state.lending_protocol_address,
+            asset_count: self.asset_configs.len() as u16,
+        });
+        Ok(())
+    }
+
+    // Only the untracked vault surplus can leave, and only to the authority's token account
+    fn rescue_tokens(&mut self, admin: Pubkey, mint: Pubkey, amount: u64) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if !is_emergency_admin(state, admin) {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if amount == 0 {
+            return Err(DepositContractError::InvalidAmount);
+        }
+
+        let asset_config = self.asset_configs.get(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        let expected_balance = asset_config.total_received
+            .checked_sub(asset_config.total_forwarded)
+            .ok_or(DepositContractError::ArithmeticOverflow)?;
+        let vault_balance = self.vault_balances.get_mut(&mint).ok_or(DepositContractError::VaultNotInitialized)?;
+        if amount > vault_balance.saturating_sub(expected_balance) {
+            return Err(DepositContractError::RescueExceedsSurplus);
+        }
+
+        *vault_balance -= amount;
+        *self.user_balances.entry((state.authority, mint)).or_insert(0) += amount;
+        Ok(())
+    }
+
+    fn reconcile_spl(&self, mint: Pubkey) -> Result<i64, DepositContractError> {
+        let asset_config = self.asset_configs.get(&mint).ok_or(DepositContractError::UnsupportedAsset)?;
+        let vault_balance = *self.vault_balances.get(&mint).unwrap_or(&0);
+        let expected_balance = asset_config.total_received
+            .checked_sub(asset_config.total_forwarded)
+            .ok_or(DepositContractError::ArithmeticOverflow)?;
+
+        i64::try_from(vault_balance as i128 - expected_balance as i128)
+            .map_err(|_| DepositContractError::ArithmeticOverflow)
+    }
+
+    // Simulates tokens landing in the vault outside of the accounted flow (e.g. a direct transfer)
+    fn simulate_vault_transfer(&mut self, mint: Pubkey, amount: u64) {
+        *self.vault_balances.entry(mint).or_insert(0) += amount;
+    }
+
+    fn borrow_cross_chain(&mut self, user: Pubkey, asset: [u8; 20], amount: u64, destination_chain: u64, recipient: impl AsRef<[u8]>) -> Result<Vec<u8>, DepositContractError> {
+        let recipient = recipient.as_ref();
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        check_gateway_program(state, self.gateway_program)?;
+        let borrowed = self.validate_borrow(user, asset, amount, destination_chain, recipient)?;
+        let zrc20_config = self.zrc20_configs.get(&asset).unwrap_or(&UNCAPPED_ZRC20);
+        let outstanding_borrow = zrc20_config.outstanding_borrow + amount;
+        let normalized = normalize_amount(amount, zrc20_config.decimals)?;
+        let lending_protocol_address = state.lending_protocol_address;
+
+        let message = Self::create_borrow_cross_chain_message(user.to_bytes(), amount, destination_chain, recipient)?;
+        self.invoke_gateway_call(lending_protocol_address, &message)?;
+        // Debt is booked only once the gateway accepted the call
+        let state = self.contract_state.as_mut().unwrap();
+        state.total_outstanding_borrows = state.total_outstanding_borrows.checked_add(normalized).ok_or(DepositContractError::ArithmeticOverflow)?;
+        self.zrc20_configs.entry(asset).or_insert(UNCAPPED_ZRC20).outstanding_borrow = outstanding_borrow;
+        self.borrow_positions.insert(user, borrowed);
+        self.log_gateway_call(user, message.len());
+        self.record_outbound_nonce(destination_chain);
+
+        Ok(message)
+    }
+
+    // Local checks shared by borrow_cross_chain and can_borrow; returns the debt after the borrow
+    fn validate_borrow(&self, user: Pubkey, asset: [u8; 20], amount: u64, destination_chain: u64, recipient: &[u8]) -> Result<u128, DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        // Validate destination_chain has a ChainConfig
+        let recipient_len = *self.chain_configs.get(&destination_chain).ok_or(DepositContractError::InvalidChainId)?;
+
+        check_operation_active(state, PAUSE_BORROW)?;
+
+        if amount == 0 {
+            return Err(DepositContractError::InvalidAmount);
+        }
+
+        let zrc20_config = self.zrc20_configs.get(&asset).unwrap_or(&UNCAPPED_ZRC20);
+        let normalized = normalize_amount(amount, zrc20_config.decimals)?;
+        let total_outstanding_borrows = state.total_outstanding_borrows.checked_add(normalized).ok_or(DepositContractError::ArithmeticOverflow)?;
+        if total_outstanding_borrows > state.global_borrow_cap {
+            return Err(DepositContractError::GlobalBorrowCapExceeded);
+        }
+
+        check_recipient(state, recipient_len, recipient)?;
+
+        if asset == [0u8; 20] {
+            return Err(DepositContractError::UnsupportedAsset);
+        }
+        let outstanding_borrow = zrc20_config.outstanding_borrow.checked_add(amount).ok_or(DepositContractError::ArithmeticOverflow)?;
+        if outstanding_borrow > zrc20_config.borrow_cap {
+            return Err(DepositContractError::Zrc20BorrowCapExceeded);
+        }
+
+        let borrowed = self.borrow_positions.get(&user).copied().unwrap_or(0)
+            .checked_add(normalized)
+            .ok_or(DepositContractError::ArithmeticOverflow)?;
+        if state.min_health_factor != 0 || state.reject_self_borrow {
+            let collateral = weighted_collateral(
+                state,
+                user,
+                asset,
+                &self.collateral_accounts,
+                &self.collateral_positions,
+                &self.asset_configs,
+                &self.price_accounts,
+                self.now,
+            )?;
+            if state.reject_self_borrow && !collateral.other_assets {
+                return Err(DepositContractError::SelfBorrow);
+            }
+            if state.min_health_factor != 0 && compute_health_factor(collateral.weighted, borrowed) < u128::from(state.min_health_factor) {
+                return Err(DepositContractError::InsufficientCollateral);
+            }
+        }
+
+        Ok(borrowed)
+    }
+
+    // Dry run: None when the borrow would be accepted, otherwise the blocking reason
+    fn can_borrow(&self, user: Pubkey, asset: [u8; 20], amount: u64, destination_chain: u64, recipient: impl AsRef<[u8]>) -> Option<DepositContractError> {
+        self.validate_borrow(user, asset, amount, destination_chain, recipient.as_ref()).err()
+    }
+
+    fn invoke_gateway_call(&self, _receiver: [u8; 20], message: &[u8]) -> Result<(), DepositContractError> {
+        if message.is_empty() {
+            return Err(DepositContractError::EmptyMessage);
+        }
+        if self.gateway_call_fails {
+            return Err(DepositContractError::GatewayCallFailed);
+        }
+
+        Ok(())
+    }
+
+    fn propose_authority(&mut self, authority: Pubkey, new_authority: Pubkey) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        check_authority_candidate(new_authority)?;
+        state.pending_authority = new_authority;
+        Ok(())
+    }
+
+    fn accept_authority(&mut self, pending_authority: Pubkey) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.pending_authority != pending_authority {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        state.authority = pending_authority;
+        state.pending_authority = SYSTEM_PROGRAM_ID;
+        Ok(())
+    }
+
+    fn get_pending_changes(&self) -> Result<PendingChanges, DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+
+        Ok(PendingChanges {
+            pending_authority: (state.pending_authority != SYSTEM_PROGRAM_ID).then_some(state.pending_authority),
+            authority_effective_at: 0,
+            // Lending protocol updates apply immediately
+            pending_lending_protocol_address: None,
+            lending_protocol_effective_at: 0,
+        })
+    }
+
+    fn set_allowed_caller_programs(&mut self, authority: Pubkey, programs: Vec<Pubkey>) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if programs.len() > MAX_ALLOWED_CALLER_PROGRAMS {
+            return Err(DepositContractError::RegistryFull);
+        }
+
+        state.allowed_caller_programs = programs;
+        Ok(())
+    }
+
+    fn set_max_price_age(&mut self, authority: Pubkey, max_price_age: i64) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if max_price_age <= 0 {
+            return Err(DepositContractError::InvalidAmount);
+        }
+
+        state.max_price_age = max_price_age;
+        Ok(())
+    }
+
+    // Shared by every USD-denominated cap check
+    fn check_price_fresh(&self, publish_time: i64, now: i64) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if now.saturating_sub(publish_time) > state.max_price_age {
+            return Err(DepositContractError::StalePrice);
+        }
+        Ok(())
+    }
+
+    // Simulated: the returned amount is also emitted as MaxBorrowComputed
+    fn get_max_borrow(&mut self, user: Pubkey, asset: [u8; 20]) -> Result<u64, DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        let max_amount = if check_operation_active(state, PAUSE_BORROW).is_err() {
+            0
+        } else {
+            let collateral = weighted_collateral(
+                state,
+                user,
+                asset,
+                &self.collateral_accounts,
+                &self.collateral_positions,
+                &self.asset_configs,
+                &self.price_accounts,
+                self.now,
+            )?;
+            let borrowed = self.borrow_positions.get(&user).copied().unwrap_or(0);
+            max_borrow(state, &collateral, borrowed, self.zrc20_configs.get(&asset))
+        };
+        self.events.push(Event::MaxBorrowComputed { user, asset, max_amount });
+        Ok(max_amount)
+    }
+
+    fn withdraw_cross_chain(&mut self, user: Pubkey, asset: [u8; 20], amount: u64, destination_chain: u64, recipient: impl AsRef<[u8]>) -> Result<Vec<u8>, DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        let recipient = recipient.as_ref();
+        
+        check_operation_active(state, PAUSE_WITHDRAW)?;
+
+        if amount == 0 {
+            return Err(DepositContractError::InvalidAmount);
+        }
+
+        check_gateway_program(state, self.gateway_program)?;
+        let recipient_len = self.chain_configs.get(&destination_chain).ok_or(DepositContractError::InvalidChainId)?;
+        check_recipient(state, *recipient_len, recipient)?;
+        // Account validation: the asset_config must map to the withdrawn ZRC-20, and the
+        // position is the one at [b"position", user, mint]
+        let mint = self.asset_configs.values()
+            .find(|asset_config| asset != [0u8; 20] && asset_config.zrc20 == asset)
+            .map(|asset_config| asset_config.mint)
+            .ok_or(DepositContractError::UnsupportedAsset)?;
+
+        let message = Self::create_withdraw_cross_chain_message(user.to_bytes(), amount, destination_chain, recipient)?;
+        self.invoke_gateway_call(state.lending_protocol_address, &message)?;
+        self.log_gateway_call(user, message.len());
+        self.record_outbound_nonce(destination_chain);
+        // Collateral is debited only once the gateway accepted the call
+        let now = self.current_timestamp()?;
+        let previous = self.collateral_positions.get(&(user, mint)).copied();
+        let position = self.collateral_positions.entry((user, mint)).or_default();
+        position.amount = position.amount.saturating_sub(amount);
+        position.last_update = now;
+
+        // A failed health check reverts the debit along with the rest of the transaction
+        let state = self.contract_state.as_ref().unwrap();
+        let borrowed = self.borrow_positions.get(&user).copied().unwrap_or(0);
+        if state.min_health_factor != 0 && borrowed > 0 {
+            let collateral = weighted_collateral(
+                state,
+                user,
+                asset,
+                &self.collateral_accounts,
+                &self.collateral_positions,
+                &self.asset_configs,
+                &self.price_accounts,
+                self.now,
+            )?;
+            if compute_health_factor(collateral.weighted, borrowed) < u128::from(state.min_health_factor) {
+                match previous {
+                    Some(previous) => self.collateral_positions.insert((user, mint), previous),
+                    None => self.collateral_positions.remove(&(user, mint)),
+                };
+                return Err(DepositContractError::InsufficientCollateral);
+            }
+        }
+
+        Ok(message)
+    }
+
+    fn credit_collateral(&mut self, owner: Pubkey, mint: Pubkey, amount: u64) -> Result<(), DepositContractError> {
+        let now = self.current_timestamp()?;
+        let position = self.collateral_positions.entry((owner, mint)).or_default();
+        position.amount = position.amount.checked_add(amount).ok_or(DepositContractError::ArithmeticOverflow)?;
+        position.last_update = now;
+        Ok(())
+    }
+
+    // Only a nonce the gateway returned is stored; nothing is counted locally
+    fn record_outbound_nonce(&mut self, destination_chain: u64) {
+        if let Some(nonce) = self.gateway_return_nonce.take() {
+            self.outbound_nonces.insert(destination_chain, nonce);
+        }
+    }
+
+    fn set_global_borrow_cap(&mut self, authority: Pubkey, global_borrow_cap: u128) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        state.global_borrow_cap = global_borrow_cap;
+        Ok(())
+    }
+
+    fn set_zrc20_borrow_cap(&mut self, authority: Pubkey, asset: [u8; 20], borrow_cap: u64) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if asset == [0u8; 20] {
+            return Err(DepositContractError::UnsupportedAsset);
+        }
+
+        self.zrc20_configs.entry(asset).or_insert(UNCAPPED_ZRC20).borrow_cap = borrow_cap;
+        Ok(())
+    }
+
+    fn set_zrc20_decimals(&mut self, authority: Pubkey, asset: [u8; 20], decimals: u8) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_ref().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if asset == [0u8; 20] {
+            return Err(DepositContractError::UnsupportedAsset);
+        }
+
+        self.zrc20_configs.entry(asset).or_insert(UNCAPPED_ZRC20).decimals = decimals;
+        Ok(())
+    }
+
+    fn set_pause_state(&mut self, admin: Pubkey, is_paused: bool) -> Result<(), DepositContractError> {
+        self.set_pause_state_with_force(admin, is_paused, false)
+    }
+
+    fn current_timestamp(&self) -> Result<i64, DepositContractError> {
+        if !self.clock_available {
+            return Err(DepositContractError::ClockUnavailable);
+        }
+        Ok(self.now)
+    }
+
+    fn set_paused_operations(&mut self, admin: Pubkey, paused_operations: u32) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if !is_emergency_admin(state, admin) {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if paused_operations & !PAUSABLE_OPERATIONS != 0 {
+            return Err(DepositContractError::InvalidAmount);
+        }
+
+        state.paused_operations = paused_operations;
+        Ok(())
+    }
+
+    fn set_pause_state_with_force(&mut self, admin: Pubkey, is_paused: bool, force: bool) -> Result<(), DepositContractError> {
+        let now = self.current_timestamp();
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if !is_emergency_admin(state, admin) {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        let was_paused = state.is_paused;
+        if is_paused != was_paused {
+            let now = now?;
+            if !force && now.saturating_sub(state.last_pause_change_ts) < MIN_PAUSE_TOGGLE_INTERVAL {
+                return Err(DepositContractError::PauseToggleTooSoon);
+            }
+            state.last_pause_change_ts = now;
+        }
+        state.is_paused = is_paused;
+
+        self.events.push(Event::PauseStateChanged { is_paused });
+        if is_paused && !was_paused {
+            let incident_metadata = state.incident_metadata.clone();
+            self.events.push(Event::CircuitBreakerTripped { incident_metadata });
+        }
+        Ok(())
+    }
+
+    fn set_incident_metadata(&mut self, authority: Pubkey, incident_metadata: &str) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        if incident_metadata.len() > MAX_INCIDENT_METADATA_LEN {
+            return Err(DepositContractError::IncidentMetadataTooLong);
+        }
+
+        state.incident_metadata = incident_metadata.to_string();
+        Ok(())
+    }
+
+    fn set_revert_policy(&mut self, authority: Pubkey, operation: OperationType, policy: RevertPolicy) -> Result<(), DepositContractError> {
+        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+        
+        if state.authority.0 != authority.0 {
+            return Err(DepositContractError::Unauthorized);
+        }
+
+        match operation {
+            OperationType::Deposit => state.deposit_revert_policy = policy,
+            OperationType::Call => state.call_revert_policy = policy,
+        }
+        Ok(())
+    }
+
+    // RevertOptions the gateway helpers would attach for an operation type
+    fn revert_options_for(&self, operation: OperationType, reason: &[u8]) -> RevertOptions {
+        let state = self.contract_state.as_ref().unwrap();
+        let policy = match operation {
+            OperationType::Deposit => state.deposit_revert_policy,
+            OperationType::Call => state.call_revert_policy,
+        };
+
+        RevertOptions {
+            revert_address: policy.revert_address,
+            call_on_revert: policy.call_on_revert,
+            abort_address: policy.abort_address,
+            revert_message: encode_revert_message(policy.retry_after_seconds, reason),
+            on_revert_gas_limit: GAS_LIMIT,
+        }
+    }
+
+    // Message creation functions matching the contract
+    fn create_supply_message(on_behalf_of: [u8; 20]) -> Result<Vec<u8>, DepositContractError> {
+        // abi.encode("supply", address)
+        abi::Encoder::new(2).dynamic(abi::encode_string("supply")).word(abi::encode_address(&on_behalf_of)).finish()
+    }
+
+    fn create_repay_message(on_behalf_of: [u8; 20]) -> Result<Vec<u8>, DepositContractError> {
+        // abi.encode("repay", address)
+        abi::Encoder::new(2).dynamic(abi::encode_string("repay")).word(abi::encode_address(&on_behalf_of)).finish()
+    }
+
+    fn create_borrow_cross_chain_message(
+        user: [u8; 32],
+        amount: u64,
+        destination_chain: u64,
+        recipient: &[u8],
+    ) -> Result<Vec<u8>, DepositContractError> {
+        if recipient.len() != EVM_ADDRESS_LEN as usize {
+            return Err(DepositContractError::InvalidRecipientLength);
+        }
+        Ok(abi_encode_cross_chain("borrowCrossChain", user, amount, destination_chain, recipient))
+    }
+
+    fn create_withdraw_cross_chain_message(
+        user: [u8; 32],
+        amount: u64,
+        destination_chain: u64,
+        recipient: &[u8],
+    ) -> Result<Vec<u8>, DepositContractError> {
+        if recipient.len() != EVM_ADDRESS_LEN as usize {
+            return Err(DepositContractError::InvalidRecipientLength);
+        }
+        Ok(abi_encode_cross_chain("withdrawCrossChain", user, amount, destination_chain, recipient))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CrossChainMessage {
+    action: &'static str,
+    user: [u8; 32],
+    amount: u64,
+    destination_chain: u64,
+    recipient: Vec<u8>,
+}
+
+const CROSS_CHAIN_ACTIONS: [&str; 2] = ["borrowCrossChain", "withdrawCrossChain"];
+
+// abi.encode(string action, bytes32 user, uint256 amount, uint256 destinationChain, address recipient)
+fn abi_encode_cross_chain(action: &str, user: [u8; 32], amount: u64, destination_chain: u64, recipient: &[u8]) -> Vec<u8> {
+    abi::Encoder::new(5)
+        .dynamic(abi::encode_string(action))
+        .word(abi::encode_bytes32(&user))
+        .word(abi::encode_u256(amount))
+        .word(abi::encode_u256(destination_chain))
+        .word(abi::encode_address(recipient.try_into().expect("recipients are addresses")))
+        .finish()
+        .expect("cross-chain messages fill their head")
+}
+
+// Mirrors the program's abi module
+mod abi {
+    use super::DepositContractError;
+
+    pub const WORD_LEN: usize = 32;
+
+    pub fn encode_u256(value: u64) -> Vec<u8> {
+        let mut word = vec![0u8; WORD_LEN];
+        word[WORD_LEN - 8..].copy_from_slice(&value.to_be_bytes());
+        word
+    }
+
+    pub fn encode_address(address: &[u8; 20]) -> Vec<u8> {
+        let mut word = vec![0u8; WORD_LEN];
+        word[WORD_LEN - address.len()..].copy_from_slice(address);
+        word
+    }
+
+    pub fn encode_bytes32(value: &[u8; 32]) -> Vec<u8> {
+        value.to_vec()
+    }
+
+    pub fn encode_string(value: &str) -> Vec<u8> {
+        encode_bytes(value.as_bytes())
+    }
+
+    pub fn encode_bytes(value: &[u8]) -> Vec<u8> {
+        let mut tail = encode_u256(value.len() as u64);
+        tail.extend_from_slice(value);
+        tail.resize(WORD_LEN + value.len().div_ceil(WORD_LEN) * WORD_LEN, 0);
+        tail
+    }
+
+    pub struct Encoder {
+        head_len: usize,
+        head: Vec<u8>,
+        tail: Vec<u8>,
+    }
+
+    impl Encoder {
+        pub fn new(field_count: usize) -> Self {
+            let head_len = field_count * WORD_LEN;
+            Self { head_len, head: Vec::with_capacity(head_len), tail: Vec::new() }
+        }
+
+        pub fn word(mut self, word: Vec<u8>) -> Self {
+            assert_eq!(word.len(), WORD_LEN);
+            self.head.extend_from_slice(&word);
+            self
+        }
+
+        pub fn dynamic(mut self, tail: Vec<u8>) -> Self {
+            let offset = self.head_len + self.tail.len();
+            self.head.extend_from_slice(&encode_u256(offset as u64));
+            self.tail.extend_from_slice(&tail);
+            self
+        }
+
+        pub fn finish(mut self) -> Result<Vec<u8>, DepositContractError> {
+            if self.head.len() != self.head_len || !self.tail.len().is_multiple_of(WORD_LEN) {
+                return Err(DepositContractError::EncodingError);
+            }
+            self.head.extend_from_slice(&self.tail);
+            Ok(self.head)
+        }
+    }
+}
+
+// Pyth v2 price account layout, at the offsets the program reads
+mod pyth {
+    use super::DepositContractError;
+
+    pub const MAGIC: u32 = 0xa1b2_c3d4;
+    pub const VERSION: u32 = 2;
+    pub const ACCOUNT_TYPE_PRICE: u32 = 3;
+    pub const STATUS_TRADING: u32 = 1;
+    pub const EXPO_OFFSET: usize = 20;
+    pub const TIMESTAMP_OFFSET: usize = 96;
+    pub const AGG_PRICE_OFFSET: usize = 208;
+    pub const AGG_STATUS_OFFSET: usize = 224;
+    pub const MIN_LEN: usize = 240;
+
+    pub struct Price {
+        pub price: u64,
+        pub expo: i32,
+        pub publish_time: i64,
+    }
+
+    fn read<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+        data[offset..offset + N].try_into().unwrap()
+    }
+
+    pub fn parse_price(data: &[u8]) -> Result<Price, DepositContractError> {
+        if data.len() < MIN_LEN {
+            return Err(DepositContractError::InvalidPriceFeed);
+        }
+        let header = [0, 4, 8].map(|offset| u32::from_le_bytes(read(data, offset)));
+        let status = u32::from_le_bytes(read(data, AGG_STATUS_OFFSET));
+        let price = i64::from_le_bytes(read(data, AGG_PRICE_OFFSET));
+        if header != [MAGIC, VERSION, ACCOUNT_TYPE_PRICE] || status != STATUS_TRADING || price <= 0 {
+            return Err(DepositContractError::InvalidPriceFeed);
+        }
+        Ok(Price {
+            price: price.unsigned_abs(),
+            expo: i32::from_le_bytes(read(data, EXPO_OFFSET)),
+            publish_time: i64::from_le_bytes(read(data, TIMESTAMP_OFFSET)),
+        })
+    }
+
+    // A trading price account as the Pyth oracle writes it (publisher components omitted)
+    pub fn price_account(price: i64, expo: i32, publish_time: i64) -> Vec<u8> {
+        let mut data = vec![0u8; MIN_LEN];
+        data[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&VERSION.to_le_bytes());
+        data[8..12].copy_from_slice(&ACCOUNT_TYPE_PRICE.to_le_bytes());
+        data[EXPO_OFFSET..EXPO_OFFSET + 4].copy_from_slice(&expo.to_le_bytes());
+        data[TIMESTAMP_OFFSET..TIMESTAMP_OFFSET + 8].copy_from_slice(&publish_time.to_le_bytes());
+        data[AGG_PRICE_OFFSET..AGG_PRICE_OFFSET + 8].copy_from_slice(&price.to_le_bytes());
+        data[AGG_STATUS_OFFSET..AGG_STATUS_OFFSET + 4].copy_from_slice(&STATUS_TRADING.to_le_bytes());
+        data
+    }
+}
+
+fn decode_cross_chain_message(payload: &[u8], accept_legacy: bool) -> Result<CrossChainMessage, DepositContractError> {
+    let legacy_action = CROSS_CHAIN_ACTIONS
+        .into_iter()
+        .find(|action| payload.starts_with(action.as_bytes()) && payload.get(action.len()) == Some(&b':'));
+    match legacy_action {
+        Some(action) if accept_legacy => decode_legacy_message(action, payload),
+        Some(_) => Err(DepositContractError::LegacyMessageDisabled),
+        None => decode_abi_message(payload),
+    }
+}
+
+fn decode_legacy_message(action: &'static str, payload: &[u8]) -> Result<CrossChainMessage, DepositContractError> {
+    let malformed = |_| DepositContractError::MalformedMessage;
+    let text = std::str::from_utf8(payload).map_err(malformed)?;
+    let fields: Vec<&str> = text.split(':').collect();
+    let [_, user, amount, destination_chain, recipient] = fields[..] else {
+        return Err(DepositContractError::MalformedMessage);
+    };
+    Ok(CrossChainMessage {
+        action,
+        user: hex::decode(user).and_then(|user| user.try_into().ok()).ok_or(DepositContractError::MalformedMessage)?,
+        amount: amount.parse().map_err(|_| DepositContractError::MalformedMessage)?,
+        destination_chain: destination_chain.parse().map_err(|_| DepositContractError::MalformedMessage)?,
+        recipient: hex::decode(recipient).ok_or(DepositContractError::MalformedMessage)?,
+    })
+}
+
+fn decode_abi_message(payload: &[u8]) -> Result<CrossChainMessage, DepositContractError> {
+    let word = |offset: usize| payload.get(offset..offset.checked_add(32)?);
+    let uint = |offset: usize| {
+        let word = word(offset)?;
+        word[..24].iter().all(|b| *b == 0).then(|| u64::from_be_bytes(word[24..].try_into().unwrap()))
+    };
+    let bytes = |head: usize| {
+        let start = usize::try_from(uint(head)?).ok()?;
+        let len = usize::try_from(uint(start)?).ok()?;
+        payload.get(start + 32..(start + 32).checked_add(len)?)
+    };
+    let address = |offset: usize| {
+        let word = word(offset)?;
+        word[..12].iter().all(|b| *b == 0).then(|| &word[12..])
+    };
+    let action_tag = bytes(0).ok_or(DepositContractError::MalformedMessage)?;
+    let action = CROSS_CHAIN_ACTIONS.into_iter().find(|action| action.as_bytes() == action_tag).ok_or(DepositContractError::MalformedMessage)?;
+    Ok(CrossChainMessage {
+        action,
+        user: word(32).ok_or(DepositContractError::MalformedMessage)?.try_into().unwrap(),
+        amount: uint(64).ok_or(DepositContractError::MalformedMessage)?,
+        destination_chain: uint(96).ok_or(DepositContractError::MalformedMessage)?,
+        recipient: address(128).ok_or(DepositContractError::MalformedMessage)?.to_vec(),
+    })
+}
+
+// Pre-ABI colon-delimited borrow/withdraw payload
+fn legacy_cross_chain_message(action: &str, user: [u8; 32], amount: u64, destination_chain: u64, recipient: &[u8]) -> Vec<u8> {
+    format!("{}:{}:{}:{}:{}", action, hex::encode(user), amount, destination_chain, hex::encode(recipient)).into_bytes()
+}
+
+// Leading action of a cross-chain message, as a decoder would read it
+fn cross_chain_action(message: &[u8]) -> &'static [u8] {
+    decode_cross_chain_message(message, false).unwrap().action.as_bytes()
+}
+
+// Test implementations
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{abi_words, assert_abi_layout, repay_message, supply_message};
+
+    // Registers a mint bridged as `zrc20`, so withdraw_cross_chain can resolve its position
+    fn map_zrc20(contract: &mut MockDepositContract, authority: Pubkey, zrc20: [u8; 20]) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.set_asset_zrc20(authority, mint, zrc20).unwrap();
+        mint
+    }
+
+    #[test]
+    fn test_contract_initialization() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let lending_protocol_address = [1u8; 20];
+        let zeta_chain_id = 7001; // ZetaChain testnet
+        
+        let result = contract.initialize(authority, lending_protocol_address, zeta_chain_id);
+        assert!(result.is_ok());
+        
+        let state = contract.contract_state.unwrap();
+        assert_eq!(state.authority.0, authority.0);
+        assert_eq!(state.lending_protocol_address, lending_protocol_address);
+        assert_eq!(state.zeta_chain_id, zeta_chain_id);
+        assert!(!state.is_paused);
+    }
+
+    #[test]
+    fn test_invalid_chain_id_initialization() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let lending_protocol_address = [1u8; 20];
+        let invalid_chain_id = 1; // Invalid chain ID
+        
+        let result = contract.initialize(authority, lending_protocol_address, invalid_chain_id);
+        assert!(matches!(result, Err(DepositContractError::InvalidChainId)));
+    }
+
+    #[test]
+    fn test_add_supported_asset() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let lending_protocol_address = [1u8; 20];
+        let zeta_chain_id = 7001;
+        
+        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        let result = contract.add_supported_asset(authority, mint, 6, false);
+        assert!(result.is_ok());
+        
+        let asset_config = contract.asset_configs.get(&mint).unwrap();
+        assert_eq!(asset_config.decimals, 6);
+        assert!(!asset_config.is_native);
+        assert!(asset_config.is_supported);
+    }
+
+    #[test]
+    fn test_add_same_asset_twice() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        let result = contract.add_supported_asset(authority, mint, 9, true);
+        assert!(matches!(result, Err(DepositContractError::AssetAlreadySupported)));
+        
+        // The original config is untouched
+        assert_eq!(contract.asset_configs[&mint].decimals, 6);
+        assert!(!contract.asset_configs[&mint].is_native);
+        
+        // Still rejected after removal; the config must be closed first
+        contract.remove_supported_asset(authority, mint).unwrap();
+        let result = contract.add_supported_asset(authority, mint, 6, false);
+        assert!(matches!(result, Err(DepositContractError::AssetAlreadySupported)));
+    }
+
+    #[test]
+    fn test_set_asset_supported_round_trip() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.set_asset_lending_protocol(authority, mint, [9u8; 20]).unwrap();
+        contract.set_require_exact_amount(authority, mint, true).unwrap();
+        contract.set_asset_metadata_uri(authority, mint, "https://example.com/usdc.json").unwrap();
+        
+        let user = Pubkey::new_unique();
+        contract.set_asset_supported(authority, mint, false).unwrap();
+        let result = contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::UnsupportedAsset)));
+        
+        contract.set_asset_supported(authority, mint, true).unwrap();
+        assert!(contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).is_ok());
+        
+        // Everything else survived the toggle
+        let asset_config = &contract.asset_configs[&mint];
+        assert_eq!(asset_config.lending_protocol_address, [9u8; 20]);
+        assert!(asset_config.require_exact_amount);
+        assert_eq!(asset_config.metadata_uri, "https://example.com/usdc.json");
+        assert_eq!(asset_config.total_received, 1_000_000);
+        
+        let result = contract.set_asset_supported(Pubkey::new_unique(), mint, false);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_sol_routes_to_native_lending_protocol_override() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        contract.set_debug_logging(authority, true).unwrap();
+        let user = Pubkey::new_unique();
+        let receiver = |contract: &MockDepositContract| match contract.events.last() {
+            Some(Event::DepositInitiated { lending_protocol, .. }) => *lending_protocol,
+            other => panic!("unexpected event {:?}", other),
+        };
+        
+        // Unregistered, then registered without an override: the contract-wide protocol
+        contract.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap();
+        assert_eq!(receiver(&contract), [1u8; 20]);
+        contract.register_native_sol(authority).unwrap();
+        contract.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap();
+        assert_eq!(receiver(&contract), [1u8; 20]);
+        
+        // The override receives both deposits and repays
+        contract.set_asset_lending_protocol(authority, SYSTEM_PROGRAM_ID, [9u8; 20]).unwrap();
+        contract.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap();
+        assert_eq!(receiver(&contract), [9u8; 20]);
+        assert!(contract.logs.iter().any(|line| line.contains(&format!("receiver={:?}", [9u8; 20]))));
+        contract.logs.clear();
+        contract.repay_sol(user, 10_000_000, [2u8; 20], &[]).unwrap();
+        assert!(contract.logs.iter().any(|line| line.contains(&format!("receiver={:?}", [9u8; 20]))));
+    }
+
+    #[test]
+    fn test_resync_asset_decimals() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        // USDC mint has 6 decimals but was registered as 9
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 9, false).unwrap();
+        
+        let result = contract.resync_asset_decimals(Pubkey::new_unique(), mint, 6);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        assert_eq!(contract.asset_configs[&mint].decimals, 9);
+        
+        contract.resync_asset_decimals(authority, mint, 6).unwrap();
+        let asset_config = &contract.asset_configs[&mint];
+        assert_eq!(asset_config.decimals, 6);
+        assert!(asset_config.is_supported);
+    }
+
+    #[test]
+    fn test_set_asset_metadata_uri() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        assert!(contract.asset_configs[&mint].metadata_uri.is_empty());
+        
+        let uri = "https://assets.zetalend.xyz/usdc.json";
+        contract.set_asset_metadata_uri(authority, mint, uri).unwrap();
+        assert_eq!(contract.asset_configs[&mint].metadata_uri, uri);
+        
+        // Exactly at the bound is accepted
+        let max_uri = "a".repeat(MAX_METADATA_URI_LEN);
+        contract.set_asset_metadata_uri(authority, mint, &max_uri).unwrap();
+        assert_eq!(contract.asset_configs[&mint].metadata_uri, max_uri);
+    }
+
+    #[test]
+    fn test_set_asset_metadata_uri_too_long() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        
+        let uri = "a".repeat(MAX_METADATA_URI_LEN + 1);
+        let result = contract.set_asset_metadata_uri(authority, mint, &uri);
+        assert!(matches!(result, Err(DepositContractError::MetadataUriTooLong)));
+        assert!(contract.asset_configs[&mint].metadata_uri.is_empty());
+    }
+
+    #[test]
+    fn test_close_removed_asset_config() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        
+        let result = contract.close_asset_config(authority, mint);
+        assert!(matches!(result, Err(DepositContractError::AssetStillSupported)));
+        
+        contract.remove_supported_asset(authority, mint).unwrap();
+        let result = contract.close_asset_config(Pubkey::new_unique(), mint);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        
+        contract.close_asset_config(authority, mint).unwrap();
+        assert!(!contract.asset_configs.contains_key(&mint));
+        
+        // A closed config frees the PDA so the asset can be listed again
+        assert!(contract.add_supported_asset(authority, mint, 6, false).is_ok());
+    }
+
+    #[test]
+    fn test_close_asset_config_requires_empty_vault() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.simulate_vault_transfer(mint, 42);
+        contract.remove_supported_asset(authority, mint).unwrap();
+        
+        let result = contract.close_asset_config(authority, mint);
+        assert!(matches!(result, Err(DepositContractError::VaultNotEmpty)));
+        assert!(contract.asset_configs.contains_key(&mint));
+    }
+
+    #[test]
+    fn test_get_asset_configs() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let mints: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        contract.add_supported_asset(authority, mints[0], 6, false).unwrap();
+        contract.add_supported_asset(authority, mints[1], 8, false).unwrap();
+        contract.add_supported_asset(authority, mints[2], 9, true).unwrap();
+        
+        let configs = contract.get_asset_configs(&mints).unwrap();
+        assert_eq!(configs.len(), 3);
+        for (config, mint) in configs.iter().zip(&mints) {
+            assert_eq!(config.mint, *mint);
+        }
+        assert_eq!(configs[1].decimals, 8);
+        assert!(configs[2].is_native);
+    }
+
+    #[test]
+    fn test_get_asset_configs_bounded() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let mints: Vec<Pubkey> = (0..MAX_ASSET_CONFIGS_PER_QUERY + 1).map(|_| Pubkey::new_unique()).collect();
+        for mint in &mints {
+            contract.add_supported_asset(authority, *mint, 6, false).unwrap();
+        }
+        
+        assert!(contract.get_asset_configs(&mints[..MAX_ASSET_CONFIGS_PER_QUERY]).is_ok());
+        let result = contract.get_asset_configs(&mints);
+        assert!(matches!(result, Err(DepositContractError::BatchTooLarge)));
+    }
+
+    #[test]
+    fn test_unauthorized_add_asset() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let wrong_authority = Pubkey::new_unique();
+        let lending_protocol_address = [1u8; 20];
+        let zeta_chain_id = 7001;
+        
+        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        let result = contract.add_supported_asset(wrong_authority, mint, 6, false);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_deposit_sol_success() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let lending_protocol_address = [1u8; 20];
+        let zeta_chain_id = 7001;
+        
+        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let amount = 10_000_000; // 0.01 SOL
+        let on_behalf_of = [2u8; 20]; // ZetaChain address
+        
+        let result = contract.deposit_sol(user, amount, on_behalf_of);
+        assert!(result.is_ok());
+        
+        let message = result.unwrap();
+        assert_eq!(message.len(), 128); // Expected ABI encoded message length
+    }
+
+    #[test]
+    fn test_link_evm_address_valid_signature() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let evm_key = MockEvmKey { address: [9u8; 20] };
+        let (signature, recovery_id) = evm_key.sign(&contract.link_payload(user));
+        
+        let result = contract.link_evm_address(user, evm_key.address, signature, recovery_id);
+        assert!(result.is_ok());
+        assert_eq!(contract.evm_links.get(&user), Some(&evm_key.address));
+    }
+
+    #[test]
+    fn test_link_evm_address_invalid_signature() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let evm_key = MockEvmKey { address: [9u8; 20] };
+        
+        // Signature over a different Solana pubkey
+        let (signature, recovery_id) = evm_key.sign(&contract.link_payload(Pubkey::new_unique()));
+        let result = contract.link_evm_address(user, evm_key.address, signature, recovery_id);
+        assert!(matches!(result, Err(DepositContractError::EvmSignatureMismatch)));
+        
+        // Valid signature claimed for someone else's address
+        let (signature, recovery_id) = evm_key.sign(&contract.link_payload(user));
+        let result = contract.link_evm_address(user, [8u8; 20], signature, recovery_id);
+        assert!(matches!(result, Err(DepositContractError::EvmSignatureMismatch)));
+        
+        // Malformed recovery id
+        let result = contract.link_evm_address(user, evm_key.address, signature, 5);
+        assert!(matches!(result, Err(DepositContractError::InvalidEvmSignature)));
+        
+        assert!(contract.evm_links.get(&user).is_none());
+    }
+
+    #[test]
+    fn test_link_signature_from_other_domain_rejected() {
+        let mut testnet = MockDepositContract::new();
+        let mut mainnet = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        testnet.initialize(authority, [1u8; 20], 7001).unwrap();
+        mainnet.initialize(authority, [1u8; 20], 7000).unwrap();
+        
+        // A link proof made for the testnet deployment can't be replayed on mainnet
+        let user = Pubkey::new_unique();
+        let evm_key = MockEvmKey { address: [9u8; 20] };
+        let (signature, recovery_id) = evm_key.sign(&testnet.link_payload(user));
+        assert!(testnet.link_evm_address(user, evm_key.address, signature, recovery_id).is_ok());
+        let result = mainnet.link_evm_address(user, evm_key.address, signature, recovery_id);
+        assert!(matches!(result, Err(DepositContractError::EvmSignatureMismatch)));
+    }
+
+    #[test]
+    fn test_messages_carry_no_domain_separator() {
+        let mut testnet = MockDepositContract::new();
+        let mut mainnet = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        testnet.initialize(authority, [1u8; 20], 7001).unwrap();
+        mainnet.initialize(authority, [1u8; 20], 7000).unwrap();
+        
+        // The domain only goes into link signatures; onCall checks the payload length,
+        // so the same deposit must produce the same bytes on every deployment
+        let user = Pubkey::new_unique();
+        let message = testnet.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap();
+        assert_eq!(message, mainnet.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap());
+        assert!(!message.windows(32).any(|window| window == testnet.domain()));
+    }
+
+    #[test]
+    fn test_deposit_return_data_is_forwarded_message() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        
+        // Borsh Vec<u8>: u32 LE length prefix, then the message bytes
+        let decode = |data: &[u8]| -> Vec<u8> {
+            let len = u32::from_le_bytes(data[..4].try_into().unwrap()) as usize;
+            assert_eq!(data.len(), 4 + len);
+            data[4..].to_vec()
+        };
+        
+        let sent = contract.deposit_sol(Pubkey::new_unique(), 10_000_000, [2u8; 20]).unwrap();
+        let returned = decode(contract.return_data.as_ref().unwrap());
+        assert_eq!(returned, sent);
+        assert_eq!(returned, MockDepositContract::create_supply_message([2u8; 20]).unwrap());
+        
+        let sent = contract.deposit_spl_token(Pubkey::new_unique(), mint, 1_000_000, [3u8; 20]).unwrap();
+        let returned = decode(contract.return_data.as_ref().unwrap());
+        assert_eq!(returned, sent);
+        assert_eq!(returned, MockDepositContract::create_supply_message([3u8; 20]).unwrap());
+    }
+
+    #[test]
+    fn test_deposit_requires_linked_evm_address() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        contract.set_require_evm_link(authority, true).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let evm_key = MockEvmKey { address: [9u8; 20] };
+        let amount = 10_000_000;
+        
+        let result = contract.deposit_sol(user, amount, evm_key.address);
+        assert!(matches!(result, Err(DepositContractError::EvmLinkRequired)));
+        
+        let (signature, recovery_id) = evm_key.sign(&contract.link_payload(user));
+        contract.link_evm_address(user, evm_key.address, signature, recovery_id).unwrap();
+        
+        assert!(contract.deposit_sol(user, amount, evm_key.address).is_ok());
+        let result = contract.deposit_sol(user, amount, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::EvmAddressNotLinked)));
+        
+        // Optional: without the requirement any address is accepted
+        contract.set_require_evm_link(authority, false).unwrap();
+        assert!(contract.deposit_sol(user, amount, [2u8; 20]).is_ok());
+    }
+
+    #[test]
+    fn test_sol_paths_reject_token_accounts() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let amount = 10_000_000;
+        let token_account = MockAccount { key: Pubkey::new_unique(), owner: TOKEN_PROGRAM_ID };
+        let token_program = MockAccount { key: TOKEN_PROGRAM_ID, owner: Pubkey::new_unique() };
+        let wallet = MockAccount { key: Pubkey::new_unique(), owner: SYSTEM_PROGRAM_ID };
+        
+        for extra in [token_account, token_program] {
+            let result = contract.deposit_sol_with_accounts(user, amount, [2u8; 20], &[wallet, extra]);
+            assert!(matches!(result, Err(DepositContractError::TokenAccountInSolPath)));
+            let result = contract.repay_sol(user, amount, [2u8; 20], &[extra]);
+            assert!(matches!(result, Err(DepositContractError::TokenAccountInSolPath)));
+        }
+        
+        // System-owned extras are harmless
+        assert!(contract.deposit_sol_with_accounts(user, amount, [2u8; 20], &[wallet]).is_ok());
+        assert!(contract.repay_sol(user, amount, [2u8; 20], &[wallet]).is_ok());
+    }
+
+    #[test]
+    fn test_pda_debit_preserves_rent_exemption() {
+        let data_len = 8 + 200;
+        let minimum = rent_exempt_minimum(data_len);
+        let balance = minimum + 1_000_000;
+        
+        // Incoming lamports only ever raise the balance; debits down to the minimum are fine
+        assert!(check_rent_exempt_after_debit(balance, data_len, 1_000_000).is_ok());
+        
+        // One lamport past the minimum, or draining the PDA entirely, is rejected
+        let result = check_rent_exempt_after_debit(balance, data_len, 1_000_001);
+        assert!(matches!(result, Err(DepositContractError::BelowRentExemption)));
+        let result = check_rent_exempt_after_debit(balance, data_len, u64::MAX);
+        assert!(matches!(result, Err(DepositContractError::BelowRentExemption)));
+    }
+
+    #[test]
+    fn test_authority_cannot_be_program_or_pda() {
+        let mut contract = MockDepositContract::new();
+        for bad in [PROGRAM_ID, contract_state_pda(), SYSTEM_PROGRAM_ID] {
+            let result = contract.initialize(bad, [1u8; 20], 7001);
+            assert!(matches!(result, Err(DepositContractError::InvalidAuthority)));
+        }
+        
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        for bad in [PROGRAM_ID, contract_state_pda(), SYSTEM_PROGRAM_ID] {
+            let result = contract.propose_authority(authority, bad);
+            assert!(matches!(result, Err(DepositContractError::InvalidAuthority)));
+        }
+        
+        // A real key goes through both steps
+        let next = Pubkey::new_unique();
+        contract.propose_authority(authority, next).unwrap();
+        let result = contract.accept_authority(Pubkey::new_unique());
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        contract.accept_authority(next).unwrap();
+        assert_eq!(contract.contract_state.as_ref().unwrap().authority, next);
+        let result = contract.propose_authority(authority, Pubkey::new_unique());
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_get_pending_changes_reflects_proposals() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let none = PendingChanges {
+            pending_authority: None,
+            authority_effective_at: 0,
+            pending_lending_protocol_address: None,
+            lending_protocol_effective_at: 0,
+        };
+        assert_eq!(contract.get_pending_changes().unwrap(), none);
+        
+        let next = Pubkey::new_unique();
+        contract.propose_authority(authority, next).unwrap();
+        let pending = contract.get_pending_changes().unwrap();
+        assert_eq!(pending.pending_authority, Some(next));
+        assert_eq!(pending.authority_effective_at, 0);
+        
+        // Accepting clears it
+        contract.accept_authority(next).unwrap();
+        assert_eq!(contract.get_pending_changes().unwrap(), none);
+    }
+
+    #[test]
+    fn test_cpi_caller_allowlist() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let amount = 10_000_000;
+        let router = Pubkey::new_unique();
+        let stranger = Pubkey::new_unique();
+        
+        // No allowlist: any CPI caller may deposit
+        contract.top_level_program = stranger;
+        assert!(contract.deposit_sol(user, amount, [2u8; 20]).is_ok());
+        
+        contract.set_allowed_caller_programs(authority, vec![router]).unwrap();
+        let result = contract.deposit_sol(user, amount, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::UnauthorizedCaller)));
+        
+        contract.top_level_program = router;
+        assert!(contract.deposit_sol(user, amount, [2u8; 20]).is_ok());
+        
+        // Direct calls are never blocked
+        contract.top_level_program = PROGRAM_ID;
+        assert!(contract.deposit_sol(user, amount, [2u8; 20]).is_ok());
+        
+        let result = contract.set_allowed_caller_programs(authority, vec![router; MAX_ALLOWED_CALLER_PROGRAMS + 1]);
+        assert!(matches!(result, Err(DepositContractError::RegistryFull)));
+        let result = contract.set_allowed_caller_programs(Pubkey::new_unique(), Vec::new());
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_bps_fee_rounding_policy() {
+        // 30 bps of 1_001 is 3.003: the fee rounds down, the user keeps the remainder
+        assert_eq!(split_bps_fee(1_001, 30).unwrap(), (3, 998));
+        // 1 bp of 9_999 is 0.9999: too small to charge
+        assert_eq!(split_bps_fee(9_999, 1).unwrap(), (0, 9_999));
+        assert_eq!(split_bps_fee(10_000, 1).unwrap(), (1, 9_999));
+        // 5 bps of an odd amount, just below and at a whole unit
+        assert_eq!(split_bps_fee(3_999, 5).unwrap(), (1, 3_998));
+        assert_eq!(split_bps_fee(4_000, 5).unwrap(), (2, 3_998));
+        
+        // Nothing is lost or created, even at the extremes
+        for (amount, bps) in [(1, 9_999), (u64::MAX, 9_999), (u64::MAX, 10_000), (7, 0)] {
+            let (fee, net) = split_bps_fee(amount, bps).unwrap();
+            assert_eq!(fee + net, amount);
+        }
+        
+        let result = split_bps_fee(1_000, 10_001);
+        assert!(matches!(result, Err(DepositContractError::InvalidAmount)));
+    }
+
+    #[test]
+    fn test_cross_chain_rejects_suspicious_recipients() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let lending_protocol = [1u8; 20];
+        contract.initialize(authority, lending_protocol, 7001).unwrap();
+        map_zrc20(&mut contract, authority, [3u8; 20]);
+        
+        let user = Pubkey::new_unique();
+        
+        // on_behalf_of-style value (the lending protocol) passed as the recipient
+        let result = contract.borrow_cross_chain(user, [3u8; 20], 1_000, 421614, lending_protocol);
+        assert!(matches!(result, Err(DepositContractError::SuspiciousRecipient)));
+        let result = contract.withdraw_cross_chain(user, [3u8; 20], 1_000, 421614, lending_protocol);
+        assert!(matches!(result, Err(DepositContractError::SuspiciousRecipient)));
+        
+        // Unset recipient
+        let result = contract.borrow_cross_chain(user, [3u8; 20], 1_000, 421614, [0u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::SuspiciousRecipient)));
+        let result = contract.withdraw_cross_chain(user, [3u8; 20], 1_000, 421614, [0u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::SuspiciousRecipient)));
+        
+        assert!(contract.borrow_cross_chain(user, [3u8; 20], 1_000, 421614, [4u8; 20]).is_ok());
+        assert!(contract.withdraw_cross_chain(user, [3u8; 20], 1_000, 421614, [4u8; 20]).is_ok());
+    }
+
+    #[test]
+    fn test_outbound_nonce_advances_per_chain() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        map_zrc20(&mut contract, authority, [3u8; 20]);
+        
+        let user = Pubkey::new_unique();
+        let (arbitrum, sepolia) = (421614, 11155111);
+        for (chain, nonce) in [(arbitrum, 1), (arbitrum, 2), (sepolia, 7)] {
+            contract.gateway_return_nonce = Some(nonce);
+            contract.borrow_cross_chain(user, [3u8; 20], 1_000, chain, [4u8; 20]).unwrap();
+        }
+        contract.gateway_return_nonce = Some(3);
+        contract.withdraw_cross_chain(user, [3u8; 20], 1_000, arbitrum, [4u8; 20]).unwrap();
+        assert_eq!(contract.outbound_nonces[&arbitrum], 3);
+        assert_eq!(contract.outbound_nonces[&sepolia], 7);
+        
+        // Without gateway return data nothing is counted locally
+        contract.borrow_cross_chain(user, [3u8; 20], 1_000, sepolia, [4u8; 20]).unwrap();
+        assert_eq!(contract.outbound_nonces[&sepolia], 7);
+        let ethereum = 1;
+        contract.set_chain_config(authority, ethereum, EVM_ADDRESS_LEN).unwrap();
+        contract.borrow_cross_chain(user, [3u8; 20], 1_000, ethereum, [4u8; 20]).unwrap();
+        assert!(!contract.outbound_nonces.contains_key(&ethereum));
+        
+        // Rejected ops leave the nonce untouched
+        contract.gateway_return_nonce = Some(99);
+        let _ = contract.withdraw_cross_chain(user, [3u8; 20], 0, arbitrum, [4u8; 20]);
+        assert_eq!(contract.outbound_nonces[&arbitrum], 3);
+    }
+
+    #[test]
+    fn test_deposit_initiated_derived_evm_sender() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let sponsor = Pubkey::new_unique();
+        contract.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap();
+        contract.deposit_sol_for(sponsor, 10_000_000, [2u8; 20], user).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).unwrap();
+        
+        let senders: Vec<[u8; 20]> = contract.events.iter().filter_map(|event| match event {
+            Event::DepositInitiated { derived_evm_sender, .. } => Some(*derived_evm_sender),
+            _ => None,
+        }).collect();
+        
+        // keccak256(signer)[12..32]: the SOL payer signs its own gateway call,
+        // while SPL deposits are forwarded by the contract PDA
+        let expected = |signer: Pubkey| mock_hash32(&[&signer.to_bytes()])[12..32].to_vec();
+        assert_eq!(senders.len(), 3);
+        assert_eq!(senders[0].to_vec(), expected(user));
+        assert_eq!(senders[1].to_vec(), expected(sponsor));
+        assert_eq!(senders[2].to_vec(), expected(contract_state_pda()));
+        assert_ne!(senders[0], senders[1]);
+    }
+
+    #[test]
+    fn test_deposit_sol_for_attributes_owner() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let sponsor = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        contract.deposit_sol_for(sponsor, 10_000_000, [2u8; 20], owner).unwrap();
+        contract.deposit_sol(owner, 10_000_000, [2u8; 20]).unwrap();
+        
+        let attributions: Vec<(Pubkey, Pubkey)> = contract.events.iter().filter_map(|event| match event {
+            Event::DepositInitiated { user, owner, .. } => Some((*user, *owner)),
+            _ => None,
+        }).collect();
+        assert_eq!(attributions, vec![(sponsor, owner), (owner, owner)]);
+    }
+
+    #[test]
+    fn test_deposit_sol_for_checks_owner_evm_link() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        contract.set_require_evm_link(authority, true).unwrap();
+        
+        let sponsor = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let evm_key = MockEvmKey { address: [9u8; 20] };
+        let (signature, recovery_id) = evm_key.sign(&contract.link_payload(owner));
+        contract.link_evm_address(owner, evm_key.address, signature, recovery_id).unwrap();
+        
+        // The beneficiary's link counts, not the sponsor's
+        assert!(contract.deposit_sol_for(sponsor, 10_000_000, evm_key.address, owner).is_ok());
+        let result = contract.deposit_sol(sponsor, 10_000_000, evm_key.address);
+        assert!(matches!(result, Err(DepositContractError::EvmLinkRequired)));
+    }
+
+    #[test]
+    fn test_deposit_sol_insufficient_fee() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let lending_protocol_address = [1u8; 20];
+        let zeta_chain_id = 7001;
+        
+        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let amount = 1_000_000; // 0.001 SOL - less than minimum fee
+        let on_behalf_of = [2u8; 20];
+        
+        let result = contract.deposit_sol(user, amount, on_behalf_of);
+        assert!(matches!(result, Err(DepositContractError::InsufficientDepositFee)));
+    }
+
+    #[test]
+    fn test_deposit_sol_lamports_overflow() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let amount = 10_000_000;
+        let net_amount = amount - DEPOSIT_FEE;
+        
+        // The gateway PDA receives the net amount; one lamport too many overflows it
+        contract.gateway_lamports = u64::MAX - net_amount + 1;
+        let result = contract.deposit_sol(user, amount, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::ArithmeticOverflow)));
+        assert_eq!(contract.fee_vault_lamports, 0);
+        
+        // Exactly reaching u64::MAX is still representable
+        contract.gateway_lamports = u64::MAX - net_amount;
+        assert!(contract.deposit_sol(user, amount, [2u8; 20]).is_ok());
+        
+        // The fee destination is checked for the fee it receives
+        contract.gateway_lamports = 0;
+        contract.fee_vault_lamports = u64::MAX - DEPOSIT_FEE + 1;
+        let result = contract.deposit_sol(user, amount, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::ArithmeticOverflow)));
+        
+        // The contract PDA is not credited, so its balance does not matter
+        contract.fee_vault_lamports = 0;
+        contract.contract_lamports = u64::MAX;
+        assert!(contract.deposit_sol(user, amount, [2u8; 20]).is_ok());
+    }
+
+    #[test]
+    fn test_deposit_sol_credits_only_forwarded_lamports() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let user = Pubkey::new_unique();
+        
+        // The fee lands in the vault and the rest in the gateway PDA, matching the credit
+        contract.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap();
+        assert_eq!(contract.fee_vault_lamports, DEPOSIT_FEE);
+        assert_eq!(contract.gateway_lamports, 10_000_000 - DEPOSIT_FEE);
+        assert_eq!(contract.collateral_positions[&(user, SYSTEM_PROGRAM_ID)].amount, contract.gateway_lamports);
+        
+        // A gateway that takes nothing leaves nothing to credit
+        contract.gateway_moves_sol = false;
+        let result = contract.deposit_sol(user, 10_000_000, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::DepositFailed)));
+        assert_eq!(contract.collateral_positions[&(user, SYSTEM_PROGRAM_ID)].amount, 10_000_000 - DEPOSIT_FEE);
+    }
+
+    #[test]
+    fn test_deposit_spl_token_success() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let lending_protocol_address = [1u8; 20];
+        let zeta_chain_id = 7001;
+        
+        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let amount = 1_000_000; // 1 USDC (6 decimals)
+        let on_behalf_of = [2u8; 20];
+        
+        let result = contract.deposit_spl_token(user, mint, amount, on_behalf_of);
+        assert!(result.is_ok());
+        
+        let message = result.unwrap();
+        assert_eq!(message.len(), 128);
+    }
+
+    #[test]
+    fn test_deposit_spl_token_underfunded_account() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        let user = Pubkey::new_unique();
+        contract.fund_user(user, mint, 999_999);
+        
+        let result = contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::InsufficientBalance)));
+        assert_eq!(contract.user_balances[&(user, mint)], 999_999);
+        assert_eq!(contract.asset_configs[&mint].total_received, 0);
+        
+        // Exactly the balance is enough
+        contract.deposit_spl_token(user, mint, 999_999, [2u8; 20]).unwrap();
+        assert_eq!(contract.user_balances[&(user, mint)], 0);
+    }
+
+    #[test]
+    fn test_create_asset_vault_then_deposit() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let result = contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::VaultNotInitialized)));
+        
+        // Only the authority can create the vault
+        let result = contract.create_asset_vault(user, mint);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        contract.create_asset_vault(authority, mint).unwrap();
+        
+        // Subsequent deposits reuse the existing vault
+        contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).unwrap();
+        contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).unwrap();
+        assert_eq!(contract.asset_configs[&mint].total_received, 2_000_000);
+    }
+
+    #[test]
+    fn test_consecutive_spl_deposits_of_same_mint() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        
+        // Deposits only write to the existing vault; none of them tries to create it
+        let user = Pubkey::new_unique();
+        let first = contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).unwrap();
+        let second = contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).unwrap();
+        assert_eq!(first, second);
+        contract.deposit_spl_token(Pubkey::new_unique(), mint, 500_000, [3u8; 20]).unwrap();
+        
+        let deposits = contract.events.iter().filter(|e| matches!(e, Event::DepositInitiated { asset, .. } if *asset == mint)).count();
+        assert_eq!(deposits, 3);
+        assert_eq!(contract.asset_configs[&mint].total_received, 2_500_000);
+        assert_eq!(contract.asset_configs[&mint].total_forwarded, 2_500_000);
+    }
+
+    #[test]
+    fn test_deposits_leave_nothing_pending() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        
+        // The gateway CPI moves the funds within the deposit instruction, so no
+        // deposit is ever held by the contract awaiting confirmation (nothing to cancel)
+        let user = Pubkey::new_unique();
+        contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).unwrap();
+        assert_eq!(contract.vault_balances[&mint], 0);
+        assert_eq!(contract.reconcile_spl(mint).unwrap(), 0);
+        
+        let contract_lamports = contract.contract_lamports;
+        contract.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap();
+        assert_eq!(contract.contract_lamports, contract_lamports);
+    }
+
+    #[test]
+    fn test_borrows_stay_outstanding_until_repaid() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        contract.set_global_borrow_cap(authority, 1_500 * 10u128.pow(18)).unwrap();
+        let user = Pubkey::new_unique();
+        let asset = [3u8; 20];
+        contract.borrow_cross_chain(user, asset, 1_000, 421614, [4u8; 20]).unwrap();
+        
+        // No confirmation ever arrives for a borrow, so elapsed time says nothing about
+        // whether it paid out: it keeps counting against the caps until repaid
+        contract.now += 365 * 24 * 60 * 60;
+        assert_eq!(contract.contract_state.as_ref().unwrap().total_outstanding_borrows, 1_000 * 10u128.pow(18));
+        assert_eq!(contract.zrc20_configs[&asset].outstanding_borrow, 1_000);
+        let result = contract.borrow_cross_chain(user, asset, 1_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::GlobalBorrowCapExceeded)));
+        
+        contract.register_native_sol(authority).unwrap();
+        contract.set_asset_zrc20(authority, SYSTEM_PROGRAM_ID, asset).unwrap();
+        contract.repay_sol(user, DEPOSIT_FEE + 1_000, derive_evm_sender(user), &[]).unwrap();
+        assert_eq!(contract.contract_state.as_ref().unwrap().total_outstanding_borrows, 0);
+        assert_eq!(contract.zrc20_configs[&asset].outstanding_borrow, 0);
+        assert!(contract.borrow_cross_chain(user, asset, 1_000, 421614, [4u8; 20]).is_ok());
+    }
+
+    #[test]
+    fn test_create_asset_vault_twice_is_a_no_op() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        assert_eq!(contract.vault_balances[&mint], 0);
+        
+        // Re-running setup later leaves a funded vault untouched
+        *contract.vault_balances.get_mut(&mint).unwrap() = 500;
+        contract.create_asset_vault(authority, mint).unwrap();
+        assert_eq!(contract.vault_balances[&mint], 500);
+        
+        // The usual checks still apply on a re-run
+        let result = contract.create_asset_vault(Pubkey::new_unique(), mint);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_create_asset_vault_requires_supported_asset() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let result = contract.create_asset_vault(authority, Pubkey::new_unique());
+        assert!(matches!(result, Err(DepositContractError::UnsupportedAsset)));
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.asset_configs.get_mut(&mint).unwrap().is_supported = false;
+        let result = contract.create_asset_vault(authority, mint);
+        assert!(matches!(result, Err(DepositContractError::UnsupportedAsset)));
+    }
+
+    #[test]
+    fn test_exact_amount_multiples() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        // 20 decimals normalize to 18 in steps of 100 base units
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 20, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        let user = Pubkey::new_unique();
+        
+        // Opt-in: remainders are truncated silently by default
+        assert!(contract.deposit_spl_token(user, mint, 1_050, [2u8; 20]).is_ok());
+        
+        contract.set_require_exact_amount(authority, mint, true).unwrap();
+        let result = contract.deposit_spl_token(user, mint, 1_050, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::NonExactAmount)));
+        assert!(contract.deposit_spl_token(user, mint, 1_000, [2u8; 20]).is_ok());
+    }
+
+    #[test]
+    fn test_exact_amount_step_for_low_decimals() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        // Assets at or below NORMALIZED_DECIMALS never lose precision
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.set_require_exact_amount(authority, mint, true).unwrap();
+        assert_eq!(normalization_step(6).unwrap(), 1);
+        assert!(contract.deposit_spl_token(Pubkey::new_unique(), mint, 1_234_567, [2u8; 20]).is_ok());
+        
+        let result = contract.set_require_exact_amount(Pubkey::new_unique(), mint, false);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_freezable_mints_rejected_unless_allowed() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let freezable = contract.create_freezable_mint(6);
+        let plain = contract.create_mint(6);
+        
+        // Check enabled by default
+        let result = contract.add_supported_asset(authority, freezable, 6, false);
+        assert!(matches!(result, Err(DepositContractError::MintHasFreezeAuthority)));
+        assert!(!contract.asset_configs.contains_key(&freezable));
+        assert!(contract.add_supported_asset(authority, plain, 6, false).is_ok());
+        
+        let result = contract.set_allow_freezable_mints(Pubkey::new_unique(), true);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        contract.set_allow_freezable_mints(authority, true).unwrap();
+        assert!(contract.add_supported_asset(authority, freezable, 6, false).is_ok());
+        
+        // Disabling again only affects later registrations
+        contract.set_allow_freezable_mints(authority, false).unwrap();
+        assert!(contract.asset_configs[&freezable].is_supported);
+        let another = contract.create_freezable_mint(9);
+        let result = contract.add_supported_asset(authority, another, 9, false);
+        assert!(matches!(result, Err(DepositContractError::MintHasFreezeAuthority)));
+    }
+
+    #[test]
+    fn test_beneficiary_allowlist_proofs() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let user = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+
+        // Four-leaf tree over beneficiaries [1..=4; 20]
+        let leaves: Vec<[u8; 32]> = (1..=4u8).map(|b| mock_hash32(&[&[b; 20]])).collect();
+        let left = merkle_parent(leaves[0], leaves[1]);
+        let right = merkle_parent(leaves[2], leaves[3]);
+        let root = merkle_parent(left, right);
+
+        // Unset root: no proof needed
+        assert!(contract.deposit_sol(user, 10_000_000, [9u8; 20]).is_ok());
+
+        let result = contract.set_beneficiary_allowlist_root(user, root);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        contract.set_beneficiary_allowlist_root(authority, root).unwrap();
+
+        // Valid proofs for both deposit paths
+        contract.beneficiary_proof = vec![leaves[3], left];
+        assert!(contract.deposit_sol(user, 10_000_000, [3u8; 20]).is_ok());
+        assert!(contract.deposit_spl_token(user, mint, 1_000, [3u8; 20]).is_ok());
+        contract.beneficiary_proof = vec![leaves[0], right];
+        assert!(contract.deposit_sol_for(user, 10_000_000, [2u8; 20], Pubkey::new_unique()).is_ok());
+
+        // A proof only vouches for its own leaf
+        let result = contract.deposit_sol(user, 10_000_000, [3u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::BeneficiaryNotAllowed)));
+        let result = contract.deposit_spl_token(user, mint, 1_000, [9u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::BeneficiaryNotAllowed)));
+
+        // Tampered, truncated, missing and oversized proofs are rejected
+        for proof in [vec![leaves[0], left], vec![leaves[0]], vec![], vec![leaves[0]; MAX_BENEFICIARY_PROOF_LEN + 1]] {
+            contract.beneficiary_proof = proof;
+            let result = contract.deposit_sol(user, 10_000_000, [2u8; 20]);
+            assert!(matches!(result, Err(DepositContractError::BeneficiaryNotAllowed)));
+        }
+
+        // An inner node is not a beneficiary
+        contract.beneficiary_proof = vec![right];
+        let mut inner = [0u8; 20];
+        inner.copy_from_slice(&left[..20]);
+        let result = contract.deposit_sol(user, 10_000_000, inner);
+        assert!(matches!(result, Err(DepositContractError::BeneficiaryNotAllowed)));
+
+        // Clearing the root disables the allowlist
+        contract.set_beneficiary_allowlist_root(authority, [0u8; 32]).unwrap();
+        assert!(contract.deposit_sol(user, 10_000_000, [9u8; 20]).is_ok());
+    }
+
+    #[test]
+    fn test_min_deposit_boundaries() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.fund_user(user, mint, 10_000_000);
+        
+        // Zero minimum: a single base unit is accepted
+        assert_eq!(contract.asset_configs[&mint].min_deposit, 0);
+        assert!(contract.deposit_spl_token(user, mint, 1, [2u8; 20]).is_ok());
+        
+        contract.set_min_deposit(authority, mint, 1_000).unwrap();
+        let result = contract.deposit_spl_token(user, mint, 999, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::BelowMinimumDeposit)));
+        assert!(contract.deposit_spl_token(user, mint, 1_000, [2u8; 20]).is_ok());
+        
+        // Repays, including batched ones, share the asset minimum
+        let result = contract.repay_spl_token(user, mint, 999, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::BelowMinimumDeposit)));
+        let items = vec![
+            RepayItem { mint, amount: 1_000, on_behalf_of: [2u8; 20] },
+            RepayItem { mint, amount: 999, on_behalf_of: [2u8; 20] },
+        ];
+        let balance_before = contract.user_balances[&(user, mint)];
+        let result = contract.repay_batch(user, &items);
+        assert!(matches!(result, Err(DepositContractError::BelowMinimumDeposit)));
+        assert_eq!(contract.user_balances[&(user, mint)], balance_before);
+        assert!(contract.repay_spl_token(user, mint, 1_000, [2u8; 20]).is_ok());
+        
+        let result = contract.set_min_deposit(Pubkey::new_unique(), mint, 0);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_deposit_cap_boundaries() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.set_deposit_cap(authority, mint, 5_000).unwrap();
+        
+        assert!(contract.deposit_spl_token(user, mint, 3_000, [2u8; 20]).is_ok());
+        
+        // One base unit over the cap is rejected without counting
+        let result = contract.deposit_spl_token(user, mint, 2_001, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::DepositCapExceeded)));
+        assert_eq!(contract.asset_configs[&mint].total_deposited, 3_000);
+        
+        // Exactly hitting the cap is allowed, after which nothing more fits
+        assert!(contract.deposit_spl_token(user, mint, 2_000, [2u8; 20]).is_ok());
+        assert_eq!(contract.asset_configs[&mint].total_deposited, 5_000);
+        let result = contract.deposit_spl_token(user, mint, 1, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::DepositCapExceeded)));
+        
+        // Repays do not count against the cap
+        let repayer = Pubkey::new_unique();
+        contract.fund_user(repayer, mint, 1_000);
+        assert!(contract.repay_spl_token(repayer, mint, 1_000, [2u8; 20]).is_ok());
+        assert_eq!(contract.asset_configs[&mint].total_deposited, 5_000);
+        
+        // Zero lifts the cap
+        contract.set_deposit_cap(authority, mint, 0).unwrap();
+        assert!(contract.deposit_spl_token(user, mint, 1, [2u8; 20]).is_ok());
+        
+        let result = contract.set_deposit_cap(Pubkey::new_unique(), mint, 1);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_debug_logging_suppresses_verbose_logs() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        map_zrc20(&mut contract, authority, [3u8; 20]);
+        
+        let user = Pubkey::new_unique();
+        assert!(contract.contract_state.as_ref().unwrap().debug_logging);
+        contract.deposit_sol_for(user, 10_000_000, [2u8; 20], user).unwrap();
+        contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]).unwrap();
+        assert!(contract.logs.iter().any(|line| line.starts_with("Gateway deposit_and_call invoked")));
+        assert!(contract.logs.iter().any(|line| line.starts_with("Gateway call invoked")));
+        
+        // Production: the program log stays quiet while events keep flowing
+        contract.set_debug_logging(authority, false).unwrap();
+        contract.logs.clear();
+        let events_before = contract.events.len();
+        contract.deposit_sol_for(user, 10_000_000, [2u8; 20], user).unwrap();
+        contract.repay_sol(user, 10_000_000, [2u8; 20], &[]).unwrap();
+        contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]).unwrap();
+        contract.withdraw_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]).unwrap();
+        assert!(contract.logs.is_empty());
+        assert!(contract.events.len() > events_before);
+        
+        let result = contract.set_debug_logging(Pubkey::new_unique(), true);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_min_sol_deposit_boundaries() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let min_sol_deposit = DEPOSIT_FEE + 1_000;
+        contract.set_min_sol_deposit(authority, min_sol_deposit).unwrap();
+        
+        // The minimum applies to the gross amount, before DEPOSIT_FEE
+        let result = contract.deposit_sol_for(user, min_sol_deposit - 1, [2u8; 20], user);
+        assert!(matches!(result, Err(DepositContractError::BelowMinimumDeposit)));
+        assert!(contract.deposit_sol_for(user, min_sol_deposit, [2u8; 20], user).is_ok());
+        
+        let result = contract.repay_sol(user, min_sol_deposit - 1, [2u8; 20], &[]);
+        assert!(matches!(result, Err(DepositContractError::BelowMinimumDeposit)));
+        assert!(contract.repay_sol(user, min_sol_deposit, [2u8; 20], &[]).is_ok());
+        
+        // Zero disables the minimum, leaving only the deposit fee check
+        contract.set_min_sol_deposit(authority, 0).unwrap();
+        assert!(contract.deposit_sol_for(user, DEPOSIT_FEE + 1, [2u8; 20], user).is_ok());
+        
+        let result = contract.set_min_sol_deposit(Pubkey::new_unique(), 0);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_borrow_message_abi_words() {
+        let user = [5u8; 32];
+        let recipient = [9u8; 20];
+        let message = MockDepositContract::create_borrow_cross_chain_message(user, 1_000_000, 421614, &recipient).unwrap();
+        
+        // 5 head words, action length + 1 data word: the 224 bytes onCall decodes
+        assert_eq!(message.len(), 7 * 32);
+        let words: Vec<&[u8]> = message.chunks(32).collect();
+        let uint = |word: &[u8]| {
+            assert!(word[..24].iter().all(|b| *b == 0));
+            u64::from_be_bytes(word[24..].try_into().unwrap())
+        };
+        
+        assert_eq!(uint(words[0]), 5 * 32); // Offset of string action
+        assert_eq!(words[1], user);
+        assert_eq!(uint(words[2]), 1_000_000);
+        assert_eq!(uint(words[3]), 421614);
+        assert_eq!(words[4][..12], [0u8; 12]); // address recipient, left-padded
+        assert_eq!(words[4][12..], recipient);
+        assert_eq!(uint(words[5]), 16); // "borrowCrossChain"
+        assert_eq!(&words[6][..16], b"borrowCrossChain");
+        assert!(words[6][16..].iter().all(|b| *b == 0));
+        
+        // Only an address fits the recipient word
+        let result = MockDepositContract::create_borrow_cross_chain_message(user, 1_000_000, 421614, &[9u8; 32]);
+        assert!(matches!(result, Err(DepositContractError::InvalidRecipientLength)));
+    }
+
+    #[test]
+    fn test_decode_legacy_and_abi_cross_chain_messages() {
+        let user = [5u8; 32];
+        let recipient = [9u8; 20];
+        let expected = CrossChainMessage {
+            action: "borrowCrossChain",
+            user,
+            amount: 1_000_000,
+            destination_chain: 421614,
+            recipient: recipient.to_vec(),
+        };
+        
+        let legacy = legacy_cross_chain_message("borrowCrossChain", user, 1_000_000, 421614, &recipient);
+        assert_eq!(decode_cross_chain_message(&legacy, true).unwrap(), expected);
+        
+        let abi = MockDepositContract::create_borrow_cross_chain_message(user, 1_000_000, 421614, &recipient).unwrap();
+        assert_eq!(decode_cross_chain_message(&abi, true).unwrap(), expected);
+        assert_eq!(decode_cross_chain_message(&abi, false).unwrap(), expected);
+        
+        // The withdraw action decodes in both formats too
+        let legacy = legacy_cross_chain_message("withdrawCrossChain", user, 7, 900, &[3u8; 20]);
+        let abi = MockDepositContract::create_withdraw_cross_chain_message(user, 7, 900, &[3u8; 20]).unwrap();
+        assert_eq!(decode_cross_chain_message(&legacy, true).unwrap(), decode_cross_chain_message(&abi, true).unwrap());
+    }
+
+    #[test]
+    fn test_legacy_messages_only_decode_during_transition() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let legacy = legacy_cross_chain_message("borrowCrossChain", [5u8; 32], 1, 421614, &[9u8; 20]);
+        let abi = MockDepositContract::create_borrow_cross_chain_message([5u8; 32], 1, 421614, &[9u8; 20]).unwrap();
+        assert_eq!(contract.decode_message(&legacy).unwrap(), contract.decode_message(&abi).unwrap());
+        
+        let result = contract.set_accept_legacy_messages(Pubkey::new_unique(), false);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        contract.set_accept_legacy_messages(authority, false).unwrap();
+        let result = contract.decode_message(&legacy);
+        assert!(matches!(result, Err(DepositContractError::LegacyMessageDisabled)));
+        assert!(contract.decode_message(&abi).is_ok());
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_cross_chain_messages() {
+        let abi = abi_encode_cross_chain("borrowCrossChain", [5u8; 32], 1, 421614, &[9u8; 20]);
+        let malformed = [
+            b"borrowCrossChain:zz:1:2:00".to_vec(),           // Non-hex user
+            b"borrowCrossChain:00:1:2".to_vec(),              // Missing field
+            abi[..abi.len() - 32].to_vec(),                   // Truncated recipient
+            abi_encode_cross_chain("repay", [5u8; 32], 1, 421614, &[9u8; 20]), // Unknown action
+            [&[1u8; 1][..], &abi[1..]].concat(),              // Offset beyond u64
+        ];
+        for payload in malformed {
+            let result = decode_cross_chain_message(&payload, true);
+            assert!(matches!(result, Err(DepositContractError::MalformedMessage)), "{:?}", payload);
+        }
+    }
+
+    #[test]
+    fn test_spl_paths_reject_non_canonical_programs() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.fund_user(user, mint, 10_000_000);
+        
+        let impostor = Pubkey::new_unique();
+        for spl_programs in [(impostor, SYSTEM_PROGRAM_ID), (ASSOCIATED_TOKEN_PROGRAM_ID, impostor)] {
+            contract.spl_programs = spl_programs;
+            let result = contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]);
+            assert!(matches!(result, Err(DepositContractError::InvalidProgramAccount)));
+            let result = contract.repay_spl_token(user, mint, 1_000_000, [2u8; 20]);
+            assert!(matches!(result, Err(DepositContractError::InvalidProgramAccount)));
+        }
+        assert_eq!(contract.user_balances[&(user, mint)], 10_000_000);
+        
+        contract.spl_programs = (ASSOCIATED_TOKEN_PROGRAM_ID, SYSTEM_PROGRAM_ID);
+        assert!(contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).is_ok());
+        assert!(contract.repay_spl_token(user, mint, 1_000_000, [2u8; 20]).is_ok());
+    }
+
+    #[test]
+    fn test_unexpected_gateway_program_rejected() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        assert_eq!(contract.contract_state.as_ref().unwrap().gateway_program_id, GATEWAY_PROGRAM_ID);
+        
+        let user = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.fund_user(user, mint, 10_000_000);
+        
+        // Any other program passed as the gateway is rejected before funds move
+        contract.gateway_program = Pubkey::new_unique();
+        let results = [
+            contract.deposit_sol(user, 10_000_000, [2u8; 20]),
+            contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]),
+            contract.repay_sol(user, 10_000_000, [2u8; 20], &[]),
+            contract.repay_spl_token(user, mint, 1_000_000, [2u8; 20]),
+            contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]),
+            contract.withdraw_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]),
+        ];
+        for result in results {
+            assert!(matches!(result, Err(DepositContractError::UnauthorizedGateway)));
+        }
+        let items = vec![RepayItem { mint, amount: 1_000_000, on_behalf_of: [2u8; 20] }];
+        let result = contract.repay_batch(user, &items);
+        assert!(matches!(result, Err(DepositContractError::UnauthorizedGateway)));
+        assert_eq!(contract.user_balances[&(user, mint)], 10_000_000);
+        assert_eq!(contract.fee_vault_lamports, 0);
+        
+        contract.gateway_program = GATEWAY_PROGRAM_ID;
+        assert!(contract.deposit_sol(user, 10_000_000, [2u8; 20]).is_ok());
+    }
+
+    #[test]
+    fn test_failover_routes_gateway_calls_to_secondary() {
+        // The failover must differ from the primary
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.secondary_gateway_program = Some(GATEWAY_PROGRAM_ID);
+        let result = contract.initialize(authority, [1u8; 20], 7001);
+        assert!(matches!(result, Err(DepositContractError::InvalidSecondaryGateway)));
+        assert!(contract.contract_state.is_none());
+        
+        // Without a secondary there is nothing to fail over to
+        contract.secondary_gateway_program = None;
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let result = contract.set_use_failover(authority, true);
+        assert!(matches!(result, Err(DepositContractError::InvalidSecondaryGateway)));
+        
+        let mut contract = MockDepositContract::new();
+        let secondary = Pubkey::new_unique();
+        contract.secondary_gateway_program = Some(secondary);
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        assert_eq!(contract.contract_state.as_ref().unwrap().secondary_gateway_program_id, secondary);
+        let result = contract.set_use_failover(Pubkey::new_unique(), true);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        
+        // Failover: only the secondary is accepted, for deposits and borrows alike
+        let user = Pubkey::new_unique();
+        contract.set_use_failover(authority, true).unwrap();
+        let result = contract.deposit_sol(user, 10_000_000, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::UnauthorizedGateway)));
+        contract.gateway_program = secondary;
+        assert!(contract.deposit_sol(user, 10_000_000, [2u8; 20]).is_ok());
+        assert!(contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]).is_ok());
+        
+        // Switching back restores the primary
+        contract.set_use_failover(authority, false).unwrap();
+        let result = contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::UnauthorizedGateway)));
+        contract.gateway_program = GATEWAY_PROGRAM_ID;
+        assert!(contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]).is_ok());
+    }
+
+    #[test]
+    fn test_initialize_strict_sets_every_required_config() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let gateway = Pubkey::new_unique();
+        let native_mint = SYSTEM_PROGRAM_ID;
+        contract
+            .initialize_strict(authority, [1u8; 20], 7001, treasury, [3u8; 20], Some(gateway), (native_mint, 9, true))
+            .unwrap();
+        
+        let state = contract.contract_state.as_ref().unwrap();
+        assert_eq!(state.treasury, treasury);
+        assert_eq!(state.gateway_program_id, gateway);
+        assert_eq!(state.deposit_revert_policy.abort_address, [3u8; 20]);
+        assert_eq!(state.call_revert_policy.abort_address, [3u8; 20]);
+        assert!(contract.asset_configs[&native_mint].is_native);
+    }
+
+    #[test]
+    fn test_initialize_strict_rejects_missing_fields() {
+        let authority = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let gateway = Some(Pubkey::new_unique());
+        let native = (SYSTEM_PROGRAM_ID, 9, true);
+        
+        let missing = [
+            (SYSTEM_PROGRAM_ID, [3u8; 20], gateway, native),              // No treasury
+            (treasury, [0u8; 20], gateway, native),                       // No abort address
+            (treasury, [3u8; 20], None, native),                          // No gateway program
+            (treasury, [3u8; 20], gateway, (native.0, 6, false)),         // No native asset
+            (treasury, [3u8; 20], gateway, (Pubkey::new_unique(), 9, true)), // Not the SOL sentinel mint
+            (treasury, [3u8; 20], gateway, (native.0, 6, true)),          // Not SOL's decimals
+        ];
+        for (treasury, abort_address, gateway, native_asset) in missing {
+            let mut contract = MockDepositContract::new();
+            let result = contract.initialize_strict(authority, [1u8; 20], 7001, treasury, abort_address, gateway, native_asset);
+            assert!(matches!(result, Err(DepositContractError::MissingStrictConfig)));
+            assert!(contract.contract_state.is_none());
+        }
+        
+        // The lenient initialize still works without any of them
+        let mut contract = MockDepositContract::new();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        assert_eq!(contract.contract_state.as_ref().unwrap().treasury, SYSTEM_PROGRAM_ID);
+    }
+
+    #[test]
+    fn test_add_supported_asset_rejects_decimal_mismatch() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let mint = contract.create_mint(6);
+        
+        let result = contract.add_supported_asset(authority, mint, 9, false);
+        assert!(matches!(result, Err(DepositContractError::DecimalMismatch)));
+        assert!(!contract.asset_configs.contains_key(&mint));
+        
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        assert_eq!(contract.asset_configs[&mint].decimals, 6);
+    }
+
+    #[test]
+    fn test_register_native_sol_matches_deposit_sol() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let result = contract.register_native_sol(Pubkey::new_unique());
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        contract.register_native_sol(authority).unwrap();
+        
+        // deposit_sol reports SOL under the same sentinel the config is keyed by
+        let user = Pubkey::new_unique();
+        contract.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap();
+        let Some(Event::DepositInitiated { asset, .. }) = contract.events.last() else {
+            panic!("deposit_sol emits DepositInitiated");
+        };
+        let config = &contract.asset_configs[asset];
+        assert_eq!(config.mint, SYSTEM_PROGRAM_ID);
+        assert_eq!(config.decimals, NATIVE_SOL_DECIMALS);
+        assert!(config.is_native && config.is_supported);
+        
+        // The SPL path refuses it, and it can only be registered once
+        let result = contract.deposit_spl_token(user, SYSTEM_PROGRAM_ID, 1_000, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::UseDepositSol)));
+        let result = contract.register_native_sol(authority);
+        assert!(matches!(result, Err(DepositContractError::AssetAlreadySupported)));
+    }
+
+    #[test]
+    fn test_zero_or_unchanged_lending_protocol_address_rejected() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        
+        let result = contract.initialize(authority, [0u8; 20], 7001);
+        assert!(matches!(result, Err(DepositContractError::InvalidLendingProtocolAddress)));
+        assert!(contract.contract_state.is_none());
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let result = contract.update_lending_protocol_address(authority, [0u8; 20], 7001);
+        assert!(matches!(result, Err(DepositContractError::InvalidLendingProtocolAddress)));
+        // Setting the address already held is a no-op and rejected
+        let result = contract.update_lending_protocol_address(authority, [1u8; 20], 7001);
+        assert!(matches!(result, Err(DepositContractError::InvalidLendingProtocolAddress)));
+        assert_eq!(contract.contract_state.as_ref().unwrap().lending_protocol_address, [1u8; 20]);
+        
+        contract.update_lending_protocol_address(authority, [2u8; 20], 7001).unwrap();
+        assert_eq!(contract.contract_state.as_ref().unwrap().lending_protocol_address, [2u8; 20]);
+    }
+
+    #[test]
+    fn test_config_snapshot_reflects_current_state() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        contract.add_supported_asset(authority, Pubkey::new_unique(), 6, false).unwrap();
+        contract.add_supported_asset(authority, Pubkey::new_unique(), 9, true).unwrap();
+        contract.set_global_borrow_cap(authority, 5_000).unwrap();
+        contract.set_pause_state(authority, true).unwrap();
+        
+        contract.emit_config_snapshot().unwrap();
+        assert_eq!(contract.events.last(), Some(&Event::ConfigSnapshot {
+            authority,
+            deposit_fee: DEPOSIT_FEE,
+            global_borrow_cap: 5_000,
+            total_outstanding_borrows: 0,
+            is_paused: true,
+            lending_protocol_address: [1u8; 20],
+            asset_count: 2,
+        }));
+        
+        // Snapshots carry no state of their own
+        contract.rotate_lending_protocol(authority, [1u8; 20], [9u8; 20], &[]).unwrap();
+        contract.emit_config_snapshot().unwrap();
+        assert!(matches!(
+            contract.events.last(),
+            Some(Event::ConfigSnapshot { lending_protocol_address, .. }) if *lending_protocol_address == [9u8; 20]
+        ));
+    }
+
+    #[test]
+    fn test_sweep_sol_dust_to_treasury() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        contract
+            .initialize_strict(authority, [1u8; 20], 7001, treasury, [3u8; 20], Some(GATEWAY_PROGRAM_ID), (SYSTEM_PROGRAM_ID, 9, true))
+            .unwrap();
+        let reserve = rent_exempt_minimum(CONTRACT_STATE_SPACE);
+        contract.contract_lamports = reserve;
+        
+        // Deposits and repays forward everything and leave the PDA at its reserve
+        let user = Pubkey::new_unique();
+        contract.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap();
+        contract.repay_sol(user, 10_000_000, [2u8; 20], &[]).unwrap();
+        let result = contract.sweep_sol_dust(authority, treasury);
+        assert!(matches!(result, Err(DepositContractError::NoDustToSweep)));
+        
+        // Dust accumulates from stray transfers
+        for dust in [1, 250, 4_000] {
+            contract.simulate_sol_transfer(dust);
+        }
+        
+        let result = contract.sweep_sol_dust(Pubkey::new_unique(), treasury);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        let result = contract.sweep_sol_dust(authority, Pubkey::new_unique());
+        assert!(matches!(result, Err(DepositContractError::InvalidTreasury)));
+        
+        // Only the dust moves; the reserve and the fee vault are untouched
+        let fee_vault_lamports = contract.fee_vault_lamports;
+        assert_eq!(contract.sweep_sol_dust(authority, treasury).unwrap(), 4_251);
+        assert_eq!(contract.contract_lamports, reserve);
+        assert_eq!(contract.fee_vault_lamports, fee_vault_lamports);
+        assert!(matches!(
+            contract.events.last(),
+            Some(Event::SolDustSwept { treasury: swept_to, amount: 4_251 }) if *swept_to == treasury
+        ));
+        
+        let result = contract.sweep_sol_dust(authority, treasury);
+        assert!(matches!(result, Err(DepositContractError::NoDustToSweep)));
+    }
+
+    #[test]
+    fn test_sweep_sol_dust_requires_treasury() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        contract.simulate_sol_transfer(rent_exempt_minimum(CONTRACT_STATE_SPACE) + 1_000);
+        
+        // Dev deployments leave the treasury unset
+        let result = contract.sweep_sol_dust(authority, SYSTEM_PROGRAM_ID);
+        assert!(matches!(result, Err(DepositContractError::InvalidTreasury)));
+    }
+
+    #[test]
+    fn test_withdraw_fees_keeps_vault_rent_exempt() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        for _ in 0..3 {
+            contract.deposit_sol(Pubkey::new_unique(), 10_000_000, [2u8; 20]).unwrap();
+        }
+        let withdrawable = 3 * DEPOSIT_FEE - rent_exempt_minimum(0);
+        
+        // Dipping into the rent reserve fails the shared PDA check; overdrawing fails first
+        let result = contract.withdraw_fees(authority, withdrawable + 1);
+        assert!(matches!(result, Err(DepositContractError::BelowRentExemption)));
+        let result = contract.withdraw_fees(authority, u64::MAX);
+        assert!(matches!(result, Err(DepositContractError::InsufficientFeeBalance)));
+        let result = contract.withdraw_fees(Pubkey::new_unique(), 1);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        
+        contract.withdraw_fees(authority, withdrawable).unwrap();
+        assert_eq!(contract.fee_vault_lamports, rent_exempt_minimum(0));
+        assert!(matches!(
+            contract.events.last(),
+            Some(Event::FeesWithdrawn { amount, remaining, .. }) if *amount == withdrawable && *remaining == rent_exempt_minimum(0)
+        ));
+    }
+
+    #[test]
+    fn test_sol_deposit_fee_is_kept_and_net_is_forwarded() {
+        let mut contract = MockDepositContract::new();
+        contract.initialize(Pubkey::new_unique(), [1u8; 20], 7001).unwrap();
+        let user = Pubkey::new_unique();
+        
+        // All fee, nothing to bridge
+        let result = contract.deposit_sol(user, DEPOSIT_FEE, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::InvalidAmount)));
+        let result = contract.repay_sol(user, DEPOSIT_FEE, [2u8; 20], &[]);
+        assert!(matches!(result, Err(DepositContractError::InvalidAmount)));
+        let result = contract.deposit_sol(user, DEPOSIT_FEE - 1, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::InsufficientDepositFee)));
+        assert_eq!(contract.fee_vault_lamports, 0);
+        
+        contract.deposit_sol(user, DEPOSIT_FEE + 1, [2u8; 20]).unwrap();
+        assert!(matches!(contract.events.last(), Some(Event::DepositInitiated { amount: 1, .. })));
+        contract.repay_sol(user, DEPOSIT_FEE + 1, [2u8; 20], &[]).unwrap();
+        assert!(matches!(contract.events.last(), Some(Event::RepayInitiated { amount: 1, .. })));
+        assert_eq!(contract.fee_vault_lamports, 2 * DEPOSIT_FEE);
+    }
+
+    #[test]
+    fn test_sol_split_accounts_fee_and_forwarded_amount() {
+        let mut contract = MockDepositContract::new();
+        contract.initialize(Pubkey::new_unique(), [1u8; 20], 7001).unwrap();
+        let user = Pubkey::new_unique();
+        
+        let amount = 10_000_000;
+        contract.deposit_sol(user, amount, [2u8; 20]).unwrap();
+        let state = contract.contract_state.as_ref().unwrap();
+        assert_eq!(state.total_sol_forwarded, amount - DEPOSIT_FEE);
+        assert_eq!(state.accrued_fees, DEPOSIT_FEE);
+        assert_eq!(contract.fee_vault_lamports, state.accrued_fees);
+        
+        // Repays split the same way; a rejected deposit books nothing
+        contract.repay_sol(user, 3_000_000, [2u8; 20], &[]).unwrap();
+        let _ = contract.deposit_sol(user, DEPOSIT_FEE, [2u8; 20]);
+        let state = contract.contract_state.as_ref().unwrap();
+        assert_eq!(state.total_sol_forwarded, amount + 3_000_000 - 2 * DEPOSIT_FEE);
+        assert_eq!(state.accrued_fees, 2 * DEPOSIT_FEE);
+        assert_eq!(state.total_sol_forwarded + state.accrued_fees, amount + 3_000_000);
+    }
+
+    #[test]
+    fn test_fee_accrued_matches_deducted_fee() {
+        let mut contract = MockDepositContract::new();
+        contract.initialize(Pubkey::new_unique(), [1u8; 20], 7001).unwrap();
+        let user = Pubkey::new_unique();
+        let amount = 10_000_000;
+        
+        contract.deposit_sol(user, amount, [2u8; 20]).unwrap();
+        contract.repay_sol(user, amount, [2u8; 20], &[]).unwrap();
+        contract.deposit_sol_for(user, amount, [2u8; 20], Pubkey::new_unique()).unwrap();
+        
+        let fees: Vec<_> = contract.events.iter().filter_map(|event| match event {
+            Event::FeeAccrued { asset, amount, operation, recipient } if *recipient == fee_vault_address() => Some((*asset, *amount, *operation)),
+            _ => None,
+        }).collect();
+        assert_eq!(fees, vec![
+            (SYSTEM_PROGRAM_ID, DEPOSIT_FEE, FeeOperation::Deposit),
+            (SYSTEM_PROGRAM_ID, DEPOSIT_FEE, FeeOperation::Repay),
+            (SYSTEM_PROGRAM_ID, DEPOSIT_FEE, FeeOperation::Deposit),
+        ]);
+        
+        // Each fee plus the forwarded amount adds back up to what the user sent
+        for event in &contract.events {
+            if let Event::DepositInitiated { amount: net, .. } | Event::RepayInitiated { amount: net, .. } = event {
+                assert_eq!(net + DEPOSIT_FEE, amount);
+            }
+        }
+        assert_eq!(contract.fee_vault_lamports, fees.iter().map(|(_, fee, _)| fee).sum::<u64>());
+        
+        // Rejected deposits accrue nothing
+        contract.events.clear();
+        let _ = contract.deposit_sol(user, DEPOSIT_FEE, [2u8; 20]);
+        assert!(contract.events.is_empty());
+    }
+
+    #[test]
+    fn test_asset_fee_recipient_receives_fees() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let user = Pubkey::new_unique();
+        let partner = Pubkey::new_unique();
+        
+        // No override while native SOL is unregistered
+        contract.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap();
+        assert_eq!(contract.fee_vault_lamports, DEPOSIT_FEE);
+        
+        contract.register_native_sol(authority).unwrap();
+        let result = contract.set_asset_fee_recipient(Pubkey::new_unique(), SYSTEM_PROGRAM_ID, partner);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        contract.set_asset_fee_recipient(authority, SYSTEM_PROGRAM_ID, partner).unwrap();
+        
+        // The fee recipient account must be passed and be the configured one
+        let result = contract.deposit_sol(user, 10_000_000, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::InvalidFeeRecipient)));
+        contract.fee_recipient = Some(Pubkey::new_unique());
+        let result = contract.repay_sol(user, 10_000_000, [2u8; 20], &[]);
+        assert!(matches!(result, Err(DepositContractError::InvalidFeeRecipient)));
+        
+        contract.fee_recipient = Some(partner);
+        contract.events.clear();
+        contract.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap();
+        contract.repay_sol(user, 10_000_000, [2u8; 20], &[]).unwrap();
+        contract.deposit_sol_for(user, 10_000_000, [2u8; 20], Pubkey::new_unique()).unwrap();
+        assert_eq!(contract.fee_recipient_lamports[&partner], 3 * DEPOSIT_FEE);
+        assert_eq!(contract.fee_vault_lamports, DEPOSIT_FEE);
+        let recipients: Vec<_> = contract.events.iter().filter_map(|event| match event {
+            Event::FeeAccrued { recipient, .. } => Some(*recipient),
+            _ => None,
+        }).collect();
+        assert_eq!(recipients, vec![partner; 3]);
+        
+        // Resetting to default routes fees back to the vault
+        contract.set_asset_fee_recipient(authority, SYSTEM_PROGRAM_ID, SYSTEM_PROGRAM_ID).unwrap();
+        contract.fee_recipient = None;
+        contract.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap();
+        assert_eq!(contract.fee_vault_lamports, 2 * DEPOSIT_FEE);
+        assert_eq!(contract.fee_recipient_lamports[&partner], 3 * DEPOSIT_FEE);
+    }
+
+    #[test]
+    fn test_depositor_whitelist_is_opt_in() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        // Off by default: anyone may deposit
+        assert!(contract.deposit_sol(Pubkey::new_unique(), 10_000_000, [2u8; 20]).is_ok());
+        
+        let result = contract.set_require_depositor_whitelist(Pubkey::new_unique(), true);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        let result = contract.add_whitelisted_depositor(Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_depositor_whitelist_gates_sol_and_spl_deposits() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.set_require_depositor_whitelist(authority, true).unwrap();
+        
+        let listed = Pubkey::new_unique();
+        let unlisted = Pubkey::new_unique();
+        contract.add_whitelisted_depositor(authority, listed).unwrap();
+        
+        assert!(contract.deposit_sol(listed, 10_000_000, [2u8; 20]).is_ok());
+        assert!(contract.deposit_spl_token(listed, mint, 1_000_000, [2u8; 20]).is_ok());
+        
+        let result = contract.deposit_sol(unlisted, 10_000_000, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::DepositorNotWhitelisted)));
+        let result = contract.deposit_spl_token(unlisted, mint, 1_000_000, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::DepositorNotWhitelisted)));
+        
+        // A sponsored deposit is gated on the sponsor who funds it
+        let result = contract.deposit_sol_for(unlisted, 10_000_000, [2u8; 20], listed);
+        assert!(matches!(result, Err(DepositContractError::DepositorNotWhitelisted)));
+        assert!(contract.deposit_sol_for(listed, 10_000_000, [2u8; 20], unlisted).is_ok());
+        
+        contract.remove_whitelisted_depositor(authority, listed).unwrap();
+        let result = contract.deposit_sol(listed, 10_000_000, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::DepositorNotWhitelisted)));
+    }
+
+    #[test]
+    fn test_rotate_lending_protocol_updates_matching_routes_atomically() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let old_protocol = [1u8; 20];
+        let new_protocol = [2u8; 20];
+        let other_protocol = [3u8; 20];
+        contract.initialize(authority, old_protocol, 7001).unwrap();
+        
+        let mints: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        for mint in &mints {
+            contract.add_supported_asset(authority, *mint, 6, false).unwrap();
+        }
+        contract.set_asset_lending_protocol(authority, mints[0], old_protocol).unwrap();
+        contract.set_asset_lending_protocol(authority, mints[1], old_protocol).unwrap();
+        contract.set_asset_lending_protocol(authority, mints[2], other_protocol).unwrap();
+        // mints[3] keeps the zero override and follows the default
+        
+        contract.rotate_lending_protocol(authority, old_protocol, new_protocol, &mints).unwrap();
+        
+        let route = |contract: &MockDepositContract, mint: &Pubkey| contract.asset_configs[mint].lending_protocol_address;
+        assert_eq!(route(&contract, &mints[0]), new_protocol);
+        assert_eq!(route(&contract, &mints[1]), new_protocol);
+        assert_eq!(route(&contract, &mints[2]), other_protocol);
+        assert_eq!(route(&contract, &mints[3]), [0u8; 20]);
+        let state = contract.contract_state.as_ref().unwrap();
+        assert_eq!(state.lending_protocol_address, new_protocol);
+        assert_eq!(MockDepositContract::resolve_lending_protocol(state, &contract.asset_configs[&mints[3]]), new_protocol);
+        
+        // One event for the whole rotation
+        let rotations: Vec<_> = contract.events.iter().filter(|e| matches!(e, Event::LendingProtocolRotated { .. })).collect();
+        assert_eq!(rotations.len(), 1);
+        assert!(matches!(
+            rotations[0],
+            Event::LendingProtocolRotated { default_rotated: true, routes_rotated: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_rotate_lending_protocol_rejects_without_partial_writes() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let old_protocol = [1u8; 20];
+        contract.initialize(authority, old_protocol, 7001).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.set_asset_lending_protocol(authority, mint, old_protocol).unwrap();
+        
+        // An unknown route aborts the rotation before the known one is touched
+        let result = contract.rotate_lending_protocol(authority, old_protocol, [2u8; 20], &[mint, Pubkey::new_unique()]);
+        assert!(matches!(result, Err(DepositContractError::UnsupportedAsset)));
+        assert_eq!(contract.asset_configs[&mint].lending_protocol_address, old_protocol);
+        assert_eq!(contract.contract_state.as_ref().unwrap().lending_protocol_address, old_protocol);
+        
+        for new_address in [[0u8; 20], old_protocol] {
+            let result = contract.rotate_lending_protocol(authority, old_protocol, new_address, &[mint]);
+            assert!(matches!(result, Err(DepositContractError::InvalidLendingProtocolAddress)));
+        }
+        
+        let result = contract.rotate_lending_protocol(Pubkey::new_unique(), old_protocol, [2u8; 20], &[mint]);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_gateway_cpi_signer_seeds_derive_contract_state() {
+        let mut contract = MockDepositContract::new();
+        contract.initialize(Pubkey::new_unique(), [1u8; 20], 7001).unwrap();
+        let stored_bump = contract.contract_state.as_ref().unwrap().bump;
+        
+        let bump = [stored_bump];
+        let seeds = contract_state_signer_seeds(&bump);
+        assert_eq!(seeds[0], b"contract_state");
+        assert_eq!(seeds[1], &[stored_bump]);
+        assert_eq!(create_program_address(&seeds), contract_state_pda());
+    }
+
+    #[test]
+    fn test_gateway_cpi_rejects_seeds_with_wrong_bump() {
+        let mut contract = MockDepositContract::new();
+        contract.initialize(Pubkey::new_unique(), [1u8; 20], 7001).unwrap();
+        let stored_bump = contract.contract_state.as_ref().unwrap().bump;
+        
+        // The seeds slice handed to invoke_signed must derive the signing PDA
+        let bump = [stored_bump];
+        assert!(check_signer_seeds(contract_state_pda(), &contract_state_signer_seeds(&bump)).is_ok());
+        
+        let wrong_bump = [stored_bump.wrapping_sub(1)];
+        let result = check_signer_seeds(contract_state_pda(), &contract_state_signer_seeds(&wrong_bump));
+        assert!(matches!(result, Err(DepositContractError::InvalidSignerSeeds)));
+        let result = check_signer_seeds(Pubkey::new_unique(), &contract_state_signer_seeds(&bump));
+        assert!(matches!(result, Err(DepositContractError::InvalidSignerSeeds)));
+    }
+
+    #[test]
+    fn test_forward_from_vault_rejects_short_balance() {
+        let mut vault_balance = 999;
+        let result = forward_from_vault(&mut vault_balance, 1_000);
+        assert!(matches!(result, Err(DepositContractError::InsufficientVaultBalance)));
+        assert_eq!(vault_balance, 999);
+        
+        forward_from_vault(&mut vault_balance, 999).unwrap();
+        assert_eq!(vault_balance, 0);
+    }
+
+    #[test]
+    fn test_normalize_amount_boundaries_at_each_decimals() {
+        for decimals in 0..=NORMALIZED_DECIMALS {
+            let scale = 10u128.pow(u32::from(NORMALIZED_DECIMALS - decimals));
+            assert_eq!(normalize_amount(1, decimals).unwrap(), scale);
+            assert_eq!(normalize_amount(u64::MAX, decimals).unwrap(), u128::from(u64::MAX) * scale);
+        }
+        
+        // Above NORMALIZED_DECIMALS the smallest representable amount is one step
+        for decimals in NORMALIZED_DECIMALS + 1..=NORMALIZED_DECIMALS + 19 {
+            let step = normalization_step(decimals).unwrap();
+            assert!(matches!(normalize_amount(step - 1, decimals), Err(DepositContractError::InvalidAmount)));
+            assert_eq!(normalize_amount(step, decimals).unwrap(), 1);
+            assert_eq!(normalize_amount(u64::MAX, decimals).unwrap(), u128::from(u64::MAX / step));
+        }
+        
+        // Beyond u64 scale no amount survives
+        assert!(matches!(normalize_amount(u64::MAX, NORMALIZED_DECIMALS + 20), Err(DepositContractError::InvalidAmount)));
+        assert!(matches!(normalize_amount(u64::MAX, u8::MAX), Err(DepositContractError::InvalidAmount)));
+    }
+
+    #[test]
+    fn test_deposit_rejects_amount_below_normalized_precision() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 20, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let result = contract.deposit_spl_token(user, mint, 99, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::InvalidAmount)));
+        assert!(contract.deposit_spl_token(user, mint, 100, [2u8; 20]).is_ok());
+    }
+
+    #[test]
+    fn test_deposit_emits_per_asset_lending_protocol() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let default_protocol = [1u8; 20];
+        contract.initialize(authority, default_protocol, 7001).unwrap();
+        
+        let routed_mint = Pubkey::new_unique();
+        let default_mint = Pubkey::new_unique();
+        for mint in [routed_mint, default_mint] {
+            contract.add_supported_asset(authority, mint, 6, false).unwrap();
+            contract.create_asset_vault(authority, mint).unwrap();
+        }
+        let routed_protocol = [9u8; 20];
+        contract.set_asset_lending_protocol(authority, routed_mint, routed_protocol).unwrap();
+        
+        let user = Pubkey::new_unique();
+        contract.deposit_spl_token(user, routed_mint, 1_000_000, [2u8; 20]).unwrap();
+        contract.deposit_spl_token(user, default_mint, 1_000_000, [2u8; 20]).unwrap();
+        
+        let receivers: Vec<([u8; 20], Pubkey)> = contract.events.iter().filter_map(|event| match event {
+            Event::DepositInitiated { asset, lending_protocol, .. } => Some((*lending_protocol, *asset)),
+            _ => None,
+        }).collect();
+        assert_eq!(receivers, vec![(routed_protocol, routed_mint), (default_protocol, default_mint)]);
+    }
+
+    #[test]
+    fn test_reconcile_spl_balanced() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        
+        let user = Pubkey::new_unique();
+        contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).unwrap();
+        contract.deposit_spl_token(user, mint, 2_500_000, [2u8; 20]).unwrap();
+        
+        let asset_config = contract.asset_configs.get(&mint).unwrap();
+        assert_eq!(asset_config.total_received, 3_500_000);
+        assert_eq!(asset_config.total_forwarded, 3_500_000);
+        assert_eq!(contract.reconcile_spl(mint).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_reconcile_spl_detects_stuck_tokens() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        
+        let user = Pubkey::new_unique();
+        contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).unwrap();
+        
+        // Tokens sent straight to the vault are never forwarded
+        contract.simulate_vault_transfer(mint, 42);
+        assert_eq!(contract.reconcile_spl(mint).unwrap(), 42);
+    }
+
+    #[test]
+    fn test_repay_disabled_asset() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.fund_user(user, mint, 10_000_000);
+        assert!(contract.asset_configs[&mint].repay_enabled);
+        
+        contract.set_repay_enabled(authority, mint, false).unwrap();
+        let result = contract.repay_spl_token(user, mint, 1_000_000, [2u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::RepayDisabled)));
+        
+        // Batches are blocked as well
+        let items = vec![RepayItem { mint, amount: 1_000_000, on_behalf_of: [2u8; 20] }];
+        let result = contract.repay_batch(user, &items);
+        assert!(matches!(result, Err(DepositContractError::RepayDisabled)));
+        assert_eq!(contract.user_balances[&(user, mint)], 10_000_000);
+        
+        contract.set_repay_enabled(authority, mint, true).unwrap();
+        assert!(contract.repay_spl_token(user, mint, 1_000_000, [2u8; 20]).is_ok());
+        
+        // SOL repays follow native SOL's config once it is registered
+        assert!(contract.repay_sol(user, 10_000_000, [2u8; 20], &[]).is_ok());
+        contract.register_native_sol(authority).unwrap();
+        contract.set_repay_enabled(authority, SYSTEM_PROGRAM_ID, false).unwrap();
+        let fee_vault_lamports = contract.fee_vault_lamports;
+        let result = contract.repay_sol(user, 10_000_000, [2u8; 20], &[]);
+        assert!(matches!(result, Err(DepositContractError::RepayDisabled)));
+        assert_eq!(contract.fee_vault_lamports, fee_vault_lamports);
+        contract.set_repay_enabled(authority, SYSTEM_PROGRAM_ID, true).unwrap();
+        assert!(contract.repay_sol(user, 10_000_000, [2u8; 20], &[]).is_ok());
+    }
+
+    #[test]
+    fn test_repay_forwards_received_amount_for_fee_token() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.fund_user(user, mint, 10_000_000);
+        contract.transfer_fee_bps.insert(mint, 100); // 1% transfer fee
+        
+        contract.repay_spl_token(user, mint, 1_000_000, [2u8; 20]).unwrap();
+        
+        // Requested 1_000_000, the vault only received 990_000
+        let received = 990_000;
+        assert_eq!(contract.asset_configs[&mint].total_received, received);
+        assert_eq!(contract.asset_configs[&mint].total_forwarded, received);
+        assert_eq!(contract.reconcile_spl(mint).unwrap(), 0);
+        assert_eq!(contract.events.last(), Some(&Event::RepayInitiated {
+            user,
+            asset: mint,
+            amount: received,
+            on_behalf_of: [2u8; 20],
+        }));
+    }
+
+    #[test]
+    fn test_deposit_credits_received_amount_for_fee_token() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.transfer_fee_bps.insert(mint, 100); // 1% transfer fee
+        
+        let message = contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).unwrap();
+        
+        // Requested 1_000_000, the vault only received 990_000: that is what is
+        // credited as collateral, forwarded and reported
+        let received = 990_000;
+        assert_eq!(contract.collateral_positions[&(user, mint)].amount, received);
+        assert_eq!(contract.asset_configs[&mint].total_received, received);
+        assert_eq!(contract.asset_configs[&mint].total_forwarded, received);
+        assert_eq!(contract.reconcile_spl(mint).unwrap(), 0);
+        assert!(matches!(
+            contract.events.last(),
+            Some(Event::DepositInitiated { amount, .. }) if *amount == received
+        ));
+        assert!(!message.is_empty());
+    }
+
+    #[test]
+    fn test_repay_batch_three_assets() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let mints: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        for mint in &mints {
+            contract.add_supported_asset(authority, *mint, 6, false).unwrap();
+            contract.fund_user(user, *mint, 10_000_000);
+        }
+        
+        let items: Vec<RepayItem> = mints.iter().enumerate().map(|(i, mint)| RepayItem {
+            mint: *mint,
+            amount: 1_000_000 * (i as u64 + 1),
+            on_behalf_of: [2u8; 20],
+        }).collect();
+        
+        let messages = contract.repay_batch(user, &items).unwrap();
+        assert_eq!(messages.len(), 3);
+        for (item, message) in items.iter().zip(&messages) {
+            assert_eq!(message.len(), 128);
+            assert_eq!(&message[96..101], b"repay");
+            assert_eq!(contract.asset_configs[&item.mint].total_forwarded, item.amount);
+            assert_eq!(contract.user_balances[&(user, item.mint)], 10_000_000 - item.amount);
+        }
+    }
+
+    #[test]
+    fn test_repay_batch_size_limit() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let mints: Vec<Pubkey> = (0..MAX_REPAY_BATCH_SIZE + 1).map(|_| Pubkey::new_unique()).collect();
+        for mint in &mints {
+            contract.add_supported_asset(authority, *mint, 6, false).unwrap();
+            contract.fund_user(user, *mint, 10_000_000);
+        }
+        let items: Vec<RepayItem> = mints.iter().map(|mint| RepayItem {
+            mint: *mint,
+            amount: 1_000_000,
+            on_behalf_of: [2u8; 20],
+        }).collect();
+        
+        // Just over the cap is rejected before anything moves
+        let result = contract.repay_batch(user, &items);
+        assert!(matches!(result, Err(DepositContractError::BatchTooLarge)));
+        assert!(contract.events.is_empty());
+        
+        // Exactly at the cap succeeds
+        let messages = contract.repay_batch(user, &items[..MAX_REPAY_BATCH_SIZE]).unwrap();
+        assert_eq!(messages.len(), MAX_REPAY_BATCH_SIZE);
+    }
+
+    #[test]
+    fn test_repay_batch_mid_batch_failure_rolls_back() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let mints: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        for mint in &mints {
+            contract.add_supported_asset(authority, *mint, 6, false).unwrap();
+            contract.fund_user(user, *mint, 1_000_000);
+        }
+        
+        // Second item exceeds the user's balance
+        let items = vec![
+            RepayItem { mint: mints[0], amount: 500_000, on_behalf_of: [2u8; 20] },
+            RepayItem { mint: mints[1], amount: 5_000_000, on_behalf_of: [2u8; 20] },
+            RepayItem { mint: mints[2], amount: 500_000, on_behalf_of: [2u8; 20] },
+        ];
+        
+        let result = contract.repay_batch(user, &items);
+        assert!(matches!(result, Err(DepositContractError::InsufficientBalance)));
+        
+        // First item's transfer and accounting were rolled back too
+        for mint in &mints {
+            assert_eq!(contract.user_balances[&(user, *mint)], 1_000_000);
+            assert_eq!(contract.asset_configs[mint].total_received, 0);
+            assert_eq!(contract.asset_configs[mint].total_forwarded, 0);
+        }
+    }
+
+    #[test]
+    fn test_deposit_unsupported_asset() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let lending_protocol_address = [1u8; 20];
+        let zeta_chain_id = 7001;
+        
+        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        
+        let mint = Pubkey::new_unique(); // Not added as supported asset
+        let user = Pubkey::new_unique();
+        let amount = 1_000_000;
+        let on_behalf_of = [2u8; 20];
+        
+        let result = contract.deposit_spl_token(user, mint, amount, on_behalf_of);
+        assert!(matches!(result, Err(DepositContractError::UnsupportedAsset)));
+    }
+
+    #[test]
+    fn test_deposit_native_asset_with_spl_function() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let lending_protocol_address = [1u8; 20];
+        let zeta_chain_id = 7001;
+        
+        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 9, true).unwrap(); // Native asset
+        
+        let user = Pubkey::new_unique();
+        let amount = 1_000_000;
+        let on_behalf_of = [2u8; 20];
+        
+        let result = contract.deposit_spl_token(user, mint, amount, on_behalf_of);
+        assert!(matches!(result, Err(DepositContractError::UseDepositSol)));
+    }
+
+    #[test]
+    fn test_borrow_cross_chain_success() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let lending_protocol_address = [1u8; 20];
+        let zeta_chain_id = 7001;
+        
+        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let asset = [3u8; 20]; // ZRC-20 token address
+        let amount = 1_000_000;
+        let destination_chain = 421614; // Arbitrum Sepolia
+        let recipient = [4u8; 20]; // Arbitrum address
+        
+        let result = contract.borrow_cross_chain(user, asset, amount, destination_chain, recipient);
+        assert!(result.is_ok());
+        
+        let message = result.unwrap();
+        assert!(!message.is_empty());
+        
+        // Verify message contains expected data
+        let message = decode_cross_chain_message(&message, false).unwrap();
+        assert_eq!(message.action, "borrowCrossChain");
+        assert_eq!(message.amount, amount);
+        assert_eq!(message.destination_chain, destination_chain);
+    }
+
+    #[test]
+    fn test_cross_chain_actions_are_distinguishable() {
+        let user = Pubkey::new_unique().to_bytes();
+        let borrow = MockDepositContract::create_borrow_cross_chain_message(user, 1_000_000, 421614, &[4u8; 20]).unwrap();
+        let withdraw = MockDepositContract::create_withdraw_cross_chain_message(user, 1_000_000, 421614, &[4u8; 20]).unwrap();
+        
+        // Full action names survive the round trip; nothing is truncated to a fixed width
+        let borrow = decode_cross_chain_message(&borrow, false).unwrap();
+        let withdraw = decode_cross_chain_message(&withdraw, false).unwrap();
+        assert_eq!(borrow.action, "borrowCrossChain");
+        assert_eq!(withdraw.action, "withdrawCrossChain");
+        assert_eq!(withdraw.action.len(), 18);
+        
+        // Identical parameters only differ by action
+        assert_eq!(withdraw, CrossChainMessage {
+            action: "withdrawCrossChain",
+            user,
+            amount: 1_000_000,
+            destination_chain: 421614,
+            recipient: vec![4u8; 20],
+        });
+        assert_eq!(CrossChainMessage { action: withdraw.action, ..borrow }, withdraw);
+    }
+
+    // Vectors below are from Solidity's abi.encode, as 32-byte hex words
+    #[test]
+    fn test_abi_primitives_match_known_vectors() {
+        assert_eq!(abi::encode_u256(0), abi_words(&["0"]));
+        assert_eq!(abi::encode_u256(1_000_000), abi_words(&["f4240"]));
+        assert_eq!(abi::encode_u256(u64::MAX), abi_words(&["ffffffffffffffff"]));
+        
+        let address: [u8; 20] = hex::decode("5fbdb2315678afecb367f032d93f642f64180aa3").unwrap().try_into().unwrap();
+        assert_eq!(abi::encode_address(&address), abi_words(&["5fbdb2315678afecb367f032d93f642f64180aa3"]));
+        
+        assert_eq!(abi::encode_bytes32(&[0xab; 32]), vec![0xab; 32]);
+        
+        // Length word, then data right-padded to whole words
+        assert_eq!(abi::encode_string(""), abi_words(&["0"]));
+        assert_eq!(abi::encode_string("supply"), abi_words(&["6", "737570706c790000000000000000000000000000000000000000000000000000"]));
+        assert_eq!(abi::encode_bytes(&[0x11; 33]).len(), 3 * 32);
+        assert_eq!(abi::encode_bytes(&[0x11; 32]).len(), 2 * 32);
+    }
+
+    #[test]
+    fn test_abi_encoder_sizes_oversized_action() {
+        let address = [0x11u8; 20];
+        // 100-byte action: the tail grows to a length word plus four data words instead of
+        // being cut back to the 128 bytes a "supply" message takes
+        let action = "x".repeat(100);
+        let encoder = abi::Encoder::new(2).dynamic(abi::encode_string(&action)).word(abi::encode_address(&address));
+        let encoded = encoder.finish().unwrap();
+        assert_eq!(encoded.len(), 224);
+        assert_eq!(&encoded[64..96], abi::encode_u256(100).as_slice());
+        assert_eq!(&encoded[96..196], action.as_bytes());
+        assert_eq!(&encoded[44..64], &address);
+
+        // A field beyond the declared head would shift every offset: reject it
+        let overfull = abi::Encoder::new(1).dynamic(abi::encode_string(&action)).word(abi::encode_address(&address));
+        assert_eq!(overfull.finish(), Err(DepositContractError::EncodingError));
+        let underfilled = abi::Encoder::new(2).dynamic(abi::encode_string(&action));
+        assert_eq!(underfilled.finish(), Err(DepositContractError::EncodingError));
+        
+        // So would a tail not padded to whole words
+        let unpadded = abi::Encoder::new(1).dynamic(action.as_bytes().to_vec());
+        assert_eq!(unpadded.finish(), Err(DepositContractError::EncodingError));
+    }
+
+    #[test]
+    fn test_abi_encoder_lays_out_head_and_tail() {
+        // abi.encode("supply", 0x5FbDB2315678afecb367f032d93F642f64180aa3)
+        let address: [u8; 20] = hex::decode("5fbdb2315678afecb367f032d93f642f64180aa3").unwrap().try_into().unwrap();
+        let supply = abi_words(&[
+            "40",
+            "5fbdb2315678afecb367f032d93f642f64180aa3",
+            "6",
+            "737570706c790000000000000000000000000000000000000000000000000000",
+        ]);
+        assert_eq!(supply_message(address), supply);
+        assert_abi_layout(&MockDepositContract::create_supply_message(address).unwrap(), &supply);
+        
+        // abi.encode(string "a", bytes 0x0102): each offset points past the previous tail
+        let encoded = abi::Encoder::new(2).dynamic(abi::encode_string("a")).dynamic(abi::encode_bytes(&[1, 2])).finish().unwrap();
+        assert_eq!(encoded, abi_words(&[
+            "40",
+            "80",
+            "1",
+            "6100000000000000000000000000000000000000000000000000000000000000",
+            "2",
+            "0102000000000000000000000000000000000000000000000000000000000000",
+        ]));
+    }
+
+    #[test]
+    fn test_messages_match_shared_layouts() {
+        // The same layouts test_connected.rs feeds to on_call's decoder
+        for on_behalf_of in [[0u8; 20], [0xffu8; 20], Pubkey::new_unique().to_bytes()[12..].try_into().unwrap()] {
+            assert_abi_layout(&MockDepositContract::create_supply_message(on_behalf_of).unwrap(), &supply_message(on_behalf_of));
+            assert_abi_layout(&MockDepositContract::create_repay_message(on_behalf_of).unwrap(), &repay_message(on_behalf_of));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "ABI word 1 differs")]
+    fn test_assert_abi_layout_names_first_differing_word() {
+        // A regression that encodes the wrong beneficiary is caught at the address word
+        assert_abi_layout(&MockDepositContract::create_supply_message([1u8; 20]).unwrap(), &supply_message([2u8; 20]));
+    }
+
+    #[test]
+    fn test_withdraw_message_abi_encodes_address_recipient() {
+        let user = Pubkey::new_unique().to_bytes();
+        let recipient = [0xabu8; 20];
+        let message = MockDepositContract::create_withdraw_cross_chain_message(user, u64::MAX, 421614, &recipient).unwrap();
+        
+        // Head (5 words) + action length word + one word of action data; no recipient tail
+        assert!(message.len().is_multiple_of(32));
+        assert_eq!(message.len(), 7 * 32);
+        
+        // Recipient word: 12 zero bytes, then the address
+        assert_eq!(message[128..140], [0u8; 12]);
+        assert_eq!(message[140..160], recipient);
+        
+        // u64 amount as a big-endian uint256
+        assert_eq!(message[64..88], [0u8; 24]);
+        assert_eq!(message[88..96], u64::MAX.to_be_bytes());
+        
+        let decoded = decode_cross_chain_message(&message, false).unwrap();
+        assert_eq!(decoded.recipient, recipient);
+        assert_eq!(decoded.amount, u64::MAX);
+        
+        // Non-address recipients cannot be encoded as an address
+        let result = MockDepositContract::create_withdraw_cross_chain_message(user, 1, 421614, &[0xabu8; 32]);
+        assert!(matches!(result, Err(DepositContractError::InvalidRecipientLength)));
+    }
+
+    #[test]
+    fn test_caller_cannot_swap_cross_chain_action() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        contract.set_chain_config(authority, 900, EVM_ADDRESS_LEN).unwrap();
+        map_zrc20(&mut contract, authority, [3u8; 20]);
+        
+        // Recipients spelling out the other action or a delimiter are just address bytes
+        let mut crafted = [0u8; 20];
+        crafted[..19].copy_from_slice(b"withdrawCrossChain:");
+        let user = Pubkey::new_unique();
+        
+        let borrow = contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 900, crafted).unwrap();
+        assert_eq!(cross_chain_action(&borrow), b"borrowCrossChain");
+        assert_eq!(decode_cross_chain_message(&borrow, false).unwrap().recipient, crafted);
+        
+        let mut crafted = [0u8; 20];
+        crafted[..17].copy_from_slice(b"borrowCrossChain:");
+        let withdraw = contract.withdraw_cross_chain(user, [3u8; 20], 1_000_000, 900, crafted).unwrap();
+        assert_eq!(cross_chain_action(&withdraw), b"withdrawCrossChain");
+        assert_eq!(decode_cross_chain_message(&withdraw, false).unwrap().recipient, crafted);
+    }
+
+    #[test]
+    fn test_borrow_cross_chain_invalid_destination() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        let lending_protocol_address = [1u8; 20];
+        let zeta_chain_id = 7001;
+        
+        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        
+        let user = Pubkey::new_unique();
+        let asset = [3u8; 20];
+        let amount = 1_000_000;
+        let invalid_destination_chain = 1; // Invalid chain
+        let recipient = [4u8; 20];
+        
+        let result = contract.borrow_cross_chain(user, asset, amount, invalid_destination_chain, recipient);
+        assert!(matches!(result, Err(DepositContractError::InvalidChainId)));
+    }
+
+    #[test]
+    fn test_price_staleness_window() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let now = 1_700_000_000;
+        assert!(contract.check_price_fresh(now - 5, now).is_ok());
+        assert!(contract.check_price_fresh(now - DEFAULT_MAX_PRICE_AGE, now).is_ok());
+        let result = contract.check_price_fresh(now - DEFAULT_MAX_PRICE_AGE - 1, now);
+        assert!(matches!(result, Err(DepositContractError::StalePrice)));
+        
+        // Widening the window accepts the same price
+        contract.set_max_price_age(authority, 300).unwrap();
+        assert!(contract.check_price_fresh(now - DEFAULT_MAX_PRICE_AGE - 1, now).is_ok());
+        
+        let result = contract.set_max_price_age(authority, 0);
+        assert!(matches!(result, Err(DepositContractError::InvalidAmount)));
+        let result = contract.set_max_price_age(Pubkey::new_unique(), 10);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_global_borrow_cap() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let result = contract.set_global_borrow_cap(Pubkey::new_unique(), 1);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        contract.set_global_borrow_cap(authority, 3 * 10u128.pow(18)).unwrap();
+        let (usdc, eth) = ([3u8; 20], [5u8; 20]);
+        contract.set_zrc20_decimals(authority, usdc, 6).unwrap();
+        contract.set_zrc20_decimals(authority, eth, 18).unwrap();
+        
+        // Drive outstanding borrows exactly to the cap; differently scaled ZRC-20s add up
+        // in NORMALIZED_DECIMALS units, not raw amounts
+        let user = Pubkey::new_unique();
+        contract.borrow_cross_chain(user, usdc, 2_000_000, 421614, &[4u8; 20]).unwrap();
+        contract.borrow_cross_chain(user, eth, 10u64.pow(18), 421614, &[4u8; 20]).unwrap();
+        assert_eq!(contract.contract_state.as_ref().unwrap().total_outstanding_borrows, 3 * 10u128.pow(18));
+        
+        let result = contract.borrow_cross_chain(user, usdc, 1, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::GlobalBorrowCapExceeded)));
+        assert!(matches!(contract.can_borrow(user, usdc, 1, 421614, [4u8; 20]), Some(DepositContractError::GlobalBorrowCapExceeded)));
+        assert_eq!(contract.contract_state.as_ref().unwrap().total_outstanding_borrows, 3 * 10u128.pow(18));
+    }
+
+    #[test]
+    fn test_zrc20_borrow_cap_boundary() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let (usdc, eth) = ([3u8; 20], [5u8; 20]);
+        
+        let result = contract.set_zrc20_borrow_cap(Pubkey::new_unique(), usdc, 1);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        contract.set_zrc20_borrow_cap(authority, usdc, 3_000_000).unwrap();
+        
+        // Exactly the cap is allowed, one more unit is not
+        let user = Pubkey::new_unique();
+        contract.borrow_cross_chain(user, usdc, 2_999_999, 421614, [4u8; 20]).unwrap();
+        contract.borrow_cross_chain(user, usdc, 1, 421614, [4u8; 20]).unwrap();
+        let result = contract.borrow_cross_chain(user, usdc, 1, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::Zrc20BorrowCapExceeded)));
+        assert_eq!(contract.zrc20_configs[&usdc].outstanding_borrow, 3_000_000);
+        
+        // Other ZRC-20s are only bound by the global cap
+        contract.borrow_cross_chain(user, eth, 10_000_000, 421614, [4u8; 20]).unwrap();
+        assert_eq!(contract.zrc20_configs[&eth].borrow_cap, u64::MAX);
+        
+        // A repay of the asset's mint frees exactly that much of its cap
+        let mint = map_zrc20(&mut contract, authority, usdc);
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.fund_user(user, mint, 500_000);
+        contract.repay_spl_token(user, mint, 500_000, [2u8; 20]).unwrap();
+        assert_eq!(contract.zrc20_configs[&usdc].outstanding_borrow, 2_500_000);
+        
+        // Another asset's repay cannot be booked against this ZRC-20's config
+        contract.repay_zrc20_config = Some(usdc);
+        let result = contract.repay_sol(user, DEPOSIT_FEE + 500_000, [2u8; 20], &[]);
+        assert!(matches!(result, Err(DepositContractError::InvalidZrc20Config)));
+        contract.repay_zrc20_config = None;
+        contract.repay_sol(user, DEPOSIT_FEE + 500_000, [2u8; 20], &[]).unwrap();
+        assert_eq!(contract.zrc20_configs[&usdc].outstanding_borrow, 2_500_000);
+        let result = contract.borrow_cross_chain(user, usdc, 500_001, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::Zrc20BorrowCapExceeded)));
+        contract.borrow_cross_chain(user, usdc, 500_000, 421614, [4u8; 20]).unwrap();
+        
+        // Lowering the cap below outstanding only blocks new borrows
+        contract.set_zrc20_borrow_cap(authority, usdc, 1_000_000).unwrap();
+        assert_eq!(contract.zrc20_configs[&usdc].outstanding_borrow, 3_000_000);
+        let result = contract.borrow_cross_chain(user, usdc, 1, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::Zrc20BorrowCapExceeded)));
+    }
+
+    #[test]
+    fn test_repay_frees_global_borrow_capacity() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        contract.set_global_borrow_cap(authority, 2 * 10u128.pow(18)).unwrap();
+        let usdc = [3u8; 20];
+        contract.set_zrc20_decimals(authority, usdc, 6).unwrap();
+        
+        let mint = map_zrc20(&mut contract, authority, usdc);
+        contract.create_asset_vault(authority, mint).unwrap();
+        let user = Pubkey::new_unique();
+        contract.fund_user(user, mint, 5_000_000);
+        
+        contract.borrow_cross_chain(user, usdc, 2_000_000, 421614, &[4u8; 20]).unwrap();
+        contract.repay_spl_token(user, mint, 500_000, derive_evm_sender(user)).unwrap();
+        assert_eq!(contract.contract_state.as_ref().unwrap().total_outstanding_borrows, 15 * 10u128.pow(17));
+        assert!(contract.borrow_cross_chain(user, usdc, 500_000, 421614, [4u8; 20]).is_ok());
+        
+        // An asset nobody borrowed, or an on_behalf_of without debt, frees nothing
+        let other_mint = map_zrc20(&mut contract, authority, [5u8; 20]);
+        contract.create_asset_vault(authority, other_mint).unwrap();
+        contract.fund_user(user, other_mint, 1_000_000);
+        contract.repay_spl_token(user, other_mint, 1_000_000, derive_evm_sender(user)).unwrap();
+        let stranger = Pubkey::new_unique();
+        contract.fund_user(stranger, mint, 1_000_000);
+        contract.repay_spl_token(stranger, mint, 1_000_000, [2u8; 20]).unwrap();
+        assert_eq!(contract.contract_state.as_ref().unwrap().total_outstanding_borrows, 2 * 10u128.pow(18));
+        
+        // Repaying more than outstanding (interest) clears only the debt
+        contract.repay_spl_token(user, mint, 3_000_000, derive_evm_sender(user)).unwrap();
+        assert_eq!(contract.contract_state.as_ref().unwrap().total_outstanding_borrows, 0);
+        assert_eq!(contract.borrow_positions[&user], 0);
+    }
+
+    #[test]
+    fn test_third_party_repay_clears_borrower_debt() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let usdc = [3u8; 20];
+        contract.set_zrc20_decimals(authority, usdc, 6).unwrap();
+        let mint = map_zrc20(&mut contract, authority, usdc);
+        contract.create_asset_vault(authority, mint).unwrap();
+        let borrower = Pubkey::new_unique();
+        let repayer = Pubkey::new_unique();
+        contract.borrow_cross_chain(borrower, usdc, 2_000_000, 421614, [4u8; 20]).unwrap();
+        contract.borrow_cross_chain(repayer, usdc, 1_000_000, 421614, [4u8; 20]).unwrap();
+        
+        // The repayer pays, but the debt cleared is the borrower's
+        contract.fund_user(repayer, mint, 500_000);
+        contract.repay_spl_token(repayer, mint, 500_000, derive_evm_sender(borrower)).unwrap();
+        assert_eq!(contract.borrow_positions[&borrower], 15 * 10u128.pow(17));
+        assert_eq!(contract.borrow_positions[&repayer], 10u128.pow(18));
+        assert_eq!(contract.contract_state.as_ref().unwrap().total_outstanding_borrows, 25 * 10u128.pow(17));
+    }
+
+    #[test]
+    fn test_collateral_position_tracks_deposits_and_withdrawals() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        
+        // A second deposit accumulates into the same position
+        let user = Pubkey::new_unique();
+        contract.deposit_spl_token(user, mint, 600_000_000, [2u8; 20]).unwrap();
+        let first_update = contract.now;
+        contract.now += 60;
+        contract.deposit_spl_token(user, mint, 400_000_000, [2u8; 20]).unwrap();
+        assert_eq!(
+            contract.collateral_positions[&(user, mint)],
+            CollateralPosition { amount: 1_000_000_000, last_update: first_update + 60 }
+        );
+        
+        // A ZRC-20 no asset maps to has no position to debit
+        let result = contract.withdraw_cross_chain(user, [3u8; 20], 100_000_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::UnsupportedAsset)));
+        assert_eq!(contract.collateral_positions[&(user, mint)].amount, 1_000_000_000);
+        
+        // The withdrawn ZRC-20's mint picks the position, which withdrawal reduces
+        contract.set_asset_zrc20(authority, mint, [3u8; 20]).unwrap();
+        contract.now += 60;
+        contract.withdraw_cross_chain(user, [3u8; 20], 250_000_000, 421614, [4u8; 20]).unwrap();
+        assert_eq!(
+            contract.collateral_positions[&(user, mint)],
+            CollateralPosition { amount: 750_000_000, last_update: first_update + 120 }
+        );
+        
+        // Collateral supplied elsewhere can be withdrawn too: the position floors at zero
+        contract.withdraw_cross_chain(user, [3u8; 20], 900_000_000, 421614, [4u8; 20]).unwrap();
+        assert_eq!(contract.collateral_positions[&(user, mint)].amount, 0);
+        
+        // Another user's withdrawal only touches their own (new, empty) position
+        let other = Pubkey::new_unique();
+        contract.deposit_spl_token(user, mint, 500_000_000, [2u8; 20]).unwrap();
+        contract.withdraw_cross_chain(other, [3u8; 20], 1, 421614, [4u8; 20]).unwrap();
+        assert_eq!(contract.collateral_positions[&(user, mint)].amount, 500_000_000);
+        assert_eq!(contract.collateral_positions[&(other, mint)].amount, 0);
+    }
+
+    #[test]
+    fn test_borrow_health_factor() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let usdc = [3u8; 20];
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.set_zrc20_decimals(authority, usdc, 6).unwrap();
+        contract.set_asset_zrc20(authority, mint, usdc).unwrap();
+        
+        // 1,000 tokens deposited in two parts, 80% of which counts as collateral
+        let user = Pubkey::new_unique();
+        contract.deposit_spl_token(user, mint, 600_000_000, [2u8; 20]).unwrap();
+        contract.deposit_spl_token(user, mint, 400_000_000, [2u8; 20]).unwrap();
+        assert_eq!(contract.collateral_positions[&(user, mint)].amount, 1_000_000_000);
+        let result = contract.set_liquidation_threshold(authority, mint, 10_001);
+        assert!(matches!(result, Err(DepositContractError::InvalidAmount)));
+        contract.set_liquidation_threshold(authority, mint, 8_000).unwrap();
+        
+        let result = contract.set_min_health_factor(Pubkey::new_unique(), 10_000);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        let result = contract.set_min_health_factor(authority, 9_999);
+        assert!(matches!(result, Err(DepositContractError::InvalidAmount)));
+        contract.set_min_health_factor(authority, 10_000).unwrap();
+        
+        // Healthy: 800 weighted against 500 borrowed is a health factor of 1.6
+        let result = contract.borrow_cross_chain(user, usdc, 500_000_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::InsufficientCollateral)));
+        contract.collateral_accounts = vec![(user, mint)];
+        contract.borrow_cross_chain(user, usdc, 500_000_000, 421614, [4u8; 20]).unwrap();
+        assert_eq!(contract.borrow_positions[&user], 500 * 10u128.pow(18));
+        
+        // Breach: 900 borrowed against 800 weighted; exactly 800 is still allowed
+        let result = contract.borrow_cross_chain(user, usdc, 400_000_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::InsufficientCollateral)));
+        assert_eq!(contract.borrow_positions[&user], 500 * 10u128.pow(18));
+        contract.borrow_cross_chain(user, usdc, 300_000_000, 421614, [4u8; 20]).unwrap();
+        
+        // Repaying frees room again
+        contract.fund_user(user, mint, 100_000_000);
+        contract.repay_spl_token(user, mint, 100_000_000, derive_evm_sender(user)).unwrap();
+        contract.borrow_cross_chain(user, usdc, 100_000_000, 421614, [4u8; 20]).unwrap();
+        
+        // A position counts once, and only the borrower's does
+        let other = Pubkey::new_unique();
+        contract.deposit_spl_token(other, mint, 1_000_000_000, [2u8; 20]).unwrap();
+        for accounts in [vec![(user, mint), (user, mint)], vec![(user, mint), (other, mint)]] {
+            contract.collateral_accounts = accounts;
+            let result = contract.borrow_cross_chain(user, usdc, 1, 421614, [4u8; 20]);
+            assert!(matches!(result, Err(DepositContractError::InvalidCollateralAccounts)));
+        }
+        
+        // Zero disables the check
+        contract.set_min_health_factor(authority, 0).unwrap();
+        contract.collateral_accounts.clear();
+        assert!(contract.borrow_cross_chain(user, usdc, 1_000_000_000, 421614, [4u8; 20]).is_ok());
+        assert_eq!(compute_health_factor(1, 0), u128::MAX);
+    }
+
+    #[test]
+    fn test_withdraw_keeps_borrows_collateralized() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let usdc = [3u8; 20];
+        let mint = map_zrc20(&mut contract, authority, usdc);
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.set_zrc20_decimals(authority, usdc, 6).unwrap();
+        contract.set_liquidation_threshold(authority, mint, 8_000).unwrap();
+        contract.set_min_health_factor(authority, 10_000).unwrap();
+        
+        // 1,000 supplied (800 weighted) against 400 borrowed
+        let user = Pubkey::new_unique();
+        contract.deposit_spl_token(user, mint, 1_000_000_000, [2u8; 20]).unwrap();
+        contract.collateral_accounts = vec![(user, mint)];
+        contract.borrow_cross_chain(user, usdc, 400_000_000, 421614, [4u8; 20]).unwrap();
+        
+        // Withdrawing 501 would leave 399.2 weighted; 500 leaves exactly 400
+        let result = contract.withdraw_cross_chain(user, usdc, 501_000_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::InsufficientCollateral)));
+        assert_eq!(contract.collateral_positions[&(user, mint)].amount, 1_000_000_000);
+        contract.withdraw_cross_chain(user, usdc, 500_000_000, 421614, [4u8; 20]).unwrap();
+        assert_eq!(contract.collateral_positions[&(user, mint)].amount, 500_000_000);
+        
+        // Leaving the position out does not help: the debt then has no collateral at all
+        contract.collateral_accounts.clear();
+        let result = contract.withdraw_cross_chain(user, usdc, 1, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::InsufficientCollateral)));
+        
+        // Without debt the whole position can go
+        let lender = Pubkey::new_unique();
+        contract.deposit_spl_token(lender, mint, 1_000_000_000, [2u8; 20]).unwrap();
+        contract.withdraw_cross_chain(lender, usdc, 1_000_000_000, 421614, [4u8; 20]).unwrap();
+        assert_eq!(contract.collateral_positions[&(lender, mint)].amount, 0);
+    }
+
+    #[test]
+    fn test_borrow_health_factor_prices_collateral() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let usdc = [3u8; 20];
+        contract.set_zrc20_decimals(authority, usdc, 6).unwrap();
+        contract.set_min_health_factor(authority, 10_000).unwrap();
+        
+        // 2 tokens at $150 (Pyth: 15_000_000_000 * 10^-8), 80% counted: $240 of collateral
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 9, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.set_liquidation_threshold(authority, mint, 8_000).unwrap();
+        let feed = Pubkey::new_unique();
+        let result = contract.set_asset_price_feed(Pubkey::new_unique(), mint, feed);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        contract.set_asset_price_feed(authority, mint, feed).unwrap();
+        let user = Pubkey::new_unique();
+        contract.deposit_spl_token(user, mint, 2_000_000_000, [2u8; 20]).unwrap();
+        contract.collateral_accounts = vec![(user, mint)];
+        
+        // The feed must accompany its position
+        let result = contract.borrow_cross_chain(user, usdc, 1, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::InvalidCollateralAccounts)));
+        
+        // Fresh price: $241 exceeds the collateral, $240 fits
+        contract.price_accounts.insert(feed, pyth::price_account(15_000_000_000, -8, contract.now - 5));
+        let result = contract.borrow_cross_chain(user, usdc, 241_000_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::InsufficientCollateral)));
+        contract.borrow_cross_chain(user, usdc, 240_000_000, 421614, [4u8; 20]).unwrap();
+        assert_eq!(usd_value(2 * 10u128.pow(18), &pyth::parse_price(&contract.price_accounts[&feed]).unwrap()).unwrap(), 300 * 10u128.pow(18));
+        
+        // A price older than max_price_age is rejected rather than trusted
+        contract.deposit_spl_token(user, mint, 2_000_000_000, [2u8; 20]).unwrap();
+        contract.price_accounts.insert(feed, pyth::price_account(15_000_000_000, -8, contract.now - DEFAULT_MAX_PRICE_AGE - 1));
+        let result = contract.borrow_cross_chain(user, usdc, 1_000_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::StalePrice)));
+        
+        // So is anything that is not a trading price account
+        let mut halted = pyth::price_account(15_000_000_000, -8, contract.now);
+        halted[pyth::AGG_STATUS_OFFSET] = 0;
+        for data in [halted, pyth::price_account(0, -8, contract.now), vec![0u8; 64]] {
+            contract.price_accounts.insert(feed, data);
+            let result = contract.borrow_cross_chain(user, usdc, 1_000_000, 421614, [4u8; 20]);
+            assert!(matches!(result, Err(DepositContractError::InvalidPriceFeed)));
+        }
+        
+        contract.price_accounts.insert(feed, pyth::price_account(15_000_000_000, -8, contract.now));
+        assert!(contract.borrow_cross_chain(user, usdc, 1_000_000, 421614, [4u8; 20]).is_ok());
+    }
+
+    #[test]
+    fn test_set_risk_params() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        
+        // Valid pairs, including the equal and full-weight edges
+        for (ltv, liquidation_threshold) in [(7_500, 8_000), (8_000, 8_000), (0, 0), (10_000, 10_000)] {
+            contract.set_risk_params(authority, mint, ltv, liquidation_threshold).unwrap();
+            let asset_config = &contract.asset_configs[&mint];
+            assert_eq!((asset_config.ltv, asset_config.liquidation_threshold), (ltv, liquidation_threshold));
+        }
+        
+        // ltv above the threshold, or a threshold above 100%, changes nothing
+        let result = contract.set_risk_params(authority, mint, 8_001, 8_000);
+        assert!(matches!(result, Err(DepositContractError::InvalidRiskParams)));
+        let result = contract.set_risk_params(authority, mint, 8_000, 10_001);
+        assert!(matches!(result, Err(DepositContractError::InvalidAmount)));
+        let result = contract.set_risk_params(Pubkey::new_unique(), mint, 5_000, 8_000);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        assert_eq!(contract.asset_configs[&mint].ltv, 10_000);
+        
+        // Lowering the threshold alone cannot drop it under the ltv
+        contract.set_risk_params(authority, mint, 7_500, 8_000).unwrap();
+        let result = contract.set_liquidation_threshold(authority, mint, 7_000);
+        assert!(matches!(result, Err(DepositContractError::InvalidRiskParams)));
+        contract.set_liquidation_threshold(authority, mint, 7_500).unwrap();
+    }
+
+    #[test]
+    fn test_get_max_borrow_matches_weighted_collateral() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let usdc_zrc20 = [3u8; 20];
+        contract.set_zrc20_decimals(authority, usdc_zrc20, 6).unwrap();
+        
+        // 1,000 USDC at face value, ltv 75%
+        let usdc = Pubkey::new_unique();
+        contract.add_supported_asset(authority, usdc, 6, false).unwrap();
+        contract.create_asset_vault(authority, usdc).unwrap();
+        contract.set_risk_params(authority, usdc, 7_500, 8_000).unwrap();
+        let user = Pubkey::new_unique();
+        contract.deposit_spl_token(user, usdc, 1_000_000_000, [2u8; 20]).unwrap();
+        
+        // 2 SOL at $150, ltv 50%
+        contract.register_native_sol(authority).unwrap();
+        contract.set_risk_params(authority, SYSTEM_PROGRAM_ID, 5_000, 6_000).unwrap();
+        let feed = Pubkey::new_unique();
+        contract.set_asset_price_feed(authority, SYSTEM_PROGRAM_ID, feed).unwrap();
+        contract.price_accounts.insert(feed, pyth::price_account(15_000_000_000, -8, contract.now));
+        contract.deposit_sol(user, 2_000_000_000 + DEPOSIT_FEE, [2u8; 20]).unwrap();
+        contract.collateral_accounts = vec![(user, usdc), (user, SYSTEM_PROGRAM_ID)];
+        
+        // 1,000 * 75% + 300 * 50% = $900, in USDC base units
+        assert_eq!(contract.get_max_borrow(user, usdc_zrc20).unwrap(), 900_000_000);
+        assert_eq!(
+            contract.events.last(),
+            Some(&Event::MaxBorrowComputed { user, asset: usdc_zrc20, max_amount: 900_000_000 })
+        );
+        
+        // Collateral in an asset no longer supported weighs nothing
+        contract.set_asset_supported(authority, SYSTEM_PROGRAM_ID, false).unwrap();
+        assert_eq!(contract.get_max_borrow(user, usdc_zrc20).unwrap(), 750_000_000);
+        contract.set_asset_supported(authority, SYSTEM_PROGRAM_ID, true).unwrap();
+        
+        // Outstanding borrows come off the top
+        contract.borrow_cross_chain(user, usdc_zrc20, 100_000_000, 421614, [4u8; 20]).unwrap();
+        assert_eq!(contract.get_max_borrow(user, usdc_zrc20).unwrap(), 800_000_000);
+        
+        // A 1.25 minimum health factor binds first: (800 + 180) / 1.25 = 784, less 100 borrowed
+        contract.set_min_health_factor(authority, 12_500).unwrap();
+        assert_eq!(contract.get_max_borrow(user, usdc_zrc20).unwrap(), 684_000_000);
+        contract.borrow_cross_chain(user, usdc_zrc20, 684_000_000, 421614, [4u8; 20]).unwrap();
+        assert_eq!(contract.get_max_borrow(user, usdc_zrc20).unwrap(), 0);
+        
+        // Caps and pauses bound the answer too
+        contract.set_min_health_factor(authority, 0).unwrap();
+        contract.set_global_borrow_cap(authority, (784 + 5) * 10u128.pow(18)).unwrap();
+        assert_eq!(contract.get_max_borrow(user, usdc_zrc20).unwrap(), 5_000_000);
+        contract.set_paused_operations(authority, PAUSE_BORROW).unwrap();
+        assert_eq!(contract.get_max_borrow(user, usdc_zrc20).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_self_borrow_guard() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let usdc_zrc20 = [3u8; 20];
+        let sol_zrc20 = [5u8; 20];
+        let usdc = Pubkey::new_unique();
+        contract.add_supported_asset(authority, usdc, 6, false).unwrap();
+        contract.create_asset_vault(authority, usdc).unwrap();
+        let result = contract.set_asset_zrc20(Pubkey::new_unique(), usdc, usdc_zrc20);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        contract.set_asset_zrc20(authority, usdc, usdc_zrc20).unwrap();
+        
+        let user = Pubkey::new_unique();
+        contract.deposit_spl_token(user, usdc, 1_000_000_000, [2u8; 20]).unwrap();
+        contract.collateral_accounts = vec![(user, usdc)];
+        
+        // Off by default: borrowing against the same asset is allowed
+        assert!(contract.borrow_cross_chain(user, usdc_zrc20, 1_000_000, 421614, [4u8; 20]).is_ok());
+        let result = contract.set_reject_self_borrow(Pubkey::new_unique(), true);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        contract.set_reject_self_borrow(authority, true).unwrap();
+        
+        // Only USDC supplied: borrowing USDC is self-referential, borrowing another asset is not
+        let result = contract.borrow_cross_chain(user, usdc_zrc20, 1_000_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::SelfBorrow)));
+        assert!(contract.borrow_cross_chain(user, sol_zrc20, 1_000_000, 421614, [4u8; 20]).is_ok());
+        
+        // No collateral accounts at all shows no other collateral either
+        contract.collateral_accounts.clear();
+        let result = contract.borrow_cross_chain(user, sol_zrc20, 1_000_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::SelfBorrow)));
+        
+        // SOL collateral alongside makes the USDC borrow acceptable; an empty position does not
+        contract.deposit_sol(user, 10_000_000, [2u8; 20]).unwrap();
+        contract.collateral_accounts = vec![(user, usdc), (user, SYSTEM_PROGRAM_ID)];
+        let result = contract.borrow_cross_chain(user, usdc_zrc20, 1_000_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::InvalidCollateralAccounts))); // SOL unregistered
+        contract.register_native_sol(authority).unwrap();
+        contract.set_asset_zrc20(authority, SYSTEM_PROGRAM_ID, sol_zrc20).unwrap();
+        assert!(contract.borrow_cross_chain(user, usdc_zrc20, 1_000_000, 421614, [4u8; 20]).is_ok());
+        
+        contract.withdraw_cross_chain(user, sol_zrc20, u64::MAX, 421614, [4u8; 20]).unwrap();
+        let result = contract.borrow_cross_chain(user, usdc_zrc20, 1_000_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::SelfBorrow)));
+    }
+
+    #[test]
+    fn test_recipient_length_per_destination_chain() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        // EVM chains stay at 20 bytes
+        let user = Pubkey::new_unique();
+        assert!(contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]).is_ok());
+        let result = contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 32]);
+        assert!(matches!(result, Err(DepositContractError::InvalidRecipientLength)));
+        
+        // Chains addressed by 32-byte keys cannot be configured until onCall decodes
+        // something other than an address, so neither operation can reach one
+        let chain_32 = 900;
+        let result = contract.set_chain_config(authority, chain_32, 32);
+        assert!(matches!(result, Err(DepositContractError::InvalidRecipientLength)));
+        let result = contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, chain_32, vec![5u8; 32]);
+        assert!(matches!(result, Err(DepositContractError::InvalidChainId)));
+    }
+
+    #[test]
+    fn test_withdraw_recipient_length() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.set_asset_zrc20(authority, mint, [3u8; 20]).unwrap();
+        let user = Pubkey::new_unique();
+        contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).unwrap();
+        
+        // A 32-byte recipient is rejected before anything is sent or debited
+        let result = contract.withdraw_cross_chain(user, [3u8; 20], 500_000, 421614, [4u8; 32]);
+        assert!(matches!(result, Err(DepositContractError::InvalidRecipientLength)));
+        assert!(matches!(contract.set_chain_config(authority, 900, 32), Err(DepositContractError::InvalidRecipientLength)));
+        let result = contract.withdraw_cross_chain(user, [3u8; 20], 500_000, 900, [4u8; 32]);
+        assert!(matches!(result, Err(DepositContractError::InvalidChainId)));
+        assert_eq!(contract.collateral_positions[&(user, mint)].amount, 1_000_000);
+        
+        assert!(contract.withdraw_cross_chain(user, [3u8; 20], 500_000, 421614, [4u8; 20]).is_ok());
+        assert_eq!(contract.collateral_positions[&(user, mint)].amount, 500_000);
+    }
+
+    #[test]
+    fn test_failed_gateway_call_keeps_collateral() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.set_asset_zrc20(authority, mint, [3u8; 20]).unwrap();
+        let user = Pubkey::new_unique();
+        contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).unwrap();
+        
+        contract.gateway_call_fails = true;
+        let result = contract.withdraw_cross_chain(user, [3u8; 20], 500_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::GatewayCallFailed)));
+        assert_eq!(contract.collateral_positions[&(user, mint)].amount, 1_000_000);
+        
+        contract.gateway_call_fails = false;
+        contract.withdraw_cross_chain(user, [3u8; 20], 500_000, 421614, [4u8; 20]).unwrap();
+        assert_eq!(contract.collateral_positions[&(user, mint)].amount, 500_000);
     }
 
-    fn set_pause_state(&mut self, authority: Pubkey, is_paused: bool) -> Result<(), DepositContractError> {
-        let state = self.contract_state.as_mut().ok_or(DepositContractError::Unauthorized)?;
+    #[test]
+    fn test_set_chain_config_validation() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
         
-        if state.authority.0 != authority.0 {
-            return Err(DepositContractError::Unauthorized);
+        let result = contract.set_chain_config(Pubkey::new_unique(), 900, EVM_ADDRESS_LEN);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        for recipient_len in [0, 19, 21, 32, 33] {
+            let result = contract.set_chain_config(authority, 900, recipient_len);
+            assert!(matches!(result, Err(DepositContractError::InvalidRecipientLength)));
         }
+        assert!(contract.set_chain_config(authority, 900, EVM_ADDRESS_LEN).is_ok());
+    }
 
-        state.is_paused = is_paused;
-        Ok(())
+    #[test]
+    fn test_can_borrow_eligible() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let user = Pubkey::new_unique();
+        assert!(contract.can_borrow(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]).is_none());
+        
+        // Dry run agrees with the real instruction
+        assert!(contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]).is_ok());
     }
 
-    // Message creation functions matching the contract
-    fn create_supply_message(on_behalf_of: [u8; 20]) -> Result<Vec<u8>, DepositContractError> {
-        // Convert 20-byte address to 32-byte address (EVM format)
-        let mut evm_address = [0u8; 32];
-        evm_address[12..32].copy_from_slice(&on_behalf_of);
+    #[test]
+    fn test_can_borrow_reports_blocking_reason() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
         
-        // Manually create ABI-encoded message: abi.encode("supply", address)
-        let mut message = Vec::with_capacity(128);
+        let user = Pubkey::new_unique();
+        let usdc = [3u8; 20];
+        let mint = map_zrc20(&mut contract, authority, usdc);
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.set_zrc20_decimals(authority, usdc, 6).unwrap();
+        contract.set_liquidation_threshold(authority, mint, 8_000).unwrap();
+        contract.deposit_spl_token(user, mint, 1_000_000_000, [2u8; 20]).unwrap();
+        contract.collateral_accounts = vec![(user, mint)];
         
-        // Offset for string "supply" (32 bytes)
-        message.extend_from_slice(&[0u8; 28]); // Padding
-        message.extend_from_slice(&64u32.to_be_bytes()); // Offset to string data
+        // Each blocking condition of borrow_cross_chain, and the same verdict from both
+        let check = |contract: &mut MockDepositContract, amount: u64, chain: u64, recipient: &[u8], expected: DepositContractError| {
+            assert_eq!(contract.can_borrow(user, usdc, amount, chain, recipient), Some(expected));
+            assert_eq!(contract.borrow_cross_chain(user, usdc, amount, chain, recipient).err(), Some(expected));
+        };
+        check(&mut contract, 0, 421614, &[4u8; 20], DepositContractError::InvalidAmount);
+        check(&mut contract, 1_000_000, 1, &[4u8; 20], DepositContractError::InvalidChainId);
+        check(&mut contract, 1_000_000, 421614, &[4u8; 32], DepositContractError::InvalidRecipientLength);
+        check(&mut contract, 1_000_000, 421614, &[1u8; 20], DepositContractError::SuspiciousRecipient);
+        check(&mut contract, 1_000_000, 421614, &[0u8; 20], DepositContractError::SuspiciousRecipient);
+        assert_eq!(contract.can_borrow(user, [0u8; 20], 1_000_000, 421614, [4u8; 20]), Some(DepositContractError::UnsupportedAsset));
         
-        // Address (32 bytes)
-        message.extend_from_slice(&evm_address);
+        contract.set_zrc20_borrow_cap(authority, usdc, 900_000).unwrap();
+        check(&mut contract, 1_000_000, 421614, &[4u8; 20], DepositContractError::Zrc20BorrowCapExceeded);
+        contract.set_zrc20_borrow_cap(authority, usdc, u64::MAX).unwrap();
         
-        // String length (32 bytes) - "supply" is 6 bytes
-        message.extend_from_slice(&[0u8; 28]); // Padding
-        message.extend_from_slice(&6u32.to_be_bytes()); // String length
+        contract.set_min_health_factor(authority, 10_000).unwrap();
+        check(&mut contract, 800_000_001, 421614, &[4u8; 20], DepositContractError::InsufficientCollateral);
+        contract.set_reject_self_borrow(authority, true).unwrap();
+        check(&mut contract, 1_000_000, 421614, &[4u8; 20], DepositContractError::SelfBorrow);
+        contract.set_reject_self_borrow(authority, false).unwrap();
         
-        // String data "supply" (padded to 32 bytes)
-        message.extend_from_slice(b"supply");
-        message.extend_from_slice(&[0u8; 26]); // Padding to 32 bytes
+        contract.set_global_borrow_cap(authority, 999_999).unwrap();
+        check(&mut contract, 1_000_000, 421614, &[4u8; 20], DepositContractError::GlobalBorrowCapExceeded);
+        contract.set_global_borrow_cap(authority, u128::MAX).unwrap();
         
-        // Pad entire message to exactly 128 bytes
-        message.resize(128, 0);
+        // Once nothing blocks it, both accept the borrow
+        assert!(contract.can_borrow(user, usdc, 1_000_000, 421614, [4u8; 20]).is_none());
+        contract.borrow_cross_chain(user, usdc, 1_000_000, 421614, [4u8; 20]).unwrap();
         
-        Ok(message)
-    }
-
-    fn create_borrow_cross_chain_message(
-        user: [u8; 32],
-        amount: u64,
-        destination_chain: u64,
-        recipient: [u8; 20],
-    ) -> Result<Vec<u8>, DepositContractError> {
-        // Simple format for testing - in real implementation would be proper ABI encoding
-        let message = format!(
-            "borrowCrossChain:{}:{}:{}:{}",
-            hex::encode(user),
-            amount,
-            destination_chain,
-            hex::encode(recipient)
-        );
-        Ok(message.into_bytes())
+        contract.set_pause_state(authority, true).unwrap();
+        check(&mut contract, 1_000_000, 421614, &[4u8; 20], DepositContractError::ContractPaused);
     }
-}
-
-// Test implementations
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
-    fn test_contract_initialization() {
-        let mut contract = MockDepositContract::new();
-        let authority = Pubkey::new_unique();
-        let lending_protocol_address = [1u8; 20];
-        let zeta_chain_id = 7001; // ZetaChain testnet
+    fn test_invoke_gateway_call_rejects_empty_message() {
+        let contract = MockDepositContract::new();
+        let receiver = [1u8; 20];
         
-        let result = contract.initialize(authority, lending_protocol_address, zeta_chain_id);
-        assert!(result.is_ok());
+        let result = contract.invoke_gateway_call(receiver, &Vec::new());
+        assert!(matches!(result, Err(DepositContractError::EmptyMessage)));
         
-        let state = contract.contract_state.unwrap();
-        assert_eq!(state.authority.0, authority.0);
-        assert_eq!(state.lending_protocol_address, lending_protocol_address);
-        assert_eq!(state.zeta_chain_id, zeta_chain_id);
-        assert!(!state.is_paused);
+        let result = contract.invoke_gateway_call(receiver, b"borrowCrossChain");
+        assert!(result.is_ok());
     }
 
     #[test]
-    fn test_invalid_chain_id_initialization() {
+    fn test_failed_gateway_call_books_no_borrow() {
         let mut contract = MockDepositContract::new();
         let authority = Pubkey::new_unique();
-        let lending_protocol_address = [1u8; 20];
-        let invalid_chain_id = 1; // Invalid chain ID
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        map_zrc20(&mut contract, authority, [3u8; 20]);
+        let user = Pubkey::new_unique();
+        contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]).unwrap();
+        let total = contract.contract_state.as_ref().unwrap().total_outstanding_borrows;
         
-        let result = contract.initialize(authority, lending_protocol_address, invalid_chain_id);
-        assert!(matches!(result, Err(DepositContractError::InvalidChainId)));
+        // The borrow never left the chain, so neither the user nor the caps are charged
+        contract.gateway_call_fails = true;
+        let result = contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]);
+        assert!(matches!(result, Err(DepositContractError::GatewayCallFailed)));
+        assert_eq!(contract.contract_state.as_ref().unwrap().total_outstanding_borrows, total);
+        assert_eq!(contract.zrc20_configs[&[3u8; 20]].outstanding_borrow, 1_000_000);
+        assert_eq!(contract.borrow_positions[&user], total);
+        
+        contract.gateway_call_fails = false;
+        contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]).unwrap();
+        assert_eq!(contract.zrc20_configs[&[3u8; 20]].outstanding_borrow, 2_000_000);
     }
 
     #[test]
-    fn test_add_supported_asset() {
+    fn test_pause_functionality() {
         let mut contract = MockDepositContract::new();
         let authority = Pubkey::new_unique();
         let lending_protocol_address = [1u8; 20];
@@ -293,201 +5831,289 @@ mod tests {
         
         contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
         
-        let mint = Pubkey::new_unique();
-        let result = contract.add_supported_asset(authority, mint, 6, false);
+        // Pause the contract
+        let result = contract.set_pause_state(authority, true);
         assert!(result.is_ok());
         
-        let asset_config = contract.asset_configs.get(&mint).unwrap();
-        assert_eq!(asset_config.decimals, 6);
-        assert!(!asset_config.is_native);
-        assert!(asset_config.is_supported);
+        // Try to deposit while paused
+        let user = Pubkey::new_unique();
+        let amount = 10_000_000;
+        let on_behalf_of = [2u8; 20];
+        
+        let result = contract.deposit_sol(user, amount, on_behalf_of);
+        assert!(matches!(result, Err(DepositContractError::ContractPaused)));
+        
+        // Unpause and try again
+        contract.now += MIN_PAUSE_TOGGLE_INTERVAL;
+        contract.set_pause_state(authority, false).unwrap();
+        let result = contract.deposit_sol(user, amount, on_behalf_of);
+        assert!(result.is_ok());
     }
 
     #[test]
-    fn test_unauthorized_add_asset() {
+    fn test_default_revert_policy() {
         let mut contract = MockDepositContract::new();
         let authority = Pubkey::new_unique();
-        let wrong_authority = Pubkey::new_unique();
-        let lending_protocol_address = [1u8; 20];
-        let zeta_chain_id = 7001;
-        
-        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
         
-        let mint = Pubkey::new_unique();
-        let result = contract.add_supported_asset(wrong_authority, mint, 6, false);
-        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        let deposit = contract.revert_options_for(OperationType::Deposit, b"SOL deposit failed");
+        let call = contract.revert_options_for(OperationType::Call, b"Cross-chain call failed");
+        assert!(deposit.call_on_revert);
+        assert!(call.call_on_revert);
+        assert_eq!(deposit.on_revert_gas_limit, GAS_LIMIT);
     }
 
     #[test]
-    fn test_deposit_sol_success() {
+    fn test_revert_policy_per_operation() {
         let mut contract = MockDepositContract::new();
         let authority = Pubkey::new_unique();
-        let lending_protocol_address = [1u8; 20];
-        let zeta_chain_id = 7001;
-        
-        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
         
-        let user = Pubkey::new_unique();
-        let amount = 10_000_000; // 0.01 SOL
-        let on_behalf_of = [2u8; 20]; // ZetaChain address
+        // Deposits revert (return funds), cross-chain calls abort
+        let revert_address = [0xAAu8; 20];
+        let abort_address = [0xBBu8; 20];
+        contract.set_revert_policy(authority, OperationType::Deposit, RevertPolicy {
+            call_on_revert: true,
+            revert_address,
+            abort_address: [0u8; 20],
+            ..RevertPolicy::default()
+        }).unwrap();
+        contract.set_revert_policy(authority, OperationType::Call, RevertPolicy {
+            call_on_revert: false,
+            revert_address: [0u8; 20],
+            abort_address,
+            ..RevertPolicy::default()
+        }).unwrap();
         
-        let result = contract.deposit_sol(user, amount, on_behalf_of);
-        assert!(result.is_ok());
+        let deposit = contract.revert_options_for(OperationType::Deposit, b"SOL deposit failed");
+        let call = contract.revert_options_for(OperationType::Call, b"Cross-chain call failed");
         
-        let message = result.unwrap();
-        assert_eq!(message.len(), 128); // Expected ABI encoded message length
+        assert!(deposit.call_on_revert);
+        assert_eq!(deposit.revert_address, revert_address);
+        assert!(!call.call_on_revert);
+        assert_eq!(call.abort_address, abort_address);
+        assert_ne!(deposit, call);
     }
 
     #[test]
-    fn test_deposit_sol_insufficient_fee() {
+    fn test_revert_message_carries_retry_hint() {
         let mut contract = MockDepositContract::new();
         let authority = Pubkey::new_unique();
-        let lending_protocol_address = [1u8; 20];
-        let zeta_chain_id = 7001;
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
         
-        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        let deposit = contract.revert_options_for(OperationType::Deposit, b"SOL deposit failed");
+        assert_eq!(deposit.revert_message[..4], DEFAULT_RETRY_AFTER_SECONDS.to_be_bytes());
+        assert_eq!(&deposit.revert_message[4..], b"SOL deposit failed");
         
-        let user = Pubkey::new_unique();
-        let amount = 1_000_000; // 0.001 SOL - less than minimum fee
-        let on_behalf_of = [2u8; 20];
+        // Congested calls back off longer; deposits are told not to retry
+        contract.set_revert_policy(authority, OperationType::Call, RevertPolicy {
+            retry_after_seconds: 900,
+            ..RevertPolicy::default()
+        }).unwrap();
+        contract.set_revert_policy(authority, OperationType::Deposit, RevertPolicy {
+            retry_after_seconds: 0,
+            ..RevertPolicy::default()
+        }).unwrap();
         
-        let result = contract.deposit_sol(user, amount, on_behalf_of);
-        assert!(matches!(result, Err(DepositContractError::InsufficientDepositFee)));
+        let call = contract.revert_options_for(OperationType::Call, b"Cross-chain call failed");
+        assert_eq!(call.revert_message, [&900u32.to_be_bytes()[..], b"Cross-chain call failed"].concat());
+        let deposit = contract.revert_options_for(OperationType::Deposit, b"SOL deposit failed");
+        assert_eq!(deposit.revert_message[..4], [0u8; 4]);
     }
 
     #[test]
-    fn test_deposit_spl_token_success() {
+    fn test_unauthorized_set_revert_policy() {
         let mut contract = MockDepositContract::new();
         let authority = Pubkey::new_unique();
-        let lending_protocol_address = [1u8; 20];
-        let zeta_chain_id = 7001;
-        
-        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
-        
-        let mint = Pubkey::new_unique();
-        contract.add_supported_asset(authority, mint, 6, false).unwrap();
-        
-        let user = Pubkey::new_unique();
-        let amount = 1_000_000; // 1 USDC (6 decimals)
-        let on_behalf_of = [2u8; 20];
-        
-        let result = contract.deposit_spl_token(user, mint, amount, on_behalf_of);
-        assert!(result.is_ok());
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
         
-        let message = result.unwrap();
-        assert_eq!(message.len(), 128);
+        let result = contract.set_revert_policy(Pubkey::new_unique(), OperationType::Call, RevertPolicy::default());
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
     }
 
     #[test]
-    fn test_deposit_unsupported_asset() {
+    fn test_circuit_breaker_emits_incident_metadata() {
         let mut contract = MockDepositContract::new();
         let authority = Pubkey::new_unique();
-        let lending_protocol_address = [1u8; 20];
-        let zeta_chain_id = 7001;
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
         
-        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        let incident = "https://status.zetalend.xyz/incidents/42";
+        contract.set_incident_metadata(authority, incident).unwrap();
+        contract.set_pause_state(authority, true).unwrap();
         
-        let mint = Pubkey::new_unique(); // Not added as supported asset
-        let user = Pubkey::new_unique();
-        let amount = 1_000_000;
-        let on_behalf_of = [2u8; 20];
+        assert!(contract.events.contains(&Event::CircuitBreakerTripped {
+            incident_metadata: incident.to_string(),
+        }));
         
-        let result = contract.deposit_spl_token(user, mint, amount, on_behalf_of);
-        assert!(matches!(result, Err(DepositContractError::UnsupportedAsset)));
+        // Re-pausing or unpausing does not trip the breaker again
+        contract.events.clear();
+        contract.set_pause_state(authority, true).unwrap();
+        contract.now += MIN_PAUSE_TOGGLE_INTERVAL;
+        contract.set_pause_state(authority, false).unwrap();
+        assert!(!contract.events.iter().any(|e| matches!(e, Event::CircuitBreakerTripped { .. })));
     }
 
     #[test]
-    fn test_deposit_native_asset_with_spl_function() {
+    fn test_rapid_pause_toggles_rejected() {
         let mut contract = MockDepositContract::new();
         let authority = Pubkey::new_unique();
-        let lending_protocol_address = [1u8; 20];
-        let zeta_chain_id = 7001;
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
         
-        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        contract.set_pause_state(authority, true).unwrap();
+        let result = contract.set_pause_state(authority, false);
+        assert!(matches!(result, Err(DepositContractError::PauseToggleTooSoon)));
+        assert!(contract.contract_state.as_ref().unwrap().is_paused);
         
-        let mint = Pubkey::new_unique();
-        contract.add_supported_asset(authority, mint, 9, true).unwrap(); // Native asset
+        // Repeating the current state is not a toggle
+        contract.now += 10;
+        assert!(contract.set_pause_state(authority, true).is_ok());
         
-        let user = Pubkey::new_unique();
-        let amount = 1_000_000;
-        let on_behalf_of = [2u8; 20];
+        // The window runs from the last toggle, not the last call
+        contract.now += MIN_PAUSE_TOGGLE_INTERVAL - 11;
+        let result = contract.set_pause_state(authority, false);
+        assert!(matches!(result, Err(DepositContractError::PauseToggleTooSoon)));
+        contract.now += 1;
+        contract.set_pause_state(authority, false).unwrap();
         
-        let result = contract.deposit_spl_token(user, mint, amount, on_behalf_of);
-        assert!(matches!(result, Err(DepositContractError::UseDepositSol)));
+        // Force overrides the window
+        contract.set_pause_state_with_force(authority, true, true).unwrap();
+        assert!(contract.contract_state.as_ref().unwrap().is_paused);
+        let result = contract.set_pause_state_with_force(Pubkey::new_unique(), false, true);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
     }
 
     #[test]
-    fn test_borrow_cross_chain_success() {
+    fn test_paused_operations_block_only_their_bits() {
         let mut contract = MockDepositContract::new();
         let authority = Pubkey::new_unique();
-        let lending_protocol_address = [1u8; 20];
-        let zeta_chain_id = 7001;
-        
-        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
-        
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
+        contract.set_asset_zrc20(authority, mint, [3u8; 20]).unwrap();
         let user = Pubkey::new_unique();
-        let asset = [3u8; 20]; // ZRC-20 token address
-        let amount = 1_000_000;
-        let destination_chain = 421614; // Arbitrum Sepolia
-        let recipient = [4u8; 20]; // Arbitrum address
         
-        let result = contract.borrow_cross_chain(user, asset, amount, destination_chain, recipient);
-        assert!(result.is_ok());
+        // Outcome of each operation: Ok, or the error it failed with
+        let run = |contract: &mut MockDepositContract| {
+            contract.fund_user(user, mint, 2_000_000);
+            [
+                contract.deposit_sol(user, 10_000_000, [2u8; 20]).err(),
+                contract.deposit_spl_token(user, mint, 1_000_000, [2u8; 20]).err(),
+                contract.repay_sol(user, 10_000_000, [2u8; 20], &[]).err(),
+                contract.repay_spl_token(user, mint, 1_000_000, [2u8; 20]).err(),
+                contract.borrow_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]).err(),
+                contract.withdraw_cross_chain(user, [3u8; 20], 1_000_000, 421614, [4u8; 20]).err(),
+            ]
+        };
+        let paused = Some(DepositContractError::OperationPaused);
         
-        let message = result.unwrap();
-        assert!(!message.is_empty());
+        let cases = [
+            (PAUSE_DEPOSIT, [paused, paused, None, None, None, None]),
+            (PAUSE_REPAY, [None, None, paused, paused, None, None]),
+            (PAUSE_BORROW, [None, None, None, None, paused, None]),
+            (PAUSE_WITHDRAW, [None, None, None, None, None, paused]),
+            (PAUSE_REPAY | PAUSE_WITHDRAW, [None, None, paused, paused, None, paused]),
+            (0, [None; 6]),
+        ];
+        for (bits, expected) in cases {
+            contract.set_paused_operations(authority, bits).unwrap();
+            assert_eq!(run(&mut contract), expected, "paused_operations {bits:#b}");
+        }
         
-        // Verify message contains expected data
-        let message_str = String::from_utf8(message).unwrap();
-        assert!(message_str.contains("borrowCrossChain"));
-        assert!(message_str.contains(&amount.to_string()));
-        assert!(message_str.contains(&destination_chain.to_string()));
+        // Batches follow the repay bit
+        contract.set_paused_operations(authority, PAUSE_REPAY).unwrap();
+        let items = vec![RepayItem { mint, amount: 1_000_000, on_behalf_of: [2u8; 20] }];
+        assert!(matches!(contract.repay_batch(user, &items), Err(DepositContractError::OperationPaused)));
+        
+        // Emergency admins may set bits; unknown bits and strangers are rejected
+        let admin = Pubkey::new_unique();
+        contract.set_emergency_admins(authority, vec![admin]).unwrap();
+        contract.set_paused_operations(admin, PAUSABLE_OPERATIONS).unwrap();
+        let result = contract.set_paused_operations(admin, 1 << 4);
+        assert!(matches!(result, Err(DepositContractError::InvalidAmount)));
+        let result = contract.set_paused_operations(Pubkey::new_unique(), 0);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        assert_eq!(contract.contract_state.as_ref().unwrap().paused_operations, PAUSABLE_OPERATIONS);
     }
 
     #[test]
-    fn test_borrow_cross_chain_invalid_destination() {
+    fn test_pause_toggle_without_clock_reports_clock_unavailable() {
         let mut contract = MockDepositContract::new();
         let authority = Pubkey::new_unique();
-        let lending_protocol_address = [1u8; 20];
-        let zeta_chain_id = 7001;
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        contract.clock_available = false;
         
-        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        // Toggles need the clock, even when forced
+        let result = contract.set_pause_state(authority, true);
+        assert!(matches!(result, Err(DepositContractError::ClockUnavailable)));
+        let result = contract.set_pause_state_with_force(authority, true, true);
+        assert!(matches!(result, Err(DepositContractError::ClockUnavailable)));
+        assert!(!contract.contract_state.as_ref().unwrap().is_paused);
+        assert!(contract.events.is_empty());
         
-        let user = Pubkey::new_unique();
-        let asset = [3u8; 20];
-        let amount = 1_000_000;
-        let invalid_destination_chain = 1; // Invalid chain
-        let recipient = [4u8; 20];
+        // Repeating the current state never reads it
+        contract.set_pause_state(authority, false).unwrap();
         
-        let result = contract.borrow_cross_chain(user, asset, amount, invalid_destination_chain, recipient);
-        assert!(matches!(result, Err(DepositContractError::InvalidChainId)));
+        contract.clock_available = true;
+        contract.set_pause_state(authority, true).unwrap();
+        assert_eq!(contract.contract_state.as_ref().unwrap().last_pause_change_ts, contract.now);
     }
 
     #[test]
-    fn test_pause_functionality() {
+    fn test_emergency_admin_can_pause_and_rescue_only() {
         let mut contract = MockDepositContract::new();
         let authority = Pubkey::new_unique();
-        let lending_protocol_address = [1u8; 20];
-        let zeta_chain_id = 7001;
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
         
-        contract.initialize(authority, lending_protocol_address, zeta_chain_id).unwrap();
+        let guardian = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        contract.add_supported_asset(authority, mint, 6, false).unwrap();
+        contract.create_asset_vault(authority, mint).unwrap();
         
-        // Pause the contract
-        let result = contract.set_pause_state(authority, true);
-        assert!(result.is_ok());
+        // Not an emergency admin yet
+        let result = contract.set_pause_state(guardian, true);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
         
-        // Try to deposit while paused
-        let user = Pubkey::new_unique();
-        let amount = 10_000_000;
-        let on_behalf_of = [2u8; 20];
+        contract.set_emergency_admins(authority, vec![guardian]).unwrap();
+        contract.set_pause_state(guardian, true).unwrap();
+        assert!(contract.contract_state.as_ref().unwrap().is_paused);
         
-        let result = contract.deposit_sol(user, amount, on_behalf_of);
-        assert!(matches!(result, Err(DepositContractError::ContractPaused)));
+        // Rescue is limited to the untracked surplus and lands with the authority
+        contract.simulate_vault_transfer(mint, 500);
+        let result = contract.rescue_tokens(guardian, mint, 501);
+        assert!(matches!(result, Err(DepositContractError::RescueExceedsSurplus)));
+        contract.rescue_tokens(guardian, mint, 500).unwrap();
+        assert_eq!(contract.user_balances[&(authority, mint)], 500);
+        assert_eq!(contract.reconcile_spl(mint).unwrap(), 0);
         
-        // Unpause and try again
-        contract.set_pause_state(authority, false).unwrap();
-        let result = contract.deposit_sol(user, amount, on_behalf_of);
-        assert!(result.is_ok());
+        // Parameters stay with the authority
+        let result = contract.add_supported_asset(guardian, Pubkey::new_unique(), 6, false);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        let result = contract.set_global_borrow_cap(guardian, 0);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        let result = contract.set_emergency_admins(guardian, vec![guardian, Pubkey::new_unique()]);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+        
+        let result = contract.set_emergency_admins(authority, vec![guardian; MAX_EMERGENCY_ADMINS + 1]);
+        assert!(matches!(result, Err(DepositContractError::RegistryFull)));
+        let result = contract.set_emergency_admins(authority, vec![PROGRAM_ID]);
+        assert!(matches!(result, Err(DepositContractError::InvalidAuthority)));
+        
+        // Removing the guardian revokes its access
+        contract.set_emergency_admins(authority, Vec::new()).unwrap();
+        let result = contract.rescue_tokens(guardian, mint, 1);
+        assert!(matches!(result, Err(DepositContractError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_incident_metadata_too_long() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let incident = "x".repeat(MAX_INCIDENT_METADATA_LEN + 1);
+        let result = contract.set_incident_metadata(authority, &incident);
+        assert!(matches!(result, Err(DepositContractError::IncidentMetadataTooLong)));
     }
 
     #[test]
@@ -581,6 +6207,97 @@ mod tests {
         assert!(DEPOSIT_FEE <= 10_000_000); // At most 0.01 SOL
     }
 
+    #[test]
+    fn test_add_assets_batch_reports_failing_index() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let existing = Pubkey::new_unique();
+        contract.add_supported_asset(authority, existing, 6, false).unwrap();
+        
+        // Item 2 re-adds an existing asset
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let items = [(a, 6, false), (b, 9, false), (existing, 6, false), (c, 8, false)];
+        let result = contract.add_supported_assets_batch(authority, &items);
+        assert!(matches!(result, Err((2, DepositContractError::AssetAlreadySupported))));
+        
+        // Nothing from the failed batch was kept
+        assert_eq!(contract.asset_configs.len(), 1);
+        assert!(!contract.asset_configs.contains_key(&a));
+        
+        // Fixing that entry lets the batch through
+        let items = [(a, 6, false), (b, 9, false), (c, 8, false)];
+        assert!(contract.add_supported_assets_batch(authority, &items).is_ok());
+        assert_eq!(contract.asset_configs.len(), 4);
+        
+        let too_many = vec![(Pubkey::new_unique(), 6, false); MAX_ADD_ASSET_BATCH_SIZE + 1];
+        let result = contract.add_supported_assets_batch(authority, &too_many);
+        assert!(matches!(result, Err((_, DepositContractError::BatchTooLarge))));
+        
+        // Each item's mint gets the single-add checks: decimals, then freeze authority
+        let (good, misstated, freezable) = (contract.create_mint(6), contract.create_mint(9), contract.create_freezable_mint(6));
+        let result = contract.add_supported_assets_batch(authority, &[(good, 6, false), (misstated, 6, false)]);
+        assert!(matches!(result, Err((1, DepositContractError::DecimalMismatch))));
+        let result = contract.add_supported_assets_batch(authority, &[(good, 6, false), (freezable, 6, false)]);
+        assert!(matches!(result, Err((1, DepositContractError::MintHasFreezeAuthority))));
+        assert!(!contract.asset_configs.contains_key(&good));
+    }
+
+    #[test]
+    fn test_asset_registry_cap() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        let mints: Vec<Pubkey> = (0..MAX_ZRC20_ASSETS).map(|_| Pubkey::new_unique()).collect();
+        for mint in &mints {
+            contract.add_supported_asset(authority, *mint, 6, false).unwrap();
+        }
+        
+        let result = contract.add_supported_asset(authority, Pubkey::new_unique(), 6, false);
+        assert!(matches!(result, Err(DepositContractError::RegistryFull)));
+        
+        // Closing a config frees its slot
+        contract.remove_supported_asset(authority, mints[0]).unwrap();
+        contract.close_asset_config(authority, mints[0]).unwrap();
+        assert!(contract.add_supported_asset(authority, Pubkey::new_unique(), 6, false).is_ok());
+    }
+
+    #[test]
+    fn test_destination_chain_registry_cap() {
+        let mut contract = MockDepositContract::new();
+        let authority = Pubkey::new_unique();
+        contract.initialize(authority, [1u8; 20], 7001).unwrap();
+        
+        // initialize already registers the two default chains
+        let mut chain_id = 1u64;
+        while contract.chain_configs.len() < MAX_DESTINATION_CHAINS {
+            contract.set_chain_config(authority, chain_id, EVM_ADDRESS_LEN).unwrap();
+            chain_id += 1;
+        }
+        
+        let result = contract.set_chain_config(authority, chain_id, EVM_ADDRESS_LEN);
+        assert!(matches!(result, Err(DepositContractError::RegistryFull)));
+        
+        // Updating an existing chain does not take a new slot
+        assert!(contract.set_chain_config(authority, 421614, EVM_ADDRESS_LEN).is_ok());
+    }
+
+    #[test]
+    fn test_error_codes_and_slugs_unique() {
+        
+        let codes: HashSet<u32> = DepositContractError::ALL.iter().map(|e| e.code()).collect();
+        let slugs: HashSet<&str> = DepositContractError::ALL.iter().map(|e| e.slug()).collect();
+        assert_eq!(codes.len(), DepositContractError::ALL.len());
+        assert_eq!(slugs.len(), DepositContractError::ALL.len());
+        
+        // Codes follow declaration order, so they must never be reordered
+        assert_eq!(DepositContractError::Unauthorized.code(), 6000);
+        assert_eq!(DepositContractError::RegistryFull.code(), 6030);
+        assert_eq!(DepositContractError::StalePrice.slug(), "stale_price");
+    }
+
     #[test]
     fn test_zeta_chain_address_format() {
         // ZetaChain addresses should be 20 bytes (Ethereum-compatible)
@@ -635,7 +6352,14 @@ fn main() {
 
 // Add hex dependency for encoding
 mod hex {
-    pub fn encode(bytes: &[u8]) -> String {
-        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    pub fn encode(bytes: impl AsRef<[u8]>) -> String {
+        bytes.as_ref().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    pub fn decode(text: &str) -> Option<Vec<u8>> {
+        if !text.len().is_multiple_of(2) {
+            return None;
+        }
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
     }
 }
\ No newline at end of file
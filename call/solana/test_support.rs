@@ -0,0 +1,89 @@
+//! # Shared Test Support
+//!
+//! Helpers for both program test suites, pulled in with
+//! `#[cfg(test)] #[path = "test_support.rs"] mod test_support;`
+//!
+//! Expected ABI layouts are spelled out word by word instead of going through either
+//! mock's encoder, so an encoding regression on either side shows up as a mismatch.
+
+// Each suite uses a different subset
+#![allow(dead_code)]
+
+pub const WORD_LEN: usize = 32;
+
+/// Concatenated 32-byte words, each given as hex and left-padded with zeros
+pub fn abi_words(words: &[&str]) -> Vec<u8> {
+    words
+        .iter()
+        .flat_map(|word| {
+            let word = format!("{:0>64}", word);
+            (0..word.len())
+                .step_by(2)
+                .map(move |i| u8::from_str_radix(&word[i..i + 2], 16).expect("hex word"))
+        })
+        .collect()
+}
+
+// uint256 word
+fn uint_word(value: u64) -> Vec<u8> {
+    let mut word = vec![0u8; WORD_LEN];
+    word[WORD_LEN - 8..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+// abi.encode(string action, address onBehalfOf): offset, address, length, padded data
+fn action_with_address(action: &str, on_behalf_of: [u8; 20]) -> Vec<u8> {
+    assert!(action.len() <= WORD_LEN, "action must fit one data word");
+    let mut message = uint_word(2 * WORD_LEN as u64);
+    message.extend_from_slice(&[0u8; 12]);
+    message.extend_from_slice(&on_behalf_of);
+    message.extend_from_slice(&uint_word(action.len() as u64));
+    message.extend_from_slice(action.as_bytes());
+    message.resize(4 * WORD_LEN, 0);
+    message
+}
+
+/// abi.encode("supply", onBehalfOf): what create_supply_message must produce and
+/// on_call must decode
+pub fn supply_message(on_behalf_of: [u8; 20]) -> Vec<u8> {
+    action_with_address("supply", on_behalf_of)
+}
+
+/// abi.encode("repay", onBehalfOf), as create_repay_message builds it
+pub fn repay_message(on_behalf_of: [u8; 20]) -> Vec<u8> {
+    action_with_address("repay", on_behalf_of)
+}
+
+/// Assert `payload` equals `expected`, reporting the first 32-byte word that differs
+#[track_caller]
+pub fn assert_abi_layout(payload: &[u8], expected: &[u8]) {
+    let differing_word = payload
+        .chunks(WORD_LEN)
+        .zip(expected.chunks(WORD_LEN))
+        .position(|(actual, expected)| actual != expected);
+    if let Some(index) = differing_word {
+        panic!(
+            "ABI word {} differs: got {:02x?}, expected {:02x?}",
+            index,
+            &payload[index * WORD_LEN..],
+            &expected[index * WORD_LEN..]
+        );
+    }
+    assert_eq!(
+        payload.len() / WORD_LEN,
+        expected.len() / WORD_LEN,
+        "ABI word count differs"
+    );
+    assert_eq!(payload.len(), expected.len(), "ABI payload length differs");
+}
+
+/// Stand-in for the TSS ed25519 signature over `message`. The mocks model the
+/// Ed25519Program instruction rather than the curve math (the check the
+/// test-bypass-tss build skips), so any 64 bytes tied to the message will do.
+pub fn fake_tss_signature(message: &[u8]) -> [u8; 64] {
+    let mut signature = [0u8; 64];
+    for (i, byte) in message.iter().enumerate() {
+        signature[i % 64] = signature[i % 64].rotate_left(3) ^ byte;
+    }
+    signature
+}